@@ -0,0 +1,7 @@
+CREATE TABLE login_attempts (
+    id SERIAL PRIMARY KEY,
+    attempt_key VARCHAR(255) NOT NULL UNIQUE,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until TIMESTAMP
+);
@@ -0,0 +1,5 @@
+DROP TABLE user_identities;
+
+-- an empty hash never verifies, these users must reset their password
+UPDATE users SET password = '' WHERE password IS NULL;
+ALTER TABLE users ALTER COLUMN password SET NOT NULL;
@@ -0,0 +1,18 @@
+-- files uploaded with a post or a comment, the bytes live in the file storage under storage_key
+CREATE TABLE attachments (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id INTEGER REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    storage_key VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE INDEX attachments_post_id_idx ON attachments (post_id);
+CREATE INDEX attachments_comment_id_idx ON attachments (comment_id);
+CREATE INDEX attachments_user_id_idx ON attachments (user_id);
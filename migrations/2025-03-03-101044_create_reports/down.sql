@@ -0,0 +1,4 @@
+ALTER TABLE comments DROP COLUMN hidden_at;
+ALTER TABLE posts DROP COLUMN hidden_at;
+
+DROP TABLE reports;
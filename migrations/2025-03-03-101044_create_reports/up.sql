@@ -0,0 +1,29 @@
+-- a report flags a post, a comment or a user profile for the moderators
+CREATE TABLE reports (
+    id SERIAL PRIMARY KEY,
+    reporter_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id INTEGER REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+    reported_user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    category VARCHAR(32) NOT NULL,
+    details TEXT NOT NULL DEFAULT '',
+    status VARCHAR(20) NOT NULL DEFAULT 'open',
+    resolution VARCHAR(32),
+    resolved_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMP,
+    CHECK (num_nonnulls(post_id, comment_id, reported_user_id) = 1)
+);
+
+-- a user has at most one open report per target, so open reports count distinct reporters
+CREATE UNIQUE INDEX reports_open_post_reporter_idx ON reports (post_id, reporter_user_id)
+    WHERE status = 'open' AND post_id IS NOT NULL;
+CREATE UNIQUE INDEX reports_open_comment_reporter_idx ON reports (comment_id, reporter_user_id)
+    WHERE status = 'open' AND comment_id IS NOT NULL;
+CREATE UNIQUE INDEX reports_open_user_reporter_idx ON reports (reported_user_id, reporter_user_id)
+    WHERE status = 'open' AND reported_user_id IS NOT NULL;
+CREATE INDEX reports_status_idx ON reports (status);
+
+-- content hidden by reports or held by a moderator, only a moderator lifts it
+ALTER TABLE posts ADD COLUMN hidden_at TIMESTAMP;
+ALTER TABLE comments ADD COLUMN hidden_at TIMESTAMP;
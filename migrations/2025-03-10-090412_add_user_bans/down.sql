@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_bans;
+
+ALTER TABLE users
+DROP COLUMN banned_until,
+DROP COLUMN ban_reason;
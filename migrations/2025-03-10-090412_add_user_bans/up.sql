@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN banned_until TIMESTAMP,
+ADD COLUMN ban_reason TEXT;
+
+-- every ban and lift issued by a moderator, the users columns only hold the current ban
+CREATE TABLE user_bans (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    moderator_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    banned_until TIMESTAMP,
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_user_bans_user_id ON user_bans(user_id);
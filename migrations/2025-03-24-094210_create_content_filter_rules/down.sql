@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM reports WHERE reporter_user_id IS NULL;
+ALTER TABLE reports ALTER COLUMN reporter_user_id SET NOT NULL;
+
+DROP TABLE content_filter_rules;
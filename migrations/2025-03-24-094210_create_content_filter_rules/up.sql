@@ -0,0 +1,12 @@
+-- regex rules of the content filter, managed by admins
+CREATE TABLE content_filter_rules (
+    id SERIAL PRIMARY KEY,
+    pattern TEXT NOT NULL,
+    action VARCHAR(16) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    created_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- reports without a reporter are filed by the content filter for held or flagged content
+ALTER TABLE reports ALTER COLUMN reporter_user_id DROP NOT NULL;
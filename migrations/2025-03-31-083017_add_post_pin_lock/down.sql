@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_posts_pinned;
+
+ALTER TABLE posts
+DROP COLUMN pinned,
+DROP COLUMN locked;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE posts
+ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- pinned posts are listed apart from the rest, there are only ever a few of them
+CREATE INDEX idx_posts_pinned ON posts(pinned) WHERE pinned;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_blocks;
+DROP TABLE messages;
+DROP TABLE conversation_members;
+DROP TABLE conversations;
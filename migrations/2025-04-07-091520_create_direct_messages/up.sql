@@ -0,0 +1,41 @@
+-- Your SQL goes here
+-- a private conversation between two users, or a small group when it has a title
+CREATE TABLE conversations (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(100) NOT NULL DEFAULT '',
+    is_group BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- bumped by every message, the inbox lists the most recent conversations first
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- members who left keep their row, their messages stay in the history
+CREATE TABLE conversation_members (
+    conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_read_at TIMESTAMP,
+    left_at TIMESTAMP,
+    PRIMARY KEY (conversation_id, user_id)
+);
+CREATE INDEX idx_conversation_members_user_id ON conversation_members(user_id);
+
+CREATE TABLE messages (
+    id SERIAL PRIMARY KEY,
+    conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP
+);
+CREATE INDEX idx_messages_conversation_id_created_at ON messages(conversation_id, created_at);
+
+-- a blocked user can not start or write in conversations with the user who blocked them
+CREATE TABLE user_blocks (
+    blocker_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    blocked_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (blocker_user_id, blocked_user_id),
+    CHECK (blocker_user_id <> blocked_user_id)
+);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE follows (
+    follower_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    followed_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (follower_user_id, followed_user_id),
+    CHECK (follower_user_id <> followed_user_id)
+);
+CREATE INDEX idx_follows_followed_user_id ON follows(followed_user_id);
+
+-- subscribers are notified of new comments on the post
+CREATE TABLE post_subscriptions (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, post_id)
+);
+CREATE INDEX idx_post_subscriptions_post_id ON post_subscriptions(post_id);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- a bookmark saves one post or one comment for its owner, filed in an optional folder
+CREATE TABLE bookmarks (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id INTEGER REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+    folder VARCHAR(50) NOT NULL DEFAULT '',
+    note VARCHAR(500) NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (num_nonnulls(post_id, comment_id) = 1)
+);
+
+CREATE UNIQUE INDEX bookmarks_user_post_idx ON bookmarks (user_id, post_id)
+    WHERE post_id IS NOT NULL;
+CREATE UNIQUE INDEX bookmarks_user_comment_idx ON bookmarks (user_id, comment_id)
+    WHERE comment_id IS NOT NULL;
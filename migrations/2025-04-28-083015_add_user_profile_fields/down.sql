@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users
+    DROP COLUMN bio,
+    DROP COLUMN website,
+    DROP COLUMN location,
+    DROP COLUMN pronouns,
+    DROP COLUMN signature;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- profile fields users fill in from their settings, empty until then
+ALTER TABLE users
+    ADD COLUMN bio TEXT NOT NULL DEFAULT '',
+    ADD COLUMN website VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN location VARCHAR(100) NOT NULL DEFAULT '',
+    ADD COLUMN pronouns VARCHAR(40) NOT NULL DEFAULT '',
+    ADD COLUMN signature VARCHAR(300) NOT NULL DEFAULT '';
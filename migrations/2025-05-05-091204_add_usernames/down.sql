@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE previous_usernames;
+
+ALTER TABLE users DROP COLUMN username_changed_at;
+ALTER TABLE users DROP COLUMN username;
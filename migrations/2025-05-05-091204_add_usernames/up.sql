@@ -0,0 +1,19 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN username VARCHAR(32);
+
+-- existing accounts get a handle derived from their name, the id keeps it unique
+UPDATE users
+SET username = LEFT(REGEXP_REPLACE(LOWER(name), '[^a-z0-9_]+', '_', 'g'), 20) || '_' || id;
+
+ALTER TABLE users ALTER COLUMN username SET NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_username_key UNIQUE (username);
+ALTER TABLE users ADD COLUMN username_changed_at TIMESTAMP;
+
+-- handles a user gave up, kept so links to them redirect and nobody else can take them
+CREATE TABLE previous_usernames (
+    username VARCHAR(32) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_previous_usernames_user_id ON previous_usernames(user_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_users_deleted_placeholder;
+
+UPDATE users SET role = 'user' WHERE role = 'deleted';
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- an account that signed up with the reserved address is not the placeholder, it gives the address up
+UPDATE users
+SET email = 'reserved-address-' || id || '@rust-forum.invalid'
+WHERE email = 'deleted-user@rust-forum.invalid'
+  AND (password IS NOT NULL OR EXISTS (
+      SELECT 1 FROM user_identities WHERE user_identities.user_id = users.id
+  ));
+
+-- a placeholder created on the first anonymized deletion keeps its id and content
+UPDATE users SET role = 'deleted' WHERE email = 'deleted-user@rust-forum.invalid';
+
+INSERT INTO users (name, email, username, role)
+SELECT 'deleted user', 'deleted-user@rust-forum.invalid', 'deleted', 'deleted'
+WHERE NOT EXISTS (SELECT 1 FROM users WHERE role = 'deleted');
+
+-- the placeholder is found by its role, there is only ever one
+CREATE UNIQUE INDEX idx_users_deleted_placeholder ON users(role) WHERE role = 'deleted';
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE previous_usernames DROP CONSTRAINT previous_usernames_username_lowercase;
+ALTER TABLE users DROP CONSTRAINT users_username_lowercase;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- handles are compared as stored, a mixed case row would dodge the unique constraints
+ALTER TABLE users ADD CONSTRAINT users_username_lowercase CHECK (username = LOWER(username));
+ALTER TABLE previous_usernames
+    ADD CONSTRAINT previous_usernames_username_lowercase CHECK (username = LOWER(username));
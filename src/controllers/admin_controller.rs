@@ -0,0 +1,400 @@
+use actix_session::Session;
+use actix_web::{
+    error, get,
+    http::header::{ContentDisposition, ContentType},
+    post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::{
+        audit::{AuditAction, AuditLogEntryPublic, AuditLogQuery, AuditRecord, AuditTarget},
+        content_filter::{ContentFilterRuleFormData, ContentFilterRulePublic, FilterAction},
+        user::UserRoleFormData,
+    },
+    utils::{
+        audit::record_audit,
+        csrf::handlebars_add_csrf_token,
+        flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::{create_redirect, get_client_ip, redirect_back},
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::{get_session_user, get_user_names, session_user_is_admin},
+    },
+    AppKit,
+};
+
+/// Link to another page of the audit log that keeps the filters
+fn audit_page_url(filter_query: &str, page: i64, per_page: i64) -> String {
+    match filter_query.is_empty() {
+        true => format!("/admin/audit?page={}&per_page={}", page, per_page),
+        false => format!(
+            "/admin/audit?{}&page={}&per_page={}",
+            filter_query, page, per_page
+        ),
+    }
+}
+
+#[get("/audit")]
+pub async fn admin_audit_log_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    query: web::Query<AuditLogQuery>,
+    pagination: QueryPagination,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let query = query.into_inner();
+    let filter_query = serde_urlencoded::to_string(&query).unwrap_or_default();
+    let filter = query.to_filter();
+
+    let page = pagination.page;
+    let per_page = pagination.limit;
+
+    let (entries, total, filter_error) = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can see the audit log"));
+        }
+
+        let filter = match filter {
+            Ok(filter) => filter,
+            Err(why) => return Ok((Vec::new(), 0, Some(why))),
+        };
+
+        let result = app_kit
+            .audit_service
+            .get_entries(&filter, &pagination)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let actor_names = get_user_names(
+            app_kit.user_service.as_ref(),
+            result.entries.iter().map(|e| e.actor_user_id),
+        );
+
+        let entries: Vec<AuditLogEntryPublic> = result
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let actor_name = actor_names
+                    .get(&entry.actor_user_id)
+                    .cloned()
+                    .unwrap_or_default();
+                AuditLogEntryPublic::new(entry, actor_name)
+            })
+            .collect();
+
+        Ok((entries, result.total, None))
+    })
+    .await?
+    .map_err(error::ErrorForbidden)?;
+
+    let total_pages = (total + per_page - 1) / per_page;
+
+    let actions: Vec<serde_json::Value> = AuditAction::ALL
+        .iter()
+        .map(|action| {
+            json!({
+                "value": action.as_str(),
+                "label": action.label(),
+                "selected": action.as_str() == query.action,
+            })
+        })
+        .collect();
+
+    let target_types: Vec<serde_json::Value> = AuditTarget::KINDS
+        .iter()
+        .map(|kind| json!({ "value": kind, "selected": *kind == query.target_type }))
+        .collect();
+
+    let export_url = match filter_query.is_empty() {
+        true => "/admin/audit/export".to_string(),
+        false => format!("/admin/audit/export?{}", filter_query),
+    };
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Audit log",
+        "entries": entries,
+        "total": total,
+        "filter_error": filter_error,
+        "query": query,
+        "actions": actions,
+        "target_types": target_types,
+        "export_url": export_url,
+        "page": page,
+        "total_pages": total_pages,
+        "prev_url": (page > 1).then(|| audit_page_url(&filter_query, page - 1, per_page)),
+        "next_url": (page < total_pages).then(|| audit_page_url(&filter_query, page + 1, per_page)),
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("admin/audit", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+#[get("/audit/export")]
+pub async fn admin_audit_log_export_route(
+    app_kit: web::Data<AppKit>,
+    query: web::Query<AuditLogQuery>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let filter = query.to_filter().map_err(error::ErrorBadRequest)?;
+
+    let bytes = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can export the audit log"));
+        }
+
+        let entries = app_kit
+            .audit_service
+            .export_entries(&filter)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        serde_json::to_vec_pretty(&entries).map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?
+    .map_err(error::ErrorForbidden)?;
+
+    let file_name = format!(
+        "rust-forum-audit-{}.json",
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(ContentDisposition::attachment(file_name))
+        .body(bytes))
+}
+
+#[post("/users/{user_id}/role")]
+pub async fn admin_user_role_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32,)>,
+    form: actix_web_validator::Form<UserRoleFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let user_id = path.into_inner().0;
+    let client_ip = get_client_ip(&req);
+
+    let role_result = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can change roles"));
+        }
+
+        let old_role = app_kit
+            .user_service
+            .get_user_by_id(user_id)
+            .map_err(|e| WebError::from(e.to_string()))?
+            .role;
+
+        let user = app_kit
+            .user_service
+            .update_user_role(user_id, session_user.id, &form.role)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let reason = match form.reason.trim() {
+            "" => format!("{} -> {}", old_role, user.role),
+            reason => format!("{} -> {}: {}", old_role, user.role, reason),
+        };
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::RoleChange,
+                target: AuditTarget::User(user_id),
+                reason: &reason,
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(())
+    })
+    .await?;
+
+    match role_result {
+        Ok(()) => set_flash_message(&session, FLASH_SUCCESS, "Role changed")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(create_redirect(&format!(
+        "/moderation/users/{}/ban",
+        user_id
+    )))
+}
+
+#[get("/filters")]
+pub async fn admin_content_filter_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (policy, rules) = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can manage the content filter"));
+        }
+
+        let rules: Vec<ContentFilterRulePublic> = app_kit
+            .content_filter_service
+            .get_rules()
+            .map_err(|e| WebError::from(e.to_string()))?
+            .into_iter()
+            .map(ContentFilterRulePublic::new)
+            .collect();
+
+        Ok((app_kit.content_filter_service.get_policy(), rules))
+    })
+    .await?
+    .map_err(error::ErrorForbidden)?;
+
+    let actions: Vec<serde_json::Value> = FilterAction::ALL
+        .iter()
+        .map(|action| json!({ "value": action.as_str(), "label": action.label() }))
+        .collect();
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Content filter",
+        "rules": rules,
+        "actions": actions,
+        "policy": {
+            "blocked_words": policy.blocked_words,
+            "blocked_words_action": policy.blocked_words_action.label(),
+            "new_account_days": policy.new_account_days,
+            "new_account_max_links": policy.new_account_max_links,
+            "new_account_links_action": policy.new_account_links_action.label(),
+            "duplicate_window_minutes": policy.duplicate_window_minutes,
+            "duplicate_action": policy.duplicate_action.label(),
+        },
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("admin/filters", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+#[post("/filters")]
+pub async fn admin_content_filter_create_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    form: actix_web_validator::Form<ContentFilterRuleFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let client_ip = get_client_ip(&req);
+
+    let action =
+        FilterAction::parse(&form.action).ok_or(error::ErrorBadRequest("Unknown filter action"))?;
+
+    let create_result = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can manage the content filter"));
+        }
+
+        let rule = app_kit
+            .content_filter_service
+            .create_rule(&form.pattern, action, &form.description, session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::FilterRuleCreate,
+                target: AuditTarget::FilterRule(rule.id),
+                reason: &format!("{}: {}", rule.action, rule.pattern),
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(())
+    })
+    .await?;
+
+    match create_result {
+        Ok(()) => set_flash_message(&session, FLASH_SUCCESS, "Filter rule added")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/filters/{rule_id}/delete")]
+pub async fn admin_content_filter_delete_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32,)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let rule_id = path.into_inner().0;
+    let client_ip = get_client_ip(&req);
+
+    let delete_result = web::block(move || {
+        if !session_user_is_admin(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only admins can manage the content filter"));
+        }
+
+        // the pattern goes into the audit entry, the rule row is gone afterwards
+        let rule = app_kit
+            .content_filter_service
+            .get_rules()
+            .map_err(|e| WebError::from(e.to_string()))?
+            .into_iter()
+            .find(|rule| rule.id == rule_id)
+            .ok_or(WebError::from("Filter rule not found"))?;
+
+        app_kit
+            .content_filter_service
+            .delete_rule(rule_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::FilterRuleDelete,
+                target: AuditTarget::FilterRule(rule_id),
+                reason: &format!("{}: {}", rule.action, rule.pattern),
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(())
+    })
+    .await?;
+
+    match delete_result {
+        Ok(()) => set_flash_message(&session, FLASH_SUCCESS, "Filter rule removed")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
@@ -0,0 +1,109 @@
+use actix_session::Session;
+use actix_web::{
+    error, get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+
+use crate::{
+    db::WebError,
+    entities::attachment::AttachmentKind,
+    utils::{
+        flash::{set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::redirect_back,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+#[get("/{attachment_id}")]
+pub async fn view_attachment_route(
+    app_kit: web::Data<AppKit>,
+    path: web::Path<i32>,
+) -> actix_web::Result<impl Responder> {
+    let attachment_id = path.into_inner();
+
+    let (attachment, bytes) = web::block(move || {
+        let attachment = app_kit
+            .attachment_service
+            .get_attachment(attachment_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        // attachments of trashed content are kept for a restore but no longer served
+        let parent_visible = match (attachment.post_id, attachment.comment_id) {
+            (Some(post_id), _) => app_kit.post_service.get_post(post_id).is_ok(),
+            (_, Some(comment_id)) => app_kit.comment_service.get_comment(comment_id).is_ok(),
+            (None, None) => false,
+        };
+
+        if !parent_visible {
+            return Err(WebError::from("Attachment not found"));
+        }
+
+        let bytes = app_kit
+            .attachment_service
+            .get_attachment_file(&attachment)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((attachment, bytes))
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    // only re-encoded images are shown inline, every other file is a download
+    let disposition_type = match attachment.kind == AttachmentKind::Image.as_str() {
+        true => DispositionType::Inline,
+        false => DispositionType::Attachment,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type)
+        .insert_header(ContentDisposition {
+            disposition: disposition_type,
+            parameters: vec![DispositionParam::Filename(attachment.file_name)],
+        })
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("Content-Security-Policy", "default-src 'none'; sandbox"))
+        .body(bytes))
+}
+
+#[post("/delete/{attachment_id}")]
+pub async fn delete_attachment_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let attachment_id = path.into_inner();
+    let session_user = get_session_user(&session)?;
+
+    let delete_attachment_result = web::block(move || {
+        let attachment = app_kit
+            .attachment_service
+            .get_attachment(attachment_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        if attachment.user_id != session_user.id {
+            return Err(WebError::from("User does not own attachment"));
+        }
+
+        app_kit
+            .attachment_service
+            .delete_attachment(attachment.id)
+            .map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?;
+
+    match delete_attachment_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Attachment deleted")?,
+        Err(why) => set_flash_message(
+            &session,
+            FLASH_ERROR,
+            &format!("Failed to delete attachment {}", why),
+        )?,
+    }
+
+    Ok(redirect_back(&req))
+}
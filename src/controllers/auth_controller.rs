@@ -0,0 +1,215 @@
+use actix_session::Session;
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+use crate::{
+    entities::{
+        oauth::{OAuthCallbackQueryString, OAuthSessionState, SESSION_KEY_OAUTH_STATE},
+        user::{user_to_user_public, SESSION_KEY_LOGIN_CHALLENGE, SESSION_KEY_USER},
+    },
+    utils::{
+        csrf::csrf_token_equal,
+        flash::{set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::create_redirect,
+        token::generate_random_token,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+fn flash_error_redirect(
+    session: &Session,
+    message: &str,
+    to_url: &str,
+) -> actix_web::Result<HttpResponse> {
+    set_flash_message(session, FLASH_ERROR, message)?;
+
+    Ok(create_redirect(to_url))
+}
+
+/// Stores a single use state in the session and sends the user to the provider
+async fn start_authorization(
+    app_kit: &AppKit,
+    session: &Session,
+    provider: &str,
+    link_user_id: Option<i32>,
+) -> actix_web::Result<HttpResponse> {
+    let failure_url = match link_user_id {
+        Some(_) => "/users/settings",
+        None => "/users/login",
+    };
+
+    let state = generate_random_token(32);
+
+    let authorization_url = match app_kit
+        .oauth_service
+        .get_authorization_url(provider, &state)
+        .await
+    {
+        Ok(authorization_url) => authorization_url,
+        Err(e) => return flash_error_redirect(session, &e.to_string(), failure_url),
+    };
+
+    session.insert(
+        SESSION_KEY_OAUTH_STATE,
+        OAuthSessionState {
+            state,
+            provider: provider.to_string(),
+            link_user_id,
+        },
+    )?;
+
+    Ok(create_redirect(&authorization_url))
+}
+
+#[get("/{provider}/login")]
+pub async fn auth_provider_login_route(
+    app_kit: web::Data<AppKit>,
+    session: Session,
+    path: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    if get_session_user(&session).is_ok() {
+        return flash_error_redirect(&session, "User already logged in!", "/");
+    }
+
+    start_authorization(&app_kit, &session, &path.into_inner(), None).await
+}
+
+#[post("/{provider}/link")]
+pub async fn auth_provider_link_route(
+    app_kit: web::Data<AppKit>,
+    session: Session,
+    path: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    start_authorization(
+        &app_kit,
+        &session,
+        &path.into_inner(),
+        Some(session_user.id),
+    )
+    .await
+}
+
+#[get("/{provider}/callback")]
+pub async fn auth_provider_callback_route(
+    app_kit: web::Data<AppKit>,
+    session: Session,
+    path: web::Path<String>,
+    query: web::Query<OAuthCallbackQueryString>,
+) -> actix_web::Result<impl Responder> {
+    let provider = path.into_inner();
+
+    // the pending request is single use, whatever the outcome
+    let oauth_state = session
+        .remove_as::<OAuthSessionState>(SESSION_KEY_OAUTH_STATE)
+        .and_then(|r| r.ok())
+        .filter(|s| s.provider == provider)
+        .filter(|s| {
+            query
+                .state
+                .as_deref()
+                .is_some_and(|state| csrf_token_equal(&s.state, state))
+        });
+
+    let oauth_state = match oauth_state {
+        Some(oauth_state) => oauth_state,
+        None => {
+            return flash_error_redirect(
+                &session,
+                "Sign in request expired, please try again",
+                "/users/login",
+            )
+        }
+    };
+
+    let failure_url = match oauth_state.link_user_id {
+        Some(_) => "/users/settings",
+        None => "/users/login",
+    };
+
+    if let Some(error) = &query.error {
+        return flash_error_redirect(
+            &session,
+            &format!("Sign in was cancelled: {}", error),
+            failure_url,
+        );
+    }
+
+    let code = match &query.code {
+        Some(code) => code.clone(),
+        None => return flash_error_redirect(&session, "Missing authorization code", failure_url),
+    };
+
+    let user_info = match app_kit
+        .oauth_service
+        .fetch_user_info(&provider, &code)
+        .await
+    {
+        Ok(user_info) => user_info,
+        Err(e) => return flash_error_redirect(&session, &e.to_string(), failure_url),
+    };
+
+    if let Some(link_user_id) = oauth_state.link_user_id {
+        // the user must still be the one who started linking
+        if get_session_user(&session).map(|u| u.id).ok() != Some(link_user_id) {
+            return flash_error_redirect(&session, "Please log in again", "/users/login");
+        }
+
+        let link_result = web::block(move || {
+            app_kit
+                .user_service
+                .link_user_identity(link_user_id, &provider, &user_info)
+        })
+        .await?;
+
+        match link_result {
+            Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Sign in provider linked")?,
+            Err(e) => set_flash_message(&session, FLASH_ERROR, &e.to_string())?,
+        }
+
+        return Ok(create_redirect("/users/settings"));
+    }
+
+    let login_result = web::block(move || {
+        app_kit
+            .user_service
+            .login_with_identity(&provider, &user_info)
+    })
+    .await?;
+
+    match login_result {
+        Ok(user) => {
+            session.insert(SESSION_KEY_USER, user_to_user_public(&user))?;
+            session.remove(SESSION_KEY_LOGIN_CHALLENGE);
+
+            Ok(create_redirect("/"))
+        }
+
+        Err(e) => flash_error_redirect(&session, &e.to_string(), "/users/login"),
+    }
+}
+
+#[post("/{provider}/unlink")]
+pub async fn auth_provider_unlink_route(
+    app_kit: web::Data<AppKit>,
+    session: Session,
+    path: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let provider = path.into_inner();
+
+    let unlink_result = web::block(move || {
+        app_kit
+            .user_service
+            .unlink_user_identity(session_user.id, &provider)
+    })
+    .await?;
+
+    match unlink_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Sign in provider unlinked")?,
+        Err(e) => set_flash_message(&session, FLASH_ERROR, &e.to_string())?,
+    }
+
+    Ok(create_redirect("/users/settings"))
+}
@@ -0,0 +1,131 @@
+use actix_session::Session;
+use actix_web::{
+    error, post,
+    web::{self},
+    HttpRequest, Responder,
+};
+
+use crate::{
+    entities::bookmark::{BookmarkFormData, BookmarkTarget},
+    utils::{
+        flash::{set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::redirect_back,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+#[post("/{target_kind}/{target_id:\\d+}")]
+pub async fn bookmark_save_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(String, i32)>,
+    form: actix_web_validator::Form<BookmarkFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (target_kind, target_id) = path.into_inner();
+    let target = BookmarkTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown bookmark target"))?;
+
+    let save_result = web::block(move || {
+        app_kit
+            .bookmark_service
+            .save_bookmark(session_user.id, target, &form.folder, &form.note)
+    })
+    .await?;
+
+    match save_result {
+        Ok(_) => set_flash_message(
+            &session,
+            FLASH_SUCCESS,
+            "Saved, find it on the Saved tab of your profile",
+        )?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{target_kind}/{target_id:\\d+}/delete")]
+pub async fn bookmark_remove_target_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(String, i32)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (target_kind, target_id) = path.into_inner();
+    let target = BookmarkTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown bookmark target"))?;
+
+    let remove_result = web::block(move || {
+        app_kit
+            .bookmark_service
+            .remove_target_bookmark(session_user.id, target)
+    })
+    .await?;
+
+    match remove_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Removed from saved")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{bookmark_id:\\d+}/update")]
+pub async fn bookmark_update_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    form: actix_web_validator::Form<BookmarkFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let bookmark_id = path.into_inner();
+
+    let update_result = web::block(move || {
+        app_kit.bookmark_service.update_bookmark(
+            session_user.id,
+            bookmark_id,
+            &form.folder,
+            &form.note,
+        )
+    })
+    .await?;
+
+    match update_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Bookmark updated")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{bookmark_id:\\d+}/delete")]
+pub async fn bookmark_delete_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let bookmark_id = path.into_inner();
+
+    let delete_result = web::block(move || {
+        app_kit
+            .bookmark_service
+            .remove_bookmark(session_user.id, bookmark_id)
+    })
+    .await?;
+
+    match delete_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Removed from saved")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
@@ -1,3 +1,4 @@
+use actix_multipart::form::MultipartForm;
 use actix_session::Session;
 use actix_web::{
     get, post,
@@ -7,37 +8,70 @@ use actix_web::{
 
 use handlebars::Handlebars;
 use serde_json::json;
+use validator::Validate;
 
 use crate::{
     db::WebError,
-    entities::comment::{CreateCommentFormData, UpdateCommentFormData},
+    entities::{
+        attachment::{AttachmentTarget, AttachmentUpload},
+        audit::{AuditAction, AuditRecord, AuditTarget},
+        comment::{CreateCommentMultipartForm, UpdateCommentFormData},
+        revision::{versions_to_revisions_public, RevisionVersion},
+    },
     models::Comment,
+    servers::actix_etc::actix_rate_limit_middleware::RateLimit,
+    services::rate_limit_service::CREATE_COMMENT_RATE_LIMIT_POLICY,
     utils::{
+        audit::record_audit,
+        csrf::handlebars_add_csrf_token,
         flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
         handlebars_helper::update_handlebars_data,
-        http::{create_redirect, redirect_back},
+        http::{create_redirect, get_client_ip, redirect_back},
         session::handlebars_add_user,
-        users::get_session_user,
+        users::{get_session_user, get_user_names, session_user_is_moderator},
     },
     AppKit,
 };
 
-#[post("/create")]
+#[post("/create", wrap = "RateLimit::new(CREATE_COMMENT_RATE_LIMIT_POLICY)")]
 pub async fn create_comment_submit_route(
     app_kit: web::Data<AppKit>,
-    form: actix_web_validator::Form<CreateCommentFormData>,
+    MultipartForm(form): MultipartForm<CreateCommentMultipartForm>,
     session: Session,
 ) -> actix_web::Result<impl Responder> {
     let user = get_session_user(&session).map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let post_id = form.post_id;
+    let form_data = form.to_form_data();
+    form_data
+        .validate()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+
+    let post_id = form_data.post_id;
 
     let result: Result<(Comment, i64), WebError> = web::block(move || {
+        let uploads = AttachmentUpload::from_temp_files(&form.attachments)
+            .map_err(|e| WebError::from(format!("Failed to read uploaded file: {}", e)))?;
+
+        // every upload is checked before anything is written
+        let prepared = app_kit
+            .attachment_service
+            .prepare_attachments(uploads)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
         let comment = app_kit
             .comment_service
-            .create_comment(user.id, form.post_id, &form.body)
+            .create_comment(user.id, form_data.post_id, &form_data.body)
             .map_err(|e| WebError::from(e.to_string()))?;
 
+        if let Err(e) = app_kit.attachment_service.store_attachments(
+            user.id,
+            AttachmentTarget::Comment(comment.id),
+            prepared,
+        ) {
+            let _ = app_kit.comment_service.delete_comment(comment.id);
+            return Err(WebError::from(e.to_string()));
+        }
+
         let target_comment_page = app_kit
             .comment_service
             .get_page_where_comment_at(&comment, 10)
@@ -48,6 +82,15 @@ pub async fn create_comment_submit_route(
     .await?;
 
     match result {
+        // held by the content filter, nobody but moderators can see it yet
+        Ok((comment, _)) if comment.hidden_at.is_some() => {
+            set_flash_message(
+                &session,
+                FLASH_SUCCESS,
+                "Created comment, it will show up once a moderator reviewed it",
+            )?;
+            Ok(create_redirect(&format!("/posts/{}", comment.post_id)))
+        }
         Ok((comment, target_comment_page)) => {
             let redirect_url = format!(
                 "/posts/{}?page={}&per_page={}#{}",
@@ -56,10 +99,14 @@ pub async fn create_comment_submit_route(
             set_flash_message(&session, "success", "Created comment!")?;
             Ok(create_redirect(&redirect_url))
         }
-        Err(_) => {
+        Err(why) => {
             let redirect_url = format!("/posts/{}", post_id);
 
-            set_flash_message(&session, "error", "Error creating comment!")?;
+            set_flash_message(
+                &session,
+                "error",
+                &format!("Error creating comment! {}", why),
+            )?;
 
             Ok(create_redirect(&redirect_url))
         }
@@ -105,6 +152,7 @@ pub async fn update_comment_route(
     update_handlebars_data(&mut data, "comment", json!(comment));
     handle_flash_message(&mut data, &session);
     handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("comments/form", &data).unwrap();
 
@@ -134,7 +182,7 @@ pub async fn update_comment_post_route(
 
         let comment = app_kit
             .comment_service
-            .update_comment(comment.id, &form.body)
+            .update_comment(comment.id, session_user.id, &form.body)
             .map_err(|e| WebError::from(format!("failed to update comment {}", e)))?;
 
         let target_comment_page = app_kit
@@ -201,7 +249,122 @@ pub async fn delete_comment_route(
 
     match delete_comment_result {
         Ok((comment, target_comment_page)) => {
-            set_flash_message(&session, FLASH_SUCCESS, "comment deleted")?;
+            set_flash_message(&session, FLASH_SUCCESS, "comment moved to trash")?;
+
+            let redirect_url = format!(
+                "/posts/{}?page={}&per_page={}#{}",
+                comment.post_id, target_comment_page, 10, comment.id
+            );
+            Ok(create_redirect(&redirect_url))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+
+            Ok(redirect_back(&req))
+        }
+    }
+}
+
+#[get("/{comment_id}/revisions")]
+pub async fn comment_revisions_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let comment_id = path.into_inner();
+    let session_user = get_session_user(&session).ok();
+
+    let (comment, revisions, allow_rollback) = web::block(move || {
+        let comment = app_kit
+            .comment_service
+            .get_comment(comment_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let mut versions: Vec<RevisionVersion> = app_kit
+            .comment_service
+            .get_comment_revisions(comment_id)
+            .map_err(|e| WebError::from(e.to_string()))?
+            .into_iter()
+            .map(RevisionVersion::from)
+            .collect();
+
+        if versions.is_empty() {
+            versions.push(RevisionVersion::from(&comment));
+        }
+
+        let user_names = get_user_names(
+            app_kit.user_service.as_ref(),
+            versions.iter().filter_map(|v| v.user_id),
+        );
+
+        let allow_rollback = session_user
+            .map(|user| session_user_is_moderator(app_kit.user_service.as_ref(), user.id))
+            .unwrap_or(false);
+
+        Ok::<_, WebError>((
+            comment,
+            versions_to_revisions_public(&versions, &user_names),
+            allow_rollback,
+        ))
+    })
+    .await?
+    .map_err(actix_web::error::ErrorNotFound)?;
+
+    let mut data = json!({
+        "parent": "base",
+        "title": format!("Revisions : comment #{}", comment.id),
+        "header": format!("Revisions of comment #{}", comment.id),
+        "back_url": format!("/posts/{}#{}", comment.post_id, comment.id),
+        "rollback_url_prefix": format!("/comments/{}/revisions", comment.id),
+        "allow_rollback": allow_rollback,
+        "revisions": revisions,
+    });
+
+    handle_flash_message(&mut data, &session);
+    let _ = handlebars_add_user(&session, &mut data);
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("revisions/index", &data)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[post("/{comment_id}/revisions/{revision_id}/rollback")]
+pub async fn rollback_comment_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let (comment_id, revision_id) = path.into_inner();
+    let session_user = get_session_user(&session)?;
+
+    let rollback_result: Result<(Comment, i64), WebError> = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can roll back comments"));
+        }
+
+        let comment = app_kit
+            .comment_service
+            .rollback_comment(comment_id, revision_id, session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let target_comment_page = app_kit
+            .comment_service
+            .get_page_where_comment_at(&comment, 10)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok((comment, target_comment_page))
+    })
+    .await?;
+
+    match rollback_result {
+        Ok((comment, target_comment_page)) => {
+            set_flash_message(&session, FLASH_SUCCESS, "Comment rolled back")?;
 
             let redirect_url = format!(
                 "/posts/{}?page={}&per_page={}#{}",
@@ -212,7 +375,104 @@ pub async fn delete_comment_route(
 
         Err(why) => {
             set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+            Ok(redirect_back(&req))
+        }
+    }
+}
+
+#[post("/{comment_id}/restore")]
+pub async fn restore_comment_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let comment_id = path.into_inner();
+    let session_user = get_session_user(&session)?;
+    let client_ip = get_client_ip(&req);
+
+    let restore_result: Result<(Comment, Option<i64>), WebError> = web::block(move || {
+        let comment = app_kit
+            .comment_service
+            .get_deleted_comment(comment_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
 
+        // authors get a grace period, moderators can restore until the comment is purged,
+        // a comment held by a moderator is only theirs to restore
+        let is_moderator =
+            session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id);
+        let author_can_restore = comment.user_id == session_user.id
+            && comment.hidden_at.is_none()
+            && comment
+                .deleted_at
+                .is_some_and(|deleted_at| app_kit.trash_policy.author_can_restore(deleted_at));
+
+        if !is_moderator && !author_can_restore {
+            return Err(WebError::from("Comment can no longer be restored"));
+        }
+
+        app_kit
+            .comment_service
+            .restore_comment(comment.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        if is_moderator && comment.hidden_at.is_some() {
+            app_kit
+                .comment_service
+                .set_comment_hidden(comment.id, false)
+                .map_err(|e| WebError::from(e.to_string()))?;
+        }
+
+        if comment.user_id != session_user.id {
+            record_audit(
+                app_kit.audit_service.as_ref(),
+                AuditRecord {
+                    actor_user_id: session_user.id,
+                    action: AuditAction::CommentRestore,
+                    target: AuditTarget::Comment(comment.id),
+                    reason: "",
+                    ip_address: &client_ip,
+                },
+            );
+        }
+
+        // the post of the comment may still be in the trash itself
+        let target_comment_page = match app_kit.post_service.get_post(comment.post_id) {
+            Ok(_) => Some(
+                app_kit
+                    .comment_service
+                    .get_page_where_comment_at(&comment, 10)
+                    .map_err(|e| WebError::from(e.to_string()))?,
+            ),
+            Err(_) => None,
+        };
+
+        Ok((comment, target_comment_page))
+    })
+    .await?;
+
+    match restore_result {
+        Ok((comment, Some(target_comment_page))) => {
+            set_flash_message(&session, FLASH_SUCCESS, "Comment restored")?;
+
+            let redirect_url = format!(
+                "/posts/{}?page={}&per_page={}#{}",
+                comment.post_id, target_comment_page, 10, comment.id
+            );
+            Ok(create_redirect(&redirect_url))
+        }
+
+        Ok((_, None)) => {
+            set_flash_message(
+                &session,
+                FLASH_SUCCESS,
+                "Comment restored, its post is still in the trash",
+            )?;
+            Ok(redirect_back(&req))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
             Ok(redirect_back(&req))
         }
     }
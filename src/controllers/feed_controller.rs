@@ -0,0 +1,217 @@
+use actix_web::{
+    error, get,
+    http::header::{ETag, LastModified},
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::{
+        comment::CommentPublic,
+        feed::{Feed, FeedEntry, FeedFormat, FEED_ENTRY_LIMIT},
+    },
+    services::comment_service::{CommentService, CommentServiceError},
+    utils::{
+        http::{body_etag, get_app_url, is_not_modified, to_http_date},
+        pagination::QueryPagination,
+    },
+    AppKit,
+};
+
+fn feed_pagination(page: i64) -> QueryPagination {
+    QueryPagination {
+        page,
+        limit: FEED_ENTRY_LIMIT,
+        ..Default::default()
+    }
+}
+
+/// The newest comments of a post, newest first, comments are listed oldest first so the
+/// last two pages hold them
+fn get_latest_comments(
+    comment_service: &dyn CommentService,
+    post_id: i32,
+) -> Result<Vec<CommentPublic>, CommentServiceError> {
+    let first_page = comment_service.get_comments_with_user(post_id, &feed_pagination(1))?;
+
+    let mut comments = match first_page.total > FEED_ENTRY_LIMIT {
+        false => first_page.comments,
+        true => {
+            let last_page = (first_page.total + FEED_ENTRY_LIMIT - 1) / FEED_ENTRY_LIMIT;
+
+            let mut comments = comment_service
+                .get_comments_with_user(post_id, &feed_pagination(last_page - 1))?
+                .comments;
+            comments.extend(
+                comment_service
+                    .get_comments_with_user(post_id, &feed_pagination(last_page))?
+                    .comments,
+            );
+
+            let skip = comments.len().saturating_sub(FEED_ENTRY_LIMIT as usize);
+            comments.split_off(skip)
+        }
+    };
+
+    comments.reverse();
+    Ok(comments)
+}
+
+/// Renders a feed, or answers 304 when the client copy is current
+fn feed_response(
+    req: &HttpRequest,
+    hb: &Handlebars<'_>,
+    format: FeedFormat,
+    feed: &Feed,
+) -> actix_web::Result<HttpResponse> {
+    let body = hb
+        .render(format.template(), &json!({ "feed": feed }))
+        .map_err(error::ErrorInternalServerError)?;
+
+    let etag = body_etag(body.as_bytes());
+    let last_modified = feed.updated_at.map(to_http_date);
+
+    let not_modified = is_not_modified(req, &etag, last_modified);
+
+    let mut response = match not_modified {
+        true => HttpResponse::NotModified(),
+        false => HttpResponse::Ok(),
+    };
+    response.insert_header(ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(last_modified));
+    }
+
+    match not_modified {
+        true => Ok(response.finish()),
+        false => Ok(response.content_type(format.content_type()).body(body)),
+    }
+}
+
+#[get("/feed.{format}")]
+pub async fn feed_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(String,)>,
+) -> actix_web::Result<impl Responder> {
+    let format = FeedFormat::parse(&path.into_inner().0)
+        .ok_or(error::ErrorNotFound("Unknown feed format"))?;
+    let app_url = get_app_url(&req);
+
+    let posts = web::block(move || {
+        // pinned posts are listed apart on the index, the feed only cares about age
+        let mut posts = app_kit
+            .post_service
+            .get_posts_with_user(&feed_pagination(1))
+            .map_err(|e| WebError::from(e.to_string()))?
+            .posts;
+        posts.extend(
+            app_kit
+                .post_service
+                .get_pinned_posts_with_user()
+                .map_err(|e| WebError::from(e.to_string()))?,
+        );
+
+        posts.sort_by_key(|post| std::cmp::Reverse(post.post.created_at));
+        posts.truncate(FEED_ENTRY_LIMIT as usize);
+
+        Ok::<_, WebError>(posts)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let feed = Feed::new(
+        "RustForum".to_string(),
+        format!("{}/", app_url),
+        format!("{}{}", app_url, req.path()),
+        posts
+            .iter()
+            .map(|post| FeedEntry::from_post(&app_url, post))
+            .collect(),
+    );
+
+    feed_response(&req, &hb, format, &feed)
+}
+
+#[get("/{user_id}/feed.{format}")]
+pub async fn profile_feed_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(i32, String)>,
+) -> actix_web::Result<impl Responder> {
+    let (user_id, format) = path.into_inner();
+    let format = FeedFormat::parse(&format).ok_or(error::ErrorNotFound("Unknown feed format"))?;
+    let app_url = get_app_url(&req);
+
+    let (user, posts) = web::block(move || {
+        let user = app_kit
+            .user_service
+            .get_user_by_id_public(user_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let posts = app_kit
+            .post_service
+            .get_posts_by_user(user.id, &feed_pagination(1))
+            .map_err(|e| WebError::from(e.to_string()))?
+            .posts;
+
+        Ok::<_, WebError>((user, posts))
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    let feed = Feed::new(
+        format!("Posts by {} - RustForum", user.name),
+        format!("{}/profile/{}", app_url, user.id),
+        format!("{}{}", app_url, req.path()),
+        posts
+            .iter()
+            .map(|post| FeedEntry::from_post(&app_url, post))
+            .collect(),
+    );
+
+    feed_response(&req, &hb, format, &feed)
+}
+
+#[get("/{post_id}/feed.{format}")]
+pub async fn post_comments_feed_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(i32, String)>,
+) -> actix_web::Result<impl Responder> {
+    let (post_id, format) = path.into_inner();
+    let format = FeedFormat::parse(&format).ok_or(error::ErrorNotFound("Unknown feed format"))?;
+    let app_url = get_app_url(&req);
+
+    let (post, comments) = web::block(move || {
+        let post = app_kit
+            .post_service
+            .get_post_with_user(post_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let comments = get_latest_comments(app_kit.comment_service.as_ref(), post.post.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((post, comments))
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    let feed = Feed::new(
+        format!("Comments on {} - RustForum", post.post.title),
+        format!("{}/posts/{}", app_url, post.post.id),
+        format!("{}{}", app_url, req.path()),
+        comments
+            .iter()
+            .map(|comment| FeedEntry::from_comment(&app_url, comment, &post.post.title))
+            .collect(),
+    );
+
+    feed_response(&req, &hb, format, &feed)
+}
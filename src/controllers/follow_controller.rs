@@ -0,0 +1,178 @@
+use actix_session::Session;
+use actix_web::{
+    error, get, post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    handlebars_helper::pagination::{
+        add_pagination_link_header, build_handlebars_pagination_result,
+        pagination_out_of_range_redirect,
+    },
+    utils::{
+        flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::redirect_back,
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+#[get("/following")]
+pub async fn following_posts_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    session: Session,
+    pagination: QueryPagination,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let pagination_clone = pagination.clone();
+    let posts_result = web::block(move || {
+        app_kit
+            .follow_service
+            .get_followed_posts(session_user.id, &pagination_clone)
+            .map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let pagination_result = build_handlebars_pagination_result(posts_result.total, &pagination);
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Following",
+        "following": true,
+        "posts_result": posts_result,
+        "pagination_result": pagination_result,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+
+    let body = hb
+        .render("posts/index", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.body(body))
+}
+
+#[post("/{user_id}/follow")]
+pub async fn profile_follow_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let followed_user_id = path.into_inner();
+
+    let follow_result = web::block(move || {
+        app_kit
+            .follow_service
+            .follow_user(session_user.id, followed_user_id)
+    })
+    .await?;
+
+    match follow_result {
+        Ok(_) => set_flash_message(
+            &session,
+            FLASH_SUCCESS,
+            "Followed, their posts show up on the Following tab",
+        )?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{user_id}/unfollow")]
+pub async fn profile_unfollow_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let followed_user_id = path.into_inner();
+
+    let unfollow_result = web::block(move || {
+        app_kit
+            .follow_service
+            .unfollow_user(session_user.id, followed_user_id)
+    })
+    .await?;
+
+    match unfollow_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Unfollowed")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{post_id}/subscribe")]
+pub async fn post_subscribe_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let post_id = path.into_inner();
+
+    let subscribe_result = web::block(move || {
+        app_kit
+            .follow_service
+            .subscribe_post(session_user.id, post_id)
+    })
+    .await?;
+
+    match subscribe_result {
+        Ok(_) => set_flash_message(
+            &session,
+            FLASH_SUCCESS,
+            "Subscribed, you will be notified of new comments",
+        )?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{post_id}/unsubscribe")]
+pub async fn post_unsubscribe_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let post_id = path.into_inner();
+
+    let unsubscribe_result = web::block(move || {
+        app_kit
+            .follow_service
+            .unsubscribe_post(session_user.id, post_id)
+    })
+    .await?;
+
+    match unsubscribe_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Unsubscribed")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
@@ -0,0 +1,135 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use actix_session::Session;
+use actix_web::{
+    error, get,
+    http::header::{CacheControl, CacheDirective},
+    rt::time::timeout,
+    web::{self, Bytes},
+    HttpResponse, Responder,
+};
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{
+    db::WebError,
+    entities::live::{LiveChannel, LiveEvent, LIVE_KEEP_ALIVE_SECONDS},
+    utils::users::get_session_user,
+    AppKit,
+};
+
+/// Server-sent events of a channel, led by `first_events` and kept open with comments while
+/// nothing happens. The stream ends after `LiveEvent::PostDeleted`, nothing follows it
+fn live_event_stream(
+    receiver: Receiver<LiveEvent>,
+    first_events: Vec<LiveEvent>,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let events = stream::unfold(Some(receiver), |receiver| async move {
+        let mut receiver = receiver?;
+        loop {
+            match timeout(
+                Duration::from_secs(LIVE_KEEP_ALIVE_SECONDS),
+                receiver.recv(),
+            )
+            .await
+            {
+                Ok(Ok(event)) => {
+                    // a deleted post has nothing more to tell its readers
+                    let receiver = match event {
+                        LiveEvent::PostDeleted => None,
+                        _ => Some(receiver),
+                    };
+                    return Some((event.to_event_stream(), receiver));
+                }
+                // a reader too slow to keep up skips the events it missed
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                Ok(Err(RecvError::Closed)) => return None,
+                // quiet for a while, a comment keeps proxies from closing the connection
+                Err(_) => return Some((": keep-alive\n\n".to_string(), Some(receiver))),
+            }
+        }
+    });
+
+    stream::iter(
+        first_events
+            .into_iter()
+            .map(|event| event.to_event_stream()),
+    )
+    .chain(events)
+    .map(|chunk| Ok(Bytes::from(chunk)))
+}
+
+fn live_event_response(
+    receiver: Receiver<LiveEvent>,
+    first_events: Vec<LiveEvent>,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        // proxies buffering the response would hold the events back
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(live_event_stream(receiver, first_events))
+}
+
+#[get("/{post_id}/events")]
+pub async fn post_events_route(
+    app_kit: web::Data<AppKit>,
+    path: web::Path<i32>,
+) -> actix_web::Result<impl Responder> {
+    let post_id = path.into_inner();
+
+    let app_kit_clone = app_kit.clone();
+    web::block(move || {
+        app_kit_clone
+            .post_service
+            .get_post(post_id)
+            .map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    let receiver = app_kit.live_service.subscribe(LiveChannel::Post(post_id));
+
+    Ok(live_event_response(receiver, vec![]))
+}
+
+#[get("/events")]
+pub async fn user_events_route(
+    app_kit: web::Data<AppKit>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    // subscribed before counting so no message or notification slips in between
+    let receiver = app_kit
+        .live_service
+        .subscribe(LiveChannel::User(session_user.id));
+
+    let (unread_messages, unread_notifications) = web::block(move || {
+        let unread_messages = app_kit
+            .message_service
+            .count_unread_messages(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let unread_notifications = app_kit
+            .notification_service
+            .count_unread_notifications(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((unread_messages, unread_notifications))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(live_event_response(
+        receiver,
+        vec![
+            LiveEvent::UnreadMessages {
+                count: unread_messages,
+            },
+            LiveEvent::UnreadNotifications {
+                count: unread_notifications,
+            },
+        ],
+    ))
+}
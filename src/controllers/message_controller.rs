@@ -0,0 +1,353 @@
+use actix_session::Session;
+use actix_web::{
+    error, get, post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::message::{MessageFormData, NewConversationFormData, NewConversationQuery},
+    handlebars_helper::pagination::{
+        add_pagination_link_header, build_handlebars_pagination_result,
+        pagination_out_of_range_redirect,
+    },
+    servers::actix_etc::actix_rate_limit_middleware::RateLimit,
+    services::rate_limit_service::SEND_MESSAGE_RATE_LIMIT_POLICY,
+    utils::{
+        csrf::handlebars_add_csrf_token,
+        flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        handlebars_helper::update_handlebars_data,
+        http::{create_redirect, redirect_back},
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+// #[get("/messages")]
+pub async fn messages_inbox_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    session: Session,
+    pagination: QueryPagination,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let pagination_clone = pagination.clone();
+    let (inbox, unread_total, blocked_users) = web::block(move || {
+        let message_service = &app_kit.message_service;
+
+        let inbox = message_service
+            .get_inbox(session_user.id, &pagination_clone)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let unread_total = message_service
+            .count_unread_messages(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let blocked_users = message_service
+            .get_blocked_users(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((inbox, unread_total, blocked_users))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let pagination_result = build_handlebars_pagination_result(inbox.total, &pagination);
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Messages",
+        "inbox": inbox,
+        "unread_total": unread_total,
+        "blocked_users": blocked_users,
+        "pagination_result": pagination_result,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("messages/inbox", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.body(body))
+}
+
+#[get("/new")]
+pub async fn messages_new_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    session: Session,
+    query: web::Query<NewConversationQuery>,
+) -> actix_web::Result<impl Responder> {
+    get_session_user(&session)?;
+
+    // a recipient picked from a profile is shown by name, the field keeps taking ids
+    let recipient = match query.to {
+        Some(user_id) => web::block(move || app_kit.user_service.get_user_by_id_public(user_id))
+            .await?
+            .ok(),
+        None => None,
+    };
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "New message",
+        "recipient": recipient,
+        "recipients": query.to.map(|user_id| user_id.to_string()).unwrap_or_default(),
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("messages/new", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[post("/new", wrap = "RateLimit::new(SEND_MESSAGE_RATE_LIMIT_POLICY)")]
+pub async fn messages_new_submit_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    form: actix_web_validator::Form<NewConversationFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let recipient_ids = form.recipient_ids().ok_or(error::ErrorBadRequest(
+        "Recipients are profile numbers separated by commas",
+    ))?;
+
+    let start_result = web::block(move || {
+        app_kit.message_service.start_conversation(
+            session_user.id,
+            &recipient_ids,
+            &form.title,
+            &form.body,
+        )
+    })
+    .await?;
+
+    match start_result {
+        Ok(conversation) => {
+            set_flash_message(&session, FLASH_SUCCESS, "Message sent")?;
+            Ok(create_redirect(&format!("/messages/{}", conversation.id)))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+            Ok(redirect_back(&req))
+        }
+    }
+}
+
+#[get("/{conversation_id}")]
+pub async fn messages_conversation_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<i32>,
+    session: Session,
+    pagination: QueryPagination,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let conversation_id = path.into_inner();
+
+    let pagination_clone = pagination.clone();
+    let (conversation, messages) = web::block(move || {
+        let message_service = &app_kit.message_service;
+
+        let conversation = message_service
+            .get_conversation(conversation_id, session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let messages = message_service
+            .get_messages(conversation_id, session_user.id, &pagination_clone)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        // the newest messages are on the first page
+        if pagination_clone.page == 1 {
+            message_service
+                .mark_conversation_read(conversation_id, session_user.id)
+                .map_err(|e| WebError::from(e.to_string()))?;
+        }
+
+        Ok::<_, WebError>((conversation, messages))
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    let pagination_result = build_handlebars_pagination_result(messages.total, &pagination);
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    let mut data = json!({ "parent": "base" });
+    update_handlebars_data(&mut data, "title", json!(conversation.display_title));
+    update_handlebars_data(&mut data, "conversation", json!(conversation));
+    update_handlebars_data(&mut data, "messages_result", json!(messages));
+    update_handlebars_data(&mut data, "pagination_result", json!(pagination_result));
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("messages/conversation", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.body(body))
+}
+
+#[post(
+    "/{conversation_id}",
+    wrap = "RateLimit::new(SEND_MESSAGE_RATE_LIMIT_POLICY)"
+)]
+pub async fn messages_send_route(
+    app_kit: web::Data<AppKit>,
+    path: web::Path<i32>,
+    form: actix_web_validator::Form<MessageFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let conversation_id = path.into_inner();
+
+    let send_result = web::block(move || {
+        app_kit
+            .message_service
+            .send_message(conversation_id, session_user.id, &form.body)
+    })
+    .await?;
+
+    if let Err(why) = send_result {
+        set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+    }
+
+    Ok(create_redirect(&format!("/messages/{}", conversation_id)))
+}
+
+#[post("/{conversation_id}/messages/{message_id}/delete")]
+pub async fn messages_delete_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let (conversation_id, message_id) = path.into_inner();
+
+    let delete_result = web::block(move || {
+        app_kit
+            .message_service
+            .delete_message(conversation_id, message_id, session_user.id)
+    })
+    .await?;
+
+    match delete_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Message deleted")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/{conversation_id}/leave")]
+pub async fn messages_leave_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let conversation_id = path.into_inner();
+
+    let leave_result = web::block(move || {
+        app_kit
+            .message_service
+            .leave_conversation(conversation_id, session_user.id)
+    })
+    .await?;
+
+    match leave_result {
+        Ok(_) => {
+            set_flash_message(&session, FLASH_SUCCESS, "You left the conversation")?;
+            Ok(create_redirect("/messages"))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+            Ok(redirect_back(&req))
+        }
+    }
+}
+
+#[post("/block/{user_id}")]
+pub async fn messages_block_user_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let blocked_user_id = path.into_inner();
+
+    let block_result = web::block(move || {
+        app_kit
+            .message_service
+            .block_user(session_user.id, blocked_user_id)
+    })
+    .await?;
+
+    match block_result {
+        Ok(_) => set_flash_message(
+            &session,
+            FLASH_SUCCESS,
+            "Blocked, they can no longer message you",
+        )?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/unblock/{user_id}")]
+pub async fn messages_unblock_user_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let blocked_user_id = path.into_inner();
+
+    let unblock_result = web::block(move || {
+        app_kit
+            .message_service
+            .unblock_user(session_user.id, blocked_user_id)
+    })
+    .await?;
+
+    match unblock_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Unblocked")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
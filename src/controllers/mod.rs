@@ -1,4 +1,17 @@
 pub mod user_controller;
 pub mod profile_controller;
+pub mod trash_controller;
 pub mod post_controller;
 pub mod comment_controller;
+pub mod auth_controller;
+pub mod attachment_controller;
+pub mod report_controller;
+pub mod moderation_controller;
+pub mod admin_controller;
+pub mod feed_controller;
+pub mod sitemap_controller;
+pub mod message_controller;
+pub mod live_controller;
+pub mod follow_controller;
+pub mod bookmark_controller;
+pub mod notification_controller;
@@ -0,0 +1,507 @@
+use actix_session::Session;
+use actix_web::{
+    error, get,
+    http::header::ContentType,
+    post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::{
+        audit::{AuditAction, AuditRecord, AuditTarget},
+        ban::{ban_until_from_days, ActiveBan, BanFormData, UserBanPublic, BAN_DURATION_DAYS},
+        report::{ModerationActionFormData, ReportTarget},
+        user::USER_ROLES,
+    },
+    handlebars_helper::pagination::{
+        add_pagination_link_header, build_handlebars_pagination_result,
+        pagination_out_of_range_redirect,
+    },
+    services::report_service::ReportServiceError,
+    utils::{
+        audit::record_audit,
+        csrf::handlebars_add_csrf_token,
+        flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::{create_redirect, get_client_ip, redirect_back},
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::{
+            get_session_user, get_user_names, session_user_is_admin, session_user_is_moderator,
+        },
+    },
+    AppKit,
+};
+
+/// The ban lengths offered in ban forms
+fn ban_durations_data() -> serde_json::Value {
+    BAN_DURATION_DAYS
+        .iter()
+        .map(|(days, label)| json!({ "days": days, "label": label }))
+        .collect()
+}
+
+#[get("/reports")]
+pub async fn moderation_reports_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    pagination: QueryPagination,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (queue, pagination_result, is_admin) = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can see the report queue"));
+        }
+        let is_admin = session_user_is_admin(app_kit.user_service.as_ref(), session_user.id);
+
+        let queue = app_kit
+            .report_service
+            .get_report_queue(&pagination)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let pagination_result = build_handlebars_pagination_result(queue.total, &pagination);
+
+        Ok((queue, pagination_result, is_admin))
+    })
+    .await?
+    .map_err(error::ErrorForbidden)?;
+
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Report queue",
+        "queue": queue,
+        "pagination_result": pagination_result,
+        "ban_durations": ban_durations_data(),
+        "is_admin": is_admin,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("moderation/reports", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.content_type(ContentType::html()).body(body))
+}
+
+#[post("/reports/{target_kind}/{target_id}/ban")]
+pub async fn moderation_report_ban_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(String, i32)>,
+    form: actix_web_validator::Form<BanFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (target_kind, target_id) = path.into_inner();
+    let target = ReportTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown report target"))?;
+    let banned_until = ban_until_from_days(form.duration_days)
+        .ok_or(error::ErrorBadRequest("Unknown ban duration"))?;
+    let client_ip = get_client_ip(&req);
+
+    let ban_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can ban users"));
+        }
+
+        let target_user_id = app_kit
+            .report_service
+            .get_target_user_id(target)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        app_kit
+            .user_service
+            .ban_user(target_user_id, session_user.id, banned_until, &form.reason)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::UserBan,
+                target: AuditTarget::User(target_user_id),
+                reason: &form.reason,
+                ip_address: &client_ip,
+            },
+        );
+
+        // the content may have been deleted first, which already closed its reports
+        match app_kit
+            .report_service
+            .resolve_reports_with_ban(target, session_user.id)
+        {
+            Ok(_) => {
+                record_audit(
+                    app_kit.audit_service.as_ref(),
+                    AuditRecord {
+                        actor_user_id: session_user.id,
+                        action: AuditAction::ReportBanUser,
+                        target: target.into(),
+                        reason: &form.reason,
+                        ip_address: &client_ip,
+                    },
+                );
+                Ok(())
+            }
+            Err(ReportServiceError::ErrorNoOpenReports) => Ok(()),
+            Err(e) => Err(WebError::from(e.to_string())),
+        }
+    })
+    .await?;
+
+    match ban_result {
+        Ok(()) => set_flash_message(&session, FLASH_SUCCESS, "User banned")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/reports/{target_kind}/{target_id}/{action}")]
+pub async fn moderation_report_action_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(String, i32, String)>,
+    form: actix_web_validator::Form<ModerationActionFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (target_kind, target_id, action) = path.into_inner();
+    let target = ReportTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown report target"))?;
+    let client_ip = get_client_ip(&req);
+
+    let action_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can resolve reports"));
+        }
+
+        let report_service = &app_kit.report_service;
+
+        let (result, audit_actions) = match action.as_str() {
+            "dismiss" => (
+                report_service
+                    .dismiss_reports(target, session_user.id)
+                    .map(|_| "Reports dismissed"),
+                vec![AuditAction::ReportDismiss],
+            ),
+            "delete" => (
+                report_service
+                    .delete_reported_content(target, session_user.id)
+                    .map(|_| "Content moved to trash"),
+                match target {
+                    ReportTarget::Post(_) => {
+                        vec![AuditAction::ReportDeleteContent, AuditAction::PostDelete]
+                    }
+                    ReportTarget::Comment(_) => {
+                        vec![AuditAction::ReportDeleteContent, AuditAction::CommentDelete]
+                    }
+                    ReportTarget::User(_) => vec![AuditAction::ReportDeleteContent],
+                },
+            ),
+            "warn" => (
+                report_service
+                    .warn_user(target, session_user.id, &form.message)
+                    .map(|_| "User warned"),
+                vec![AuditAction::ReportWarnUser],
+            ),
+            _ => return Err(WebError::from("Unknown moderation action")),
+        };
+
+        let message = result.map_err(|e| WebError::from(e.to_string()))?;
+
+        for audit_action in audit_actions {
+            record_audit(
+                app_kit.audit_service.as_ref(),
+                AuditRecord {
+                    actor_user_id: session_user.id,
+                    action: audit_action,
+                    target: target.into(),
+                    reason: &form.message,
+                    ip_address: &client_ip,
+                },
+            );
+        }
+
+        Ok(message)
+    })
+    .await?;
+
+    match action_result {
+        Ok(message) => set_flash_message(&session, FLASH_SUCCESS, message)?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[post("/posts/{post_id}/{action}")]
+pub async fn moderation_post_action_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32, String)>,
+    form: actix_web_validator::Form<ModerationActionFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (post_id, action) = path.into_inner();
+    let client_ip = get_client_ip(&req);
+
+    let action_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can pin and lock posts"));
+        }
+
+        let post_service = &app_kit.post_service;
+
+        let (result, audit_action) = match action.as_str() {
+            "pin" => (
+                post_service
+                    .set_post_pinned(post_id, true)
+                    .map(|_| "Post pinned"),
+                AuditAction::PostPin,
+            ),
+            "unpin" => (
+                post_service
+                    .set_post_pinned(post_id, false)
+                    .map(|_| "Post unpinned"),
+                AuditAction::PostUnpin,
+            ),
+            "lock" => (
+                post_service
+                    .set_post_locked(post_id, true)
+                    .map(|_| "Post locked"),
+                AuditAction::PostLock,
+            ),
+            "unlock" => (
+                post_service
+                    .set_post_locked(post_id, false)
+                    .map(|_| "Post unlocked"),
+                AuditAction::PostUnlock,
+            ),
+            _ => return Err(WebError::from("Unknown moderation action")),
+        };
+
+        let message = result.map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: audit_action,
+                target: AuditTarget::Post(post_id),
+                reason: &form.message,
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(message)
+    })
+    .await?;
+
+    match action_result {
+        Ok(message) => set_flash_message(&session, FLASH_SUCCESS, message)?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
+
+#[get("/users/{user_id}/ban")]
+pub async fn moderation_ban_user_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(i32,)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let user_id = path.into_inner().0;
+
+    let (target_user, active_ban, bans, is_admin) = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can ban users"));
+        }
+        let is_admin = session_user_is_admin(app_kit.user_service.as_ref(), session_user.id);
+
+        let target_user = app_kit
+            .user_service
+            .get_user_by_id(user_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let user_bans = app_kit
+            .user_service
+            .get_user_bans(user_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let moderator_names = get_user_names(
+            app_kit.user_service.as_ref(),
+            user_bans.iter().filter_map(|b| b.moderator_user_id),
+        );
+
+        let bans: Vec<UserBanPublic> = user_bans
+            .iter()
+            .map(|user_ban| {
+                let moderator_name = user_ban
+                    .moderator_user_id
+                    .and_then(|id| moderator_names.get(&id).cloned())
+                    .unwrap_or_default();
+                UserBanPublic::new(user_ban, moderator_name)
+            })
+            .collect();
+
+        let active_ban = ActiveBan::of_user(&target_user).map(|ban| {
+            json!({
+                "until_human": ban.until_human(),
+                "reason": ban.reason,
+            })
+        });
+
+        Ok((target_user, active_ban, bans, is_admin))
+    })
+    .await?
+    .map_err(error::ErrorForbidden)?;
+
+    let mut data = json!({
+        "parent": "base",
+        "title": format!("Ban {}", target_user.name),
+        "target_user": {
+            "id": target_user.id,
+            "name": target_user.name,
+            "role": target_user.role,
+        },
+        "active_ban": active_ban,
+        "bans": bans,
+        "ban_durations": ban_durations_data(),
+        "is_admin": is_admin,
+        "user_roles": USER_ROLES,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("moderation/ban", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+#[post("/users/{user_id}/ban")]
+pub async fn moderation_ban_user_submit_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32,)>,
+    form: actix_web_validator::Form<BanFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let user_id = path.into_inner().0;
+
+    let banned_until = ban_until_from_days(form.duration_days)
+        .ok_or(error::ErrorBadRequest("Unknown ban duration"))?;
+    let client_ip = get_client_ip(&req);
+
+    let ban_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can ban users"));
+        }
+
+        app_kit
+            .user_service
+            .ban_user(user_id, session_user.id, banned_until, &form.reason)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::UserBan,
+                target: AuditTarget::User(user_id),
+                reason: &form.reason,
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(())
+    })
+    .await?;
+
+    match ban_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "User banned")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(create_redirect(&format!(
+        "/moderation/users/{}/ban",
+        user_id
+    )))
+}
+
+#[post("/users/{user_id}/unban")]
+pub async fn moderation_unban_user_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32,)>,
+    form: actix_web_validator::Form<ModerationActionFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let user_id = path.into_inner().0;
+    let client_ip = get_client_ip(&req);
+
+    let unban_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can lift bans"));
+        }
+
+        app_kit
+            .user_service
+            .unban_user(user_id, session_user.id, &form.message)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        record_audit(
+            app_kit.audit_service.as_ref(),
+            AuditRecord {
+                actor_user_id: session_user.id,
+                action: AuditAction::UserUnban,
+                target: AuditTarget::User(user_id),
+                reason: &form.message,
+                ip_address: &client_ip,
+            },
+        );
+
+        Ok(())
+    })
+    .await?;
+
+    match unban_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Ban lifted")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(create_redirect(&format!(
+        "/moderation/users/{}/ban",
+        user_id
+    )))
+}
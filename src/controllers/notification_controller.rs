@@ -0,0 +1,119 @@
+use actix_session::Session;
+use actix_web::{
+    error, get, post,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::notification::notification_url,
+    handlebars_helper::pagination::{
+        add_pagination_link_header, build_handlebars_pagination_result,
+        pagination_out_of_range_redirect,
+    },
+    utils::{
+        csrf::handlebars_add_csrf_token,
+        flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
+        http::{create_redirect, redirect_back},
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+// #[get("/notifications")]
+pub async fn notifications_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    session: Session,
+    pagination: QueryPagination,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let pagination_clone = pagination.clone();
+    let (notifications, unread_total) = web::block(move || {
+        let notification_service = &app_kit.notification_service;
+
+        let notifications = notification_service
+            .get_notifications(session_user.id, &pagination_clone)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let unread_total = notification_service
+            .count_unread_notifications(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((notifications, unread_total))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let pagination_result = build_handlebars_pagination_result(notifications.total, &pagination);
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    let mut data = json!({
+        "parent": "base",
+        "title": "Notifications",
+        "notifications": notifications,
+        "unread_total": unread_total,
+        "pagination_result": pagination_result,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("notifications/index", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.body(body))
+}
+
+/// Marks a notification read and opens the comment it is about
+#[get("/{notification_id:\\d+}")]
+pub async fn notification_open_route(
+    app_kit: web::Data<AppKit>,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let notification_id = path.into_inner();
+
+    let notification = web::block(move || {
+        app_kit
+            .notification_service
+            .read_notification(session_user.id, notification_id)
+    })
+    .await?
+    .map_err(error::ErrorNotFound)?;
+
+    Ok(create_redirect(&notification_url(&notification)))
+}
+
+#[post("/read")]
+pub async fn notifications_mark_read_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let mark_result =
+        web::block(move || app_kit.notification_service.mark_all_read(session_user.id)).await?;
+
+    match mark_result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "All notifications marked read")?,
+        Err(why) => set_flash_message(&session, FLASH_ERROR, &why.to_string())?,
+    }
+
+    Ok(redirect_back(&req))
+}
@@ -1,27 +1,44 @@
+use actix_multipart::form::MultipartForm;
 use actix_session::Session;
 use actix_web::{
-    get, post,
+    get,
+    http::header,
+    post,
     web::{self},
     HttpRequest, HttpResponse, Responder,
 };
 
 use handlebars::Handlebars;
 use serde_json::json;
+use validator::Validate;
 
-use crate::handlebars_helper::pagination::build_handlebars_pagination_result;
+use crate::handlebars_helper::pagination::{
+    add_pagination_link_header, build_handlebars_pagination_result,
+    pagination_out_of_range_redirect,
+};
+use crate::servers::actix_etc::actix_rate_limit_middleware::RateLimit;
+use crate::services::{
+    post_service::PostServiceError, rate_limit_service::CREATE_POST_RATE_LIMIT_POLICY,
+};
 use crate::{
     db::WebError,
     entities::{
-        comment::ListCommentResult,
-        post::{PostFormData, PostPublic},
+        attachment::{AttachmentPublic, AttachmentTarget, AttachmentUpload},
+        audit::{AuditAction, AuditRecord, AuditTarget},
+        bookmark::BookmarkTarget,
+        post::{parse_post_path_segment, post_path, CreatePostMultipartForm, PostFormData},
+        revision::{versions_to_revisions_public, RevisionVersion},
     },
     utils::{
+        audit::record_audit,
+        csrf::handlebars_add_csrf_token,
         flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
         handlebars_helper::update_handlebars_data,
-        http::{create_redirect, redirect_back},
+        http::{create_redirect, get_client_ip, redirect_back},
         pagination::QueryPagination,
+        seo::{handlebars_add_page_meta, OpenGraphType, PageMeta},
         session::handlebars_add_user,
-        users::get_session_user,
+        users::{get_session_user, get_user_names, session_user_is_moderator},
     },
     AppKit,
 };
@@ -37,44 +54,87 @@ pub async fn create_post_route(
         "form_action": "/posts/create",
         "form_header": "Create new post",
         "form_submit_button_text": "Create",
+        "allow_attachments": true,
     });
 
     handle_flash_message(&mut data, &session);
     handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("posts/form", &data).unwrap();
 
     Ok(HttpResponse::Ok().body(body))
 }
 
-#[post("/create")]
+#[post("/create", wrap = "RateLimit::new(CREATE_POST_RATE_LIMIT_POLICY)")]
 pub async fn create_post_submit_route(
     app_kit: web::Data<AppKit>,
-    form: actix_web_validator::Form<PostFormData>,
+    MultipartForm(form): MultipartForm<CreatePostMultipartForm>,
     session: Session,
 ) -> actix_web::Result<impl Responder> {
     let user = get_session_user(&session)?;
 
+    let form_data = form.to_form_data();
+    form_data
+        .validate()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+
     let create_post_result = web::block(move || {
-        app_kit
+        let uploads = AttachmentUpload::from_temp_files(&form.attachments)
+            .map_err(|e| WebError::from(format!("Failed to read uploaded file: {}", e)))?;
+
+        // every upload is checked before anything is written
+        let prepared = app_kit
+            .attachment_service
+            .prepare_attachments(uploads)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let new_post = app_kit
             .post_service
-            .create_post(user.id, &form.title, &form.body)
+            .create_post(user.id, &form_data.title, &form_data.body)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        if let Err(e) = app_kit.attachment_service.store_attachments(
+            user.id,
+            AttachmentTarget::Post(new_post.id),
+            prepared,
+        ) {
+            let _ = app_kit.post_service.delete_post(new_post.id);
+            return Err(WebError::from(e.to_string()));
+        }
+
+        Ok(new_post)
     })
     .await?;
 
     match create_post_result {
+        // held by the content filter, nobody but moderators can see it yet
+        Ok(new_post) if new_post.hidden_at.is_some() => {
+            set_flash_message(
+                &session,
+                FLASH_SUCCESS,
+                "Created post, it will show up once a moderator reviewed it",
+            )?;
+
+            Ok(create_redirect("/"))
+        }
+
         Ok(new_post) => {
-            let new_post_url = format!("/posts/{}", new_post.id);
+            let new_post_url = post_path(new_post.id, &new_post.title);
 
             set_flash_message(&session, "success", "Created post!")?;
 
             Ok(create_redirect(&new_post_url))
         }
 
-        Err(_) => {
-            set_flash_message(&session, "error", "Failed to create post!")?;
+        Err(why) => {
+            set_flash_message(
+                &session,
+                "error",
+                &format!("Failed to create post! {}", why),
+            )?;
 
-            Ok(create_redirect(""))
+            Ok(create_redirect("/posts/create"))
         }
     }
 }
@@ -82,34 +142,104 @@ pub async fn create_post_submit_route(
 #[get("/{post_id}")]
 pub async fn view_post_route(
     app_kit: web::Data<AppKit>,
+    req: HttpRequest,
     hb: web::Data<Handlebars<'_>>,
-    path: web::Path<i32>,
+    path: web::Path<String>,
     session: Session,
     pagination: QueryPagination,
 ) -> actix_web::Result<impl Responder> {
-    let post_id = path.into_inner();
+    let post_id = parse_post_path_segment(&path.into_inner())
+        .ok_or(actix_web::error::ErrorNotFound("Post not found"))?;
     let mut hb_data = json!({ "parent": "base" });
     let session_user = get_session_user(&session);
+    let session_user_id = session_user.as_ref().ok().map(|user| user.id);
 
     let pagination_clone = pagination.clone();
 
-    let data_result: Result<(PostPublic, ListCommentResult), WebError> = web::block(move || {
-        let post = app_kit
+    let data_result = web::block(move || {
+        let is_moderator = session_user_id.is_some_and(|user_id| {
+            session_user_is_moderator(app_kit.user_service.as_ref(), user_id)
+        });
+
+        let mut post = app_kit
             .post_service
             .get_post_with_user(post_id)
             .map_err(|e| WebError::from(format!("Failed to get post: {}", e)))?;
 
-        let comments = app_kit
+        let mut comments = app_kit
             .comment_service
             .get_comments_with_user(post.post.id, &pagination_clone)
             .map_err(|e| WebError::from(format!("Failed to get comments: {}", e)))?;
 
-        Ok((post, comments))
+        post.attachments = app_kit
+            .attachment_service
+            .get_post_attachments(post.post.id)
+            .map_err(|e| WebError::from(e.to_string()))?
+            .iter()
+            .map(AttachmentPublic::from)
+            .collect();
+
+        let comment_ids: Vec<i32> = comments.comments.iter().map(|c| c.comment.id).collect();
+        let comment_attachments = app_kit
+            .attachment_service
+            .get_comments_attachments(&comment_ids)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        for comment in comments.comments.iter_mut() {
+            comment.attachments = comment_attachments
+                .iter()
+                .filter(|a| a.comment_id == Some(comment.comment.id))
+                .map(AttachmentPublic::from)
+                .collect();
+        }
+
+        let is_subscribed = match session_user_id {
+            Some(user_id) => app_kit
+                .follow_service
+                .is_subscribed(user_id, post.post.id)
+                .map_err(|e| WebError::from(e.to_string()))?,
+            None => false,
+        };
+
+        let is_bookmarked = match session_user_id {
+            Some(user_id) => {
+                let bookmarked_comment_ids = app_kit
+                    .bookmark_service
+                    .get_bookmarked_comment_ids(user_id, &comment_ids)
+                    .map_err(|e| WebError::from(e.to_string()))?;
+                for comment in comments.comments.iter_mut() {
+                    comment.is_bookmarked = bookmarked_comment_ids.contains(&comment.comment.id);
+                }
+
+                app_kit
+                    .bookmark_service
+                    .is_bookmarked(user_id, BookmarkTarget::Post(post.post.id))
+                    .map_err(|e| WebError::from(e.to_string()))?
+            }
+            None => false,
+        };
+
+        Ok::<_, WebError>((post, comments, is_moderator, is_subscribed, is_bookmarked))
     })
     .await?;
 
+    let mut response = HttpResponse::Ok();
+
     match data_result {
-        Ok((mut post, mut comment_result)) => {
+        Ok((mut post, mut comment_result, is_moderator, is_subscribed, is_bookmarked)) => {
+            // old links and links without the slug move to the canonical path
+            let canonical_path = post_path(post.post.id, &post.post.title);
+            if req.path() != canonical_path {
+                let location = match req.query_string() {
+                    "" => canonical_path,
+                    query => format!("{}?{}", canonical_path, query),
+                };
+
+                return Ok(HttpResponse::MovedPermanently()
+                    .insert_header((header::LOCATION, location))
+                    .finish());
+            }
+
             // if post.user_id is equal session user id then allow update
             if let Ok(user) = session_user {
                 if post.user.id == user.id {
@@ -126,15 +256,40 @@ pub async fn view_post_route(
             let comment_pagination_result =
                 build_handlebars_pagination_result(comment_result.total, &pagination);
 
+            if let Some(redirect) =
+                pagination_out_of_range_redirect(&req, &comment_pagination_result)
+            {
+                return Ok(redirect);
+            }
+            add_pagination_link_header(&mut response, &req, &comment_pagination_result);
+
             update_handlebars_data(
                 &mut hb_data,
                 "pagination_result",
                 json!(comment_pagination_result),
             );
 
+            handlebars_add_page_meta(
+                &mut hb_data,
+                &PageMeta::new(
+                    &req,
+                    &canonical_path,
+                    pagination.page,
+                    OpenGraphType::Article,
+                    &post.post.body,
+                ),
+            );
             update_handlebars_data(&mut hb_data, "title", json!(post.post.title));
             update_handlebars_data(&mut hb_data, "post", json!(post));
             update_handlebars_data(&mut hb_data, "comments_result", json!(comment_result));
+            update_handlebars_data(&mut hb_data, "is_moderator", json!(is_moderator));
+            update_handlebars_data(&mut hb_data, "is_subscribed", json!(is_subscribed));
+            update_handlebars_data(&mut hb_data, "is_bookmarked", json!(is_bookmarked));
+            update_handlebars_data(
+                &mut hb_data,
+                "feed_url",
+                json!(format!("/posts/{}/feed", post.post.id)),
+            );
         }
 
         Err(e) => {
@@ -150,17 +305,19 @@ pub async fn view_post_route(
 
     let _ = handlebars_add_user(&session, &mut hb_data);
     handle_flash_message(&mut hb_data, &session);
+    handlebars_add_csrf_token(&session, &mut hb_data)?;
 
     let body = hb
         .render("posts/view", &hb_data)
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(body))
+    Ok(response.body(body))
 }
 
 // #[get("/")]
 pub async fn index_list_posts_route(
     app_kit: web::Data<AppKit>,
+    req: HttpRequest,
     pagination: QueryPagination,
     hb: web::Data<Handlebars<'_>>,
     session: Session,
@@ -171,18 +328,33 @@ pub async fn index_list_posts_route(
 
     let pagination_data_clone = pagination.clone();
     let posts_result = web::block(move || {
-        app_kit
+        let result = app_kit
             .post_service
-            .get_posts_with_user(&pagination_data_clone)
+            .get_posts_with_user(&pagination_data_clone)?;
+
+        // pinned posts sit above the first page and are not counted in the pages
+        let pinned_posts = match pagination_data_clone.page {
+            1 => app_kit.post_service.get_pinned_posts_with_user()?,
+            _ => vec![],
+        };
+
+        Ok::<_, PostServiceError>((result, pinned_posts))
     })
     .await?;
 
-    match posts_result {
-        Ok(result) => {
-            update_handlebars_data(&mut data, "posts_result", json!(&result));
+    let mut response = HttpResponse::Ok();
 
+    match posts_result {
+        Ok((result, pinned_posts)) => {
             let pagination_result = build_handlebars_pagination_result(result.total, &pagination);
 
+            if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+                return Ok(redirect);
+            }
+            add_pagination_link_header(&mut response, &req, &pagination_result);
+
+            update_handlebars_data(&mut data, "posts_result", json!(&result));
+            update_handlebars_data(&mut data, "pinned_posts", json!(&pinned_posts));
             update_handlebars_data(&mut data, "pagination_result", json!(pagination_result));
         }
 
@@ -195,11 +367,22 @@ pub async fn index_list_posts_route(
 
     handle_flash_message(&mut data, &session);
     update_handlebars_data(&mut data, "title", json!("Posts"));
+    update_handlebars_data(&mut data, "feed_url", json!("/feed"));
+    handlebars_add_page_meta(
+        &mut data,
+        &PageMeta::new(
+            &req,
+            "/",
+            pagination.page,
+            OpenGraphType::Website,
+            "The latest posts on RustForum",
+        ),
+    );
     let _ = handlebars_add_user(&session, &mut data);
 
     let body = hb.render("posts/index", &data).unwrap();
 
-    Ok(HttpResponse::Ok().body(body))
+    Ok(response.body(body))
 }
 
 #[get("/update/{post_id}")]
@@ -237,6 +420,7 @@ pub async fn update_post_route(
     update_handlebars_data(&mut data, "post", json!(post.post));
     handle_flash_message(&mut data, &session);
     handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("posts/form", &data).unwrap();
 
@@ -266,7 +450,12 @@ pub async fn update_post_submit_route(
 
         let post = app_kit
             .post_service
-            .update_post(fetch_result.post.id, &form.title, &form.body)
+            .update_post(
+                fetch_result.post.id,
+                session_user.id,
+                &form.title,
+                &form.body,
+            )
             .map_err(|e| WebError::from(format!("failed to update post {}", e)))?;
 
         Ok(post)
@@ -274,9 +463,18 @@ pub async fn update_post_submit_route(
     .await?;
 
     match update_post_result {
+        Ok(post) if post.hidden_at.is_some() => {
+            set_flash_message(
+                &session,
+                FLASH_SUCCESS,
+                "Post updated, it will show up again once a moderator reviewed it",
+            )?;
+            Ok(create_redirect("/"))
+        }
+
         Ok(post) => {
             set_flash_message(&session, FLASH_SUCCESS, "Post updated")?;
-            Ok(create_redirect(&format!("/posts/{}", post.id)))
+            Ok(create_redirect(&post_path(post.id, &post.title)))
         }
 
         Err(why) => {
@@ -322,7 +520,7 @@ pub async fn delete_post_route(
 
     match delete_post_result {
         Ok(_) => {
-            set_flash_message(&session, FLASH_SUCCESS, "Post deleted")?;
+            set_flash_message(&session, FLASH_SUCCESS, "Post moved to trash")?;
             Ok(create_redirect("/"))
         }
 
@@ -337,3 +535,178 @@ pub async fn delete_post_route(
         }
     }
 }
+
+#[get("/{post_id}/revisions")]
+pub async fn post_revisions_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let post_id = path.into_inner();
+    let session_user = get_session_user(&session).ok();
+
+    let (post, revisions, allow_rollback) = web::block(move || {
+        let post = app_kit
+            .post_service
+            .get_post(post_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let mut versions: Vec<RevisionVersion> = app_kit
+            .post_service
+            .get_post_revisions(post_id)
+            .map_err(|e| WebError::from(e.to_string()))?
+            .into_iter()
+            .map(RevisionVersion::from)
+            .collect();
+
+        if versions.is_empty() {
+            versions.push(RevisionVersion::from(&post));
+        }
+
+        let user_names = get_user_names(
+            app_kit.user_service.as_ref(),
+            versions.iter().filter_map(|v| v.user_id),
+        );
+
+        let allow_rollback = session_user
+            .map(|user| session_user_is_moderator(app_kit.user_service.as_ref(), user.id))
+            .unwrap_or(false);
+
+        Ok::<_, WebError>((
+            post,
+            versions_to_revisions_public(&versions, &user_names),
+            allow_rollback,
+        ))
+    })
+    .await?
+    .map_err(actix_web::error::ErrorNotFound)?;
+
+    let mut data = json!({
+        "parent": "base",
+        "title": format!("Revisions : {}", post.title),
+        "header": format!("Revisions of post : {}", post.title),
+        "back_url": post_path(post.id, &post.title),
+        "rollback_url_prefix": format!("/posts/{}/revisions", post.id),
+        "allow_rollback": allow_rollback,
+        "revisions": revisions,
+    });
+
+    handle_flash_message(&mut data, &session);
+    let _ = handlebars_add_user(&session, &mut data);
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("revisions/index", &data)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[post("/{post_id}/revisions/{revision_id}/rollback")]
+pub async fn rollback_post_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let (post_id, revision_id) = path.into_inner();
+    let session_user = get_session_user(&session)?;
+
+    let rollback_result = web::block(move || {
+        if !session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id) {
+            return Err(WebError::from("Only moderators can roll back posts"));
+        }
+
+        app_kit
+            .post_service
+            .rollback_post(post_id, revision_id, session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?;
+
+    match rollback_result {
+        Ok(post) => {
+            set_flash_message(&session, FLASH_SUCCESS, "Post rolled back")?;
+            Ok(create_redirect(&post_path(post.id, &post.title)))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+            Ok(redirect_back(&req))
+        }
+    }
+}
+
+#[post("/{post_id}/restore")]
+pub async fn restore_post_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let post_id = path.into_inner();
+    let session_user = get_session_user(&session)?;
+    let client_ip = get_client_ip(&req);
+
+    let restore_result = web::block(move || {
+        let post = app_kit
+            .post_service
+            .get_deleted_post(post_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        // authors get a grace period, moderators can restore until the post is purged,
+        // a post held by a moderator is only theirs to restore
+        let is_moderator =
+            session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id);
+        let author_can_restore = post.user_id == session_user.id
+            && post.hidden_at.is_none()
+            && post
+                .deleted_at
+                .is_some_and(|deleted_at| app_kit.trash_policy.author_can_restore(deleted_at));
+
+        if !is_moderator && !author_can_restore {
+            return Err(WebError::from("Post can no longer be restored"));
+        }
+
+        app_kit
+            .post_service
+            .restore_post(post_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        if is_moderator && post.hidden_at.is_some() {
+            app_kit
+                .post_service
+                .set_post_hidden(post_id, false)
+                .map_err(|e| WebError::from(e.to_string()))?;
+        }
+
+        if post.user_id != session_user.id {
+            record_audit(
+                app_kit.audit_service.as_ref(),
+                AuditRecord {
+                    actor_user_id: session_user.id,
+                    action: AuditAction::PostRestore,
+                    target: AuditTarget::Post(post_id),
+                    reason: "",
+                    ip_address: &client_ip,
+                },
+            );
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    match restore_result {
+        Ok(_) => {
+            set_flash_message(&session, FLASH_SUCCESS, "Post restored")?;
+            Ok(create_redirect(&format!("/posts/{}", post_id)))
+        }
+
+        Err(why) => {
+            set_flash_message(&session, FLASH_ERROR, &why.to_string())?;
+            Ok(redirect_back(&req))
+        }
+    }
+}
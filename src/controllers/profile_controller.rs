@@ -2,21 +2,33 @@ use std::sync::{Arc, Mutex};
 
 use actix_session::Session;
 use actix_web::{
-    http::header::ContentType, web, FromRequest, HttpRequest, HttpResponse, Responder,
+    http::header::{self, ContentType},
+    web, FromRequest, HttpRequest, HttpResponse, Responder,
 };
 use futures::future::{ready, Ready};
 use handlebars::Handlebars;
 use serde_json::json;
 
 use crate::handlebars_helper::pagination::{
-    build_handlebars_pagination_result, HandlebarsPaginationResult,
+    add_pagination_link_header, build_handlebars_pagination_result,
+    pagination_out_of_range_redirect, HandlebarsPaginationResult,
 };
 use crate::{
     db::WebError,
-    entities::{comment::CommentPublic, post::PostPublic},
+    entities::{
+        bookmark::{BookmarkPublic, SavedQuery},
+        comment::CommentPublic,
+        post::PostPublic,
+        user::{normalize_username, UserPublic, UsernameLookup},
+    },
     utils::{
-        flash::handle_flash_message, handlebars_helper::update_handlebars_data,
-        pagination::QueryPagination, session::handlebars_add_user,
+        csrf::handlebars_add_csrf_token,
+        flash::handle_flash_message,
+        handlebars_helper::update_handlebars_data,
+        pagination::QueryPagination,
+        seo::{handlebars_add_page_meta, OpenGraphType, PageMeta},
+        session::handlebars_add_user,
+        users::{get_session_user, session_user_is_moderator},
     },
     AppKit,
 };
@@ -40,19 +52,85 @@ impl FromRequest for OptionalFetchMode {
     }
 }
 
+/// The user a profile url names, by id under `/profile` or by handle under `/u`
+pub enum ProfilePath {
+    UserId(i32),
+    Username(String),
+}
+
+impl FromRequest for ProfilePath {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let match_info = req.match_info();
+
+        let profile_path = match match_info.get("username") {
+            Some(username) => Ok(ProfilePath::Username(username.to_string())),
+            None => match_info
+                .get("user_id")
+                .and_then(|user_id| user_id.parse().ok())
+                .map(ProfilePath::UserId)
+                .ok_or_else(|| actix_web::error::ErrorNotFound("Profile not found")),
+        };
+
+        ready(profile_path)
+    }
+}
+
+/// Sends a handle url to the same page under the current handle
+fn moved_handle_redirect(req: &HttpRequest, username: &str) -> HttpResponse {
+    let handle_path = match req.match_info().get("fetch_mode") {
+        Some(fetch_mode) => format!("/u/{}/{}", username, fetch_mode),
+        None => format!("/u/{}", username),
+    };
+    let location = match req.query_string() {
+        "" => handle_path,
+        query => format!("{}?{}", handle_path, query),
+    };
+
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
 // #[get("/profile/{user_id}/{fetch_mode:.*}")]
+// #[get("/u/{username}/{fetch_mode:.*}")]
 pub async fn profile_view_route(
-    _: HttpRequest,
+    req: HttpRequest,
     app_kit: web::Data<AppKit>,
     session: Session,
-    path: web::Path<(i32,)>,
+    profile_path: ProfilePath,
     fetch_mode: OptionalFetchMode,
     pagination: QueryPagination,
     hb: web::Data<Handlebars<'_>>,
 ) -> actix_web::Result<impl Responder> {
-    let user_id = path.into_inner().0;
+    let user_id = match profile_path {
+        ProfilePath::UserId(user_id) => user_id,
+        ProfilePath::Username(username) => {
+            let app_kit = app_kit.clone();
+            let username_clone = username.clone();
+            let lookup = web::block(move || app_kit.user_service.resolve_username(&username_clone))
+                .await?
+                .map_err(|_| actix_web::error::ErrorNotFound("Profile not found"))?;
+
+            // old handles and other casings move to the current handle
+            match lookup {
+                UsernameLookup::Current(user_id) if username == normalize_username(&username) => {
+                    user_id
+                }
+                UsernameLookup::Current(_) => {
+                    return Ok(moved_handle_redirect(&req, &normalize_username(&username)))
+                }
+                UsernameLookup::Moved(new_username) => {
+                    return Ok(moved_handle_redirect(&req, &new_username))
+                }
+            }
+        }
+    };
     let fetch_mode = fetch_mode.0;
     let fetch_mode_clone = fetch_mode.clone();
+    let pagination_page = pagination.page;
 
     let mut hb_data = json!({
         "parent": "base",
@@ -60,67 +138,209 @@ pub async fn profile_view_route(
 
     let user_created_posts: Arc<Mutex<Vec<PostPublic>>> = Arc::new(Mutex::new(vec![]));
     let user_created_comments: Arc<Mutex<Vec<CommentPublic>>> = Arc::new(Mutex::new(vec![]));
+    let user_follows: Arc<Mutex<Vec<UserPublic>>> = Arc::new(Mutex::new(vec![]));
+    let user_bookmarks: Arc<Mutex<Vec<BookmarkPublic>>> = Arc::new(Mutex::new(vec![]));
+    let user_bookmark_folders: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
     let pagination_result: Arc<Mutex<HandlebarsPaginationResult>> =
         Arc::new(Mutex::new(HandlebarsPaginationResult::default()));
 
     let post_service_cloned = app_kit.post_service.clone();
     let comment_service_cloned = app_kit.comment_service.clone();
+    let user_service_cloned = app_kit.user_service.clone();
 
     let user_created_posts_cloned = user_created_posts.clone();
     let user_created_comments_cloned = user_created_comments.clone();
+    let user_follows_cloned = user_follows.clone();
+    let user_bookmarks_cloned = user_bookmarks.clone();
+    let user_bookmark_folders_cloned = user_bookmark_folders.clone();
     let pagination_result_cloned = pagination_result.clone();
 
-    let user_data = web::block(move || {
-        let user_sanitized = app_kit
-            .user_service
-            .get_user_by_id_public(user_id)
-            .map_err(|_| WebError::from("Failed to get comments"))?;
-
-        // get_user_sanitized_by_id(&mut conn, user_id)?;
-
-        if fetch_mode_clone == "posts" {
-            let created_posts = post_service_cloned
-                .get_posts_by_user(user_sanitized.id, &pagination)
-                .map_err(|_| WebError::from("Failed to get posts by user"))?;
-            // (&mut conn, user_id, &pagination)?;
-
-            user_created_posts_cloned
-                .lock()
-                .unwrap()
-                .extend(created_posts.posts);
-            // .unwrap()
-            // .extend(created_posts.posts);
-
-            *(pagination_result_cloned.lock().unwrap()) =
-                build_handlebars_pagination_result(created_posts.total, &pagination);
-        } else if fetch_mode_clone == "comments" {
-            let created_comments = comment_service_cloned
-                .get_comments_by_user(user_sanitized.id, &pagination)
+    let session_user_id = get_session_user(&session).ok().map(|user| user.id);
+    // the Saved tab can be narrowed to one folder
+    let saved_folder = web::Query::<SavedQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().folder)
+        .filter(|folder| !folder.is_empty());
+    let saved_folder_clone = saved_folder.clone();
+
+    let (user_data, is_moderator, is_blocked, follow_counts, is_following) =
+        web::block(move || {
+            let user_sanitized = app_kit
+                .user_service
+                .get_user_by_id_public(user_id)
                 .map_err(|_| WebError::from("Failed to get comments"))?;
 
-            user_created_comments_cloned
-                .lock()
-                .unwrap()
-                .extend(created_comments.comments);
+            // get_user_sanitized_by_id(&mut conn, user_id)?;
 
-            *pagination_result_cloned.lock().unwrap() =
-                build_handlebars_pagination_result(created_comments.total, &pagination);
-        } else {
-            return Err(WebError::from("no fetch mode was provide"));
-        }
+            if fetch_mode_clone == "posts" {
+                let created_posts = post_service_cloned
+                    .get_posts_by_user(user_sanitized.id, &pagination)
+                    .map_err(|_| WebError::from("Failed to get posts by user"))?;
+                // (&mut conn, user_id, &pagination)?;
+
+                user_created_posts_cloned
+                    .lock()
+                    .unwrap()
+                    .extend(created_posts.posts);
+                // .unwrap()
+                // .extend(created_posts.posts);
+
+                *(pagination_result_cloned.lock().unwrap()) =
+                    build_handlebars_pagination_result(created_posts.total, &pagination);
+            } else if fetch_mode_clone == "comments" {
+                let created_comments = comment_service_cloned
+                    .get_comments_by_user(user_sanitized.id, &pagination)
+                    .map_err(|_| WebError::from("Failed to get comments"))?;
+
+                user_created_comments_cloned
+                    .lock()
+                    .unwrap()
+                    .extend(created_comments.comments);
+
+                *pagination_result_cloned.lock().unwrap() =
+                    build_handlebars_pagination_result(created_comments.total, &pagination);
+            } else if fetch_mode_clone == "followers" || fetch_mode_clone == "following" {
+                let follows = match fetch_mode_clone.as_str() {
+                    "followers" => app_kit
+                        .follow_service
+                        .get_followers(user_sanitized.id, &pagination),
+                    _ => app_kit
+                        .follow_service
+                        .get_following(user_sanitized.id, &pagination),
+                }
+                .map_err(|e| WebError::from(e.to_string()))?;
+
+                user_follows_cloned.lock().unwrap().extend(follows.users);
+
+                *pagination_result_cloned.lock().unwrap() =
+                    build_handlebars_pagination_result(follows.total, &pagination);
+            } else if fetch_mode_clone == "saved" {
+                // bookmarks are private to their owner
+                if session_user_id != Some(user_sanitized.id) {
+                    return Err(WebError::from("Only you can see what you saved"));
+                }
+
+                let bookmarks = app_kit
+                    .bookmark_service
+                    .get_bookmarks(
+                        user_sanitized.id,
+                        saved_folder_clone.as_deref(),
+                        &pagination,
+                    )
+                    .map_err(|e| WebError::from(e.to_string()))?;
+                let folders = app_kit
+                    .bookmark_service
+                    .get_folders(user_sanitized.id)
+                    .map_err(|e| WebError::from(e.to_string()))?;
+
+                user_bookmarks_cloned
+                    .lock()
+                    .unwrap()
+                    .extend(bookmarks.bookmarks);
+                user_bookmark_folders_cloned.lock().unwrap().extend(folders);
+
+                *pagination_result_cloned.lock().unwrap() =
+                    build_handlebars_pagination_result(bookmarks.total, &pagination);
+            } else {
+                return Err(WebError::from("no fetch mode was provide"));
+            }
+
+            // moderators get a link to the ban tools of the profile
+            let is_moderator = session_user_id.is_some_and(|session_user_id| {
+                session_user_is_moderator(app_kit.user_service.as_ref(), session_user_id)
+            });
+
+            let is_blocked = match session_user_id {
+                Some(session_user_id) => app_kit
+                    .message_service
+                    .is_blocked(session_user_id, user_sanitized.id)
+                    .map_err(|e| WebError::from(e.to_string()))?,
+                None => false,
+            };
+
+            let follow_counts = app_kit
+                .follow_service
+                .get_follow_counts(user_sanitized.id)
+                .map_err(|e| WebError::from(e.to_string()))?;
 
-        Ok(user_sanitized)
+            let is_following = match session_user_id {
+                Some(session_user_id) => app_kit
+                    .follow_service
+                    .is_following(session_user_id, user_sanitized.id)
+                    .map_err(|e| WebError::from(e.to_string()))?,
+                None => false,
+            };
+
+            Ok((
+                user_sanitized,
+                is_moderator,
+                is_blocked,
+                follow_counts,
+                is_following,
+            ))
+        })
+        .await?
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let profile_user_id = user_data.id;
+    let (user_profile, user_stats) = web::block(move || {
+        let user_profile = user_service_cloned
+            .get_user_profile(profile_user_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+        let user_stats = user_service_cloned
+            .get_user_stats(profile_user_id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        Ok::<_, WebError>((user_profile, user_stats))
     })
     .await?
     .map_err(actix_web::error::ErrorInternalServerError)?;
 
     update_handlebars_data(&mut hb_data, "profile_users", json!(user_data));
+    update_handlebars_data(&mut hb_data, "profile", json!(user_profile));
+    update_handlebars_data(&mut hb_data, "profile_stats", json!(user_stats));
+    update_handlebars_data(&mut hb_data, "is_moderator", json!(is_moderator));
+    update_handlebars_data(&mut hb_data, "is_blocked", json!(is_blocked));
+    update_handlebars_data(&mut hb_data, "follow_counts", json!(follow_counts));
+    update_handlebars_data(&mut hb_data, "is_following", json!(is_following));
+    update_handlebars_data(
+        &mut hb_data,
+        "is_own_profile",
+        json!(session_user_id == Some(user_data.id)),
+    );
+    update_handlebars_data(
+        &mut hb_data,
+        "feed_url",
+        json!(format!("/profile/{}/feed", user_data.id)),
+    );
     update_handlebars_data(
         &mut hb_data,
         "title",
         json!(format!("Profile {}", user_data.name)),
     );
 
+    // the posts listing is the profile page itself
+    let canonical_path = match fetch_mode.as_str() {
+        "comments" | "followers" | "following" | "saved" => {
+            format!("/profile/{}/{}", user_data.id, fetch_mode)
+        }
+        _ => format!("/profile/{}", user_data.id),
+    };
+    handlebars_add_page_meta(
+        &mut hb_data,
+        &PageMeta::new(
+            &req,
+            &canonical_path,
+            pagination_page,
+            OpenGraphType::Profile,
+            &match user_profile.bio.is_empty() {
+                true => format!("Posts and comments by {} on RustForum", user_data.name),
+                false => user_profile.bio.clone(),
+            },
+        )
+        .with_image(&req, Some(&user_data.user_profile_picture_url)),
+    );
+
     if fetch_mode == "posts" || fetch_mode.is_empty() {
         let profile_users_created_posts = &*user_created_posts.lock().unwrap();
 
@@ -140,10 +360,42 @@ pub async fn profile_view_route(
         );
 
         update_handlebars_data(&mut hb_data, "fetch_mode_comments", json!(true));
+    } else if fetch_mode == "followers" || fetch_mode == "following" {
+        let profile_users_follows = &*user_follows.lock().unwrap();
+        update_handlebars_data(
+            &mut hb_data,
+            "profile_users_follows",
+            json!(profile_users_follows),
+        );
+
+        update_handlebars_data(
+            &mut hb_data,
+            &format!("fetch_mode_{}", fetch_mode),
+            json!(true),
+        );
+    } else if fetch_mode == "saved" {
+        let profile_users_bookmarks = &*user_bookmarks.lock().unwrap();
+        update_handlebars_data(
+            &mut hb_data,
+            "profile_users_bookmarks",
+            json!(profile_users_bookmarks),
+        );
+        update_handlebars_data(
+            &mut hb_data,
+            "bookmark_folders",
+            json!(&*user_bookmark_folders.lock().unwrap()),
+        );
+        update_handlebars_data(&mut hb_data, "saved_folder", json!(saved_folder));
+
+        update_handlebars_data(&mut hb_data, "fetch_mode_saved", json!(true));
     }
 
     let pagination_result_deref = &*(pagination_result.lock().unwrap());
 
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, pagination_result_deref) {
+        return Ok(redirect);
+    }
+
     update_handlebars_data(
         &mut hb_data,
         "pagination_result",
@@ -152,12 +404,14 @@ pub async fn profile_view_route(
 
     handlebars_add_user(&session, &mut hb_data)?;
     handle_flash_message(&mut hb_data, &session);
+    handlebars_add_csrf_token(&session, &mut hb_data)?;
 
     let body = hb
         .render("users/profile", &hb_data)
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(body))
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, pagination_result_deref);
+
+    Ok(response.content_type(ContentType::html()).body(body))
 }
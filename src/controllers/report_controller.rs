@@ -0,0 +1,150 @@
+use actix_session::Session;
+use actix_web::{
+    error, get, post,
+    web::{self},
+    HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    db::WebError,
+    entities::report::{ReportCategory, ReportFormData, ReportTarget},
+    servers::actix_etc::actix_rate_limit_middleware::RateLimit,
+    services::rate_limit_service::REPORT_RATE_LIMIT_POLICY,
+    utils::{
+        csrf::handlebars_add_csrf_token,
+        flash::{handle_flash_message, set_flash_message, FLASH_SUCCESS},
+        http::create_redirect,
+        session::handlebars_add_user,
+        users::get_session_user,
+    },
+    AppKit,
+};
+
+/// A short description of what is being reported and where it lives
+fn describe_report_target(
+    app_kit: &AppKit,
+    target: ReportTarget,
+) -> Result<(String, String), WebError> {
+    match target {
+        ReportTarget::Post(post_id) => {
+            let post = app_kit
+                .post_service
+                .get_post(post_id)
+                .map_err(|_| WebError::from("Reported content not found"))?;
+            Ok((
+                format!("post \"{}\"", post.title),
+                format!("/posts/{}", post.id),
+            ))
+        }
+
+        ReportTarget::Comment(comment_id) => {
+            let comment = app_kit
+                .comment_service
+                .get_comment(comment_id)
+                .map_err(|_| WebError::from("Reported content not found"))?;
+            Ok((
+                format!("comment #{}", comment.id),
+                format!("/posts/{}#{}", comment.post_id, comment.id),
+            ))
+        }
+
+        ReportTarget::User(user_id) => {
+            let user = app_kit
+                .user_service
+                .get_user_by_id_public(user_id)
+                .map_err(|_| WebError::from("Reported user not found"))?;
+            Ok((
+                format!("user {}", user.name),
+                format!("/profile/{}", user.id),
+            ))
+        }
+    }
+}
+
+#[get("/{target_kind}/{target_id}")]
+pub async fn report_route(
+    app_kit: web::Data<AppKit>,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(String, i32)>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    get_session_user(&session)?;
+
+    let (target_kind, target_id) = path.into_inner();
+    let target = ReportTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown report target"))?;
+
+    let (target_description, target_url) =
+        web::block(move || describe_report_target(&app_kit, target))
+            .await?
+            .map_err(error::ErrorNotFound)?;
+
+    let categories: Vec<_> = ReportCategory::ALL
+        .iter()
+        .map(|category| json!({ "value": category.as_str(), "label": category.label() }))
+        .collect();
+
+    let mut data = json!({
+        "parent": "base",
+        "title": format!("Report {}", target_description),
+        "form_header": format!("Report {}", target_description),
+        "form_action": format!("/reports/{}/{}", target.kind(), target.id()),
+        "back_url": target_url,
+        "categories": categories,
+    });
+
+    handle_flash_message(&mut data, &session);
+    handlebars_add_user(&session, &mut data)?;
+    handlebars_add_csrf_token(&session, &mut data)?;
+
+    let body = hb
+        .render("reports/form", &data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[post(
+    "/{target_kind}/{target_id}",
+    wrap = "RateLimit::new(REPORT_RATE_LIMIT_POLICY)"
+)]
+pub async fn report_submit_route(
+    app_kit: web::Data<AppKit>,
+    path: web::Path<(String, i32)>,
+    form: actix_web_validator::Form<ReportFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let (target_kind, target_id) = path.into_inner();
+    let target = ReportTarget::parse(&target_kind, target_id)
+        .ok_or(error::ErrorNotFound("Unknown report target"))?;
+    let category = ReportCategory::parse(&form.category)
+        .ok_or(error::ErrorBadRequest("Pick a reason for the report"))?;
+
+    let target_url = web::block(move || {
+        app_kit
+            .report_service
+            .create_report(session_user.id, target, category, &form.details)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        // the report may have been the one that hid the content
+        let target_url = describe_report_target(&app_kit, target)
+            .map(|(_, target_url)| target_url)
+            .unwrap_or("/".to_string());
+
+        Ok::<_, WebError>(target_url)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    set_flash_message(
+        &session,
+        FLASH_SUCCESS,
+        "Thanks for the report, the moderators will review it",
+    )?;
+
+    Ok(create_redirect(&target_url))
+}
@@ -0,0 +1,106 @@
+use actix_web::{
+    error, get,
+    http::header::ETag,
+    web::{self},
+    HttpRequest, HttpResponse, Responder,
+};
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+
+use crate::{
+    entities::sitemap::SitemapSection,
+    services::sitemap_service::SitemapServiceError,
+    utils::http::{body_etag, get_app_url, is_not_modified},
+    AppKit,
+};
+
+const SITEMAP_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+/// Pages behind a login or only useful to staff, kept out of search results
+const ROBOTS_DISALLOWED_PATHS: [&str; 4] = ["/admin", "/moderation", "/trash", "/users"];
+
+/// Renders a sitemap file, or answers 304 when the client copy is current
+fn sitemap_response(
+    req: &HttpRequest,
+    hb: &Handlebars<'_>,
+    template: &str,
+    data: &Value,
+) -> actix_web::Result<HttpResponse> {
+    let body = hb
+        .render(template, data)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let etag = body_etag(body.as_bytes());
+
+    match is_not_modified(req, &etag, None) {
+        true => Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .finish()),
+        false => Ok(HttpResponse::Ok()
+            .insert_header(ETag(etag))
+            .content_type(SITEMAP_CONTENT_TYPE)
+            .body(body)),
+    }
+}
+
+#[get("/robots.txt")]
+pub async fn robots_route(req: HttpRequest) -> actix_web::Result<impl Responder> {
+    let disallowed: String = ROBOTS_DISALLOWED_PATHS
+        .iter()
+        .map(|path| format!("Disallow: {}\n", path))
+        .collect();
+    let body = format!(
+        "User-agent: *\n{}\nSitemap: {}/sitemap.xml\n",
+        disallowed,
+        get_app_url(&req)
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(body))
+}
+
+#[get("/sitemap.xml")]
+pub async fn sitemap_index_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let app_url = get_app_url(&req);
+
+    let mut files = web::block(move || app_kit.sitemap_service.get_index())
+        .await?
+        .map_err(error::ErrorInternalServerError)?;
+
+    for file in files.iter_mut() {
+        file.loc = format!("{}{}", app_url, file.loc);
+    }
+
+    sitemap_response(&req, &hb, "sitemaps/index", &json!({ "files": files }))
+}
+
+#[get("/sitemaps/{section}-{chunk:\\d+}.xml")]
+pub async fn sitemap_chunk_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    hb: web::Data<Handlebars<'_>>,
+    path: web::Path<(String, i64)>,
+) -> actix_web::Result<impl Responder> {
+    let (section, chunk) = path.into_inner();
+    let section =
+        SitemapSection::parse(&section).ok_or(error::ErrorNotFound("Sitemap not found"))?;
+    let app_url = get_app_url(&req);
+
+    let mut urls = web::block(move || app_kit.sitemap_service.get_chunk(section, chunk))
+        .await?
+        .map_err(|e| match e {
+            SitemapServiceError::ErrorChunkNotFound => error::ErrorNotFound(e),
+            _ => error::ErrorInternalServerError(e),
+        })?;
+
+    for url in urls.iter_mut() {
+        url.loc = format!("{}{}", app_url, url.loc);
+    }
+
+    sitemap_response(&req, &hb, "sitemaps/urlset", &json!({ "urls": urls }))
+}
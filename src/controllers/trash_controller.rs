@@ -0,0 +1,146 @@
+use actix_session::Session;
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse, Responder};
+use handlebars::Handlebars;
+use serde_json::json;
+
+use crate::{
+    controllers::profile_controller::OptionalFetchMode,
+    db::WebError,
+    entities::trash::{TrashCommentPublic, TrashPostPublic, TrashStatePublic},
+    handlebars_helper::pagination::{
+        add_pagination_link_header, build_handlebars_pagination_result,
+        pagination_out_of_range_redirect,
+    },
+    utils::{
+        csrf::handlebars_add_csrf_token,
+        flash::handle_flash_message,
+        handlebars_helper::update_handlebars_data,
+        pagination::QueryPagination,
+        session::handlebars_add_user,
+        users::{get_session_user, session_user_is_moderator},
+    },
+    AppKit,
+};
+
+// #[get("/trash/{fetch_mode:.*}")]
+pub async fn trash_view_route(
+    app_kit: web::Data<AppKit>,
+    req: HttpRequest,
+    session: Session,
+    fetch_mode: OptionalFetchMode,
+    pagination: QueryPagination,
+    hb: web::Data<Handlebars<'_>>,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let fetch_mode = fetch_mode.0;
+    let fetch_mode_clone = fetch_mode.clone();
+
+    let trash_policy = app_kit.trash_policy;
+
+    let mut hb_data = json!({
+        "parent": "base",
+        "title": "Trash",
+        "restore_grace_days": trash_policy.restore_grace_days,
+        "purge_after_days": trash_policy.purge_after_days,
+    });
+
+    let (trash_items, pagination_result, is_moderator) = web::block(move || {
+        // moderators look after the trash of everyone, users only see their own
+        let is_moderator =
+            session_user_is_moderator(app_kit.user_service.as_ref(), session_user.id);
+        let owner = match is_moderator {
+            true => None,
+            false => Some(session_user.id),
+        };
+
+        let (trash_items, total) = match fetch_mode_clone.as_str() {
+            "posts" => {
+                let deleted_posts = app_kit
+                    .post_service
+                    .get_deleted_posts(owner, &pagination)
+                    .map_err(|e| WebError::from(e.to_string()))?;
+
+                let trash_posts: Vec<TrashPostPublic> = deleted_posts
+                    .posts
+                    .into_iter()
+                    .filter_map(|post| {
+                        let deleted_at = post.post.deleted_at?;
+                        Some(TrashPostPublic {
+                            trash: TrashStatePublic::new(
+                                &trash_policy,
+                                deleted_at,
+                                post.post.hidden_at.is_some(),
+                                is_moderator,
+                            ),
+                            post,
+                        })
+                    })
+                    .collect();
+
+                (json!(trash_posts), deleted_posts.total)
+            }
+
+            "comments" => {
+                let deleted_comments = app_kit
+                    .comment_service
+                    .get_deleted_comments(owner, &pagination)
+                    .map_err(|e| WebError::from(e.to_string()))?;
+
+                let trash_comments: Vec<TrashCommentPublic> = deleted_comments
+                    .comments
+                    .into_iter()
+                    .filter_map(|comment| {
+                        let deleted_at = comment.comment.deleted_at?;
+                        Some(TrashCommentPublic {
+                            trash: TrashStatePublic::new(
+                                &trash_policy,
+                                deleted_at,
+                                comment.comment.hidden_at.is_some(),
+                                is_moderator,
+                            ),
+                            comment,
+                        })
+                    })
+                    .collect();
+
+                (json!(trash_comments), deleted_comments.total)
+            }
+
+            _ => return Err(WebError::from("no fetch mode was provide")),
+        };
+
+        Ok((
+            trash_items,
+            build_handlebars_pagination_result(total, &pagination),
+            is_moderator,
+        ))
+    })
+    .await?
+    .map_err(actix_web::error::ErrorNotFound)?;
+
+    if let Some(redirect) = pagination_out_of_range_redirect(&req, &pagination_result) {
+        return Ok(redirect);
+    }
+
+    update_handlebars_data(&mut hb_data, "trash_items", trash_items);
+    update_handlebars_data(
+        &mut hb_data,
+        &format!("fetch_mode_{}", fetch_mode),
+        json!(true),
+    );
+    update_handlebars_data(&mut hb_data, "is_moderator", json!(is_moderator));
+    update_handlebars_data(&mut hb_data, "pagination_result", json!(pagination_result));
+
+    handlebars_add_user(&session, &mut hb_data)?;
+    handlebars_add_csrf_token(&session, &mut hb_data)?;
+    handle_flash_message(&mut hb_data, &session);
+
+    let body = hb
+        .render("trash/index", &hb_data)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    add_pagination_link_header(&mut response, &req, &pagination_result);
+
+    Ok(response.content_type(ContentType::html()).body(body))
+}
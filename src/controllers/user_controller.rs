@@ -1,7 +1,9 @@
 use actix_multipart::form::MultipartForm;
 use actix_session::Session;
 use actix_web::{
-    error, get, post,
+    error, get,
+    http::header::ContentDisposition,
+    post,
     web::{self},
     HttpRequest, HttpResponse, Responder,
 };
@@ -11,12 +13,25 @@ use serde_json::json;
 
 use crate::{
     db::WebError,
-    entities::user::{user_to_user_public, validate_user_password, SESSION_KEY_USER},
+    entities::{
+        account::{UserDataExportQueryString, UserDeleteAccountFormData},
+        user::{
+            user_to_user_profile_public, user_to_user_public, validate_user_password,
+            SESSION_KEY_LOGIN_CHALLENGE, SESSION_KEY_USER,
+        },
+    },
     models::UpdateUserNameAndProfilePicture,
+    servers::actix_etc::actix_rate_limit_middleware::RateLimit,
+    services::{
+        rate_limit_service::RESET_PASSWORD_RATE_LIMIT_POLICY,
+        user_service::{UserServiceError, USERNAME_CHANGE_COOLDOWN_DAYS},
+    },
     utils::{
+        avatar::{avatar_file_keys, avatar_file_name, process_avatar, AVATAR_DEFAULT_SIZE},
+        csrf::handlebars_add_csrf_token,
         flash::{handle_flash_message, set_flash_message, FLASH_ERROR, FLASH_SUCCESS},
         handlebars_helper::update_handlebars_data,
-        http::{create_redirect, redirect_back},
+        http::{create_redirect, get_client_ip, redirect_back},
         users::get_session_user,
     },
     validate_password_and_confirm_password, AppKit,
@@ -24,14 +39,17 @@ use crate::{
 
 use crate::entities::user::{
     UserChangePasswordFormData, UserLoginFormData, UserPasswordResetRequest,
-    UserPasswordResetTokenQueryString, UserPasswordResetTokenRequest, UserRegisterFormData,
-    UserUpdateFormData, UserUploadProfilePictureForm,
+    UserPasswordResetTokenQueryString, UserPasswordResetTokenRequest, UserProfileFormData,
+    UserRegisterFormData, UserSetPasswordFormData, UserUpdateFormData,
+    UserUploadProfilePictureForm, UserUsernameFormData,
 };
 
 #[get("/login")]
 pub async fn users_login_route(
+    app_kit: web::Data<AppKit>,
     hb: web::Data<Handlebars<'_>>,
     session: Session,
+    req: HttpRequest,
 ) -> actix_web::Result<impl Responder> {
     if get_session_user(&session).is_ok() {
         set_flash_message(&session, "error", "User already logged in!")?;
@@ -42,8 +60,29 @@ pub async fn users_login_route(
         "parent": "base"
     });
 
+    // only show turnstile after repeated failures from this client or session
+    let session_requires_challenge = session
+        .get::<bool>(SESSION_KEY_LOGIN_CHALLENGE)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+
+    let oauth_providers = app_kit.oauth_service.get_providers();
+
+    let client_ip = get_client_ip(&req);
+    let show_turnstile = session_requires_challenge
+        || web::block(move || {
+            app_kit
+                .user_service
+                .login_requires_challenge(None, &client_ip)
+        })
+        .await?;
+
     update_handlebars_data(&mut hb_data, "title", json!("Login"));
+    update_handlebars_data(&mut hb_data, "show_turnstile", json!(show_turnstile));
+    update_handlebars_data(&mut hb_data, "oauth_providers", json!(oauth_providers));
     handle_flash_message(&mut hb_data, &session);
+    handlebars_add_csrf_token(&session, &mut hb_data)?;
     let body = hb.render("users/login", &hb_data).unwrap();
 
     // dbg!(&hb_data);
@@ -61,6 +100,7 @@ pub async fn users_login_post_route(
 ) -> actix_web::Result<impl Responder> {
     let mut data = json!({
         "parent": "base",
+        "title": "Login",
     });
 
     // get what client send
@@ -81,12 +121,45 @@ pub async fn users_login_post_route(
     //     return Ok(crate::utils::http::redirect_back(&req));
     // }
 
-    crate::validate_turnstile_field!(form, session, req);
+    let client_ip = get_client_ip(&req);
+
+    update_handlebars_data(
+        &mut data,
+        "oauth_providers",
+        json!(app_kit.oauth_service.get_providers()),
+    );
+
+    let challenge_email = form.email.clone();
+    let challenge_client_ip = client_ip.clone();
+    let challenge_app_kit = app_kit.clone();
+    let requires_challenge = web::block(move || {
+        challenge_app_kit
+            .user_service
+            .login_requires_challenge(Some(&challenge_email), &challenge_client_ip)
+    })
+    .await?;
 
-    let login_result =
-        web::block(move || app_kit.user_service.login_user(&form.email, &form.password))
-            .await
-            .map_err(actix_web::error::ErrorInternalServerError)?;
+    if requires_challenge {
+        // keep showing the challenge even if the client changes address
+        session.insert(SESSION_KEY_LOGIN_CHALLENGE, true)?;
+
+        crate::validate_turnstile_field!(form, session, req);
+    }
+
+    let (login_result, show_turnstile) = web::block(move || {
+        let login_result = app_kit
+            .user_service
+            .login_user(&form.email, &form.password, &client_ip);
+
+        let show_turnstile = login_result.is_err()
+            && app_kit
+                .user_service
+                .login_requires_challenge(Some(&form.email), &client_ip);
+
+        (login_result, show_turnstile)
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
 
     match login_result {
         Ok(user) => {
@@ -94,21 +167,34 @@ pub async fn users_login_post_route(
 
             // set session user value
             session.insert(SESSION_KEY_USER, user_public)?;
+            session.remove(SESSION_KEY_LOGIN_CHALLENGE);
 
             return Ok(create_redirect("/"));
         }
 
+        Err(UserServiceError::ErrorLogin(reason)) => {
+            update_handlebars_data(&mut data, "error", json!(reason.to_string()));
+        }
+
         Err(_) => {
             update_handlebars_data(&mut data, "error", json!("Invalid login"));
         }
     }
 
+    if show_turnstile {
+        session.insert(SESSION_KEY_LOGIN_CHALLENGE, true)?;
+    }
+
+    update_handlebars_data(&mut data, "show_turnstile", json!(show_turnstile));
+    handlebars_add_csrf_token(&session, &mut data)?;
+
     let body = hb.render("users/login", &data).unwrap();
     Ok(HttpResponse::Ok().body(body))
 }
 
 #[get("/register")]
 pub async fn users_register_route(
+    app_kit: web::Data<AppKit>,
     hb: web::Data<Handlebars<'_>>,
     session: Session,
 ) -> actix_web::Result<impl Responder> {
@@ -123,7 +209,13 @@ pub async fn users_register_route(
     });
 
     update_handlebars_data(&mut data, "title", json!("Register"));
+    update_handlebars_data(
+        &mut data,
+        "oauth_providers",
+        json!(app_kit.oauth_service.get_providers()),
+    );
     handle_flash_message(&mut data, &session);
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("users/register", &data).unwrap();
 
@@ -144,10 +236,16 @@ pub async fn users_register_post_route(
 
     crate::validate_turnstile_field!(form, session, req);
 
+    update_handlebars_data(
+        &mut hb_data,
+        "oauth_providers",
+        json!(app_kit.oauth_service.get_providers()),
+    );
+
     let create_user_result = web::block(move || {
         app_kit
             .user_service
-            .register_user(&form.name, &form.email, &form.password)
+            .register_user(&form.name, &form.username, &form.email, &form.password)
     })
     .await?;
 
@@ -158,10 +256,21 @@ pub async fn users_register_post_route(
             Ok(create_redirect("/"))
         }
 
+        Err(UserServiceError::ErrorUsername(reason)) => {
+            set_flash_message(&session, FLASH_ERROR, &reason.to_string())?;
+
+            handle_flash_message(&mut hb_data, &session);
+            handlebars_add_csrf_token(&session, &mut hb_data)?;
+
+            let body = hb.render("users/register", &hb_data).unwrap();
+            Ok(HttpResponse::Ok().body(body))
+        }
+
         Err(_) => {
             set_flash_message(&session, FLASH_ERROR, "Failed to register user.")?;
 
             handle_flash_message(&mut hb_data, &session);
+            handlebars_add_csrf_token(&session, &mut hb_data)?;
 
             let body = hb.render("users/register", &hb_data).unwrap();
             Ok(HttpResponse::Ok().body(body))
@@ -185,9 +294,8 @@ pub async fn users_settings_route(
     hb: web::Data<Handlebars<'_>>,
     session: Session,
 ) -> actix_web::Result<impl Responder> {
-    let session_user = get_session_user(&session).map_err(|e| {
+    let session_user = get_session_user(&session).inspect_err(|e| {
         dbg!("get session user err", &e);
-        e
     })?;
 
     dbg!(&session_user);
@@ -197,17 +305,50 @@ pub async fn users_settings_route(
         "parent": "base"
     });
 
-    let user_public_result = web::block(move || {
+    let oauth_providers = app_kit.oauth_service.get_providers();
+
+    let user_result = web::block(move || {
         // // we need to get updated data from db
         // get_user_sanitized_by_id(&mut conn, session_user.id)
         //     .map_err(|_| WebError::from("User by session not found"))
 
-        app_kit.user_service.get_user_by_id_public(session_user.id)
+        let user = app_kit.user_service.get_user_by_id(session_user.id)?;
+        let identities = app_kit.user_service.get_user_identities(session_user.id)?;
+
+        Ok::<_, UserServiceError>((user, identities))
     })
     .await?;
 
-    match user_public_result {
-        Ok(user) => update_handlebars_data(&mut hb_data, "user", json!(user)),
+    match user_result {
+        Ok((user, identities)) => {
+            let sign_in_providers: Vec<_> = oauth_providers
+                .iter()
+                .map(|provider| {
+                    let identity = identities.iter().find(|i| i.provider == provider.name);
+
+                    json!({
+                        "name": provider.name,
+                        "display_name": provider.display_name,
+                        "linked": identity.is_some(),
+                        "email": identity.and_then(|i| i.email.clone()),
+                    })
+                })
+                .collect();
+
+            update_handlebars_data(&mut hb_data, "user", json!(user_to_user_public(&user)));
+            update_handlebars_data(
+                &mut hb_data,
+                "profile",
+                json!(user_to_user_profile_public(&user)),
+            );
+            update_handlebars_data(
+                &mut hb_data,
+                "username_change_cooldown_days",
+                json!(USERNAME_CHANGE_COOLDOWN_DAYS),
+            );
+            update_handlebars_data(&mut hb_data, "has_password", json!(user.password.is_some()));
+            update_handlebars_data(&mut hb_data, "oauth_providers", json!(sign_in_providers));
+        }
 
         Err(why) => {
             dbg!(&why);
@@ -220,6 +361,7 @@ pub async fn users_settings_route(
     }
 
     handle_flash_message(&mut hb_data, &session);
+    handlebars_add_csrf_token(&session, &mut hb_data)?;
 
     let body = hb
         .render("users/settings", &hb_data)
@@ -277,6 +419,51 @@ pub async fn users_changepassword_post_route(
     Ok(create_redirect("/users/settings"))
 }
 
+#[post("/setpassword")]
+pub async fn users_setpassword_post_route(
+    app_kit: web::Data<AppKit>,
+    form: actix_web_validator::Form<UserSetPasswordFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    validate_password_and_confirm_password!(form);
+
+    let user = web::block(move || {
+        let user = app_kit
+            .user_service
+            .get_user_by_id(session_user.id)
+            .map_err(|_| WebError::from("User by session not found"))?;
+
+        // users with a password must confirm it through change password
+        if user.password.is_some() {
+            return Err(WebError::from("Password already set!"));
+        }
+
+        app_kit
+            .user_service
+            .update_user_password(user.id, &form.confirm_password)
+            .map_err(|_| WebError::from("failed to update user password"))
+    })
+    .await?;
+
+    match user {
+        Ok(_) => set_flash_message(
+            &session,
+            FLASH_SUCCESS,
+            "Password set, you can now login with email!",
+        )?,
+
+        Err(why) => set_flash_message(
+            &session,
+            FLASH_ERROR,
+            &format!("Failed to set user password! : {why}"),
+        )?,
+    }
+
+    Ok(create_redirect("/users/settings"))
+}
+
 #[post("/update")]
 pub async fn users_update_data_post_route(
     app_kit: web::Data<AppKit>,
@@ -313,22 +500,73 @@ pub async fn users_update_data_post_route(
     Ok(create_redirect("/users/settings"))
 }
 
+#[post("/username")]
+pub async fn users_update_username_post_route(
+    app_kit: web::Data<AppKit>,
+    form: actix_web_validator::Form<UserUsernameFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let user = web::block(move || {
+        app_kit
+            .user_service
+            .change_username(session_user.id, &form.new_username)
+    })
+    .await?;
+
+    match user {
+        Ok(user) => {
+            // the session keeps the handle shown in the navigation
+            session.insert(SESSION_KEY_USER, user_to_user_public(&user))?;
+            set_flash_message(&session, FLASH_SUCCESS, "Updated username")?
+        }
+
+        Err(why) => set_flash_message(
+            &session,
+            FLASH_ERROR,
+            &format!("Failed to update username! : {why}"),
+        )?,
+    }
+
+    Ok(create_redirect("/users/settings"))
+}
+
+#[post("/profile")]
+pub async fn users_update_profile_post_route(
+    app_kit: web::Data<AppKit>,
+    form: actix_web_validator::Form<UserProfileFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let user = web::block(move || {
+        app_kit
+            .user_service
+            .update_user_profile(session_user.id, &form)
+    })
+    .await?;
+
+    match user {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Updated profile")?,
+
+        Err(why) => set_flash_message(
+            &session,
+            FLASH_ERROR,
+            &format!("Failed to update profile! : {why}"),
+        )?,
+    }
+
+    Ok(create_redirect("/users/settings"))
+}
+
 #[post("/profilepicture")]
 pub async fn users_profile_picture_upload_post_route(
     app_kit: web::Data<AppKit>,
     session: Session,
     MultipartForm(form): MultipartForm<UserUploadProfilePictureForm>,
 ) -> actix_web::Result<impl Responder> {
-    // check content type
-    let content_type = form
-        .new_profile_picture
-        .content_type
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid MIME type"))?;
-
-    let content_type_string = content_type.to_string();
-    if content_type_string != "image/jpeg" && content_type_string != "image/png" {
-        return Err(actix_web::error::ErrorBadRequest("Invalid file type"));
-    }
+    let session_user = get_session_user(&session)?;
 
     // check file size
     let size = form.new_profile_picture.size;
@@ -336,46 +574,41 @@ pub async fn users_profile_picture_upload_post_route(
         return Err(actix_web::error::ErrorBadRequest("File too large"));
     }
 
-    // generate save file path
-    let static_file_dir_path =
-        std::env::var("STATIC_FILE_DIR").unwrap_or("/app/static".to_string());
-    let unix_time = chrono::Utc::now().timestamp();
-    let filename = format!("{}.jpg", unix_time);
-    let static_file_path = format!("{static_file_dir_path}/{filename}");
-
-    let session_user = get_session_user(&session)?;
-
-    web::block(move || {
-        dbg!(&static_file_path);
-
-        // save file to static file directory
-
-        // fixing Invalid cross-device link (os error 18)
-        // this happens when we try to rename a file to different filesystem
-        // we fix by persist the file to a temporary location first
-        // then copy it to the static file directory and delete the temporary file
-        let tmp_file_path = format!("/tmp/{unix_time}.jpg");
-        form.new_profile_picture
-            .file
-            .persist(&tmp_file_path)
-            .map_err(WebError::from)?;
-
-        // copy the file to the static file directory
-        std::fs::copy(&tmp_file_path, &static_file_path).map_err(|e| {
-            WebError::from(format!("Failed to copy file to static directory: {}", e))
-        })?;
-
-        // delete the temporary file
-        std::fs::remove_file(&tmp_file_path)
-            .map_err(|e| WebError::from(format!("Failed to delete temporary file: {}", e)))?;
-
-        println!(
-            "saved profile picture of user {} to {}",
-            session_user.id, static_file_path
-        );
+    let result = web::block(move || {
+        let upload_bytes = std::fs::read(form.new_profile_picture.file.path())
+            .map_err(|e| WebError::from(format!("Failed to read uploaded file: {}", e)))?;
+
+        // the content type sent by the client is ignored, the bytes must decode as an image
+        let avatar_id = uuid::Uuid::new_v4().simple().to_string();
+        let variants =
+            process_avatar(&avatar_id, &upload_bytes).map_err(|e| WebError::from(e.to_string()))?;
+
+        let file_storage = app_kit.file_storage.clone();
+        let remove_files = |keys: &[String]| {
+            for key in keys {
+                if let Err(e) = file_storage.delete_file(key) {
+                    println!("failed to remove avatar file {}: {}", key, e);
+                }
+            }
+        };
+
+        let variant_keys: Vec<String> = variants.iter().map(|v| v.file_name.clone()).collect();
+
+        for variant in &variants {
+            let content_type = match variant.file_name.ends_with(".webp") {
+                true => "image/webp",
+                false => "image/jpeg",
+            };
+
+            if let Err(e) = file_storage.put_file(&variant.file_name, &variant.bytes, content_type)
+            {
+                remove_files(&variant_keys);
+                return Err(WebError::from(e.to_string()));
+            }
+        }
 
-        // add slash to make it accessible from client
-        let public_static_url = format!("/static/{}", &filename);
+        let public_static_url =
+            file_storage.public_url(&avatar_file_name(&avatar_id, AVATAR_DEFAULT_SIZE, "jpg"));
 
         let db_user = app_kit
             .user_service
@@ -383,25 +616,43 @@ pub async fn users_profile_picture_upload_post_route(
             .map_err(|_| WebError::from("User by session not found"))?;
 
         // update user data with new profile image url
-        app_kit
-            .user_service
-            .update_user_data(
-                db_user.id,
-                &UpdateUserNameAndProfilePicture {
-                    name: None,
-                    user_profile_picture_url: Some(&public_static_url),
-                },
-            )
-            .map_err(|e| WebError::from(e.to_string()))?;
+        let update_result = app_kit.user_service.update_user_data(
+            db_user.id,
+            &UpdateUserNameAndProfilePicture {
+                name: None,
+                user_profile_picture_url: Some(&public_static_url),
+            },
+        );
+
+        if let Err(e) = update_result {
+            remove_files(&variant_keys);
+            return Err(WebError::from(e.to_string()));
+        }
+
+        // the replaced avatar is no longer referenced
+        if let Some(old_key) = db_user
+            .user_profile_picture_url
+            .as_deref()
+            .and_then(|url| file_storage.key_from_public_url(url))
+        {
+            remove_files(&avatar_file_keys(&old_key));
+        }
 
         println!("user profile picture uploaded: {}", &public_static_url);
 
         Ok::<_, WebError>(())
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await?;
 
-    set_flash_message(&session, FLASH_SUCCESS, "Profile picture uploaded")?;
+    match result {
+        Ok(_) => set_flash_message(&session, FLASH_SUCCESS, "Profile picture uploaded")?,
+
+        Err(why) => set_flash_message(
+            &session,
+            FLASH_ERROR,
+            &format!("Failed to upload profile picture! : {why}"),
+        )?,
+    }
 
     Ok(create_redirect("/users/settings"))
 }
@@ -423,13 +674,17 @@ pub async fn users_resetpassword_route(
     });
 
     handle_flash_message(&mut data, &session);
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("users/resetpassword", &data).unwrap();
 
     Ok(HttpResponse::Ok().body(body))
 }
 
-#[post("/resetpassword")]
+#[post(
+    "/resetpassword",
+    wrap = "RateLimit::new(RESET_PASSWORD_RATE_LIMIT_POLICY)"
+)]
 pub async fn users_resetpassword_post_route(
     app_kit: web::Data<AppKit>,
     form: actix_web_validator::Form<UserPasswordResetRequest>,
@@ -499,6 +754,7 @@ pub async fn users_resetpasswordtoken_route(
     });
 
     handle_flash_message(&mut data, &session);
+    handlebars_add_csrf_token(&session, &mut data)?;
 
     let body = hb.render("users/resetpasswordtoken", &data).unwrap();
 
@@ -542,3 +798,101 @@ pub async fn users_resetpasswordtoken_post_route(
 
     Ok(create_redirect("/users/login"))
 }
+
+#[get("/export")]
+pub async fn users_export_route(
+    app_kit: web::Data<AppKit>,
+    query: web::Query<UserDataExportQueryString>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+    let as_json = query.format.as_deref() == Some("json");
+
+    let (exported_at, bytes) = web::block(move || {
+        let export = app_kit
+            .account_service
+            .export_user_data(session_user.id)
+            .map_err(|e| WebError::from(e.to_string()))?;
+
+        let bytes = match as_json {
+            true => export
+                .to_json()
+                .map_err(|e| WebError::from(e.to_string()))?,
+            false => export
+                .to_zip(app_kit.file_storage.as_ref())
+                .map_err(|e| WebError::from(e.to_string()))?,
+        };
+
+        Ok::<_, WebError>((export.exported_at, bytes))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let (extension, content_type) = match as_json {
+        true => ("json", "application/json"),
+        false => ("zip", "application/zip"),
+    };
+
+    let file_name = format!(
+        "rust-forum-data-{}.{}",
+        exported_at.format("%Y%m%d%H%M%S"),
+        extension
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition::attachment(file_name))
+        .body(bytes))
+}
+
+#[post("/delete")]
+pub async fn users_delete_account_post_route(
+    app_kit: web::Data<AppKit>,
+    form: actix_web_validator::Form<UserDeleteAccountFormData>,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    let session_user = get_session_user(&session)?;
+
+    let result = web::block(move || {
+        let user = app_kit
+            .user_service
+            .get_user_by_id(session_user.id)
+            .map_err(|_| WebError::from("User by session not found"))?;
+
+        if !user.email.eq_ignore_ascii_case(form.confirm_email.trim()) {
+            return Err(WebError::from("Email does not match your account!"));
+        }
+
+        // accounts created through a sign in provider have no password to confirm
+        if user.password.is_some()
+            && !validate_user_password(&user, form.password.as_deref().unwrap_or_default())
+        {
+            return Err(WebError::from("Invalid password!"));
+        }
+
+        app_kit
+            .account_service
+            .delete_account(user.id)
+            .map_err(|e| WebError::from(e.to_string()))
+    })
+    .await?;
+
+    match result {
+        Ok(_) => {
+            session.clear();
+            set_flash_message(&session, FLASH_SUCCESS, "Your account has been deleted")?;
+
+            Ok(create_redirect("/"))
+        }
+
+        Err(why) => {
+            set_flash_message(
+                &session,
+                FLASH_ERROR,
+                &format!("Failed to delete account! : {why}"),
+            )?;
+
+            Ok(create_redirect("/users/settings"))
+        }
+    }
+}
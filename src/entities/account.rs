@@ -0,0 +1,110 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    models::{Attachment, Comment, Post, User, UserIdentity},
+    services::file_storage_service::{FileStorage, FileStorageError},
+    utils::zip::{ZipArchiveBuilder, ZipLimitError},
+};
+
+/// File name of the JSON document inside the export archive
+pub const EXPORT_DATA_FILE_NAME: &str = "data.json";
+
+#[derive(Serialize, Debug)]
+pub struct UserProfileExport {
+    pub id: i32,
+    pub name: String,
+    pub username: String,
+    pub email: String,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub user_profile_picture_url: Option<String>,
+    pub has_password: bool,
+    pub bio: String,
+    pub website: String,
+    pub location: String,
+    pub pronouns: String,
+    pub signature: String,
+}
+
+impl From<&User> for UserProfileExport {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name.clone(),
+            username: user.username.clone(),
+            email: user.email.clone(),
+            role: user.role.clone(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            user_profile_picture_url: user.user_profile_picture_url.clone(),
+            has_password: user.password.is_some(),
+            bio: user.bio.clone(),
+            website: user.website.clone(),
+            location: user.location.clone(),
+            pronouns: user.pronouns.clone(),
+            signature: user.signature.clone(),
+        }
+    }
+}
+
+/// Everything stored about a user
+#[derive(Serialize, Debug)]
+pub struct UserDataExport {
+    pub exported_at: NaiveDateTime,
+    pub profile: UserProfileExport,
+    pub identities: Vec<UserIdentity>,
+    pub posts: Vec<Post>,
+    pub comments: Vec<Comment>,
+    pub attachments: Vec<Attachment>,
+    /// Paths of the uploaded files inside the archive
+    pub uploads: Vec<String>,
+    /// Storage keys of the uploaded files, keyed by their archive path
+    #[serde(skip)]
+    pub upload_files: Vec<(String, String)>,
+}
+
+impl UserDataExport {
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    /// Packs the JSON document and the uploaded files into a ZIP archive
+    pub fn to_zip(&self, file_storage: &dyn FileStorage) -> Result<Vec<u8>, FileStorageError> {
+        let mut archive = ZipArchiveBuilder::new(self.exported_at);
+
+        let json = self
+            .to_json()
+            .map_err(|e| FileStorageError::Io(e.to_string()))?;
+        let zip_error = |e: ZipLimitError| FileStorageError::Io(e.to_string());
+
+        archive
+            .add_file(EXPORT_DATA_FILE_NAME, &json)
+            .map_err(zip_error)?;
+
+        for (archive_path, key) in &self.upload_files {
+            archive
+                .add_file(archive_path, &file_storage.get_file(key)?)
+                .map_err(zip_error)?;
+        }
+
+        Ok(archive.finish())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserDataExportQueryString {
+    /// `zip` (default) or `json`
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct UserDeleteAccountFormData {
+    #[validate(email(message = "Invalid email format"))]
+    pub confirm_email: String,
+
+    /// Required when the account has a password
+    pub password: Option<String>,
+}
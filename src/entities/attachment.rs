@@ -0,0 +1,110 @@
+use actix_multipart::form::tempfile::TempFile;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Attachment;
+
+/// Maximum number of attachments on a single post or comment
+pub const ATTACHMENT_MAX_PER_TARGET: usize = 4;
+
+/// Size limit of an uploaded image, before re-encoding
+pub const ATTACHMENT_IMAGE_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// Size limit of any other uploaded file
+pub const ATTACHMENT_FILE_MAX_BYTES: usize = 10 * 1024 * 1024;
+
+/// Prefix of every attachment storage key
+pub const ATTACHMENT_KEY_PREFIX: &str = "attachments/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    /// JPEG, PNG or WebP, re-encoded and rendered inline
+    Image,
+    /// PDF, ZIP or plain text, only offered as a download
+    File,
+}
+
+impl AttachmentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentKind::Image => "image",
+            AttachmentKind::File => "file",
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        match self {
+            AttachmentKind::Image => ATTACHMENT_IMAGE_MAX_BYTES,
+            AttachmentKind::File => ATTACHMENT_FILE_MAX_BYTES,
+        }
+    }
+}
+
+/// Where an attachment belongs, exactly one post or one comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentTarget {
+    Post(i32),
+    Comment(i32),
+}
+
+/// A raw file as sent by the client
+pub struct AttachmentUpload {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl AttachmentUpload {
+    /// Reads the multipart temp files, skipping the empty part browsers send for an empty input
+    pub fn from_temp_files(files: &[TempFile]) -> std::io::Result<Vec<AttachmentUpload>> {
+        files
+            .iter()
+            .filter(|file| file.size > 0)
+            .map(|file| {
+                Ok(AttachmentUpload {
+                    file_name: file.file_name.clone().unwrap_or_default(),
+                    bytes: std::fs::read(file.file.path())?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A validated upload, ready to be stored
+#[derive(Debug)]
+pub struct PreparedAttachment {
+    pub kind: AttachmentKind,
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AttachmentPublic {
+    pub id: i32,
+    pub kind: String,
+    pub file_name: String,
+    pub size_human: String,
+    pub url: String,
+    pub is_image: bool,
+}
+
+impl From<&Attachment> for AttachmentPublic {
+    fn from(attachment: &Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            kind: attachment.kind.clone(),
+            file_name: attachment.file_name.clone(),
+            size_human: bytes_to_human_readable(attachment.size_bytes),
+            url: format!("/attachments/{}", attachment.id),
+            is_image: attachment.kind == AttachmentKind::Image.as_str(),
+        }
+    }
+}
+
+fn bytes_to_human_readable(size_bytes: i64) -> String {
+    match size_bytes {
+        s if s >= 1024 * 1024 => format!("{:.1} MB", s as f64 / (1024.0 * 1024.0)),
+        s if s >= 1024 => format!("{:.1} KB", s as f64 / 1024.0),
+        s => format!("{} B", s),
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entities::report::ReportTarget, models::AuditLogEntry, utils::time::time_to_human_readable,
+};
+
+/// Most entries a single JSON export returns
+pub const AUDIT_LOG_EXPORT_LIMIT: i64 = 10_000;
+
+/// A privileged action recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AuditAction {
+    RoleChange,
+    PostDelete,
+    CommentDelete,
+    PostRestore,
+    CommentRestore,
+    UserBan,
+    UserUnban,
+    ReportDismiss,
+    ReportDeleteContent,
+    ReportWarnUser,
+    ReportBanUser,
+    FilterRuleCreate,
+    FilterRuleDelete,
+    PostPin,
+    PostUnpin,
+    PostLock,
+    PostUnlock,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 17] = [
+        AuditAction::RoleChange,
+        AuditAction::PostDelete,
+        AuditAction::CommentDelete,
+        AuditAction::PostRestore,
+        AuditAction::CommentRestore,
+        AuditAction::UserBan,
+        AuditAction::UserUnban,
+        AuditAction::ReportDismiss,
+        AuditAction::ReportDeleteContent,
+        AuditAction::ReportWarnUser,
+        AuditAction::ReportBanUser,
+        AuditAction::FilterRuleCreate,
+        AuditAction::FilterRuleDelete,
+        AuditAction::PostPin,
+        AuditAction::PostUnpin,
+        AuditAction::PostLock,
+        AuditAction::PostUnlock,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::RoleChange => "user.role_change",
+            AuditAction::PostDelete => "post.delete",
+            AuditAction::CommentDelete => "comment.delete",
+            AuditAction::PostRestore => "post.restore",
+            AuditAction::CommentRestore => "comment.restore",
+            AuditAction::UserBan => "user.ban",
+            AuditAction::UserUnban => "user.unban",
+            AuditAction::ReportDismiss => "report.dismiss",
+            AuditAction::ReportDeleteContent => "report.delete_content",
+            AuditAction::ReportWarnUser => "report.warn_user",
+            AuditAction::ReportBanUser => "report.ban_user",
+            AuditAction::FilterRuleCreate => "filter_rule.create",
+            AuditAction::FilterRuleDelete => "filter_rule.delete",
+            AuditAction::PostPin => "post.pin",
+            AuditAction::PostUnpin => "post.unpin",
+            AuditAction::PostLock => "post.lock",
+            AuditAction::PostUnlock => "post.unlock",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::RoleChange => "Role changed",
+            AuditAction::PostDelete => "Post deleted",
+            AuditAction::CommentDelete => "Comment deleted",
+            AuditAction::PostRestore => "Post restored",
+            AuditAction::CommentRestore => "Comment restored",
+            AuditAction::UserBan => "User banned",
+            AuditAction::UserUnban => "Ban lifted",
+            AuditAction::ReportDismiss => "Reports dismissed",
+            AuditAction::ReportDeleteContent => "Reported content deleted",
+            AuditAction::ReportWarnUser => "Reported user warned",
+            AuditAction::ReportBanUser => "Reported user banned",
+            AuditAction::FilterRuleCreate => "Filter rule added",
+            AuditAction::FilterRuleDelete => "Filter rule removed",
+            AuditAction::PostPin => "Post pinned",
+            AuditAction::PostUnpin => "Post unpinned",
+            AuditAction::PostLock => "Post locked",
+            AuditAction::PostUnlock => "Post unlocked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<AuditAction> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+    }
+}
+
+/// What an audit log entry is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTarget {
+    Post(i32),
+    Comment(i32),
+    User(i32),
+    FilterRule(i32),
+}
+
+impl AuditTarget {
+    pub const KINDS: [&'static str; 4] = ["post", "comment", "user", "filter_rule"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditTarget::Post(_) => "post",
+            AuditTarget::Comment(_) => "comment",
+            AuditTarget::User(_) => "user",
+            AuditTarget::FilterRule(_) => "filter_rule",
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            AuditTarget::Post(id)
+            | AuditTarget::Comment(id)
+            | AuditTarget::User(id)
+            | AuditTarget::FilterRule(id) => *id,
+        }
+    }
+}
+
+impl From<ReportTarget> for AuditTarget {
+    fn from(target: ReportTarget) -> Self {
+        match target {
+            ReportTarget::Post(id) => AuditTarget::Post(id),
+            ReportTarget::Comment(id) => AuditTarget::Comment(id),
+            ReportTarget::User(id) => AuditTarget::User(id),
+        }
+    }
+}
+
+/// One privileged action, recorded after it succeeded
+#[derive(Debug)]
+pub struct AuditRecord<'a> {
+    pub actor_user_id: i32,
+    pub action: AuditAction,
+    pub target: AuditTarget,
+    pub reason: &'a str,
+    pub ip_address: &'a str,
+}
+
+/// Narrows the audit log, `None` fields match every entry
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AuditLogFilter {
+    pub action: Option<AuditAction>,
+    pub actor_user_id: Option<i32>,
+    pub target_type: Option<&'static str>,
+    pub target_id: Option<i32>,
+}
+
+/// The filter form of the audit log view and export, blank fields are ignored
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuditLogQuery {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_id: String,
+}
+
+impl AuditLogQuery {
+    pub fn to_filter(&self) -> Result<AuditLogFilter, String> {
+        let parse_id = |field: &str, value: &str| match value.trim() {
+            "" => Ok(None),
+            value => value
+                .parse::<i32>()
+                .map(Some)
+                .map_err(|_| format!("{} must be a number", field)),
+        };
+
+        let action = match self.action.as_str() {
+            "" => None,
+            value => Some(AuditAction::parse(value).ok_or("Unknown action")?),
+        };
+
+        let target_type = match self.target_type.as_str() {
+            "" => None,
+            value => Some(
+                AuditTarget::KINDS
+                    .into_iter()
+                    .find(|kind| *kind == value)
+                    .ok_or("Unknown target type")?,
+            ),
+        };
+
+        Ok(AuditLogFilter {
+            action,
+            actor_user_id: parse_id("Actor", &self.actor)?,
+            target_type,
+            target_id: parse_id("Target id", &self.target_id)?,
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuditLogEntryPublic {
+    #[serde(flatten)]
+    pub entry: AuditLogEntry,
+    pub actor_name: String,
+    pub action_label: String,
+    pub target_url: Option<String>,
+    pub time_human: String,
+}
+
+impl AuditLogEntryPublic {
+    pub fn new(entry: AuditLogEntry, actor_name: String) -> Self {
+        let target_url = match entry.target_type.as_str() {
+            "post" => Some(format!("/posts/{}", entry.target_id)),
+            "user" => Some(format!("/profile/{}", entry.target_id)),
+            _ => None,
+        };
+
+        Self {
+            actor_name,
+            action_label: AuditAction::parse(&entry.action)
+                .map(|action| action.label().to_string())
+                .unwrap_or(entry.action.clone()),
+            target_url,
+            time_human: time_to_human_readable(entry.created_at),
+            entry,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListAuditLogResult {
+    pub entries: Vec<AuditLogEntry>,
+    pub total: i64,
+}
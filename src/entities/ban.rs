@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    models::{User, UserBan},
+    utils::time::time_to_human_readable,
+};
+
+/// Ban lengths offered to moderators in days, 0 bans for good
+pub const BAN_DURATION_DAYS: [(i64, &str); 6] = [
+    (1, "1 day"),
+    (3, "3 days"),
+    (7, "1 week"),
+    (30, "1 month"),
+    (365, "1 year"),
+    (0, "Permanently"),
+];
+
+/// Permanent bans are stored as a ban that ends on the last day of year 9999
+pub fn permanent_ban_until() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(9999, 12, 31)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("permanent ban date is valid")
+}
+
+/// When a ban of one of the offered lengths issued now ends
+pub fn ban_until_from_days(days: i64) -> Option<NaiveDateTime> {
+    if !BAN_DURATION_DAYS.iter().any(|(d, _)| *d == days) {
+        return None;
+    }
+
+    match days {
+        0 => Some(permanent_ban_until()),
+        days => Some(Utc::now().naive_utc() + Duration::days(days)),
+    }
+}
+
+fn ban_until_to_human_readable(banned_until: NaiveDateTime) -> String {
+    match banned_until.year() >= permanent_ban_until().year() {
+        true => "permanently".to_string(),
+        false => format!("until {} UTC", time_to_human_readable(banned_until)),
+    }
+}
+
+/// The ban a user is currently under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveBan {
+    pub banned_until: NaiveDateTime,
+    pub reason: String,
+}
+
+impl ActiveBan {
+    /// The ban of a user, `None` when the user was never banned or the ban ran out
+    pub fn of_user(user: &User) -> Option<ActiveBan> {
+        let banned_until = user
+            .banned_until
+            .filter(|until| *until > Utc::now().naive_utc())?;
+
+        Some(ActiveBan {
+            banned_until,
+            reason: user.ban_reason.clone().unwrap_or_default(),
+        })
+    }
+
+    /// `permanently` or `until <time> UTC`
+    pub fn until_human(&self) -> String {
+        ban_until_to_human_readable(self.banned_until)
+    }
+}
+
+impl Display for ActiveBan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Your account is banned {}", self.until_human())?;
+
+        if !self.reason.is_empty() {
+            write!(f, ". Reason: {}", self.reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BanFormData {
+    pub duration_days: i64,
+
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Reason must be between 1 and 500 characters"
+    ))]
+    pub reason: String,
+}
+
+/// One entry of the ban history shown to moderators
+#[derive(Serialize, Debug)]
+pub struct UserBanPublic {
+    pub moderator_user_id: Option<i32>,
+    pub moderator_name: String,
+    /// `None` for an entry that lifted a ban
+    pub banned_until_human: Option<String>,
+    pub reason: String,
+    pub time_human: String,
+}
+
+impl UserBanPublic {
+    pub fn new(user_ban: &UserBan, moderator_name: String) -> Self {
+        Self {
+            moderator_user_id: user_ban.moderator_user_id,
+            moderator_name,
+            banned_until_human: user_ban.banned_until.map(ban_until_to_human_readable),
+            reason: user_ban.reason.clone(),
+            time_human: time_to_human_readable(user_ban.created_at),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::Bookmark;
+
+/// What a bookmark points at, one post or one comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookmarkTarget {
+    Post(i32),
+    Comment(i32),
+}
+
+impl BookmarkTarget {
+    /// Reads the `{target_kind}/{target_id}` pair used in bookmark routes
+    pub fn parse(kind: &str, id: i32) -> Option<BookmarkTarget> {
+        match kind {
+            "post" => Some(BookmarkTarget::Post(id)),
+            "comment" => Some(BookmarkTarget::Comment(id)),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BookmarkTarget::Post(_) => "post",
+            BookmarkTarget::Comment(_) => "comment",
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            BookmarkTarget::Post(id) | BookmarkTarget::Comment(id) => *id,
+        }
+    }
+
+    pub fn of_bookmark(bookmark: &Bookmark) -> Option<BookmarkTarget> {
+        match (bookmark.post_id, bookmark.comment_id) {
+            (Some(post_id), _) => Some(BookmarkTarget::Post(post_id)),
+            (_, Some(comment_id)) => Some(BookmarkTarget::Comment(comment_id)),
+            _ => None,
+        }
+    }
+}
+
+/// The folder and note of a bookmark, both optional
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct BookmarkFormData {
+    #[validate(length(max = 50, message = "Folder must be at most 50 characters"))]
+    #[serde(default)]
+    pub folder: String,
+
+    #[validate(length(max = 500, message = "Note must be at most 500 characters"))]
+    #[serde(default)]
+    pub note: String,
+}
+
+/// Narrows the Saved tab to one folder
+#[derive(Debug, Deserialize)]
+pub struct SavedQuery {
+    pub folder: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BookmarkPublic {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    pub target_kind: &'static str,
+    pub target_id: i32,
+    /// Where the content can be seen, `None` once it is no longer visible
+    pub target_url: Option<String>,
+    pub target_title: String,
+    pub target_excerpt: String,
+    pub time_human: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListBookmarkResult {
+    pub bookmarks: Vec<BookmarkPublic>,
+    pub total: i64,
+}
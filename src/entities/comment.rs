@@ -1,8 +1,11 @@
-use diesel::Queryable;
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use crate::models::{Comment, Post, User};
+use crate::{
+    entities::attachment::AttachmentPublic,
+    models::{Comment, Post, User},
+};
 
 #[derive(Deserialize, Validate)]
 pub struct CreateCommentFormData {
@@ -17,6 +20,24 @@ pub struct CreateCommentFormData {
     pub body: String,
 }
 
+/// The create form, posted as multipart so files can be attached
+#[derive(Debug, MultipartForm)]
+pub struct CreateCommentMultipartForm {
+    pub post_id: Text<i32>,
+    pub body: Text<String>,
+    #[multipart(limit = "10MB")]
+    pub attachments: Vec<TempFile>,
+}
+
+impl CreateCommentMultipartForm {
+    pub fn to_form_data(&self) -> CreateCommentFormData {
+        CreateCommentFormData {
+            post_id: *self.post_id,
+            body: self.body.clone(),
+        }
+    }
+}
+
 #[derive(Deserialize, Validate)]
 pub struct UpdateCommentFormData {
     #[validate(length(
@@ -27,13 +48,18 @@ pub struct UpdateCommentFormData {
     pub body: String,
 }
 
-#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct CommentPublic {
     pub comment: Comment,
     pub user: User,
     pub time_human: String,
+    /// Set once the content was edited after it was created
+    pub edited_time_human: Option<String>,
     pub allow_update: bool,
     pub parent_post: Option<Post>,
+    pub attachments: Vec<AttachmentPublic>,
+    /// Set when the session user saved the comment
+    pub is_bookmarked: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    models::{ContentFilterRule, User},
+    utils::time::time_to_human_readable,
+};
+
+pub const CONTENT_FILTER_NEW_ACCOUNT_DAYS_DEFAULT: i64 = 3;
+pub const CONTENT_FILTER_NEW_ACCOUNT_MAX_LINKS_DEFAULT: usize = 2;
+pub const CONTENT_FILTER_DUPLICATE_WINDOW_MINUTES_DEFAULT: i64 = 10;
+
+/// What happens to content a filter rule matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum FilterAction {
+    /// Published, with a report for the moderators
+    Flag,
+    /// Published hidden until a moderator dismisses the report
+    Hold,
+    /// Refused, the author gets the reason back
+    Reject,
+}
+
+impl FilterAction {
+    pub const ALL: [FilterAction; 3] =
+        [FilterAction::Reject, FilterAction::Hold, FilterAction::Flag];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterAction::Flag => "flag",
+            FilterAction::Hold => "hold",
+            FilterAction::Reject => "reject",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterAction::Flag => "Allow and flag",
+            FilterAction::Hold => "Hold for moderation",
+            FilterAction::Reject => "Reject",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<FilterAction> {
+        FilterAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+    }
+}
+
+/// The built-in rules of the content filter, the regex rules are managed by admins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentFilterPolicy {
+    /// Words and phrases matched case insensitively on word boundaries
+    pub blocked_words: Vec<String>,
+    pub blocked_words_action: FilterAction,
+    /// Accounts younger than this many days are limited in links, 0 turns the limit off
+    pub new_account_days: i64,
+    pub new_account_max_links: usize,
+    pub new_account_links_action: FilterAction,
+    /// Same content by the same author within this many minutes is a duplicate, 0 turns it off
+    pub duplicate_window_minutes: i64,
+    pub duplicate_action: FilterAction,
+}
+
+impl Default for ContentFilterPolicy {
+    fn default() -> Self {
+        Self {
+            blocked_words: vec![],
+            blocked_words_action: FilterAction::Reject,
+            new_account_days: CONTENT_FILTER_NEW_ACCOUNT_DAYS_DEFAULT,
+            new_account_max_links: CONTENT_FILTER_NEW_ACCOUNT_MAX_LINKS_DEFAULT,
+            new_account_links_action: FilterAction::Hold,
+            duplicate_window_minutes: CONTENT_FILTER_DUPLICATE_WINDOW_MINUTES_DEFAULT,
+            duplicate_action: FilterAction::Reject,
+        }
+    }
+}
+
+impl ContentFilterPolicy {
+    /// Reads the `CONTENT_FILTER_*` variables, unset ones keep their default
+    pub fn from_env() -> Self {
+        let read_var = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+        let read_number = |key: &str, default: i64| match read_var(key) {
+            Some(value) => value
+                .parse::<i64>()
+                .ok()
+                .filter(|number| *number >= 0)
+                .unwrap_or_else(|| panic!("{} must be a positive number, got {:?}", key, value)),
+            None => default,
+        };
+        let read_action = |key: &str, default: FilterAction| match read_var(key) {
+            Some(value) => FilterAction::parse(&value)
+                .unwrap_or_else(|| panic!("{} must be reject, hold or flag, got {:?}", key, value)),
+            None => default,
+        };
+
+        let policy = Self::default();
+
+        Self {
+            blocked_words: read_var("CONTENT_FILTER_BLOCKED_WORDS")
+                .map(|words| {
+                    words
+                        .split(',')
+                        .map(|word| word.trim().to_string())
+                        .filter(|word| !word.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            blocked_words_action: read_action(
+                "CONTENT_FILTER_BLOCKED_WORDS_ACTION",
+                policy.blocked_words_action,
+            ),
+            new_account_days: read_number(
+                "CONTENT_FILTER_NEW_ACCOUNT_DAYS",
+                policy.new_account_days,
+            ),
+            new_account_max_links: read_number(
+                "CONTENT_FILTER_NEW_ACCOUNT_MAX_LINKS",
+                policy.new_account_max_links as i64,
+            ) as usize,
+            new_account_links_action: read_action(
+                "CONTENT_FILTER_NEW_ACCOUNT_LINKS_ACTION",
+                policy.new_account_links_action,
+            ),
+            duplicate_window_minutes: read_number(
+                "CONTENT_FILTER_DUPLICATE_WINDOW_MINUTES",
+                policy.duplicate_window_minutes,
+            ),
+            duplicate_action: read_action(
+                "CONTENT_FILTER_DUPLICATE_ACTION",
+                policy.duplicate_action,
+            ),
+        }
+    }
+}
+
+/// What is being written, a duplicate of the content being edited does not count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilteredTarget {
+    NewPost,
+    EditedPost(i32),
+    NewComment,
+}
+
+/// Content about to be stored, checked against the filter
+#[derive(Debug)]
+pub struct FilteredContent<'a> {
+    pub author: &'a User,
+    pub target: FilteredTarget,
+    /// Empty for comments
+    pub title: &'a str,
+    pub body: &'a str,
+}
+
+/// One rule that matched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterHit {
+    pub action: FilterAction,
+    pub reason: String,
+}
+
+/// The rules some content matched, the strictest action applies
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContentFilterVerdict {
+    pub hits: Vec<FilterHit>,
+}
+
+impl ContentFilterVerdict {
+    /// The action to take, `None` when no rule matched
+    pub fn action(&self) -> Option<FilterAction> {
+        self.hits.iter().map(|hit| hit.action).max()
+    }
+
+    /// Why the content was matched, one reason per rule
+    pub fn reasons(&self) -> String {
+        self.hits
+            .iter()
+            .map(|hit| hit.reason.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ContentFilterRuleFormData {
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Pattern must be between 1 and 500 characters"
+    ))]
+    pub pattern: String,
+
+    pub action: String,
+
+    #[validate(length(max = 200, message = "Description must be at most 200 characters"))]
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContentFilterRulePublic {
+    #[serde(flatten)]
+    pub rule: ContentFilterRule,
+    pub action_label: String,
+    pub time_human: String,
+}
+
+impl ContentFilterRulePublic {
+    pub fn new(rule: ContentFilterRule) -> Self {
+        Self {
+            action_label: FilterAction::parse(&rule.action)
+                .map(|action| action.label().to_string())
+                .unwrap_or(rule.action.clone()),
+            time_human: time_to_human_readable(rule.created_at),
+            rule,
+        }
+    }
+}
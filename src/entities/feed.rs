@@ -0,0 +1,146 @@
+use chrono::{DateTime, NaiveDateTime};
+use serde::Serialize;
+
+use crate::entities::{comment::CommentPublic, post::PostPublic};
+
+/// Most entries a feed carries, the newest ones
+pub const FEED_ENTRY_LIMIT: i64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    /// Reads the format from the extension of the feed url
+    pub fn parse(extension: &str) -> Option<FeedFormat> {
+        match extension {
+            "atom" => Some(FeedFormat::Atom),
+            "rss" => Some(FeedFormat::Rss),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+
+    pub fn template(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "feeds/atom",
+            FeedFormat::Rss => "feeds/rss",
+        }
+    }
+}
+
+/// Timestamp as written in Atom feeds
+fn format_atom_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Timestamp as written in RSS feeds
+fn format_rss_time(time: NaiveDateTime) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[derive(Serialize, Debug)]
+pub struct FeedEntry {
+    /// Absolute link to the content, doubles as the entry id
+    pub url: String,
+    pub title: String,
+    pub author_name: String,
+    pub author_url: String,
+    pub content: String,
+    #[serde(skip)]
+    pub updated_at: NaiveDateTime,
+    pub published_atom: String,
+    pub updated_atom: String,
+    pub published_rss: String,
+}
+
+impl FeedEntry {
+    fn new(
+        url: String,
+        title: String,
+        author_name: String,
+        author_url: String,
+        content: String,
+        created_at: NaiveDateTime,
+        updated_at: NaiveDateTime,
+    ) -> Self {
+        Self {
+            url,
+            title,
+            author_name,
+            author_url,
+            content,
+            updated_at,
+            published_atom: format_atom_time(created_at),
+            updated_atom: format_atom_time(updated_at),
+            published_rss: format_rss_time(created_at),
+        }
+    }
+
+    pub fn from_post(app_url: &str, post: &PostPublic) -> Self {
+        Self::new(
+            format!("{}/posts/{}", app_url, post.post.id),
+            post.post.title.clone(),
+            post.user.name.clone(),
+            format!("{}/profile/{}", app_url, post.user.id),
+            post.post.body.clone(),
+            post.post.created_at,
+            post.post.updated_at,
+        )
+    }
+
+    pub fn from_comment(app_url: &str, comment: &CommentPublic, post_title: &str) -> Self {
+        Self::new(
+            format!(
+                "{}/posts/{}#{}",
+                app_url, comment.comment.post_id, comment.comment.id
+            ),
+            format!("Re: {}", post_title),
+            comment.user.name.clone(),
+            format!("{}/profile/{}", app_url, comment.user.id),
+            comment.comment.content.clone(),
+            comment.comment.created_at,
+            comment.comment.updated_at,
+        )
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Feed {
+    pub title: String,
+    /// Absolute link to the page the feed follows
+    pub url: String,
+    /// Absolute link to the feed itself
+    pub self_url: String,
+    /// Latest change of any entry, `None` for an empty feed
+    #[serde(skip)]
+    pub updated_at: Option<NaiveDateTime>,
+    pub updated_atom: String,
+    pub updated_rss: String,
+    pub entries: Vec<FeedEntry>,
+}
+
+impl Feed {
+    pub fn new(title: String, url: String, self_url: String, entries: Vec<FeedEntry>) -> Self {
+        let updated_at = entries.iter().map(|entry| entry.updated_at).max();
+        let updated = updated_at.unwrap_or(DateTime::UNIX_EPOCH.naive_utc());
+
+        Self {
+            title,
+            url,
+            self_url,
+            updated_at,
+            updated_atom: format_atom_time(updated),
+            updated_rss: format_rss_time(updated),
+            entries,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+use super::user::UserPublic;
+
+/// How many users follow a user and how many it follows
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FollowCounts {
+    pub followers: i64,
+    pub following: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListFollowResult {
+    /// Most recently followed first
+    pub users: Vec<UserPublic>,
+    pub total: i64,
+}
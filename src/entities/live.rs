@@ -0,0 +1,83 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::models::Comment;
+
+/// Events buffered per channel, a listener further behind skips the oldest ones
+pub const LIVE_CHANNEL_CAPACITY: usize = 64;
+
+/// Seconds between the comments sent to keep idle event streams open through proxies
+pub const LIVE_KEEP_ALIVE_SECONDS: u64 = 20;
+
+/// Who an event is fanned out to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiveChannel {
+    /// Everyone viewing a post
+    Post(i32),
+    /// Every open page of a logged in user
+    User(i32),
+}
+
+/// A comment as pushed to the readers of a post
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveComment {
+    pub id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    pub user_name: String,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl LiveComment {
+    pub fn new(comment: &Comment, user_name: &str) -> Self {
+        Self {
+            id: comment.id,
+            post_id: comment.post_id,
+            user_id: comment.user_id,
+            user_name: user_name.to_string(),
+            content: comment.content.clone(),
+            created_at: comment.created_at,
+        }
+    }
+}
+
+/// Something that changed, serialized as the data of a server-sent event
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    CommentCreated {
+        comment: LiveComment,
+    },
+    CommentUpdated {
+        comment_id: i32,
+        content: String,
+    },
+    CommentDeleted {
+        comment_id: i32,
+    },
+    PostUpdated {
+        title: String,
+        body: String,
+    },
+    PostDeleted,
+    PostLocked {
+        locked: bool,
+    },
+    /// The unread notifications of the user, replies on their posts and subscriptions
+    UnreadNotifications {
+        count: i64,
+    },
+    /// The unread messages of the user over every conversation
+    UnreadMessages {
+        count: i64,
+    },
+}
+
+impl LiveEvent {
+    /// The event framed for a `text/event-stream` response
+    pub fn to_event_stream(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_default();
+        format!("data: {}\n\n", data)
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    entities::user::UserPublic,
+    models::{Conversation, Message},
+    utils::time::time_to_human_readable,
+};
+
+/// Most members of a group conversation, the starter included
+pub const CONVERSATION_MAX_MEMBERS: usize = 10;
+
+/// Longest message preview shown in the inbox
+const MESSAGE_PREVIEW_MAX_CHARS: usize = 80;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct NewConversationFormData {
+    /// Profile ids of the recipients, separated by commas
+    #[validate(length(min = 1, max = 200, message = "Add at least one recipient"))]
+    pub recipients: String,
+
+    /// Only used for group conversations
+    #[validate(length(max = 100, message = "Title must be at most 100 characters"))]
+    #[serde(default)]
+    pub title: String,
+
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Message must be at least 1 character and max 5000"
+    ))]
+    pub body: String,
+}
+
+impl NewConversationFormData {
+    /// The recipient ids, `None` when one of them is not a number
+    pub fn recipient_ids(&self) -> Option<Vec<i32>> {
+        let mut recipient_ids = vec![];
+
+        for recipient in self.recipients.split(',').map(str::trim) {
+            if recipient.is_empty() {
+                continue;
+            }
+            let recipient_id = recipient.parse::<i32>().ok()?;
+            if !recipient_ids.contains(&recipient_id) {
+                recipient_ids.push(recipient_id);
+            }
+        }
+
+        Some(recipient_ids)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MessageFormData {
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Message must be at least 1 character and max 5000"
+    ))]
+    pub body: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NewConversationQuery {
+    /// Recipient picked from a profile
+    #[serde(default)]
+    pub to: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MessagePublic {
+    #[serde(flatten)]
+    pub message: Message,
+    pub user: UserPublic,
+    pub time_human: String,
+    /// Only the author deletes a message
+    pub allow_delete: bool,
+}
+
+impl MessagePublic {
+    pub fn new(message: Message, user: UserPublic, session_user_id: i32) -> Self {
+        Self {
+            allow_delete: message.user_id == session_user_id && message.deleted_at.is_none(),
+            time_human: time_to_human_readable(message.created_at),
+            message,
+            user,
+        }
+    }
+}
+
+/// A conversation as listed in the inbox
+#[derive(Serialize, Debug)]
+pub struct ConversationSummary {
+    pub conversation: Conversation,
+    /// The title of a group, or the names of the other members
+    pub display_title: String,
+    pub members: Vec<UserPublic>,
+    pub last_message_preview: Option<String>,
+    pub unread_count: i64,
+    pub time_human: String,
+}
+
+impl ConversationSummary {
+    pub fn new(
+        conversation: Conversation,
+        members: Vec<UserPublic>,
+        last_message: Option<&Message>,
+        unread_count: i64,
+        session_user_id: i32,
+    ) -> Self {
+        Self {
+            display_title: conversation_display_title(&conversation, &members, session_user_id),
+            last_message_preview: last_message.map(message_preview),
+            time_human: time_to_human_readable(conversation.updated_at),
+            conversation,
+            members,
+            unread_count,
+        }
+    }
+}
+
+/// The title of a group, or the names of the members other than the session user
+pub fn conversation_display_title(
+    conversation: &Conversation,
+    members: &[UserPublic],
+    session_user_id: i32,
+) -> String {
+    if conversation.is_group && !conversation.title.is_empty() {
+        return conversation.title.clone();
+    }
+
+    let names = members
+        .iter()
+        .filter(|member| member.id != session_user_id)
+        .map(|member| member.name.as_str())
+        .collect::<Vec<_>>();
+
+    match names.is_empty() {
+        true => "Only you".to_string(),
+        false => names.join(", "),
+    }
+}
+
+/// First line of a message cut for the inbox
+fn message_preview(message: &Message) -> String {
+    if message.deleted_at.is_some() {
+        return "Message deleted".to_string();
+    }
+
+    let line = message.body.lines().next().unwrap_or_default();
+    match line.char_indices().nth(MESSAGE_PREVIEW_MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListConversationResult {
+    pub conversations: Vec<ConversationSummary>,
+    pub total: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListMessageResult {
+    /// Oldest first, the first page holds the newest messages
+    pub messages: Vec<MessagePublic>,
+    pub total: i64,
+}
@@ -1,3 +1,19 @@
+pub mod account;
+pub mod attachment;
+pub mod audit;
+pub mod ban;
+pub mod bookmark;
 pub mod comment;
+pub mod content_filter;
+pub mod feed;
+pub mod follow;
+pub mod live;
+pub mod message;
+pub mod notification;
+pub mod oauth;
 pub mod post;
+pub mod report;
+pub mod revision;
+pub mod sitemap;
+pub mod trash;
 pub mod user;
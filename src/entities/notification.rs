@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use crate::{models::Notification, utils::time::time_to_human_readable};
+
+/// Someone commented on a post of the user
+pub const NOTIFICATION_KIND_POST_REPLY: &str = "post_reply";
+
+/// Someone commented on a post the user subscribed to
+pub const NOTIFICATION_KIND_SUBSCRIPTION_REPLY: &str = "subscription_reply";
+
+#[derive(Serialize, Debug)]
+pub struct NotificationPublic {
+    #[serde(flatten)]
+    pub notification: Notification,
+    pub post_title: String,
+    /// Name of the user who wrote the comment
+    pub actor_name: String,
+    pub unread: bool,
+    pub time_human: String,
+}
+
+impl NotificationPublic {
+    pub fn new(notification: Notification, post_title: String, actor_name: String) -> Self {
+        Self {
+            post_title,
+            actor_name,
+            unread: notification.read_at.is_none(),
+            time_human: time_to_human_readable(notification.created_at),
+            notification,
+        }
+    }
+}
+
+/// Where a notification leads, the comment on its post
+pub fn notification_url(notification: &Notification) -> String {
+    format!(
+        "/posts/{}#{}",
+        notification.post_id, notification.comment_id
+    )
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListNotificationResult {
+    pub notifications: Vec<NotificationPublic>,
+    pub total: i64,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+pub const SESSION_KEY_OAUTH_STATE: &str = "oauth_state";
+
+/// Pending authorization request, kept in the session until the provider calls back
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OAuthSessionState {
+    pub state: String,
+    pub provider: String,
+    /// Set when a signed-in user is linking the provider from settings
+    pub link_user_id: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OAuthCallbackQueryString {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
@@ -1,5 +1,9 @@
-use crate::models::{Post, User};
-use diesel::Queryable;
+use crate::{
+    entities::attachment::AttachmentPublic,
+    models::{Post, User},
+    utils::slug::slugify,
+};
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
@@ -20,12 +24,33 @@ pub struct PostFormData {
     pub body: String,
 }
 
-#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// The create form, posted as multipart so files can be attached
+#[derive(Debug, MultipartForm)]
+pub struct CreatePostMultipartForm {
+    pub title: Text<String>,
+    pub body: Text<String>,
+    #[multipart(limit = "10MB")]
+    pub attachments: Vec<TempFile>,
+}
+
+impl CreatePostMultipartForm {
+    pub fn to_form_data(&self) -> PostFormData {
+        PostFormData {
+            title: self.title.clone(),
+            body: self.body.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct PostPublic {
     pub post: Post,
     pub user: User,
     pub time_human: String,
+    /// Set once the content was edited after it was created
+    pub edited_time_human: Option<String>,
     pub allow_update: bool,
+    pub attachments: Vec<AttachmentPublic>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -33,3 +58,20 @@ pub struct ListPostResult {
     pub posts: Vec<PostPublic>,
     pub total: i64,
 }
+
+/// Canonical path of a post, `/posts/{id}-{slug}` or `/posts/{id}` when the title gives no slug
+pub fn post_path(post_id: i32, title: &str) -> String {
+    match slugify(title) {
+        slug if slug.is_empty() => format!("/posts/{}", post_id),
+        slug => format!("/posts/{}-{}", post_id, slug),
+    }
+}
+
+/// Reads the post id from the last segment of a post path, the slug is ignored
+pub fn parse_post_path_segment(segment: &str) -> Option<i32> {
+    segment
+        .split_once('-')
+        .map_or(segment, |(post_id, _)| post_id)
+        .parse()
+        .ok()
+}
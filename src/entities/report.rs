@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::Report;
+
+/// Distinct reporters after which a post or comment is hidden until a moderator looks at it
+pub const REPORT_AUTO_HIDE_THRESHOLD_DEFAULT: i64 = 3;
+
+/// Shown as the reporter of reports filed by the content filter
+pub const REPORT_CONTENT_FILTER_REPORTER_NAME: &str = "Content filter";
+
+pub const REPORT_STATUS_OPEN: &str = "open";
+pub const REPORT_STATUS_DISMISSED: &str = "dismissed";
+pub const REPORT_STATUS_RESOLVED: &str = "resolved";
+
+pub const REPORT_RESOLUTION_DISMISSED: &str = "dismissed";
+pub const REPORT_RESOLUTION_CONTENT_DELETED: &str = "content_deleted";
+pub const REPORT_RESOLUTION_USER_WARNED: &str = "user_warned";
+pub const REPORT_RESOLUTION_USER_BANNED: &str = "user_banned";
+
+/// Reads `REPORT_AUTO_HIDE_THRESHOLD`, 0 turns automatic hiding off
+pub fn report_auto_hide_threshold_from_env() -> i64 {
+    match std::env::var("REPORT_AUTO_HIDE_THRESHOLD") {
+        Ok(value) if !value.is_empty() => value
+            .parse::<i64>()
+            .ok()
+            .filter(|threshold| *threshold >= 0)
+            .unwrap_or_else(|| {
+                panic!(
+                    "REPORT_AUTO_HIDE_THRESHOLD must be a number of reports, got {:?}",
+                    value
+                )
+            }),
+        _ => REPORT_AUTO_HIDE_THRESHOLD_DEFAULT,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ReportCategory {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Nsfw,
+    Misinformation,
+    Other,
+}
+
+impl ReportCategory {
+    pub const ALL: [ReportCategory; 6] = [
+        ReportCategory::Spam,
+        ReportCategory::Harassment,
+        ReportCategory::HateSpeech,
+        ReportCategory::Nsfw,
+        ReportCategory::Misinformation,
+        ReportCategory::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportCategory::Spam => "spam",
+            ReportCategory::Harassment => "harassment",
+            ReportCategory::HateSpeech => "hate_speech",
+            ReportCategory::Nsfw => "nsfw",
+            ReportCategory::Misinformation => "misinformation",
+            ReportCategory::Other => "other",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReportCategory::Spam => "Spam or advertising",
+            ReportCategory::Harassment => "Harassment or bullying",
+            ReportCategory::HateSpeech => "Hate speech",
+            ReportCategory::Nsfw => "Sexual or violent content",
+            ReportCategory::Misinformation => "Misinformation",
+            ReportCategory::Other => "Something else",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ReportCategory> {
+        ReportCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str() == value)
+    }
+}
+
+/// What a report points at, one post, one comment or one user profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportTarget {
+    Post(i32),
+    Comment(i32),
+    User(i32),
+}
+
+impl ReportTarget {
+    /// Reads the `{target_kind}/{target_id}` pair used in report and moderation routes
+    pub fn parse(kind: &str, id: i32) -> Option<ReportTarget> {
+        match kind {
+            "post" => Some(ReportTarget::Post(id)),
+            "comment" => Some(ReportTarget::Comment(id)),
+            "user" => Some(ReportTarget::User(id)),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReportTarget::Post(_) => "post",
+            ReportTarget::Comment(_) => "comment",
+            ReportTarget::User(_) => "user",
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            ReportTarget::Post(id) | ReportTarget::Comment(id) | ReportTarget::User(id) => *id,
+        }
+    }
+
+    pub fn of_report(report: &Report) -> Option<ReportTarget> {
+        match (report.post_id, report.comment_id, report.reported_user_id) {
+            (Some(post_id), _, _) => Some(ReportTarget::Post(post_id)),
+            (_, Some(comment_id), _) => Some(ReportTarget::Comment(comment_id)),
+            (_, _, Some(user_id)) => Some(ReportTarget::User(user_id)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReportFormData {
+    pub category: String,
+
+    #[validate(length(max = 1000, message = "Details must be at most 1000 characters"))]
+    pub details: String,
+}
+
+/// The optional note a moderator sends along with a queue action
+#[derive(Debug, Deserialize, Validate)]
+pub struct ModerationActionFormData {
+    #[validate(length(max = 1000, message = "Message must be at most 1000 characters"))]
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportPublic {
+    pub id: i32,
+    /// `None` for a report filed by the content filter
+    pub reporter_user_id: Option<i32>,
+    pub reporter_name: String,
+    pub category: String,
+    pub category_label: String,
+    pub details: String,
+    pub time_human: String,
+}
+
+/// The open reports of one target, grouped for the moderation queue
+#[derive(Serialize, Debug)]
+pub struct ReportQueueItemPublic {
+    pub target_kind: &'static str,
+    pub target_id: i32,
+    /// Where the content can be seen, `None` once it is no longer visible
+    pub target_url: Option<String>,
+    pub target_title: String,
+    pub target_excerpt: String,
+    pub target_user_id: Option<i32>,
+    pub target_user_name: String,
+    pub is_hidden: bool,
+    pub is_deleted: bool,
+    /// Only posts and comments still outside the trash can be deleted from the queue
+    pub allow_delete: bool,
+    pub reporter_count: usize,
+    pub reports: Vec<ReportPublic>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListReportQueueResult {
+    pub items: Vec<ReportQueueItemPublic>,
+    pub total: i64,
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{
+    entities::user::DELETED_USER_NAME,
+    models::{Comment, CommentRevision, Post, PostRevision},
+    utils::{
+        diff::{diff_lines, DiffLine},
+        time::time_to_human_readable,
+    },
+};
+
+/// A version of a post or a comment, posts carry a title as well
+pub struct RevisionVersion {
+    /// `None` for the live content of something that was never edited
+    pub revision_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub title: Option<String>,
+    pub content: String,
+}
+
+impl From<PostRevision> for RevisionVersion {
+    fn from(revision: PostRevision) -> Self {
+        Self {
+            revision_id: Some(revision.id),
+            user_id: revision.user_id,
+            created_at: revision.created_at,
+            title: Some(revision.title),
+            content: revision.body,
+        }
+    }
+}
+
+impl From<&Post> for RevisionVersion {
+    fn from(post: &Post) -> Self {
+        Self {
+            revision_id: None,
+            user_id: Some(post.user_id),
+            created_at: post.updated_at,
+            title: Some(post.title.clone()),
+            content: post.body.clone(),
+        }
+    }
+}
+
+impl From<CommentRevision> for RevisionVersion {
+    fn from(revision: CommentRevision) -> Self {
+        Self {
+            revision_id: Some(revision.id),
+            user_id: revision.user_id,
+            created_at: revision.created_at,
+            title: None,
+            content: revision.content,
+        }
+    }
+}
+
+impl From<&Comment> for RevisionVersion {
+    fn from(comment: &Comment) -> Self {
+        Self {
+            revision_id: None,
+            user_id: Some(comment.user_id),
+            created_at: comment.updated_at,
+            title: None,
+            content: comment.content.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RevisionPublic {
+    pub revision_id: Option<i32>,
+    pub number: usize,
+    pub user_id: Option<i32>,
+    pub user_name: String,
+    pub time_human: String,
+    pub title: Option<String>,
+    /// Empty when the title did not change
+    pub title_diff: Vec<DiffLine>,
+    pub diff: Vec<DiffLine>,
+    pub is_current: bool,
+}
+
+/// Diffs every version against the one before it, newest first
+///
+/// `versions` is ordered oldest first, the last one is the live content.
+pub fn versions_to_revisions_public(
+    versions: &[RevisionVersion],
+    user_names: &HashMap<i32, String>,
+) -> Vec<RevisionPublic> {
+    versions
+        .iter()
+        .enumerate()
+        .map(|(index, version)| {
+            let previous = index.checked_sub(1).map(|i| &versions[i]);
+
+            let previous_title = previous.and_then(|p| p.title.as_deref());
+            let title_diff = match (previous_title, version.title.as_deref()) {
+                (Some(old), Some(new)) if old != new => diff_lines(old, new),
+                _ => vec![],
+            };
+
+            RevisionPublic {
+                revision_id: version.revision_id,
+                number: index + 1,
+                user_id: version.user_id,
+                user_name: version
+                    .user_id
+                    .and_then(|id| user_names.get(&id).cloned())
+                    .unwrap_or(DELETED_USER_NAME.to_string()),
+                time_human: time_to_human_readable(version.created_at),
+                title: version.title.clone(),
+                title_diff,
+                diff: diff_lines(
+                    previous.map(|p| p.content.as_str()).unwrap_or_default(),
+                    &version.content,
+                ),
+                is_current: index + 1 == versions.len(),
+            }
+        })
+        .rev()
+        .collect()
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Most urls in one sitemap file, the protocol allows up to 50,000
+pub const SITEMAP_CHUNK_SIZE: i64 = 10_000;
+
+/// The kind of pages a sitemap file lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SitemapSection {
+    Posts,
+    Profiles,
+}
+
+impl SitemapSection {
+    pub const ALL: [SitemapSection; 2] = [SitemapSection::Posts, SitemapSection::Profiles];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SitemapSection::Posts => "posts",
+            SitemapSection::Profiles => "profiles",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<SitemapSection> {
+        SitemapSection::ALL
+            .into_iter()
+            .find(|section| section.as_str() == value)
+    }
+
+    /// Path of one chunk of the section, chunks are numbered from 1
+    pub fn chunk_path(&self, chunk: i64) -> String {
+        format!("/sitemaps/{}-{}.xml", self.as_str(), chunk)
+    }
+}
+
+/// Timestamp as written in sitemaps
+pub fn format_sitemap_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// One page listed in a sitemap file
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SitemapUrl {
+    /// Absolute once the controller adds the forum url, a path until then
+    pub loc: String,
+    pub lastmod: String,
+}
+
+impl SitemapUrl {
+    pub fn new(path: String, updated_at: NaiveDateTime) -> Self {
+        Self {
+            loc: path,
+            lastmod: format_sitemap_time(updated_at),
+        }
+    }
+}
+
+/// One sitemap file listed in the sitemap index
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SitemapFile {
+    pub section: &'static str,
+    pub chunk: i64,
+    /// Absolute once the controller adds the forum url, a path until then
+    pub loc: String,
+}
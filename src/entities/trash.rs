@@ -0,0 +1,115 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    entities::{comment::CommentPublic, post::PostPublic},
+    utils::time::time_to_human_readable,
+};
+
+pub const TRASH_RESTORE_GRACE_DAYS_DEFAULT: i64 = 7;
+pub const TRASH_PURGE_AFTER_DAYS_DEFAULT: i64 = 30;
+
+/// How long soft deleted content can be restored and when it is purged for good
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrashPolicy {
+    /// Days an author can restore their own content, moderators can until it is purged
+    pub restore_grace_days: i64,
+    /// Days after which soft deleted content is hard deleted
+    pub purge_after_days: i64,
+}
+
+impl Default for TrashPolicy {
+    fn default() -> Self {
+        Self {
+            restore_grace_days: TRASH_RESTORE_GRACE_DAYS_DEFAULT,
+            purge_after_days: TRASH_PURGE_AFTER_DAYS_DEFAULT,
+        }
+    }
+}
+
+impl TrashPolicy {
+    /// Reads `TRASH_RESTORE_GRACE_DAYS` and `TRASH_PURGE_AFTER_DAYS`, defaults to 7 and 30 days
+    pub fn from_env() -> Self {
+        let read_days = |key: &str, default: i64| match std::env::var(key) {
+            Ok(value) if !value.is_empty() => value
+                .parse::<i64>()
+                .ok()
+                .filter(|days| *days >= 0)
+                .unwrap_or_else(|| panic!("{} must be a number of days, got {:?}", key, value)),
+            _ => default,
+        };
+
+        Self {
+            restore_grace_days: read_days(
+                "TRASH_RESTORE_GRACE_DAYS",
+                TRASH_RESTORE_GRACE_DAYS_DEFAULT,
+            ),
+            purge_after_days: read_days("TRASH_PURGE_AFTER_DAYS", TRASH_PURGE_AFTER_DAYS_DEFAULT),
+        }
+    }
+
+    /// Whether the author of content deleted at `deleted_at` can still restore it
+    pub fn author_can_restore(&self, deleted_at: NaiveDateTime) -> bool {
+        Utc::now().naive_utc() < self.restore_deadline(deleted_at)
+    }
+
+    pub fn restore_deadline(&self, deleted_at: NaiveDateTime) -> NaiveDateTime {
+        deleted_at + Duration::days(self.restore_grace_days)
+    }
+
+    pub fn purge_time(&self, deleted_at: NaiveDateTime) -> NaiveDateTime {
+        deleted_at + Duration::days(self.purge_after_days)
+    }
+
+    /// Content soft deleted before this point in time is due to be purged
+    pub fn purge_cutoff(&self) -> NaiveDateTime {
+        Utc::now().naive_utc() - Duration::days(self.purge_after_days)
+    }
+}
+
+/// Where a trash entry stands, shown next to a deleted post or comment
+#[derive(Serialize, Debug)]
+pub struct TrashStatePublic {
+    pub deleted_time_human: String,
+    pub restore_deadline_human: String,
+    pub purge_time_human: String,
+    pub allow_restore: bool,
+}
+
+impl TrashStatePublic {
+    /// `is_held` is set for content a moderator removed, its author can not restore it
+    pub fn new(
+        policy: &TrashPolicy,
+        deleted_at: NaiveDateTime,
+        is_held: bool,
+        is_moderator: bool,
+    ) -> Self {
+        Self {
+            deleted_time_human: time_to_human_readable(deleted_at),
+            restore_deadline_human: time_to_human_readable(policy.restore_deadline(deleted_at)),
+            purge_time_human: time_to_human_readable(policy.purge_time(deleted_at)),
+            allow_restore: is_moderator || (!is_held && policy.author_can_restore(deleted_at)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrashPostPublic {
+    #[serde(flatten)]
+    pub post: PostPublic,
+    pub trash: TrashStatePublic,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrashCommentPublic {
+    #[serde(flatten)]
+    pub comment: CommentPublic,
+    pub trash: TrashStatePublic,
+}
+
+/// The result of one purge run
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct TrashPurgeResult {
+    pub posts: usize,
+    pub comments: usize,
+}
@@ -3,20 +3,98 @@ use std::sync::LazyLock;
 use actix_multipart::form::{tempfile::TempFile, MultipartForm};
 use bcrypt::verify;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidateUrl, ValidationError};
 
 use regex::Regex;
 
 use actix_web::{FromRequest, HttpRequest};
 use chrono::NaiveDateTime;
 
-use crate::models::User;
+use crate::{
+    models::User,
+    utils::{markdown::render_markdown, token::generate_random_token},
+};
 
 use futures::future::{ready, Ready};
 
 static NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9 ]{2,32}$").unwrap());
+static USERNAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_]{3,32}$").unwrap());
+
+/// Handles nobody can pick, they would pass for staff or the forum itself
+pub const RESERVED_USERNAMES: [&str; 8] = [
+    "admin",
+    "administrator",
+    "moderator",
+    "mod",
+    "staff",
+    "support",
+    "system",
+    "deleted",
+];
 
 pub const SESSION_KEY_USER: &str = "user";
+pub const SESSION_KEY_LOGIN_CHALLENGE: &str = "login_challenge";
+
+/// Placeholder author that takes over the content of anonymized deleted accounts.
+/// It is created by a migration and found by its role, the address is reserved
+pub const DELETED_USER_NAME: &str = "deleted user";
+pub const DELETED_USER_EMAIL: &str = "deleted-user@rust-forum.invalid";
+pub const DELETED_USER_USERNAME: &str = "deleted";
+
+/// Roles stored in `users.role`, every other value is a regular user
+pub const USER_ROLE_USER: &str = "user";
+pub const USER_ROLE_MODERATOR: &str = "moderator";
+pub const USER_ROLE_ADMIN: &str = "admin";
+/// Only held by the deleted user placeholder, never handed out
+pub const USER_ROLE_DELETED: &str = "deleted";
+
+/// The roles an admin can hand out
+pub const USER_ROLES: [&str; 3] = [USER_ROLE_USER, USER_ROLE_MODERATOR, USER_ROLE_ADMIN];
+
+/// Moderators and admins may act on content they do not own
+pub fn user_is_moderator(user: &User) -> bool {
+    user.role == USER_ROLE_MODERATOR || user.role == USER_ROLE_ADMIN
+}
+
+/// The placeholder that owns the content of anonymized deleted accounts
+pub fn user_is_deleted_placeholder(user: &User) -> bool {
+    user.role == USER_ROLE_DELETED
+}
+
+/// Nobody signs up with the placeholder address, whatever its casing
+pub fn email_is_reserved(email: &str) -> bool {
+    email.trim().eq_ignore_ascii_case(DELETED_USER_EMAIL)
+}
+
+/// Only admins change roles and read the audit log
+pub fn user_is_admin(user: &User) -> bool {
+    user.role == USER_ROLE_ADMIN
+}
+
+/// Handles are compared and stored in lowercase, `Alice` and `alice` are the same user
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// Builds a free handle out of a display name, for accounts that did not choose one
+pub fn username_from_name(name: &str) -> String {
+    let name_part = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .take(20)
+        .collect::<String>();
+
+    // the random part keeps generated handles apart
+    format!(
+        "{}_{}",
+        name_part.trim_matches('_'),
+        generate_random_token(8).to_lowercase()
+    )
+}
 
 #[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct UserLoginFormData {
@@ -46,6 +124,12 @@ pub struct UserRegisterFormData {
     ))]
     pub name: String,
 
+    #[validate(regex(
+        path = *USERNAME_REGEX,
+        message = "Username must contain only letters, numbers and underscores. length between 3 and 32."
+    ))]
+    pub username: String,
+
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
 
@@ -86,6 +170,22 @@ pub struct UserChangePasswordFormData {
     pub confirm_password: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct UserSetPasswordFormData {
+    #[validate(length(
+        min = 8,
+        max = 100,
+        message = "New password must be at least 8 characters and max 100 long"
+    ))]
+    pub new_password: String,
+    #[validate(length(
+        min = 8,
+        max = 100,
+        message = "Confirm password must be at least 8 characters and max 100 long"
+    ))]
+    pub confirm_password: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct UserUpdateFormData {
     #[validate(regex(
@@ -95,6 +195,67 @@ pub struct UserUpdateFormData {
     pub new_name: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct UserUsernameFormData {
+    #[validate(regex(
+        path = *USERNAME_REGEX,
+        message = "Username must contain only letters, numbers and underscores. length between 3 and 32."
+    ))]
+    pub new_username: String,
+}
+
+/// The profile fields a user edits from the settings, every field may be left empty
+#[derive(Serialize, Deserialize, Debug, Default, Validate)]
+pub struct UserProfileFormData {
+    #[validate(length(max = 2000, message = "Bio must be at most 2000 characters"))]
+    #[serde(default)]
+    pub bio: String,
+
+    #[validate(
+        length(max = 255, message = "Website must be at most 255 characters"),
+        custom(function = "validate_profile_website")
+    )]
+    #[serde(default)]
+    pub website: String,
+
+    #[validate(length(max = 100, message = "Location must be at most 100 characters"))]
+    #[serde(default)]
+    pub location: String,
+
+    #[validate(length(max = 40, message = "Pronouns must be at most 40 characters"))]
+    #[serde(default)]
+    pub pronouns: String,
+
+    #[validate(length(max = 300, message = "Signature must be at most 300 characters"))]
+    #[serde(default)]
+    pub signature: String,
+}
+
+/// A website is an http or https url, other schemes could run script when clicked
+fn validate_profile_website(website: &str) -> Result<(), ValidationError> {
+    let website = website.trim();
+    if website.is_empty() {
+        return Ok(());
+    }
+
+    let has_web_scheme = website.starts_with("https://") || website.starts_with("http://");
+    if !has_web_scheme || !website.validate_url() {
+        return Err(ValidationError::new("website")
+            .with_message("Website must be a link starting with http:// or https://".into()));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct UserRoleFormData {
+    pub role: String,
+
+    #[validate(length(max = 500, message = "Reason must be at most 500 characters"))]
+    #[serde(default)]
+    pub reason: String,
+}
+
 #[derive(Debug, MultipartForm)]
 pub struct UserUploadProfilePictureForm {
     #[multipart(limit = "10MB")]
@@ -142,6 +303,9 @@ pub struct UserPasswordResetTokenRequest {
 pub struct UserPublic {
     pub id: i32,
     pub name: String,
+    /// Defaults for sessions stored before users had a handle
+    #[serde(default)]
+    pub username: String,
     pub created_at: NaiveDateTime,
     pub user_profile_picture_url: String,
 }
@@ -150,6 +314,7 @@ pub fn user_to_user_public(user: &User) -> UserPublic {
     UserPublic {
         id: user.id,
         name: user.name.clone(),
+        username: user.username.clone(),
         created_at: user.created_at,
         user_profile_picture_url: user.user_profile_picture_url.clone().unwrap_or(format!(
             "https://ui-avatars.com/api/?size=250&name={}",
@@ -158,6 +323,48 @@ pub fn user_to_user_public(user: &User) -> UserPublic {
     }
 }
 
+/// The profile fields shown on the profile page, kept apart from `UserPublic` which lives in the session cookie
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct UserProfilePublic {
+    pub bio: String,
+    /// The bio rendered from markdown and sanitized
+    pub bio_html: String,
+    pub website: String,
+    pub location: String,
+    pub pronouns: String,
+    pub signature: String,
+}
+
+pub fn user_to_user_profile_public(user: &User) -> UserProfilePublic {
+    UserProfilePublic {
+        bio: user.bio.clone(),
+        bio_html: render_markdown(&user.bio),
+        website: user.website.clone(),
+        location: user.location.clone(),
+        pronouns: user.pronouns.clone(),
+        signature: user.signature.clone(),
+    }
+}
+
+/// Activity of a user, counted from their content
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct UserStats {
+    pub post_count: i64,
+    pub comment_count: i64,
+    /// Followers plus the saves and subscriptions other users gave to the user's content
+    pub reputation: i64,
+    /// The latest post, comment or message of the user, `None` before any
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub last_seen_human: Option<String>,
+}
+
+/// Where a handle points, old handles lead to the one that replaced them
+#[derive(Debug, PartialEq, Eq)]
+pub enum UsernameLookup {
+    Current(i32),
+    Moved(String),
+}
+
 pub struct OptionalFetchMode(pub String);
 
 impl FromRequest for OptionalFetchMode {
@@ -175,6 +382,9 @@ impl FromRequest for OptionalFetchMode {
     }
 }
 
+/// Users signed up through an identity provider have no password and never match
 pub fn validate_user_password(user: &User, user_password: &str) -> bool {
-    verify(user_password, &user.password).unwrap_or(false)
+    user.password
+        .as_ref()
+        .is_some_and(|hashed| verify(user_password, hashed).unwrap_or(false))
 }
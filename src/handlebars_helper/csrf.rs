@@ -0,0 +1,40 @@
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::utils::csrf::CSRF_FORM_FIELD;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CsrfHandlebarsRenderValueContext {
+    pub field_name: String,
+    pub token: String,
+}
+
+/// Emits the hidden CSRF input, the route must call `handlebars_add_csrf_token` first
+pub fn handlebars_csrf_helper(
+    _h: &Helper,
+    hb_registry: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    output: &mut dyn Output,
+) -> HelperResult {
+    let token = ctx
+        .data()
+        .get(CSRF_FORM_FIELD)
+        .and_then(|v| v.as_str())
+        .ok_or(handlebars::RenderErrorReason::MissingVariable(Some(
+            CSRF_FORM_FIELD.to_string(),
+        )))?;
+
+    let hb_render_value_context = CsrfHandlebarsRenderValueContext {
+        field_name: CSRF_FORM_FIELD.to_string(),
+        token: token.to_string(),
+    };
+    let hb_render_value_json = json!({ "csrf": hb_render_value_context });
+
+    let output_html = hb_registry.render("utils/csrf", &hb_render_value_json)?;
+
+    output.write(&output_html)?;
+
+    Ok(())
+}
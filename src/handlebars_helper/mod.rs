@@ -1,2 +1,5 @@
+pub mod csrf;
 pub mod pagination;
+pub mod post_path;
 pub mod turnstile;
+pub mod url_encode;
@@ -1,13 +1,20 @@
 use crate::utils::pagination::QueryPagination;
+use actix_web::{http::header, HttpRequest, HttpResponse, HttpResponseBuilder};
 use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Pages linked on each side of the current page, pages further away are left to first and last
+pub const PAGINATION_WINDOW: i64 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 pub struct HandlebarsPaginationResult {
     pub page: i64,
     pub limit: i64,
     pub total_pages: i64,
+    /// Query parameters of the listing besides the page, e.g. `folder`
+    #[serde(default)]
+    pub filters: Vec<(String, String)>,
 }
 
 pub fn build_handlebars_pagination_result(
@@ -18,22 +25,151 @@ pub fn build_handlebars_pagination_result(
         page: pagination.page,
         limit: pagination.limit,
         total_pages: (total_entity as f64 / pagination.limit as f64).ceil() as i64,
+        filters: pagination.filters.clone(),
     }
 }
 
+impl HandlebarsPaginationResult {
+    /// Query string of a page, relative to the listing
+    pub fn page_url(&self, page: i64) -> String {
+        self.page_url_with_limit(page, self.limit)
+    }
+
+    /// Query string of a page at another page size, the filters stay
+    pub fn page_url_with_limit(&self, page: i64, limit: i64) -> String {
+        match serde_urlencoded::to_string(&self.filters) {
+            Ok(filter_query) if !filter_query.is_empty() => {
+                format!("?page={}&per_page={}&{}", page, limit, filter_query)
+            }
+            _ => format!("?page={}&per_page={}", page, limit),
+        }
+    }
+
+    pub fn prev_page(&self) -> Option<i64> {
+        (self.page > 1 && self.page <= self.total_pages).then_some(self.page - 1)
+    }
+
+    pub fn next_page(&self) -> Option<i64> {
+        (self.page < self.total_pages).then_some(self.page + 1)
+    }
+
+    /// The last page when the requested one is past it, an empty list has a single empty page
+    pub fn out_of_range_page(&self) -> Option<i64> {
+        let last_page = self.total_pages.max(1);
+
+        (self.page > last_page).then_some(last_page)
+    }
+
+    /// First, previous, the pages around the current one, next and last
+    pub fn links(&self) -> Vec<HandlebarsPaginationLink> {
+        if self.total_pages < 1 {
+            return vec![];
+        }
+
+        let link = |label: String, page: Option<i64>| HandlebarsPaginationLink {
+            label,
+            url: page.map(|page| self.page_url(page)),
+            active: false,
+        };
+
+        let window_start = (self.page - PAGINATION_WINDOW).max(1);
+        let window_end = (self.page + PAGINATION_WINDOW).min(self.total_pages);
+
+        let mut links = vec![
+            link("First".to_string(), (self.page != 1).then_some(1)),
+            link("Prev".to_string(), self.prev_page()),
+        ];
+
+        if window_start > 1 {
+            links.push(link("…".to_string(), None));
+        }
+
+        for page in window_start..=window_end {
+            links.push(HandlebarsPaginationLink {
+                active: page == self.page,
+                ..link(page.to_string(), Some(page))
+            });
+        }
+
+        if window_end < self.total_pages {
+            links.push(link("…".to_string(), None));
+        }
+
+        links.push(link("Next".to_string(), self.next_page()));
+        links.push(link(
+            "Last".to_string(),
+            (self.page != self.total_pages).then_some(self.total_pages),
+        ));
+
+        links
+    }
+
+    /// Value of the `Link` header pointing at the previous and next pages of `path`
+    pub fn link_header(&self, path: &str) -> Option<String> {
+        let links: Vec<String> = [("prev", self.prev_page()), ("next", self.next_page())]
+            .into_iter()
+            .filter_map(|(rel, page)| {
+                page.map(|page| format!("<{}{}>; rel=\"{}\"", path, self.page_url(page), rel))
+            })
+            .collect();
+
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+/// Sends a request for a page past the end of a listing to its last page
+pub fn pagination_out_of_range_redirect(
+    req: &HttpRequest,
+    pagination_result: &HandlebarsPaginationResult,
+) -> Option<HttpResponse> {
+    let last_page = pagination_result.out_of_range_page()?;
+
+    Some(
+        HttpResponse::Found()
+            .insert_header((
+                header::LOCATION,
+                format!("{}{}", req.path(), pagination_result.page_url(last_page)),
+            ))
+            .finish(),
+    )
+}
+
+/// Adds the `rel="prev"` and `rel="next"` links of a listing page to its response
+pub fn add_pagination_link_header(
+    response: &mut HttpResponseBuilder,
+    req: &HttpRequest,
+    pagination_result: &HandlebarsPaginationResult,
+) {
+    if let Some(link) = pagination_result.link_header(req.path()) {
+        response.insert_header((header::LINK, link));
+    }
+}
+
+/// One entry of the pager, a gap or a link that leads nowhere has no url
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HandlebarsPaginationLink {
+    pub label: String,
+    pub url: Option<String>,
+    pub active: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct HandleBarsPaginationPerPage {
     pub option_tag_attr: String,
     pub limit: i64,
+    pub url: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct HandlebarsPaginationRenderContext {
     pub page: i64,
     pub per_page: i64,
+    pub total_pages: i64,
 
-    pub pages: Vec<i64>,
+    pub links: Vec<HandlebarsPaginationLink>,
     pub per_pages: Vec<HandleBarsPaginationPerPage>,
+    /// Sent again as hidden fields by the page form
+    pub filters: Vec<(String, String)>,
 }
 
 pub fn handlebars_pagination_helper(
@@ -58,8 +194,6 @@ pub fn handlebars_pagination_helper(
         serde_json::from_value(pagination_result_json.clone())
             .map_err(|e| handlebars::RenderErrorReason::InvalidJsonIndex(e.to_string()))?;
 
-    let pages: Vec<i64> = (1..=pagination_result.total_pages).collect();
-
     let mut per_pages: Vec<HandleBarsPaginationPerPage> = vec![];
     for limit in [10, 20, 50, 100].into_iter() {
         let option_select_attr = if pagination_result.limit == limit {
@@ -71,14 +205,17 @@ pub fn handlebars_pagination_helper(
         per_pages.push(HandleBarsPaginationPerPage {
             option_tag_attr: option_select_attr.to_string(),
             limit,
+            url: pagination_result.page_url_with_limit(1, limit),
         });
     }
 
     let hb_pagination_render_context = HandlebarsPaginationRenderContext {
         page: pagination_result.page,
         per_page: pagination_result.limit,
-        pages,
+        total_pages: pagination_result.total_pages,
+        links: pagination_result.links(),
         per_pages,
+        filters: pagination_result.filters.clone(),
     };
     let json_value = json!({ "pagination": hb_pagination_render_context });
 
@@ -0,0 +1,21 @@
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
+
+use crate::entities::post::post_path;
+
+/// Emits the canonical path of a post, `{{post_path post.id post.title}}`
+pub fn handlebars_post_path_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    output: &mut dyn Output,
+) -> HelperResult {
+    let post_id = h.param(0).and_then(|v| v.value().as_i64()).ok_or(
+        handlebars::RenderErrorReason::ParamNotFoundForIndex("post_path", 0),
+    )?;
+    let title = h.param(1).and_then(|v| v.value().as_str()).unwrap_or("");
+
+    output.write(&post_path(post_id as i32, title))?;
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
+
+/// Emits a value encoded for a query string, `?folder={{url_encode folder}}`
+pub fn handlebars_url_encode_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    output: &mut dyn Output,
+) -> HelperResult {
+    let value = h.param(0).and_then(|v| v.value().as_str()).ok_or(
+        handlebars::RenderErrorReason::ParamNotFoundForIndex("url_encode", 0),
+    )?;
+
+    // form encoding of a lone value, the leading `=` is left out
+    let encoded = serde_urlencoded::to_string([("", value)]).unwrap_or_default();
+    output.write(encoded.strip_prefix('=').unwrap_or(&encoded))?;
+
+    Ok(())
+}
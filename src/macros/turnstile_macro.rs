@@ -38,21 +38,21 @@ macro_rules! validate_turnstile_field {
 
             // Line 2: Validate the token
             let turnstile_result =
-                crate::utils::turnstile::validate_turnstile_wrapper(&cf_turnstile_response).await;
+                $crate::utils::turnstile::validate_turnstile_wrapper(&cf_turnstile_response).await;
 
             // Line 3: Handle the error
             if let Err(turnstile_error) = turnstile_result {
                 // Line 4: Set flash message.
                 // This `?` will propagate if `set_flash_message` fails.
-                crate::utils::flash::set_flash_message(
+                $crate::utils::flash::set_flash_message(
                     &$session,
-                    crate::utils::flash::FLASH_ERROR, // Using the constant from your snippet
+                    $crate::utils::flash::FLASH_ERROR, // Using the constant from your snippet
                     &turnstile_error.message,
                 )?;
 
                 // Line 5: Return a redirect response.
                 // This exits the calling function.
-                return Ok(crate::utils::http::redirect_back(&$req));
+                return Ok($crate::utils::http::redirect_back(&$req));
             }
         }
     };
@@ -2,17 +2,53 @@ use std::sync::Arc;
 
 use actix_web::HttpServer;
 
+use rust_forum::entities::content_filter::ContentFilterPolicy;
+use rust_forum::entities::live::LIVE_CHANNEL_CAPACITY;
+use rust_forum::entities::report::report_auto_hide_threshold_from_env;
+use rust_forum::entities::sitemap::SITEMAP_CHUNK_SIZE;
+use rust_forum::entities::trash::TrashPolicy;
 use rust_forum::db::{MIGRATIONS, establish_connection, initialize_db_pool, run_migrations};
+use rust_forum::repositories::attachment_repository::PostgresAttachmentRepository;
+use rust_forum::repositories::audit_log_repository::PostgresAuditLogRepository;
+use rust_forum::repositories::bookmark_repository::PostgresBookmarkRepository;
 use rust_forum::repositories::comment_repository::PostgresCommentRepository;
+use rust_forum::repositories::content_filter_rule_repository::PostgresContentFilterRuleRepository;
+use rust_forum::repositories::login_attempt_repository::PostgresLoginAttemptRepository;
+use rust_forum::repositories::follow_repository::PostgresFollowRepository;
+use rust_forum::repositories::message_repository::PostgresMessageRepository;
+use rust_forum::repositories::notification_repository::PostgresNotificationRepository;
 use rust_forum::repositories::post_repository::PostgresPostRepository;
+use rust_forum::repositories::report_repository::PostgresReportRepository;
+use rust_forum::repositories::sitemap_repository::PostgresSitemapRepository;
 use rust_forum::repositories::token_repository::PostgresTokenRepository;
+use rust_forum::repositories::user_ban_repository::PostgresUserBanRepository;
+use rust_forum::repositories::user_identity_repository::PostgresUserIdentityRepository;
+use rust_forum::repositories::account_repository::PostgresAccountRepository;
 use rust_forum::repositories::user_repository_postgres::PostgresUserRepository;
+use rust_forum::repositories::user_stats_repository::PostgresUserStatsRepository;
 use rust_forum::servers::server_actix::create_actix_app;
+use rust_forum::services::account_service::{AccountDeletionMode, BasedAccountService};
+use rust_forum::services::attachment_service::BasedAttachmentService;
+use rust_forum::services::audit_service::BasedAuditService;
+use rust_forum::services::bookmark_service::BasedBookmarkService;
 use rust_forum::services::comment_service::BasedCommentService;
+use rust_forum::services::content_filter_service::BasedContentFilterService;
+use rust_forum::services::file_storage_service::{file_storage_from_env, FileStorage};
+use rust_forum::services::follow_service::BasedFollowService;
+use rust_forum::services::live_service::InMemoryLiveService;
 use rust_forum::services::email_service::BasedEmailService;
+use rust_forum::services::message_service::BasedMessageService;
+use rust_forum::services::notification_service::BasedNotificationService;
+use rust_forum::services::oauth_service::BasedOAuthService;
 use rust_forum::services::post_service::BasedPostService;
+use rust_forum::services::report_service::BasedReportService;
+use rust_forum::services::sitemap_service::BasedSitemapService;
+use rust_forum::services::rate_limit_service::{
+    InMemoryRateLimitService, RateLimitService, RedisRateLimitService,
+};
 use rust_forum::services::token_service::BasedTokenService;
 use rust_forum::services::user_service::BasedUserService;
+use rust_forum::utils::trash::spawn_trash_purge_job;
 use rust_forum::{AppKit};
 
 use dotenv::dotenv;
@@ -86,9 +122,51 @@ async fn main() -> std::io::Result<()> {
     let token_repo = PostgresTokenRepository::new(db_pool_arc.clone());
     let token_repo = Arc::new(token_repo);
 
+    let login_attempt_repo = PostgresLoginAttemptRepository::new(db_pool_arc.clone());
+    let login_attempt_repo = Arc::new(login_attempt_repo);
+
     let user_repo = PostgresUserRepository::new(db_pool_arc.clone());
     let user_repo = Arc::new(user_repo);
 
+    let user_identity_repo = PostgresUserIdentityRepository::new(db_pool_arc.clone());
+    let user_identity_repo = Arc::new(user_identity_repo);
+
+    let account_repo = PostgresAccountRepository::new(db_pool_arc.clone());
+    let account_repo = Arc::new(account_repo);
+
+    let user_ban_repo = PostgresUserBanRepository::new(db_pool_arc.clone());
+    let user_ban_repo = Arc::new(user_ban_repo);
+
+    let user_stats_repo = PostgresUserStatsRepository::new(db_pool_arc.clone());
+    let user_stats_repo = Arc::new(user_stats_repo);
+
+    let attachment_repo = PostgresAttachmentRepository::new(db_pool_arc.clone());
+    let attachment_repo = Arc::new(attachment_repo);
+
+    let report_repo = PostgresReportRepository::new(db_pool_arc.clone());
+    let report_repo = Arc::new(report_repo);
+
+    let audit_log_repo = PostgresAuditLogRepository::new(db_pool_arc.clone());
+    let audit_log_repo = Arc::new(audit_log_repo);
+
+    let content_filter_rule_repo = PostgresContentFilterRuleRepository::new(db_pool_arc.clone());
+    let content_filter_rule_repo = Arc::new(content_filter_rule_repo);
+
+    let sitemap_repo = PostgresSitemapRepository::new(db_pool_arc.clone());
+    let sitemap_repo = Arc::new(sitemap_repo);
+
+    let message_repo = PostgresMessageRepository::new(db_pool_arc.clone());
+    let message_repo = Arc::new(message_repo);
+
+    let follow_repo = PostgresFollowRepository::new(db_pool_arc.clone());
+    let follow_repo = Arc::new(follow_repo);
+
+    let bookmark_repo = PostgresBookmarkRepository::new(db_pool_arc.clone());
+    let bookmark_repo = Arc::new(bookmark_repo);
+
+    let notification_repo = PostgresNotificationRepository::new(db_pool_arc.clone());
+    let notification_repo = Arc::new(notification_repo);
+
     // --- service setup ---
     let token_service = BasedTokenService::new(token_repo.clone());
     let token_service = Arc::new(token_service);
@@ -96,15 +174,17 @@ async fn main() -> std::io::Result<()> {
     let email_service = BasedEmailService::new();
     let email_service = Arc::new(email_service);
 
-    let user_service = BasedUserService::new(user_repo.clone(), token_repo.clone());
+    let user_service = BasedUserService::new(
+        user_repo.clone(),
+        token_repo.clone(),
+        login_attempt_repo.clone(),
+        user_identity_repo.clone(),
+        user_ban_repo.clone(),
+        user_stats_repo.clone(),
+        email_service.clone(),
+    );
     let user_service = Arc::new(user_service);
 
-    let post_service = BasedPostService::new(post_repo.clone());
-    let post_service = Arc::new(post_service);
-
-    let comment_service = BasedCommentService::new(comment_repo.clone());
-    let comment_service = Arc::new(comment_service);
-
     // Setup CORS
     let cors_origins_env = std::env::var("APP_CORS_ORIGINS")
         .unwrap_or("http://localhost:3000,http://127.0.0.1:3000".to_string());
@@ -114,16 +194,47 @@ async fn main() -> std::io::Result<()> {
     let cors_origins_vec: Vec<String> =
         cors_origins_env.split(',').map(|s| s.to_string()).collect();
 
-    // Setup Redis Rate Limit
-    let ratelimit_redis_host = std::env::var("REDIS_HOST").unwrap_or("127.0.0.1".to_string());
-    let ratelimit_redis_password = std::env::var("REDIS_PASSWORD").unwrap_or("".to_string());
-    let redis_ratelimit_url = if ratelimit_redis_password.is_empty() {
-        format!("redis://{ratelimit_redis_host}")
-    } else {
-        format!("redis://default:{ratelimit_redis_password}@{ratelimit_redis_host}")
+    // Setup Rate Limit backend
+    // use redis when REDIS_HOST is set, otherwise keep counters in memory
+    let ratelimit_redis_host = std::env::var("REDIS_HOST").unwrap_or("".to_string());
+    let ratelimit_backend = std::env::var("RATE_LIMIT_BACKEND").unwrap_or(
+        if ratelimit_redis_host.is_empty() {
+            "memory".to_string()
+        } else {
+            "redis".to_string()
+        },
+    );
+
+    let rate_limit_service: Arc<dyn RateLimitService> = match ratelimit_backend.as_str() {
+        "redis" => {
+            let ratelimit_redis_host = if ratelimit_redis_host.is_empty() {
+                "127.0.0.1".to_string()
+            } else {
+                ratelimit_redis_host
+            };
+            let ratelimit_redis_password =
+                std::env::var("REDIS_PASSWORD").unwrap_or("".to_string());
+            let redis_ratelimit_url = if ratelimit_redis_password.is_empty() {
+                format!("redis://{ratelimit_redis_host}")
+            } else {
+                format!("redis://default:{ratelimit_redis_password}@{ratelimit_redis_host}")
+            };
+
+            println!("ratelimit_redis_url: {:?}", &redis_ratelimit_url);
+
+            Arc::new(RedisRateLimitService::new(&redis_ratelimit_url))
+        }
+
+        "memory" => Arc::new(InMemoryRateLimitService::new()),
+
+        other => panic!("unknown RATE_LIMIT_BACKEND {:?}, expected redis or memory", other),
     };
 
-    println!("ratelimit_redis_url: {:?}", &redis_ratelimit_url);
+    println!("RATE_LIMIT_BACKEND={}", ratelimit_backend);
+
+    // Setup OAuth2 / OpenID Connect providers
+    let oauth_service = BasedOAuthService::from_env();
+    let oauth_service = Arc::new(oauth_service);
 
     // -- setup static file directory --
     let static_file_dir_path = std::env::var("STATIC_FILE_DIR").unwrap_or("".to_string());
@@ -134,6 +245,124 @@ async fn main() -> std::io::Result<()> {
     std::fs::create_dir_all(&static_file_dir_path).expect("Failed to create static directory");
     println!("create STATIC_FILE_DIR at {:?}", &static_file_dir_path);
 
+    // Setup file storage for uploads, local STATIC_FILE_DIR or an S3 compatible bucket
+    let file_storage = file_storage_from_env(&static_file_dir_path)
+        .unwrap_or_else(|e| panic!("failed to setup file storage: {}", e));
+    let file_storage: Arc<dyn FileStorage> = Arc::from(file_storage);
+    println!(
+        "FILE_STORAGE={}",
+        std::env::var("FILE_STORAGE").unwrap_or("local".to_string())
+    );
+
+    let attachment_service = BasedAttachmentService::new(attachment_repo.clone(), file_storage.clone());
+    let attachment_service = Arc::new(attachment_service);
+
+    // Setup reports, content is hidden after REPORT_AUTO_HIDE_THRESHOLD distinct reports
+    let report_auto_hide_threshold = report_auto_hide_threshold_from_env();
+    println!("REPORT_AUTO_HIDE_THRESHOLD={}", report_auto_hide_threshold);
+
+    let report_service = BasedReportService::new(
+        report_repo.clone(),
+        post_repo.clone(),
+        comment_repo.clone(),
+        user_repo.clone(),
+        email_service.clone(),
+        report_auto_hide_threshold,
+    );
+    let report_service = Arc::new(report_service);
+
+    // Setup the content filter, built-in rules come from the CONTENT_FILTER_* variables
+    let content_filter_policy = ContentFilterPolicy::from_env();
+    println!(
+        "CONTENT_FILTER_BLOCKED_WORDS={} CONTENT_FILTER_NEW_ACCOUNT_DAYS={} CONTENT_FILTER_DUPLICATE_WINDOW_MINUTES={}",
+        content_filter_policy.blocked_words.len(),
+        content_filter_policy.new_account_days,
+        content_filter_policy.duplicate_window_minutes
+    );
+
+    let content_filter_service = BasedContentFilterService::new(
+        content_filter_rule_repo.clone(),
+        post_repo.clone(),
+        comment_repo.clone(),
+        report_service.clone(),
+        content_filter_policy,
+    );
+    let content_filter_service = Arc::new(content_filter_service);
+
+    // Setup live updates, events reach the pages served by this process only
+    let live_service = InMemoryLiveService::new(LIVE_CHANNEL_CAPACITY);
+    let live_service = Arc::new(live_service);
+
+    let notification_service =
+        BasedNotificationService::new(notification_repo.clone(), live_service.clone());
+    let notification_service = Arc::new(notification_service);
+
+    let post_service = BasedPostService::new(
+        post_repo.clone(),
+        user_repo.clone(),
+        attachment_service.clone(),
+        content_filter_service.clone(),
+        live_service.clone(),
+    );
+    let post_service = Arc::new(post_service);
+
+    let comment_service = BasedCommentService::new(
+        comment_repo.clone(),
+        user_repo.clone(),
+        attachment_service.clone(),
+        content_filter_service.clone(),
+        live_service.clone(),
+        follow_repo.clone(),
+        notification_service.clone(),
+    );
+    let comment_service = Arc::new(comment_service);
+
+    // Setup account deletion, anonymize or cascade the content of deleted users
+    let account_deletion_mode = AccountDeletionMode::from_env();
+    println!("ACCOUNT_DELETION_MODE={:?}", account_deletion_mode);
+
+    let account_service = BasedAccountService::new(
+        user_repo.clone(),
+        post_repo.clone(),
+        comment_repo.clone(),
+        account_repo.clone(),
+        attachment_service.clone(),
+        file_storage.clone(),
+        account_deletion_mode,
+    );
+    let account_service = Arc::new(account_service);
+
+    let audit_service = BasedAuditService::new(audit_log_repo.clone());
+    let audit_service = Arc::new(audit_service);
+
+    let sitemap_service = BasedSitemapService::new(sitemap_repo.clone(), SITEMAP_CHUNK_SIZE);
+    let sitemap_service = Arc::new(sitemap_service);
+
+    let message_service = BasedMessageService::new(
+        message_repo.clone(),
+        user_repo.clone(),
+        live_service.clone(),
+    );
+    let message_service = Arc::new(message_service);
+
+    let follow_service =
+        BasedFollowService::new(follow_repo.clone(), user_repo.clone(), post_repo.clone());
+    let follow_service = Arc::new(follow_service);
+
+    let bookmark_service = BasedBookmarkService::new(
+        bookmark_repo.clone(),
+        post_repo.clone(),
+        comment_repo.clone(),
+    );
+    let bookmark_service = Arc::new(bookmark_service);
+
+    // Setup trash, how long deleted content can be restored and when it is purged
+    let trash_policy = TrashPolicy::from_env();
+    println!(
+        "TRASH_RESTORE_GRACE_DAYS={} TRASH_PURGE_AFTER_DAYS={}",
+        trash_policy.restore_grace_days, trash_policy.purge_after_days
+    );
+
     // --- app kit setup ---
     let app_kit = AppKit {
         user_service: user_service.clone(),
@@ -141,11 +370,28 @@ async fn main() -> std::io::Result<()> {
         token_service: token_service.clone(),
         post_service: post_service.clone(),
         comment_service: comment_service.clone(),
+        account_service: account_service.clone(),
+        attachment_service: attachment_service.clone(),
+        report_service: report_service.clone(),
+        audit_service: audit_service.clone(),
+        content_filter_service: content_filter_service.clone(),
+        sitemap_service: sitemap_service.clone(),
+        message_service: message_service.clone(),
+        live_service: live_service.clone(),
+        notification_service: notification_service.clone(),
+        follow_service: follow_service.clone(),
+        bookmark_service: bookmark_service.clone(),
+        rate_limit_service,
+        oauth_service,
+        file_storage,
+        trash_policy,
         cors_origins: cors_origins_vec,
-        redis_ratelimit_url,
         static_file_dir_path,
     };
 
+    // hard delete content that stayed in the trash past TRASH_PURGE_AFTER_DAYS
+    spawn_trash_purge_job(app_kit.clone());
+
     HttpServer::new(move || {
         let app_kit_clone = app_kit.clone();
         create_actix_app(app_kit_clone)
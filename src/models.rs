@@ -25,6 +25,11 @@ pub struct Post {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub deleted_at: Option<chrono::NaiveDateTime>,
+    pub hidden_at: Option<chrono::NaiveDateTime>,
+    /// Listed above the other posts on the index
+    pub pinned: bool,
+    /// No new comments can be written
+    pub locked: bool,
 }
 
 #[derive(Insertable)]
@@ -59,6 +64,7 @@ pub struct Comment {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub deleted_at: Option<chrono::NaiveDateTime>,
+    pub hidden_at: Option<chrono::NaiveDateTime>,
 }
 
 #[derive(Insertable)]
@@ -87,19 +93,33 @@ pub struct User {
     pub id: i32,
     pub name: String,
     pub email: String,
-    pub password: String,
+    pub password: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub user_profile_picture_url: Option<String>,
     pub role: String,
+    pub banned_until: Option<chrono::NaiveDateTime>,
+    pub ban_reason: Option<String>,
+    /// Markdown, rendered through `render_markdown` before it is shown
+    pub bio: String,
+    pub website: String,
+    pub location: String,
+    pub pronouns: String,
+    /// Plain text shown under the posts and comments of the user
+    pub signature: String,
+    /// Unique lowercase handle, unlike `name` it identifies the user in urls
+    pub username: String,
+    /// Last time the user picked a new handle, `None` while they keep the one they registered with
+    pub username_changed_at: Option<chrono::NaiveDateTime>,
 }
 
 #[derive(Insertable)]
 #[diesel(table_name=users)]
 pub struct NewUser<'a> {
     pub name: &'a str,
+    pub username: &'a str,
     pub email: &'a str,
-    pub password: &'a str,
+    pub password: Option<&'a str>,
 }
 
 #[derive(AsChangeset)]
@@ -109,6 +129,16 @@ pub struct UpdateUserNameAndProfilePicture<'a> {
     pub user_profile_picture_url: Option<&'a str>,
 }
 
+#[derive(AsChangeset)]
+#[diesel(table_name = users)]
+pub struct UpdateUserProfile<'a> {
+    pub bio: &'a str,
+    pub website: &'a str,
+    pub location: &'a str,
+    pub pronouns: &'a str,
+    pub signature: &'a str,
+}
+
 #[derive(
     Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, AsChangeset, Serialize, Deserialize,
 )]
@@ -129,3 +159,342 @@ pub struct NewPasswordReset {
     pub reset_token: String,
     pub expires_at: chrono::NaiveDateTime,
 }
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = login_attempts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LoginAttempt {
+    pub id: i32,
+    pub attempt_key: String,
+    pub failed_count: i32,
+    pub last_failed_at: chrono::NaiveDateTime,
+    pub locked_until: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = login_attempts)]
+pub struct NewLoginAttempt<'a> {
+    pub attempt_key: &'a str,
+    pub failed_count: i32,
+    pub last_failed_at: chrono::NaiveDateTime,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = user_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    pub provider_subject: String,
+    pub email: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_identities)]
+pub struct NewUserIdentity<'a> {
+    pub user_id: i32,
+    pub provider: &'a str,
+    pub provider_subject: &'a str,
+    pub email: Option<&'a str>,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attachment {
+    pub id: i32,
+    pub user_id: i32,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub kind: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = attachments)]
+pub struct NewAttachment<'a> {
+    pub user_id: i32,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub kind: &'a str,
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub size_bytes: i64,
+    pub storage_key: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = post_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PostRevision {
+    pub id: i32,
+    pub post_id: i32,
+    pub user_id: Option<i32>,
+    pub title: String,
+    pub body: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_revisions)]
+pub struct NewPostRevision<'a> {
+    pub post_id: i32,
+    pub user_id: Option<i32>,
+    pub title: &'a str,
+    pub body: &'a str,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = comment_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CommentRevision {
+    pub id: i32,
+    pub comment_id: i32,
+    pub user_id: Option<i32>,
+    pub content: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = comment_revisions)]
+pub struct NewCommentRevision<'a> {
+    pub comment_id: i32,
+    pub user_id: Option<i32>,
+    pub content: &'a str,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = reports)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Report {
+    pub id: i32,
+    /// `None` for a report filed by the content filter
+    pub reporter_user_id: Option<i32>,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub reported_user_id: Option<i32>,
+    pub category: String,
+    pub details: String,
+    pub status: String,
+    pub resolution: Option<String>,
+    pub resolved_by_user_id: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = reports)]
+pub struct NewReport<'a> {
+    pub reporter_user_id: Option<i32>,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub reported_user_id: Option<i32>,
+    pub category: &'a str,
+    pub details: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = user_bans)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserBan {
+    pub id: i32,
+    pub user_id: i32,
+    pub moderator_user_id: Option<i32>,
+    /// `None` records a ban being lifted
+    pub banned_until: Option<chrono::NaiveDateTime>,
+    pub reason: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_bans)]
+pub struct NewUserBan<'a> {
+    pub user_id: i32,
+    pub moderator_user_id: Option<i32>,
+    pub banned_until: Option<chrono::NaiveDateTime>,
+    pub reason: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = audit_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuditLogEntry {
+    pub id: i32,
+    pub actor_user_id: i32,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i32,
+    pub reason: String,
+    pub ip_address: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditLogEntry<'a> {
+    pub actor_user_id: i32,
+    pub action: &'a str,
+    pub target_type: &'a str,
+    pub target_id: i32,
+    pub reason: &'a str,
+    pub ip_address: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = content_filter_rules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ContentFilterRule {
+    pub id: i32,
+    pub pattern: String,
+    pub action: String,
+    pub description: String,
+    pub created_by_user_id: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = content_filter_rules)]
+pub struct NewContentFilterRule<'a> {
+    pub pattern: &'a str,
+    pub action: &'a str,
+    pub description: &'a str,
+    pub created_by_user_id: Option<i32>,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = conversations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Conversation {
+    pub id: i32,
+    pub title: String,
+    pub is_group: bool,
+    pub created_by_user_id: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = conversations)]
+pub struct NewConversation<'a> {
+    pub title: &'a str,
+    pub is_group: bool,
+    pub created_by_user_id: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+#[diesel(table_name = conversation_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ConversationMember {
+    pub conversation_id: i32,
+    pub user_id: i32,
+    pub joined_at: chrono::NaiveDateTime,
+    pub last_read_at: Option<chrono::NaiveDateTime>,
+    pub left_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = conversation_members)]
+pub struct NewConversationMember {
+    pub conversation_id: i32,
+    pub user_id: i32,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = messages)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Message {
+    pub id: i32,
+    pub conversation_id: i32,
+    pub user_id: i32,
+    pub body: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = messages)]
+pub struct NewMessage<'a> {
+    pub conversation_id: i32,
+    pub user_id: i32,
+    pub body: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = bookmarks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Bookmark {
+    pub id: i32,
+    pub user_id: i32,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    /// Empty when the bookmark is not filed in a folder
+    pub folder: String,
+    pub note: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = bookmarks)]
+pub struct NewBookmark<'a> {
+    pub user_id: i32,
+    pub post_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub folder: &'a str,
+    pub note: &'a str,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Debug, Eq, PartialEq, Serialize, Deserialize, Clone,
+)]
+#[diesel(table_name = notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub post_id: i32,
+    pub comment_id: i32,
+    pub kind: String,
+    pub read_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification<'a> {
+    pub user_id: i32,
+    pub post_id: i32,
+    pub comment_id: i32,
+    pub kind: &'a str,
+}
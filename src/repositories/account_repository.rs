@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    BoolExpressionMethods, Connection, ExpressionMethods, NullableExpressionMethods, PgConnection,
+    QueryDsl, RunQueryDsl,
+};
+
+use crate::{
+    db::WebError,
+    models::UserIdentity,
+    repositories::{
+        user_identity_repository::UserIdentityRepository, user_repository::UserRepositoryWithError,
+    },
+    schema::{attachments, comments, posts, user_identities, users},
+};
+
+/// Repository trait for the account level data of a user, read for exports and removed on deletion
+pub trait AccountRepository: Send + Sync + 'static {
+    /// Lists every identity linked to a user
+    ///
+    /// # Arguments
+    /// * `target_user_id` - The user to list identities for
+    fn get_user_identities(&self, target_user_id: i32) -> Result<Vec<UserIdentity>, WebError>;
+
+    /// Deletes a user, their identities and attachments in one transaction.
+    /// Returns the storage keys of the deleted attachments, their files are not touched
+    ///
+    /// # Arguments
+    /// * `target_user_id` - The user to delete
+    /// * `content_owner_user_id` - Who takes over the posts and comments of the user,
+    ///   with `None` they are deleted along with the attachments under them
+    fn delete_account(
+        &self,
+        target_user_id: i32,
+        content_owner_user_id: Option<i32>,
+    ) -> Result<Vec<String>, WebError>;
+}
+
+pub struct PostgresAccountRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresAccountRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl AccountRepository for PostgresAccountRepository {
+    fn get_user_identities(&self, target_user_id: i32) -> Result<Vec<UserIdentity>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let identities = user_identities::table
+            .filter(user_identities::user_id.eq(target_user_id))
+            .order(user_identities::created_at.asc())
+            .load(&mut conn)?;
+
+        Ok(identities)
+    }
+
+    fn delete_account(
+        &self,
+        target_user_id: i32,
+        content_owner_user_id: Option<i32>,
+    ) -> Result<Vec<String>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let storage_keys = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let user_post_ids = posts::table
+                .filter(posts::user_id.eq(target_user_id))
+                .select(posts::id);
+
+            // the foreign keys would drop these rows and leave their files behind
+            let storage_keys: Vec<String> = match content_owner_user_id {
+                Some(_) => attachments::table
+                    .filter(attachments::user_id.eq(target_user_id))
+                    .select(attachments::storage_key)
+                    .load(conn)?,
+                None => attachments::table
+                    .filter(
+                        attachments::user_id
+                            .eq(target_user_id)
+                            .or(attachments::post_id
+                                .eq_any(user_post_ids.select(posts::id.nullable())))
+                            .or(attachments::comment_id.eq_any(
+                                comments::table
+                                    .filter(comments::post_id.eq_any(user_post_ids))
+                                    .select(comments::id.nullable()),
+                            )),
+                    )
+                    .select(attachments::storage_key)
+                    .load(conn)?,
+            };
+
+            diesel::delete(
+                attachments::table.filter(attachments::storage_key.eq_any(&storage_keys)),
+            )
+            .execute(conn)?;
+
+            // without a new owner the users foreign keys remove posts and comments
+            if let Some(owner_user_id) = content_owner_user_id {
+                diesel::update(posts::table.filter(posts::user_id.eq(target_user_id)))
+                    .set(posts::user_id.eq(owner_user_id))
+                    .execute(conn)?;
+
+                diesel::update(comments::table.filter(comments::user_id.eq(target_user_id)))
+                    .set(comments::user_id.eq(owner_user_id))
+                    .execute(conn)?;
+            }
+
+            diesel::delete(
+                user_identities::table.filter(user_identities::user_id.eq(target_user_id)),
+            )
+            .execute(conn)?;
+
+            let row_affected =
+                diesel::delete(users::table.filter(users::id.eq(target_user_id))).execute(conn)?;
+            if row_affected == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+
+            Ok(storage_keys)
+        })?;
+
+        Ok(storage_keys)
+    }
+}
+
+/// Account data kept in process memory, paired with `InMemoryUserRepository`.
+/// Attachments live in postgres and never belong to in-memory users
+pub struct InMemoryAccountRepository {
+    user_repository: Arc<UserRepositoryWithError>,
+    user_identity_repository: Arc<dyn UserIdentityRepository>,
+}
+
+impl InMemoryAccountRepository {
+    pub fn new(
+        user_repository: Arc<UserRepositoryWithError>,
+        user_identity_repository: Arc<dyn UserIdentityRepository>,
+    ) -> Self {
+        Self {
+            user_repository,
+            user_identity_repository,
+        }
+    }
+}
+
+impl AccountRepository for InMemoryAccountRepository {
+    fn get_user_identities(&self, target_user_id: i32) -> Result<Vec<UserIdentity>, WebError> {
+        self.user_identity_repository
+            .get_user_identities_by_user(target_user_id)
+    }
+
+    fn delete_account(
+        &self,
+        target_user_id: i32,
+        _content_owner_user_id: Option<i32>,
+    ) -> Result<Vec<String>, WebError> {
+        let user = self.user_repository.get_user_by_id(target_user_id)?;
+
+        for identity in self
+            .user_identity_repository
+            .get_user_identities_by_user(target_user_id)?
+        {
+            self.user_identity_repository
+                .delete_user_identity(target_user_id, &identity.provider)?;
+        }
+
+        self.user_repository.delete_user(&user)?;
+
+        Ok(Vec::new())
+    }
+}
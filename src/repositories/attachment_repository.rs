@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    BoolExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{Attachment, NewAttachment},
+};
+
+pub trait AttachmentRepository: Send + Sync {
+    type Error;
+
+    /// Stores the metadata of an uploaded attachment
+    fn create_attachment(&self, new_attachment: &NewAttachment) -> Result<Attachment, Self::Error>;
+
+    /// Retrieves an attachment by its ID
+    fn get_attachment(&self, attachment_id: i32) -> Result<Attachment, Self::Error>;
+
+    /// Retrieves the attachments of a post, without those of its comments
+    fn get_attachments_by_post(&self, target_post_id: i32) -> Result<Vec<Attachment>, Self::Error>;
+
+    /// Retrieves the attachments of several comments
+    fn get_attachments_by_comments(
+        &self,
+        target_comment_ids: &[i32],
+    ) -> Result<Vec<Attachment>, Self::Error>;
+
+    /// Retrieves the attachments of a post together with those of its comments
+    fn get_all_attachments_by_post(
+        &self,
+        target_post_id: i32,
+    ) -> Result<Vec<Attachment>, Self::Error>;
+
+    /// Retrieves every attachment uploaded by a user
+    fn get_attachments_by_user(&self, target_user_id: i32) -> Result<Vec<Attachment>, Self::Error>;
+
+    /// Deletes attachment records, the stored files are not touched
+    fn delete_attachments(&self, attachment_ids: &[i32]) -> Result<usize, Self::Error>;
+}
+
+pub type AttachmentRepositoryWithError = dyn AttachmentRepository<Error = WebError>;
+
+pub struct PostgresAttachmentRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresAttachmentRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl AttachmentRepository for PostgresAttachmentRepository {
+    type Error = WebError;
+
+    fn create_attachment(&self, new_attachment: &NewAttachment) -> Result<Attachment, Self::Error> {
+        use crate::schema::attachments::table as attachments_table;
+
+        let mut conn = self.pool.get()?;
+
+        let attachment = diesel::insert_into(attachments_table)
+            .values(new_attachment)
+            .returning(Attachment::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(attachment)
+    }
+
+    fn get_attachment(&self, attachment_id: i32) -> Result<Attachment, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let attachment = attachments.find(attachment_id).first(&mut conn)?;
+
+        Ok(attachment)
+    }
+
+    fn get_attachments_by_post(&self, target_post_id: i32) -> Result<Vec<Attachment>, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let attachments_vec = attachments
+            .filter(post_id.eq(target_post_id))
+            .order(id.asc())
+            .load(&mut conn)?;
+
+        Ok(attachments_vec)
+    }
+
+    fn get_attachments_by_comments(
+        &self,
+        target_comment_ids: &[i32],
+    ) -> Result<Vec<Attachment>, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let attachments_vec = attachments
+            .filter(comment_id.eq_any(target_comment_ids))
+            .order(id.asc())
+            .load(&mut conn)?;
+
+        Ok(attachments_vec)
+    }
+
+    fn get_all_attachments_by_post(
+        &self,
+        target_post_id: i32,
+    ) -> Result<Vec<Attachment>, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+        use crate::schema::comments::dsl as comments_dsl;
+
+        let mut conn = self.pool.get()?;
+
+        let post_comment_ids = comments_dsl::comments
+            .filter(comments_dsl::post_id.eq(target_post_id))
+            .select(comments_dsl::id);
+
+        let attachments_vec = attachments
+            .filter(
+                post_id
+                    .eq(target_post_id)
+                    .or(comment_id.eq_any(post_comment_ids.nullable())),
+            )
+            .order(id.asc())
+            .load(&mut conn)?;
+
+        Ok(attachments_vec)
+    }
+
+    fn get_attachments_by_user(&self, target_user_id: i32) -> Result<Vec<Attachment>, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let attachments_vec = attachments
+            .filter(user_id.eq(target_user_id))
+            .order(id.asc())
+            .load(&mut conn)?;
+
+        Ok(attachments_vec)
+    }
+
+    fn delete_attachments(&self, attachment_ids: &[i32]) -> Result<usize, Self::Error> {
+        use crate::schema::attachments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let row_affected =
+            diesel::delete(attachments.filter(id.eq_any(attachment_ids))).execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+}
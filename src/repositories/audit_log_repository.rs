@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use diesel::{
+    pg::Pg,
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    entities::audit::AuditLogFilter,
+    models::{AuditLogEntry, NewAuditLogEntry},
+    schema::audit_log,
+    utils::pagination::QueryPagination,
+};
+
+/// Repository trait for the audit log of privileged actions
+///
+/// The log is append-only, the table refuses updates and deletes.
+pub trait AuditLogRepository: Send + Sync + 'static {
+    /// Appends an entry to the log
+    ///
+    /// # Arguments
+    /// * `new_entry` - The entry to store
+    fn create_audit_log_entry(
+        &self,
+        new_entry: &NewAuditLogEntry,
+    ) -> Result<AuditLogEntry, WebError>;
+
+    /// Lists the entries matching a filter, newest first
+    ///
+    /// # Arguments
+    /// * `filter` - The entries to keep
+    /// * `pagination` - The page of entries to return
+    fn get_audit_log_entries(
+        &self,
+        filter: &AuditLogFilter,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<AuditLogEntry>, WebError>;
+
+    /// Counts the entries matching a filter
+    ///
+    /// # Arguments
+    /// * `filter` - The entries to count
+    fn count_audit_log_entries(&self, filter: &AuditLogFilter) -> Result<i64, WebError>;
+}
+
+pub struct PostgresAuditLogRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresAuditLogRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+/// The entries matching a filter
+fn filtered_audit_log(filter: &AuditLogFilter) -> audit_log::BoxedQuery<'static, Pg> {
+    use crate::schema::audit_log::dsl::*;
+
+    let mut query = audit_log.into_boxed();
+
+    if let Some(filter_action) = filter.action {
+        query = query.filter(action.eq(filter_action.as_str()));
+    }
+    if let Some(filter_actor_user_id) = filter.actor_user_id {
+        query = query.filter(actor_user_id.eq(filter_actor_user_id));
+    }
+    if let Some(filter_target_type) = filter.target_type {
+        query = query.filter(target_type.eq(filter_target_type));
+    }
+    if let Some(filter_target_id) = filter.target_id {
+        query = query.filter(target_id.eq(filter_target_id));
+    }
+
+    query
+}
+
+impl AuditLogRepository for PostgresAuditLogRepository {
+    fn create_audit_log_entry(
+        &self,
+        new_entry: &NewAuditLogEntry,
+    ) -> Result<AuditLogEntry, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let entry = diesel::insert_into(audit_log::table)
+            .values(new_entry)
+            .returning(AuditLogEntry::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(entry)
+    }
+
+    fn get_audit_log_entries(
+        &self,
+        filter: &AuditLogFilter,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<AuditLogEntry>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let entries = filtered_audit_log(filter)
+            .order(audit_log::id.desc())
+            .offset(pagination.get_offset())
+            .limit(pagination.get_limit())
+            .select(AuditLogEntry::as_select())
+            .load(&mut conn)?;
+
+        Ok(entries)
+    }
+
+    fn count_audit_log_entries(&self, filter: &AuditLogFilter) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = filtered_audit_log(filter).count().get_result(&mut conn)?;
+
+        Ok(total)
+    }
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    entities::bookmark::BookmarkTarget,
+    models::{Bookmark, NewBookmark},
+    schema::bookmarks,
+    utils::pagination::QueryPagination,
+};
+
+/// Repository trait for the posts and comments users saved
+pub trait BookmarkRepository: Send + Sync + 'static {
+    /// Saves a post or a comment for a user
+    ///
+    /// # Arguments
+    /// * `new_bookmark` - The bookmark, exactly one of `post_id` and `comment_id` is set
+    fn create_bookmark(&self, new_bookmark: &NewBookmark) -> Result<Bookmark, WebError>;
+
+    /// Retrieves the bookmark a user has on a post or a comment
+    fn find_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<Option<Bookmark>, WebError>;
+
+    /// Files a bookmark of a user in another folder and replaces its note
+    fn update_bookmark(
+        &self,
+        bookmark_id: i32,
+        user_id: i32,
+        folder: &str,
+        note: &str,
+    ) -> Result<usize, WebError>;
+
+    /// Removes a bookmark of a user
+    fn delete_bookmark(&self, bookmark_id: i32, user_id: i32) -> Result<usize, WebError>;
+
+    /// Removes the bookmark a user has on a post or a comment
+    fn delete_bookmark_by_target(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<usize, WebError>;
+
+    /// Retrieves a page of the bookmarks of a user, most recent first, `folder` narrows to one folder
+    fn get_bookmarks(
+        &self,
+        user_id: i32,
+        folder: Option<&str>,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<Bookmark>, WebError>;
+
+    /// Counts the bookmarks of a user, `folder` narrows to one folder
+    fn count_bookmarks(&self, user_id: i32, folder: Option<&str>) -> Result<i64, WebError>;
+
+    /// The folders a user filed bookmarks in, sorted by name
+    fn get_folders(&self, user_id: i32) -> Result<Vec<String>, WebError>;
+
+    /// The comments among `comment_ids` a user saved
+    fn get_bookmarked_comment_ids(
+        &self,
+        user_id: i32,
+        comment_ids: &[i32],
+    ) -> Result<Vec<i32>, WebError>;
+}
+
+pub struct PostgresBookmarkRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresBookmarkRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl BookmarkRepository for PostgresBookmarkRepository {
+    fn create_bookmark(&self, new_bookmark: &NewBookmark) -> Result<Bookmark, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let bookmark = diesel::insert_into(bookmarks::table)
+            .values(new_bookmark)
+            .returning(Bookmark::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(bookmark)
+    }
+
+    fn find_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<Option<Bookmark>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let query = bookmarks::table
+            .filter(bookmarks::user_id.eq(user_id))
+            .select(Bookmark::as_select())
+            .into_boxed();
+        let query = match target {
+            BookmarkTarget::Post(post_id) => query.filter(bookmarks::post_id.eq(post_id)),
+            BookmarkTarget::Comment(comment_id) => {
+                query.filter(bookmarks::comment_id.eq(comment_id))
+            }
+        };
+
+        let bookmark = query.first(&mut conn).optional()?;
+
+        Ok(bookmark)
+    }
+
+    fn update_bookmark(
+        &self,
+        bookmark_id: i32,
+        user_id: i32,
+        folder: &str,
+        note: &str,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::update(
+            bookmarks::table
+                .filter(bookmarks::id.eq(bookmark_id))
+                .filter(bookmarks::user_id.eq(user_id)),
+        )
+        .set((bookmarks::folder.eq(folder), bookmarks::note.eq(note)))
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn delete_bookmark(&self, bookmark_id: i32, user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::delete(
+            bookmarks::table
+                .filter(bookmarks::id.eq(bookmark_id))
+                .filter(bookmarks::user_id.eq(user_id)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn delete_bookmark_by_target(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let by_user = bookmarks::table.filter(bookmarks::user_id.eq(user_id));
+        let row_affected = match target {
+            BookmarkTarget::Post(post_id) => {
+                diesel::delete(by_user.filter(bookmarks::post_id.eq(post_id))).execute(&mut conn)?
+            }
+            BookmarkTarget::Comment(comment_id) => {
+                diesel::delete(by_user.filter(bookmarks::comment_id.eq(comment_id)))
+                    .execute(&mut conn)?
+            }
+        };
+
+        Ok(row_affected)
+    }
+
+    fn get_bookmarks(
+        &self,
+        user_id: i32,
+        folder: Option<&str>,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<Bookmark>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = bookmarks::table
+            .filter(bookmarks::user_id.eq(user_id))
+            .select(Bookmark::as_select())
+            .into_boxed();
+        if let Some(folder) = folder {
+            query = query.filter(bookmarks::folder.eq(folder));
+        }
+
+        let bookmarks = query
+            .order((bookmarks::created_at.desc(), bookmarks::id.desc()))
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .load(&mut conn)?;
+
+        Ok(bookmarks)
+    }
+
+    fn count_bookmarks(&self, user_id: i32, folder: Option<&str>) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = bookmarks::table
+            .filter(bookmarks::user_id.eq(user_id))
+            .into_boxed();
+        if let Some(folder) = folder {
+            query = query.filter(bookmarks::folder.eq(folder));
+        }
+
+        let total = query.count().get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn get_folders(&self, user_id: i32) -> Result<Vec<String>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let folders = bookmarks::table
+            .filter(bookmarks::user_id.eq(user_id))
+            .filter(bookmarks::folder.ne(""))
+            .select(bookmarks::folder)
+            .distinct()
+            .order(bookmarks::folder.asc())
+            .load(&mut conn)?;
+
+        Ok(folders)
+    }
+
+    fn get_bookmarked_comment_ids(
+        &self,
+        user_id: i32,
+        comment_ids: &[i32],
+    ) -> Result<Vec<i32>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let bookmarked_ids = bookmarks::table
+            .filter(bookmarks::user_id.eq(user_id))
+            .filter(bookmarks::comment_id.eq_any(comment_ids))
+            .select(bookmarks::comment_id.assume_not_null())
+            .load(&mut conn)?;
+
+        Ok(bookmarked_ids)
+    }
+}
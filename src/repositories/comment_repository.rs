@@ -1,12 +1,18 @@
 use crate::db::WebError;
 use crate::entities::comment::{CommentPublic, ListCommentResult};
-use crate::models::{Comment, NewComment, Post, User};
+use crate::models::{Comment, CommentRevision, NewComment, NewCommentRevision, Post, User};
 use crate::utils::pagination::QueryPagination;
-use crate::utils::time::time_to_human_readable;
+use crate::utils::time::{edited_time_to_human_readable, time_to_human_readable};
+use chrono::NaiveDateTime;
 use diesel::r2d2::{ConnectionManager, Pool};
-use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
 use std::sync::Arc;
 
+/// Comments on a soft deleted or hidden post are hidden along with it, the queries for live
+/// comments filter on the post as well so they come back when the post is restored
 pub trait CommentRepository: Send + Sync {
     type Error;
 
@@ -24,10 +30,11 @@ pub trait CommentRepository: Send + Sync {
     /// Retrieves all comments for a post
     fn get_comments(&self, parent_post_id: i32) -> Result<Vec<Comment>, Self::Error>;
 
-    /// Updates an existing comment
+    /// Updates an existing comment and records the new revision
     fn update_comment(
         &self,
         target_comment_id: i32,
+        editor_user_id: i32,
         new_body: &str,
     ) -> Result<Comment, Self::Error>;
 
@@ -54,6 +61,66 @@ pub trait CommentRepository: Send + Sync {
         target_comment: &Comment,
         page_limit: i64,
     ) -> Result<i64, Self::Error>;
+
+    /// Retrieves every comment of a user, soft deleted ones included
+    fn get_all_comments_by_user(&self, target_user_id: i32) -> Result<Vec<Comment>, Self::Error>;
+
+    /// Retrieves every revision of a comment, oldest first
+    fn get_comment_revisions(
+        &self,
+        target_comment_id: i32,
+    ) -> Result<Vec<CommentRevision>, Self::Error>;
+
+    /// Retrieves a comment revision by its ID
+    fn get_comment_revision(&self, revision_id: i32) -> Result<CommentRevision, Self::Error>;
+
+    /// Retrieves a soft deleted comment by its ID
+    fn get_deleted_comment(&self, comment_id: i32) -> Result<Comment, Self::Error>;
+
+    /// Retrieves a paginated list of soft deleted comments, of every user when `target_user_id` is `None`
+    fn get_deleted_comments(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListCommentResult, Self::Error>;
+
+    /// Clears the deleted mark of a comment
+    fn restore_comment(&self, target_comment_id: i32) -> Result<usize, Self::Error>;
+
+    /// Retrieves the IDs of the comments soft deleted before a point in time
+    fn get_comment_ids_deleted_before(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<Vec<i32>, Self::Error>;
+
+    /// Permanently deletes soft deleted comments, their revisions go with them
+    fn purge_comments(&self, comment_ids: &[i32]) -> Result<usize, Self::Error>;
+
+    /// Retrieves a comment by its ID, soft deleted and hidden ones included
+    fn get_comment_unfiltered(&self, comment_id: i32) -> Result<Comment, Self::Error>;
+
+    /// Hides a comment from everyone but moderators, or lifts the hold
+    fn set_comment_hidden(
+        &self,
+        target_comment_id: i32,
+        hidden: bool,
+    ) -> Result<usize, Self::Error>;
+
+    /// Counts the comments of a user with the same body created since a point in time, hidden
+    /// ones included
+    fn count_duplicate_comments(
+        &self,
+        target_user_id: i32,
+        comment_body: &str,
+        created_since: NaiveDateTime,
+    ) -> Result<i64, Self::Error>;
+
+    /// Whether a post is locked against new comments, `None` when the post is missing,
+    /// in the trash or hidden
+    fn is_post_locked(&self, parent_post_id: i32) -> Result<Option<bool>, Self::Error>;
+
+    /// Author of the post a comment is written on
+    fn get_post_user_id(&self, parent_post_id: i32) -> Result<i32, Self::Error>;
 }
 
 pub type CommentRepositoryWithError = dyn CommentRepository<Error = WebError>;
@@ -98,10 +165,16 @@ impl CommentRepository for PostgresCommentRepository {
     fn get_comment(&self, comment_id: i32) -> Result<Comment, Self::Error> {
         let mut conn = self.pool.get()?;
         use crate::schema::comments::dsl::*;
+        use crate::schema::posts::dsl as post_dsl;
 
         let comment = comments
-            .find(comment_id)
+            .inner_join(post_dsl::posts)
+            .filter(id.eq(comment_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
+            .select(Comment::as_select())
             .first(&mut conn)?;
 
         Ok(comment)
@@ -112,10 +185,17 @@ impl CommentRepository for PostgresCommentRepository {
 
         use crate::schema::comments::dsl::*;
 
+        use crate::schema::posts::dsl as post_dsl;
+
         let comments_vec = comments
+            .inner_join(post_dsl::posts)
             .filter(post_id.eq(parent_post_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .order(created_at.desc())
+            .select(Comment::as_select())
             .load(&mut conn)?;
 
         Ok(comments_vec)
@@ -124,16 +204,54 @@ impl CommentRepository for PostgresCommentRepository {
     fn update_comment(
         &self,
         target_comment_id: i32,
+        editor_user_id: i32,
         new_body: &str,
     ) -> Result<Comment, Self::Error> {
         let mut conn = self.pool.get()?;
 
+        use crate::schema::comment_revisions::dsl as revision_dsl;
         use crate::schema::comments::dsl::*;
 
-        let comment = diesel::update(comments.find(target_comment_id))
-            .set(content.eq(new_body))
-            .returning(Comment::as_returning())
-            .get_result(&mut conn)?;
+        let comment = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let current_comment = comments
+                .find(target_comment_id)
+                .select(Comment::as_select())
+                .for_update()
+                .first(conn)?;
+
+            // comments written before revisions existed get their current version recorded first
+            let revision_count = revision_dsl::comment_revisions
+                .filter(revision_dsl::comment_id.eq(target_comment_id))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            if revision_count == 0 {
+                diesel::insert_into(revision_dsl::comment_revisions)
+                    .values(&NewCommentRevision {
+                        comment_id: target_comment_id,
+                        user_id: Some(current_comment.user_id),
+                        content: &current_comment.content,
+                        created_at: current_comment.updated_at,
+                    })
+                    .execute(conn)?;
+            }
+
+            let updated_comment = diesel::update(comments.find(target_comment_id))
+                .set((content.eq(new_body), updated_at.eq(diesel::dsl::now)))
+                .returning(Comment::as_returning())
+                .get_result(conn)?;
+
+            diesel::insert_into(revision_dsl::comment_revisions)
+                .values(&NewCommentRevision {
+                    comment_id: target_comment_id,
+                    user_id: Some(editor_user_id),
+                    content: &updated_comment.content,
+                    created_at: updated_comment.updated_at,
+                })
+                .execute(conn)?;
+
+            Ok(updated_comment)
+        })?;
 
         Ok(comment)
     }
@@ -157,14 +275,19 @@ impl CommentRepository for PostgresCommentRepository {
     ) -> Result<ListCommentResult, Self::Error> {
         let mut conn = self.pool.get()?;
 
-        use crate::schema::comments::dsl::{comments, created_at, deleted_at, post_id};
+        use crate::schema::comments::dsl::{comments, created_at, deleted_at, hidden_at, post_id};
         use crate::schema::comments::table as comments_table;
+        use crate::schema::posts::dsl as post_dsl;
         use crate::schema::users::dsl::users;
 
         let comments_joined = comments
             .inner_join(users)
+            .inner_join(post_dsl::posts)
             .filter(post_id.eq(parent_post_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .order(created_at.asc())
             .limit(pagination.limit)
             .offset(pagination.get_offset())
@@ -175,16 +298,26 @@ impl CommentRepository for PostgresCommentRepository {
             .into_iter()
             .map(|(comment, user)| CommentPublic {
                 time_human: time_to_human_readable(comment.created_at),
+                edited_time_human: edited_time_to_human_readable(
+                    comment.created_at,
+                    comment.updated_at,
+                ),
                 comment,
                 user,
                 allow_update: false,
                 parent_post: None,
+                attachments: vec![],
+                is_bookmarked: false,
             })
             .collect();
 
         let total = comments_table
+            .inner_join(post_dsl::posts)
             .filter(post_id.eq(parent_post_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .count()
             .get_result::<i64>(&mut conn)?;
 
@@ -201,15 +334,19 @@ impl CommentRepository for PostgresCommentRepository {
     ) -> Result<ListCommentResult, Self::Error> {
         let mut conn = self.pool.get()?;
 
-        use crate::schema::comments::dsl::{comments, created_at, deleted_at, user_id};
+        use crate::schema::comments::dsl::{comments, created_at, deleted_at, hidden_at, user_id};
         use crate::schema::comments::table as comments_table;
+        use crate::schema::posts::dsl as post_dsl;
         use crate::schema::users::dsl::users;
 
         let comments_joined = comments
             .inner_join(users)
-            .inner_join(crate::schema::posts::table)
+            .inner_join(post_dsl::posts)
             .filter(user_id.eq(target_user_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .order(created_at.desc())
             .limit(pagination.limit)
             .offset(pagination.get_offset())
@@ -220,16 +357,26 @@ impl CommentRepository for PostgresCommentRepository {
             .into_iter()
             .map(|(comment, user, post)| CommentPublic {
                 time_human: time_to_human_readable(comment.created_at),
+                edited_time_human: edited_time_to_human_readable(
+                    comment.created_at,
+                    comment.updated_at,
+                ),
                 comment,
                 user,
                 allow_update: false,
                 parent_post: Some(post),
+                attachments: vec![],
+                is_bookmarked: false,
             })
             .collect();
 
         let total = comments_table
+            .inner_join(post_dsl::posts)
             .filter(user_id.eq(target_user_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .count()
             .get_result::<i64>(&mut conn)?;
 
@@ -246,12 +393,17 @@ impl CommentRepository for PostgresCommentRepository {
     ) -> Result<i64, Self::Error> {
         let mut conn = self.pool.get()?;
 
-        use crate::schema::comments::dsl::{deleted_at, id, post_id};
+        use crate::schema::comments::dsl::{deleted_at, hidden_at, id, post_id};
         use crate::schema::comments::table as comments_table;
+        use crate::schema::posts::dsl as post_dsl;
 
         let nth_row_comment = comments_table
+            .inner_join(post_dsl::posts)
             .filter(post_id.eq(target_comment.post_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .order(id.asc())
             .select(id)
             .load::<i32>(&mut conn)?
@@ -264,4 +416,244 @@ impl CommentRepository for PostgresCommentRepository {
 
         Ok(page)
     }
+
+    fn get_all_comments_by_user(&self, target_user_id: i32) -> Result<Vec<Comment>, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let user_comments = comments
+            .filter(user_id.eq(target_user_id))
+            .order(created_at.asc())
+            .select(Comment::as_select())
+            .load(&mut conn)?;
+
+        Ok(user_comments)
+    }
+
+    fn get_comment_revisions(
+        &self,
+        target_comment_id: i32,
+    ) -> Result<Vec<CommentRevision>, Self::Error> {
+        use crate::schema::comment_revisions::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let revisions = comment_revisions
+            .filter(comment_id.eq(target_comment_id))
+            .order(id.asc())
+            .select(CommentRevision::as_select())
+            .load(&mut conn)?;
+
+        Ok(revisions)
+    }
+
+    fn get_comment_revision(&self, revision_id: i32) -> Result<CommentRevision, Self::Error> {
+        use crate::schema::comment_revisions::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let revision = comment_revisions
+            .find(revision_id)
+            .select(CommentRevision::as_select())
+            .first(&mut conn)?;
+
+        Ok(revision)
+    }
+
+    fn get_deleted_comment(&self, comment_id: i32) -> Result<Comment, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let comment = comments
+            .find(comment_id)
+            .filter(deleted_at.is_not_null())
+            .select(Comment::as_select())
+            .first(&mut conn)?;
+
+        Ok(comment)
+    }
+
+    fn get_deleted_comments(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListCommentResult, Self::Error> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::comments::dsl::{comments, deleted_at, user_id};
+        use crate::schema::users::dsl::users;
+
+        let mut comments_query = comments
+            .inner_join(users)
+            .inner_join(crate::schema::posts::table)
+            .filter(deleted_at.is_not_null())
+            .into_boxed();
+        let mut total_query = comments.filter(deleted_at.is_not_null()).into_boxed();
+
+        if let Some(target_user_id) = target_user_id {
+            comments_query = comments_query.filter(user_id.eq(target_user_id));
+            total_query = total_query.filter(user_id.eq(target_user_id));
+        }
+
+        let comments_joined = comments_query
+            .order(deleted_at.desc())
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select((Comment::as_select(), User::as_select(), Post::as_select()))
+            .load::<(Comment, User, Post)>(&mut conn)?;
+
+        let comments_mapped = comments_joined
+            .into_iter()
+            .map(|(comment, user, post)| CommentPublic {
+                time_human: time_to_human_readable(comment.created_at),
+                edited_time_human: edited_time_to_human_readable(
+                    comment.created_at,
+                    comment.updated_at,
+                ),
+                comment,
+                user,
+                allow_update: false,
+                parent_post: Some(post),
+                attachments: vec![],
+                is_bookmarked: false,
+            })
+            .collect();
+
+        let total = total_query.count().get_result::<i64>(&mut conn)?;
+
+        Ok(ListCommentResult {
+            comments: comments_mapped,
+            total,
+        })
+    }
+
+    fn restore_comment(&self, target_comment_id: i32) -> Result<usize, Self::Error> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::comments::dsl::*;
+
+        let restore_usize = diesel::update(comments.find(target_comment_id))
+            .filter(deleted_at.is_not_null())
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .execute(&mut conn)?;
+
+        Ok(restore_usize)
+    }
+
+    fn get_comment_ids_deleted_before(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<Vec<i32>, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let comment_ids = comments
+            .filter(deleted_at.lt(deleted_before))
+            .order(id.asc())
+            .select(id)
+            .load::<i32>(&mut conn)?;
+
+        Ok(comment_ids)
+    }
+
+    fn purge_comments(&self, comment_ids: &[i32]) -> Result<usize, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        // only ever hard deletes comments that are already in the trash
+        let row_affected = diesel::delete(
+            comments
+                .filter(id.eq_any(comment_ids))
+                .filter(deleted_at.is_not_null()),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn get_comment_unfiltered(&self, comment_id: i32) -> Result<Comment, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let comment = comments
+            .find(comment_id)
+            .select(Comment::as_select())
+            .first(&mut conn)?;
+
+        Ok(comment)
+    }
+
+    fn set_comment_hidden(
+        &self,
+        target_comment_id: i32,
+        hidden: bool,
+    ) -> Result<usize, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let new_hidden_at = hidden.then(|| chrono::Utc::now().naive_utc());
+
+        // updated_at is left alone, hiding a comment is not an edit
+        let row_affected = diesel::update(comments.find(target_comment_id))
+            .set(hidden_at.eq(new_hidden_at))
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn count_duplicate_comments(
+        &self,
+        target_user_id: i32,
+        comment_body: &str,
+        created_since: NaiveDateTime,
+    ) -> Result<i64, Self::Error> {
+        use crate::schema::comments::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let total = comments
+            .filter(user_id.eq(target_user_id))
+            .filter(content.eq(comment_body))
+            .filter(created_at.ge(created_since))
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn is_post_locked(&self, parent_post_id: i32) -> Result<Option<bool>, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let post_locked = posts
+            .find(parent_post_id)
+            .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .select(locked)
+            .get_result(&mut conn)
+            .optional()?;
+
+        Ok(post_locked)
+    }
+
+    fn get_post_user_id(&self, parent_post_id: i32) -> Result<i32, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let post_user_id = posts
+            .find(parent_post_id)
+            .select(user_id)
+            .get_result(&mut conn)?;
+
+        Ok(post_user_id)
+    }
 }
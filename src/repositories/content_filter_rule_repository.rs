@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{ContentFilterRule, NewContentFilterRule},
+};
+
+/// Repository trait for the regex rules of the content filter
+pub trait ContentFilterRuleRepository: Send + Sync + 'static {
+    /// Lists every rule, oldest first
+    fn get_content_filter_rules(&self) -> Result<Vec<ContentFilterRule>, WebError>;
+
+    /// Stores a new rule
+    ///
+    /// # Arguments
+    /// * `new_rule` - The rule to store
+    fn create_content_filter_rule(
+        &self,
+        new_rule: &NewContentFilterRule,
+    ) -> Result<ContentFilterRule, WebError>;
+
+    /// Deletes a rule
+    ///
+    /// # Arguments
+    /// * `rule_id` - The rule to delete
+    fn delete_content_filter_rule(&self, rule_id: i32) -> Result<usize, WebError>;
+}
+
+pub struct PostgresContentFilterRuleRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresContentFilterRuleRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl ContentFilterRuleRepository for PostgresContentFilterRuleRepository {
+    fn get_content_filter_rules(&self) -> Result<Vec<ContentFilterRule>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::content_filter_rules::dsl::*;
+
+        let rules = content_filter_rules
+            .order(id.asc())
+            .select(ContentFilterRule::as_select())
+            .load(&mut conn)?;
+
+        Ok(rules)
+    }
+
+    fn create_content_filter_rule(
+        &self,
+        new_rule: &NewContentFilterRule,
+    ) -> Result<ContentFilterRule, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::content_filter_rules::dsl::*;
+
+        let rule = diesel::insert_into(content_filter_rules)
+            .values(new_rule)
+            .returning(ContentFilterRule::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(rule)
+    }
+
+    fn delete_content_filter_rule(&self, rule_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::content_filter_rules::dsl::*;
+
+        let row_affected = diesel::delete(content_filter_rules.find(rule_id)).execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+}
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    entities::post::{ListPostResult, PostPublic},
+    models::{Post, User},
+    schema::{follows, post_subscriptions, posts, users},
+    utils::{
+        pagination::QueryPagination,
+        time::{edited_time_to_human_readable, time_to_human_readable},
+    },
+};
+
+/// Repository trait for users following users and subscribing to posts
+pub trait FollowRepository: Send + Sync + 'static {
+    /// Makes a user follow another, following twice changes nothing
+    ///
+    /// # Arguments
+    /// * `follower_user_id` - The user who follows
+    /// * `followed_user_id` - The user being followed
+    fn follow_user(&self, follower_user_id: i32, followed_user_id: i32) -> Result<usize, WebError>;
+
+    /// Stops a user from following another
+    fn unfollow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<usize, WebError>;
+
+    /// Whether a user follows another
+    fn is_following(&self, follower_user_id: i32, followed_user_id: i32) -> Result<bool, WebError>;
+
+    /// Counts the users following a user
+    fn count_followers(&self, user_id: i32) -> Result<i64, WebError>;
+
+    /// Counts the users a user follows
+    fn count_following(&self, user_id: i32) -> Result<i64, WebError>;
+
+    /// Retrieves a page of the users following a user, most recent first
+    fn get_followers(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<User>, WebError>;
+
+    /// Retrieves a page of the users a user follows, most recent first
+    fn get_following(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<User>, WebError>;
+
+    /// Retrieves a page of the posts written by the users a user follows, newest first
+    fn get_followed_posts_with_user(
+        &self,
+        follower_user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, WebError>;
+
+    /// Subscribes a user to the new comments of a post, subscribing twice changes nothing
+    fn subscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, WebError>;
+
+    /// Stops the notifications of a post for a user
+    fn unsubscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, WebError>;
+
+    /// Whether a user is subscribed to a post
+    fn is_subscribed(&self, user_id: i32, post_id: i32) -> Result<bool, WebError>;
+
+    /// Users subscribed to a post
+    fn get_post_subscriber_ids(&self, post_id: i32) -> Result<Vec<i32>, WebError>;
+}
+
+pub struct PostgresFollowRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresFollowRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl FollowRepository for PostgresFollowRepository {
+    fn follow_user(&self, follower_user_id: i32, followed_user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::insert_into(follows::table)
+            .values((
+                follows::follower_user_id.eq(follower_user_id),
+                follows::followed_user_id.eq(followed_user_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn unfollow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::delete(
+            follows::table
+                .filter(follows::follower_user_id.eq(follower_user_id))
+                .filter(follows::followed_user_id.eq(followed_user_id)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn is_following(&self, follower_user_id: i32, followed_user_id: i32) -> Result<bool, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = follows::table
+            .filter(follows::follower_user_id.eq(follower_user_id))
+            .filter(follows::followed_user_id.eq(followed_user_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(total > 0)
+    }
+
+    fn count_followers(&self, user_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = follows::table
+            .filter(follows::followed_user_id.eq(user_id))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn count_following(&self, user_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = follows::table
+            .filter(follows::follower_user_id.eq(user_id))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn get_followers(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<User>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let followers = follows::table
+            .inner_join(users::table.on(users::id.eq(follows::follower_user_id)))
+            .filter(follows::followed_user_id.eq(user_id))
+            .order(follows::created_at.desc())
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select(User::as_select())
+            .load(&mut conn)?;
+
+        Ok(followers)
+    }
+
+    fn get_following(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<User>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let following = follows::table
+            .inner_join(users::table.on(users::id.eq(follows::followed_user_id)))
+            .filter(follows::follower_user_id.eq(user_id))
+            .order(follows::created_at.desc())
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select(User::as_select())
+            .load(&mut conn)?;
+
+        Ok(following)
+    }
+
+    fn get_followed_posts_with_user(
+        &self,
+        follower_user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let followed_user_ids = follows::table
+            .filter(follows::follower_user_id.eq(follower_user_id))
+            .select(follows::followed_user_id);
+
+        let posts_raw = posts::table
+            .inner_join(users::table)
+            .filter(posts::user_id.eq_any(followed_user_ids))
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .order(posts::created_at.desc())
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select((Post::as_select(), User::as_select()))
+            .load::<(Post, User)>(&mut conn)?;
+
+        let posts_mapped = posts_raw
+            .into_iter()
+            .map(|(post, user)| PostPublic {
+                user,
+                time_human: time_to_human_readable(post.created_at),
+                edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
+                post,
+                allow_update: false,
+                attachments: vec![],
+            })
+            .collect();
+
+        let total_posts = posts::table
+            .filter(posts::user_id.eq_any(followed_user_ids))
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(ListPostResult {
+            posts: posts_mapped,
+            total: total_posts,
+        })
+    }
+
+    fn subscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::insert_into(post_subscriptions::table)
+            .values((
+                post_subscriptions::user_id.eq(user_id),
+                post_subscriptions::post_id.eq(post_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn unsubscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::delete(
+            post_subscriptions::table
+                .filter(post_subscriptions::user_id.eq(user_id))
+                .filter(post_subscriptions::post_id.eq(post_id)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn is_subscribed(&self, user_id: i32, post_id: i32) -> Result<bool, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = post_subscriptions::table
+            .filter(post_subscriptions::user_id.eq(user_id))
+            .filter(post_subscriptions::post_id.eq(post_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(total > 0)
+    }
+
+    fn get_post_subscriber_ids(&self, post_id: i32) -> Result<Vec<i32>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let subscriber_ids = post_subscriptions::table
+            .filter(post_subscriptions::post_id.eq(post_id))
+            .select(post_subscriptions::user_id)
+            .load(&mut conn)?;
+
+        Ok(subscriber_ids)
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{LoginAttempt, NewLoginAttempt},
+};
+
+/// Repository trait for tracking failed login attempts
+///
+/// Attempts are keyed by a free-form string so the same table can throttle
+/// both accounts (`account:<email>`) and client addresses (`ip:<address>`).
+pub trait LoginAttemptRepository: Send + Sync + 'static {
+    /// Retrieves the attempt record for a key, if any
+    ///
+    /// # Arguments
+    /// * `target_key` - The throttle key to look up
+    fn get_login_attempt(&self, target_key: &str) -> Result<Option<LoginAttempt>, WebError>;
+
+    /// Increments the failed counter for a key, creating the record when missing
+    ///
+    /// # Arguments
+    /// * `target_key` - The throttle key to record the failure against
+    /// * `failed_at` - The time of the failed attempt
+    fn record_failed_login(
+        &self,
+        target_key: &str,
+        failed_at: chrono::NaiveDateTime,
+    ) -> Result<LoginAttempt, WebError>;
+
+    /// Locks a key until the given time
+    ///
+    /// # Arguments
+    /// * `target_key` - The throttle key to lock
+    /// * `until` - The time the lock expires
+    fn lock_login_attempt(
+        &self,
+        target_key: &str,
+        until: chrono::NaiveDateTime,
+    ) -> Result<usize, WebError>;
+
+    /// Deletes the attempt record for a key
+    ///
+    /// # Arguments
+    /// * `target_key` - The throttle key to clear
+    fn clear_login_attempt(&self, target_key: &str) -> Result<usize, WebError>;
+}
+
+pub struct PostgresLoginAttemptRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresLoginAttemptRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl LoginAttemptRepository for PostgresLoginAttemptRepository {
+    fn get_login_attempt(&self, target_key: &str) -> Result<Option<LoginAttempt>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::login_attempts::dsl::*;
+
+        let login_attempt = login_attempts
+            .filter(attempt_key.eq(target_key))
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(login_attempt)
+    }
+
+    fn record_failed_login(
+        &self,
+        target_key: &str,
+        failed_at: chrono::NaiveDateTime,
+    ) -> Result<LoginAttempt, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::login_attempts::dsl::*;
+
+        let new_login_attempt = NewLoginAttempt {
+            attempt_key: target_key,
+            failed_count: 1,
+            last_failed_at: failed_at,
+        };
+
+        let login_attempt = diesel::insert_into(login_attempts)
+            .values(&new_login_attempt)
+            .on_conflict(attempt_key)
+            .do_update()
+            .set((
+                failed_count.eq(failed_count + 1),
+                last_failed_at.eq(failed_at),
+            ))
+            .returning(LoginAttempt::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(login_attempt)
+    }
+
+    fn lock_login_attempt(
+        &self,
+        target_key: &str,
+        until: chrono::NaiveDateTime,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::login_attempts::dsl::*;
+
+        let row_affected = diesel::update(login_attempts.filter(attempt_key.eq(target_key)))
+            .set(locked_until.eq(until))
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn clear_login_attempt(&self, target_key: &str) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::login_attempts::dsl::*;
+
+        let row_affected =
+            diesel::delete(login_attempts.filter(attempt_key.eq(target_key))).execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+}
@@ -0,0 +1,474 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, NullableExpressionMethods,
+    OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{
+        Conversation, ConversationMember, Message, NewConversation, NewConversationMember,
+        NewMessage, User,
+    },
+    schema::{conversation_members, conversations, messages, user_blocks, users},
+    utils::pagination::QueryPagination,
+};
+
+/// Repository trait for private conversations, their messages and the blocks between users
+pub trait MessageRepository: Send + Sync + 'static {
+    /// Finds the one-to-one conversation between two users
+    ///
+    /// # Arguments
+    /// * `user_id` - One of the users
+    /// * `other_user_id` - The other user
+    fn find_direct_conversation(
+        &self,
+        user_id: i32,
+        other_user_id: i32,
+    ) -> Result<Option<Conversation>, WebError>;
+
+    /// Stores a conversation together with its members
+    ///
+    /// # Arguments
+    /// * `new_conversation` - The conversation to store
+    /// * `member_ids` - Every member, the starter included
+    fn create_conversation(
+        &self,
+        new_conversation: &NewConversation,
+        member_ids: &[i32],
+    ) -> Result<Conversation, WebError>;
+
+    /// Reads a conversation
+    fn get_conversation(&self, conversation_id: i32) -> Result<Conversation, WebError>;
+
+    /// Reads the membership of a user, members who left are returned too
+    fn get_conversation_member(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<Option<ConversationMember>, WebError>;
+
+    /// Lists the members who did not leave of several conversations
+    ///
+    /// # Arguments
+    /// * `conversation_ids` - The conversations to list the members of
+    fn get_conversation_members(
+        &self,
+        conversation_ids: &[i32],
+    ) -> Result<Vec<(ConversationMember, User)>, WebError>;
+
+    /// Lists the conversations a user did not leave, most recent first
+    ///
+    /// # Arguments
+    /// * `user_id` - The member
+    /// * `pagination` - The page of conversations to return
+    fn get_user_conversations(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<Conversation>, WebError>;
+
+    /// Counts the conversations a user did not leave
+    fn count_user_conversations(&self, user_id: i32) -> Result<i64, WebError>;
+
+    /// Reads the newest message of each conversation
+    fn get_last_messages(&self, conversation_ids: &[i32]) -> Result<Vec<Message>, WebError>;
+
+    /// Counts the messages of others a user has not read, per conversation they did not leave
+    fn get_unread_counts(&self, user_id: i32) -> Result<Vec<(i32, i64)>, WebError>;
+
+    /// Lists a page of messages with their authors, newest first
+    ///
+    /// # Arguments
+    /// * `conversation_id` - The conversation to read
+    /// * `pagination` - The page of messages to return
+    fn get_messages_with_user(
+        &self,
+        conversation_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<(Message, User)>, WebError>;
+
+    /// Counts the messages of a conversation, deleted ones included as they keep their place
+    fn count_messages(&self, conversation_id: i32) -> Result<i64, WebError>;
+
+    /// Stores a message, moves the conversation up the inbox and marks it read for the author
+    fn create_message(&self, new_message: &NewMessage) -> Result<Message, WebError>;
+
+    /// Soft deletes a message of its author
+    ///
+    /// # Arguments
+    /// * `message_id` - The message to delete
+    /// * `user_id` - The author
+    fn delete_message(&self, message_id: i32, user_id: i32) -> Result<usize, WebError>;
+
+    /// Marks every message of a conversation read for a member
+    fn mark_conversation_read(&self, conversation_id: i32, user_id: i32)
+        -> Result<usize, WebError>;
+
+    /// Takes a member out of a conversation, their messages stay
+    fn leave_conversation(&self, conversation_id: i32, user_id: i32) -> Result<usize, WebError>;
+
+    /// Blocks a user from messaging another, blocking twice changes nothing
+    fn block_user(&self, blocker_user_id: i32, blocked_user_id: i32) -> Result<usize, WebError>;
+
+    /// Lifts a block
+    fn unblock_user(&self, blocker_user_id: i32, blocked_user_id: i32) -> Result<usize, WebError>;
+
+    /// Lists the users a user blocked
+    fn get_blocked_users(&self, blocker_user_id: i32) -> Result<Vec<User>, WebError>;
+
+    /// Which of the given users blocked a user
+    ///
+    /// # Arguments
+    /// * `blocked_user_id` - The user who might be blocked
+    /// * `user_ids` - The users who might have blocked them
+    fn get_blockers_of(&self, blocked_user_id: i32, user_ids: &[i32])
+        -> Result<Vec<i32>, WebError>;
+}
+
+pub struct PostgresMessageRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresMessageRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl MessageRepository for PostgresMessageRepository {
+    fn find_direct_conversation(
+        &self,
+        user_id: i32,
+        other_user_id: i32,
+    ) -> Result<Option<Conversation>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let conversation_ids_of = |member_user_id: i32| {
+            conversation_members::table
+                .filter(conversation_members::user_id.eq(member_user_id))
+                .select(conversation_members::conversation_id)
+        };
+
+        let conversation = conversations::table
+            .filter(conversations::is_group.eq(false))
+            .filter(conversations::id.eq_any(conversation_ids_of(user_id)))
+            .filter(conversations::id.eq_any(conversation_ids_of(other_user_id)))
+            .select(Conversation::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(conversation)
+    }
+
+    fn create_conversation(
+        &self,
+        new_conversation: &NewConversation,
+        member_ids: &[i32],
+    ) -> Result<Conversation, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let conversation = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let conversation = diesel::insert_into(conversations::table)
+                .values(new_conversation)
+                .returning(Conversation::as_returning())
+                .get_result(conn)?;
+
+            let new_members: Vec<NewConversationMember> = member_ids
+                .iter()
+                .map(|member_id| NewConversationMember {
+                    conversation_id: conversation.id,
+                    user_id: *member_id,
+                })
+                .collect();
+
+            diesel::insert_into(conversation_members::table)
+                .values(&new_members)
+                .execute(conn)?;
+
+            Ok(conversation)
+        })?;
+
+        Ok(conversation)
+    }
+
+    fn get_conversation(&self, conversation_id: i32) -> Result<Conversation, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let conversation = conversations::table
+            .find(conversation_id)
+            .select(Conversation::as_select())
+            .first(&mut conn)?;
+
+        Ok(conversation)
+    }
+
+    fn get_conversation_member(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<Option<ConversationMember>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let member = conversation_members::table
+            .filter(conversation_members::conversation_id.eq(conversation_id))
+            .filter(conversation_members::user_id.eq(user_id))
+            .select(ConversationMember::as_select())
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(member)
+    }
+
+    fn get_conversation_members(
+        &self,
+        conversation_ids: &[i32],
+    ) -> Result<Vec<(ConversationMember, User)>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let members = conversation_members::table
+            .inner_join(users::table)
+            .filter(conversation_members::conversation_id.eq_any(conversation_ids))
+            .filter(conversation_members::left_at.is_null())
+            .order(conversation_members::joined_at.asc())
+            .select((ConversationMember::as_select(), User::as_select()))
+            .load(&mut conn)?;
+
+        Ok(members)
+    }
+
+    fn get_user_conversations(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<Conversation>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let user_conversations = conversations::table
+            .inner_join(conversation_members::table)
+            .filter(conversation_members::user_id.eq(user_id))
+            .filter(conversation_members::left_at.is_null())
+            .order((conversations::updated_at.desc(), conversations::id.desc()))
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select(Conversation::as_select())
+            .load(&mut conn)?;
+
+        Ok(user_conversations)
+    }
+
+    fn count_user_conversations(&self, user_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = conversation_members::table
+            .filter(conversation_members::user_id.eq(user_id))
+            .filter(conversation_members::left_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn get_last_messages(&self, conversation_ids: &[i32]) -> Result<Vec<Message>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let last_messages = messages::table
+            .filter(messages::conversation_id.eq_any(conversation_ids))
+            .distinct_on(messages::conversation_id)
+            .order((
+                messages::conversation_id,
+                messages::created_at.desc(),
+                messages::id.desc(),
+            ))
+            .select(Message::as_select())
+            .load(&mut conn)?;
+
+        Ok(last_messages)
+    }
+
+    fn get_unread_counts(&self, user_id: i32) -> Result<Vec<(i32, i64)>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let unread_counts =
+            messages::table
+                .inner_join(
+                    conversation_members::table.on(conversation_members::conversation_id
+                        .eq(messages::conversation_id)
+                        .and(conversation_members::user_id.eq(user_id))),
+                )
+                .filter(conversation_members::left_at.is_null())
+                .filter(messages::user_id.ne(user_id))
+                .filter(messages::deleted_at.is_null())
+                .filter(conversation_members::last_read_at.is_null().or(
+                    messages::created_at.gt(conversation_members::last_read_at.assume_not_null()),
+                ))
+                .group_by(messages::conversation_id)
+                .select((messages::conversation_id, diesel::dsl::count_star()))
+                .load(&mut conn)?;
+
+        Ok(unread_counts)
+    }
+
+    fn get_messages_with_user(
+        &self,
+        conversation_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<(Message, User)>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let conversation_messages = messages::table
+            .inner_join(users::table)
+            .filter(messages::conversation_id.eq(conversation_id))
+            .order((messages::created_at.desc(), messages::id.desc()))
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select((Message::as_select(), User::as_select()))
+            .load(&mut conn)?;
+
+        Ok(conversation_messages)
+    }
+
+    fn count_messages(&self, conversation_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let total = messages::table
+            .filter(messages::conversation_id.eq(conversation_id))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn create_message(&self, new_message: &NewMessage) -> Result<Message, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let message = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let message = diesel::insert_into(messages::table)
+                .values(new_message)
+                .returning(Message::as_returning())
+                .get_result(conn)?;
+
+            diesel::update(conversations::table.find(message.conversation_id))
+                .set(conversations::updated_at.eq(message.created_at))
+                .execute(conn)?;
+
+            diesel::update(
+                conversation_members::table
+                    .filter(conversation_members::conversation_id.eq(message.conversation_id))
+                    .filter(conversation_members::user_id.eq(message.user_id)),
+            )
+            .set(conversation_members::last_read_at.eq(message.created_at))
+            .execute(conn)?;
+
+            Ok(message)
+        })?;
+
+        Ok(message)
+    }
+
+    fn delete_message(&self, message_id: i32, user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::update(
+            messages::table
+                .filter(messages::id.eq(message_id))
+                .filter(messages::user_id.eq(user_id))
+                .filter(messages::deleted_at.is_null()),
+        )
+        .set(messages::deleted_at.eq(diesel::dsl::now))
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn mark_conversation_read(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::update(
+            conversation_members::table
+                .filter(conversation_members::conversation_id.eq(conversation_id))
+                .filter(conversation_members::user_id.eq(user_id)),
+        )
+        .set(conversation_members::last_read_at.eq(diesel::dsl::now))
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn leave_conversation(&self, conversation_id: i32, user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::update(
+            conversation_members::table
+                .filter(conversation_members::conversation_id.eq(conversation_id))
+                .filter(conversation_members::user_id.eq(user_id))
+                .filter(conversation_members::left_at.is_null()),
+        )
+        .set(conversation_members::left_at.eq(diesel::dsl::now))
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn block_user(&self, blocker_user_id: i32, blocked_user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::insert_into(user_blocks::table)
+            .values((
+                user_blocks::blocker_user_id.eq(blocker_user_id),
+                user_blocks::blocked_user_id.eq(blocked_user_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn unblock_user(&self, blocker_user_id: i32, blocked_user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::delete(
+            user_blocks::table
+                .filter(user_blocks::blocker_user_id.eq(blocker_user_id))
+                .filter(user_blocks::blocked_user_id.eq(blocked_user_id)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn get_blocked_users(&self, blocker_user_id: i32) -> Result<Vec<User>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let blocked_users = user_blocks::table
+            .inner_join(users::table.on(users::id.eq(user_blocks::blocked_user_id)))
+            .filter(user_blocks::blocker_user_id.eq(blocker_user_id))
+            .order(user_blocks::created_at.desc())
+            .select(User::as_select())
+            .load(&mut conn)?;
+
+        Ok(blocked_users)
+    }
+
+    fn get_blockers_of(
+        &self,
+        blocked_user_id: i32,
+        user_ids: &[i32],
+    ) -> Result<Vec<i32>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let blocker_ids = user_blocks::table
+            .filter(user_blocks::blocked_user_id.eq(blocked_user_id))
+            .filter(user_blocks::blocker_user_id.eq_any(user_ids))
+            .select(user_blocks::blocker_user_id)
+            .load(&mut conn)?;
+
+        Ok(blocker_ids)
+    }
+}
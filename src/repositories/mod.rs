@@ -4,3 +4,17 @@ pub mod user_repository;
 pub mod token_repository;
 pub mod user_repository_postgres;
 pub mod user_repository_inmemory;
+pub mod login_attempt_repository;
+pub mod user_identity_repository;
+pub mod attachment_repository;
+pub mod report_repository;
+pub mod user_ban_repository;
+pub mod audit_log_repository;
+pub mod content_filter_rule_repository;
+pub mod sitemap_repository;
+pub mod message_repository;
+pub mod follow_repository;
+pub mod bookmark_repository;
+pub mod user_stats_repository;
+pub mod account_repository;
+pub mod notification_repository;
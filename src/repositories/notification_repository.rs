@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{NewNotification, Notification},
+    schema::{comments, notifications, posts, users},
+    utils::pagination::QueryPagination,
+};
+
+/// A notification with the title of its post and the name of the user who commented
+pub type NotificationWithContext = (Notification, String, String);
+
+/// Repository trait for the replies users are told about
+pub trait NotificationRepository: Send + Sync + 'static {
+    /// Stores notifications, one per receiving user
+    fn create_notifications(
+        &self,
+        new_notifications: &[NewNotification],
+    ) -> Result<usize, WebError>;
+
+    /// Retrieves a page of the notifications of a user, most recent first.
+    /// Replies deleted or hidden since, or on a post deleted or hidden since, are left out
+    fn get_notifications(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<NotificationWithContext>, WebError>;
+
+    /// Counts the notifications of a user, with the visibility rules of `get_notifications`
+    fn count_notifications(&self, user_id: i32) -> Result<i64, WebError>;
+
+    /// Counts the unread notifications of a user, with the visibility rules of `get_notifications`
+    fn count_unread_notifications(&self, user_id: i32) -> Result<i64, WebError>;
+
+    /// Marks a notification of a user read and returns it
+    fn mark_notification_read(
+        &self,
+        user_id: i32,
+        notification_id: i32,
+    ) -> Result<Notification, WebError>;
+
+    /// Marks every notification of a user read
+    fn mark_all_notifications_read(&self, user_id: i32) -> Result<usize, WebError>;
+}
+
+pub struct PostgresNotificationRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresNotificationRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl NotificationRepository for PostgresNotificationRepository {
+    fn create_notifications(
+        &self,
+        new_notifications: &[NewNotification],
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::insert_into(notifications::table)
+            .values(new_notifications)
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn get_notifications(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<Vec<NotificationWithContext>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let notifications = notifications::table
+            .inner_join(posts::table)
+            .inner_join(comments::table.inner_join(users::table))
+            .filter(notifications::user_id.eq(user_id))
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .filter(comments::deleted_at.is_null())
+            .filter(comments::hidden_at.is_null())
+            .select((Notification::as_select(), posts::title, users::name))
+            .order((notifications::created_at.desc(), notifications::id.desc()))
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .load(&mut conn)?;
+
+        Ok(notifications)
+    }
+
+    fn count_notifications(&self, user_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let count = notifications::table
+            .inner_join(posts::table)
+            .inner_join(comments::table)
+            .filter(notifications::user_id.eq(user_id))
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .filter(comments::deleted_at.is_null())
+            .filter(comments::hidden_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(count)
+    }
+
+    fn count_unread_notifications(&self, user_id: i32) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let count = notifications::table
+            .inner_join(posts::table)
+            .inner_join(comments::table)
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::read_at.is_null())
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .filter(comments::deleted_at.is_null())
+            .filter(comments::hidden_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(count)
+    }
+
+    fn mark_notification_read(
+        &self,
+        user_id: i32,
+        notification_id: i32,
+    ) -> Result<Notification, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let notification = diesel::update(
+            notifications::table
+                .filter(notifications::id.eq(notification_id))
+                .filter(notifications::user_id.eq(user_id)),
+        )
+        .set(notifications::read_at.eq(diesel::dsl::now.nullable()))
+        .returning(Notification::as_returning())
+        .get_result(&mut conn)?;
+
+        Ok(notification)
+    }
+
+    fn mark_all_notifications_read(&self, user_id: i32) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let row_affected = diesel::update(
+            notifications::table
+                .filter(notifications::user_id.eq(user_id))
+                .filter(notifications::read_at.is_null()),
+        )
+        .set(notifications::read_at.eq(diesel::dsl::now.nullable()))
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+}
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
 use crate::db::WebError;
-use crate::models::{NewPost, Post, User};
+use crate::models::{NewPost, NewPostRevision, Post, PostRevision, User};
 
 use crate::utils::pagination::QueryPagination;
-use crate::utils::time::time_to_human_readable;
+use crate::utils::time::{edited_time_to_human_readable, time_to_human_readable};
+use chrono::NaiveDateTime;
 use diesel::r2d2::{ConnectionManager, Pool};
-use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
 
 use crate::entities::post::{ListPostResult, PostPublic};
 
@@ -28,10 +31,11 @@ pub trait PostRepository: Send + Sync {
     /// Retrieves a paginated list of posts
     fn get_posts(&self, pagination: &QueryPagination) -> Result<Vec<Post>, Self::Error>;
 
-    /// Updates an existing post
+    /// Updates an existing post and records the new revision
     fn update_post(
         &self,
         post_id: i32,
+        editor_user_id: i32,
         post_title: &str,
         post_body: &str,
     ) -> Result<Post, Self::Error>;
@@ -39,7 +43,7 @@ pub trait PostRepository: Send + Sync {
     /// Soft deletes a post
     fn delete_post(&self, post_id: i32) -> Result<usize, Self::Error>;
 
-    /// Retrieves a paginated list of posts with user information
+    /// Retrieves a paginated list of posts with user information, pinned posts are left out
     fn get_posts_with_user(
         &self,
         pagination: &QueryPagination,
@@ -54,6 +58,62 @@ pub trait PostRepository: Send + Sync {
 
     /// Retrieves a single post with user information
     fn get_post_with_user(&self, post_id: i32) -> Result<PostPublic, Self::Error>;
+
+    /// Retrieves every post of a user, soft deleted ones included
+    fn get_all_posts_by_user(&self, target_user_id: i32) -> Result<Vec<Post>, Self::Error>;
+
+    /// Retrieves every revision of a post, oldest first
+    fn get_post_revisions(&self, target_post_id: i32) -> Result<Vec<PostRevision>, Self::Error>;
+
+    /// Retrieves a post revision by its ID
+    fn get_post_revision(&self, revision_id: i32) -> Result<PostRevision, Self::Error>;
+
+    /// Retrieves a soft deleted post by its ID
+    fn get_deleted_post(&self, post_id: i32) -> Result<Post, Self::Error>;
+
+    /// Retrieves a paginated list of soft deleted posts, of every user when `target_user_id` is `None`
+    fn get_deleted_posts(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, Self::Error>;
+
+    /// Clears the deleted mark of a post
+    fn restore_post(&self, post_id: i32) -> Result<usize, Self::Error>;
+
+    /// Retrieves the IDs of the posts soft deleted before a point in time
+    fn get_post_ids_deleted_before(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<Vec<i32>, Self::Error>;
+
+    /// Permanently deletes soft deleted posts, their comments and revisions go with them
+    fn purge_posts(&self, post_ids: &[i32]) -> Result<usize, Self::Error>;
+
+    /// Retrieves a post by its ID, soft deleted and hidden ones included
+    fn get_post_unfiltered(&self, post_id: i32) -> Result<Post, Self::Error>;
+
+    /// Hides a post from everyone but moderators, or lifts the hold
+    fn set_post_hidden(&self, post_id: i32, hidden: bool) -> Result<usize, Self::Error>;
+
+    /// Retrieves every visible pinned post with user information, newest first
+    fn get_pinned_posts_with_user(&self) -> Result<Vec<PostPublic>, Self::Error>;
+
+    /// Pins a post above the other posts, or unpins it
+    fn set_post_pinned(&self, post_id: i32, pinned: bool) -> Result<usize, Self::Error>;
+
+    /// Locks a post against new comments, or unlocks it
+    fn set_post_locked(&self, post_id: i32, locked: bool) -> Result<usize, Self::Error>;
+
+    /// Counts the posts of a user with the same body created since a point in time, hidden
+    /// ones included and `exclude_post_id` left out
+    fn count_duplicate_posts(
+        &self,
+        target_user_id: i32,
+        post_body: &str,
+        created_since: NaiveDateTime,
+        exclude_post_id: Option<i32>,
+    ) -> Result<i64, Self::Error>;
 }
 
 // pub trait PostRepositoryWithError: PostRepository<Error = WebError> {}
@@ -105,6 +165,7 @@ impl PostRepository for PostgresPostRepository {
         let post = posts
             .find(post_id)
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
             .first(&mut conn)?;
 
         Ok(post)
@@ -117,6 +178,7 @@ impl PostRepository for PostgresPostRepository {
 
         let posts_vec = posts
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
             .order(created_at.desc())
             .limit(pagination.limit)
             .offset(pagination.get_offset())
@@ -128,21 +190,61 @@ impl PostRepository for PostgresPostRepository {
     fn update_post(
         &self,
         post_id: i32,
+        editor_user_id: i32,
         post_title: &str,
         post_body: &str,
     ) -> Result<Post, Self::Error> {
+        use crate::schema::post_revisions::dsl as revision_dsl;
         use crate::schema::posts::dsl::*;
 
         let mut conn = self.pool.get()?;
 
-        let update_result = diesel::update(posts.find(post_id))
-            .set((
-                title.eq(post_title),
-                body.eq(post_body),
-                updated_at.eq(diesel::dsl::now),
-            ))
-            .returning(Post::as_returning())
-            .get_result(&mut conn)?;
+        let update_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let current_post = posts
+                .find(post_id)
+                .select(Post::as_select())
+                .for_update()
+                .first(conn)?;
+
+            // posts written before revisions existed get their current version recorded first
+            let revision_count = revision_dsl::post_revisions
+                .filter(revision_dsl::post_id.eq(post_id))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            if revision_count == 0 {
+                diesel::insert_into(revision_dsl::post_revisions)
+                    .values(&NewPostRevision {
+                        post_id,
+                        user_id: Some(current_post.user_id),
+                        title: &current_post.title,
+                        body: &current_post.body,
+                        created_at: current_post.updated_at,
+                    })
+                    .execute(conn)?;
+            }
+
+            let updated_post = diesel::update(posts.find(post_id))
+                .set((
+                    title.eq(post_title),
+                    body.eq(post_body),
+                    updated_at.eq(diesel::dsl::now),
+                ))
+                .returning(Post::as_returning())
+                .get_result(conn)?;
+
+            diesel::insert_into(revision_dsl::post_revisions)
+                .values(&NewPostRevision {
+                    post_id,
+                    user_id: Some(editor_user_id),
+                    title: &updated_post.title,
+                    body: &updated_post.body,
+                    created_at: updated_post.updated_at,
+                })
+                .execute(conn)?;
+
+            Ok(updated_post)
+        })?;
 
         Ok(update_result)
     }
@@ -163,7 +265,7 @@ impl PostRepository for PostgresPostRepository {
         &self,
         pagination: &QueryPagination,
     ) -> Result<ListPostResult, Self::Error> {
-        use crate::schema::posts::dsl::{created_at, deleted_at, posts};
+        use crate::schema::posts::dsl::{created_at, deleted_at, hidden_at, pinned, posts};
         use crate::schema::posts::table as post_table;
         use crate::schema::users::dsl::users;
 
@@ -172,6 +274,8 @@ impl PostRepository for PostgresPostRepository {
         let posts_raw = posts
             .inner_join(users)
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(pinned.eq(false))
             .order(created_at.desc())
             .limit(pagination.limit)
             .offset(pagination.get_offset())
@@ -183,13 +287,17 @@ impl PostRepository for PostgresPostRepository {
             .map(|(post, user)| PostPublic {
                 user,
                 time_human: time_to_human_readable(post.created_at),
+                edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
                 post,
                 allow_update: false,
+                attachments: vec![],
             })
             .collect();
 
         let total_posts = post_table
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(pinned.eq(false))
             .count()
             .get_result::<i64>(&mut conn)?;
 
@@ -216,6 +324,7 @@ impl PostRepository for PostgresPostRepository {
             .inner_join(user_dsl::users)
             .filter(post_dsl::user_id.eq(target_user_id))
             .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .order(post_dsl::created_at.desc())
             .limit(pagination.limit)
             .offset(pagination.get_offset())
@@ -227,14 +336,17 @@ impl PostRepository for PostgresPostRepository {
             .map(|(post, user)| PostPublic {
                 user,
                 time_human: time_to_human_readable(post.created_at),
+                edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
                 post,
                 allow_update: false,
+                attachments: vec![],
             })
             .collect();
 
         let total_posts = post_table
             .filter(post_dsl::user_id.eq(target_user_id))
             .filter(post_dsl::deleted_at.is_null())
+            .filter(post_dsl::hidden_at.is_null())
             .count()
             .get_result::<i64>(&mut conn)?;
 
@@ -245,7 +357,7 @@ impl PostRepository for PostgresPostRepository {
     }
 
     fn get_post_with_user(&self, post_id: i32) -> Result<PostPublic, Self::Error> {
-        use crate::schema::posts::dsl::{deleted_at, id, posts};
+        use crate::schema::posts::dsl::{deleted_at, hidden_at, id, posts};
         use crate::schema::users::table as user_table;
 
         let mut conn = self.pool.get()?;
@@ -254,15 +366,280 @@ impl PostRepository for PostgresPostRepository {
             .inner_join(user_table)
             .filter(id.eq(post_id))
             .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
             .first::<(Post, User)>(&mut conn)?;
 
         let post_public = PostPublic {
             user,
             time_human: time_to_human_readable(post.created_at),
+            edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
             post,
             allow_update: false,
+            attachments: vec![],
         };
 
         Ok(post_public)
     }
+
+    fn get_all_posts_by_user(&self, target_user_id: i32) -> Result<Vec<Post>, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let user_posts = posts
+            .filter(user_id.eq(target_user_id))
+            .order(created_at.asc())
+            .select(Post::as_select())
+            .load(&mut conn)?;
+
+        Ok(user_posts)
+    }
+
+    fn get_post_revisions(&self, target_post_id: i32) -> Result<Vec<PostRevision>, Self::Error> {
+        use crate::schema::post_revisions::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let revisions = post_revisions
+            .filter(post_id.eq(target_post_id))
+            .order(id.asc())
+            .select(PostRevision::as_select())
+            .load(&mut conn)?;
+
+        Ok(revisions)
+    }
+
+    fn get_post_revision(&self, revision_id: i32) -> Result<PostRevision, Self::Error> {
+        use crate::schema::post_revisions::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let revision = post_revisions
+            .find(revision_id)
+            .select(PostRevision::as_select())
+            .first(&mut conn)?;
+
+        Ok(revision)
+    }
+
+    fn get_deleted_post(&self, post_id: i32) -> Result<Post, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let post = posts
+            .find(post_id)
+            .filter(deleted_at.is_not_null())
+            .select(Post::as_select())
+            .first(&mut conn)?;
+
+        Ok(post)
+    }
+
+    fn get_deleted_posts(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, Self::Error> {
+        use crate::schema::posts::dsl as post_dsl;
+        use crate::schema::users::dsl as user_dsl;
+
+        let mut conn = self.pool.get()?;
+
+        let mut posts_query = post_dsl::posts
+            .inner_join(user_dsl::users)
+            .filter(post_dsl::deleted_at.is_not_null())
+            .into_boxed();
+        let mut total_query = post_dsl::posts
+            .filter(post_dsl::deleted_at.is_not_null())
+            .into_boxed();
+
+        if let Some(target_user_id) = target_user_id {
+            posts_query = posts_query.filter(post_dsl::user_id.eq(target_user_id));
+            total_query = total_query.filter(post_dsl::user_id.eq(target_user_id));
+        }
+
+        let posts_raw = posts_query
+            .order(post_dsl::deleted_at.desc())
+            .limit(pagination.limit)
+            .offset(pagination.get_offset())
+            .select((Post::as_select(), User::as_select()))
+            .load::<(Post, User)>(&mut conn)?;
+
+        let posts_mapped = posts_raw
+            .into_iter()
+            .map(|(post, user)| PostPublic {
+                user,
+                time_human: time_to_human_readable(post.created_at),
+                edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
+                post,
+                allow_update: false,
+                attachments: vec![],
+            })
+            .collect();
+
+        let total_posts = total_query.count().get_result::<i64>(&mut conn)?;
+
+        Ok(ListPostResult {
+            posts: posts_mapped,
+            total: total_posts,
+        })
+    }
+
+    fn restore_post(&self, post_id: i32) -> Result<usize, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let restore_result = diesel::update(posts.find(post_id))
+            .filter(deleted_at.is_not_null())
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .execute(&mut conn)?;
+
+        Ok(restore_result)
+    }
+
+    fn get_post_ids_deleted_before(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<Vec<i32>, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let post_ids = posts
+            .filter(deleted_at.lt(deleted_before))
+            .order(id.asc())
+            .select(id)
+            .load::<i32>(&mut conn)?;
+
+        Ok(post_ids)
+    }
+
+    fn purge_posts(&self, post_ids: &[i32]) -> Result<usize, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        // only ever hard deletes posts that are already in the trash
+        let row_affected = diesel::delete(
+            posts
+                .filter(id.eq_any(post_ids))
+                .filter(deleted_at.is_not_null()),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn get_post_unfiltered(&self, post_id: i32) -> Result<Post, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let post = posts
+            .find(post_id)
+            .select(Post::as_select())
+            .first(&mut conn)?;
+
+        Ok(post)
+    }
+
+    fn set_post_hidden(&self, post_id: i32, hidden: bool) -> Result<usize, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let new_hidden_at = hidden.then(|| chrono::Utc::now().naive_utc());
+
+        // updated_at is left alone, hiding a post is not an edit
+        let row_affected = diesel::update(posts.find(post_id))
+            .set(hidden_at.eq(new_hidden_at))
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn get_pinned_posts_with_user(&self) -> Result<Vec<PostPublic>, Self::Error> {
+        use crate::schema::posts::dsl::{created_at, deleted_at, hidden_at, pinned, posts};
+        use crate::schema::users::dsl::users;
+
+        let mut conn = self.pool.get()?;
+
+        let posts_raw = posts
+            .inner_join(users)
+            .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .filter(pinned.eq(true))
+            .order(created_at.desc())
+            .select((Post::as_select(), User::as_select()))
+            .load::<(Post, User)>(&mut conn)?;
+
+        let posts_mapped = posts_raw
+            .into_iter()
+            .map(|(post, user)| PostPublic {
+                user,
+                time_human: time_to_human_readable(post.created_at),
+                edited_time_human: edited_time_to_human_readable(post.created_at, post.updated_at),
+                post,
+                allow_update: false,
+                attachments: vec![],
+            })
+            .collect();
+
+        Ok(posts_mapped)
+    }
+
+    fn set_post_pinned(&self, post_id: i32, new_pinned: bool) -> Result<usize, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        // updated_at is left alone, pinning a post is not an edit
+        let row_affected = diesel::update(posts.find(post_id))
+            .set(pinned.eq(new_pinned))
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn set_post_locked(&self, post_id: i32, new_locked: bool) -> Result<usize, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        // updated_at is left alone, locking a post is not an edit
+        let row_affected = diesel::update(posts.find(post_id))
+            .set(locked.eq(new_locked))
+            .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+
+    fn count_duplicate_posts(
+        &self,
+        target_user_id: i32,
+        post_body: &str,
+        created_since: NaiveDateTime,
+        exclude_post_id: Option<i32>,
+    ) -> Result<i64, Self::Error> {
+        use crate::schema::posts::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let mut query = posts
+            .filter(user_id.eq(target_user_id))
+            .filter(body.eq(post_body))
+            .filter(created_at.ge(created_since))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(exclude_post_id) = exclude_post_id {
+            query = query.filter(id.ne(exclude_post_id));
+        }
+
+        let total = query.count().get_result(&mut conn)?;
+
+        Ok(total)
+    }
 }
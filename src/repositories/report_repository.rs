@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use diesel::{
+    pg::Pg,
+    r2d2::{ConnectionManager, Pool},
+    AggregateExpressionMethods, ExpressionMethods, NullableExpressionMethods, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    entities::report::{ReportTarget, REPORT_STATUS_OPEN},
+    models::{NewReport, Report},
+    schema::reports,
+};
+
+pub trait ReportRepository: Send + Sync {
+    type Error;
+
+    /// Stores a new report, a user can only have one open report per target
+    fn create_report(&self, new_report: &NewReport) -> Result<Report, Self::Error>;
+
+    /// Retrieves a report by its ID
+    fn get_report(&self, report_id: i32) -> Result<Report, Self::Error>;
+
+    /// Retrieves every open report, oldest first
+    fn get_open_reports(&self) -> Result<Vec<Report>, Self::Error>;
+
+    /// Retrieves the open reports of a single target, oldest first
+    fn get_open_reports_by_target(&self, target: ReportTarget) -> Result<Vec<Report>, Self::Error>;
+
+    /// Counts the distinct users with an open report on a target
+    fn count_open_reporters(&self, target: ReportTarget) -> Result<i64, Self::Error>;
+
+    /// Closes every open report of a target
+    fn resolve_reports(
+        &self,
+        target: ReportTarget,
+        new_status: &str,
+        new_resolution: &str,
+        moderator_user_id: i32,
+    ) -> Result<usize, Self::Error>;
+}
+
+pub type ReportRepositoryWithError = dyn ReportRepository<Error = WebError>;
+
+pub struct PostgresReportRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresReportRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+/// The open reports of one target
+fn open_reports_of_target(target: ReportTarget) -> reports::BoxedQuery<'static, Pg> {
+    use crate::schema::reports::dsl::*;
+
+    let open_reports = reports.filter(status.eq(REPORT_STATUS_OPEN)).into_boxed();
+
+    match target {
+        ReportTarget::Post(target_id) => open_reports.filter(post_id.eq(target_id)),
+        ReportTarget::Comment(target_id) => open_reports.filter(comment_id.eq(target_id)),
+        ReportTarget::User(target_id) => open_reports.filter(reported_user_id.eq(target_id)),
+    }
+}
+
+impl ReportRepository for PostgresReportRepository {
+    type Error = WebError;
+
+    fn create_report(&self, new_report: &NewReport) -> Result<Report, Self::Error> {
+        use crate::schema::reports::table as reports_table;
+
+        let mut conn = self.pool.get()?;
+
+        let report = diesel::insert_into(reports_table)
+            .values(new_report)
+            .returning(Report::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(report)
+    }
+
+    fn get_report(&self, report_id: i32) -> Result<Report, Self::Error> {
+        use crate::schema::reports::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let report = reports
+            .find(report_id)
+            .select(Report::as_select())
+            .first(&mut conn)?;
+
+        Ok(report)
+    }
+
+    fn get_open_reports(&self) -> Result<Vec<Report>, Self::Error> {
+        use crate::schema::reports::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let open_reports = reports
+            .filter(status.eq(REPORT_STATUS_OPEN))
+            .order(id.asc())
+            .select(Report::as_select())
+            .load(&mut conn)?;
+
+        Ok(open_reports)
+    }
+
+    fn get_open_reports_by_target(&self, target: ReportTarget) -> Result<Vec<Report>, Self::Error> {
+        let mut conn = self.pool.get()?;
+
+        let target_reports = open_reports_of_target(target)
+            .order(crate::schema::reports::id.asc())
+            .select(Report::as_select())
+            .load(&mut conn)?;
+
+        Ok(target_reports)
+    }
+
+    fn count_open_reporters(&self, target: ReportTarget) -> Result<i64, Self::Error> {
+        use crate::schema::reports::reporter_user_id;
+
+        let mut conn = self.pool.get()?;
+
+        let reporters = open_reports_of_target(target)
+            .select(diesel::dsl::count(reporter_user_id).aggregate_distinct())
+            .first::<i64>(&mut conn)?;
+
+        Ok(reporters)
+    }
+
+    fn resolve_reports(
+        &self,
+        target: ReportTarget,
+        new_status: &str,
+        new_resolution: &str,
+        moderator_user_id: i32,
+    ) -> Result<usize, Self::Error> {
+        use crate::schema::reports::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        let target_reports = reports.filter(status.eq(REPORT_STATUS_OPEN));
+
+        let changes = (
+            status.eq(new_status),
+            resolution.eq(Some(new_resolution)),
+            resolved_by_user_id.eq(Some(moderator_user_id)),
+            resolved_at.eq(diesel::dsl::now.nullable()),
+        );
+
+        let row_affected = match target {
+            ReportTarget::Post(target_id) => {
+                diesel::update(target_reports.filter(post_id.eq(target_id)))
+                    .set(changes)
+                    .execute(&mut conn)?
+            }
+            ReportTarget::Comment(target_id) => {
+                diesel::update(target_reports.filter(comment_id.eq(target_id)))
+                    .set(changes)
+                    .execute(&mut conn)?
+            }
+            ReportTarget::User(target_id) => {
+                diesel::update(target_reports.filter(reported_user_id.eq(target_id)))
+                    .set(changes)
+                    .execute(&mut conn)?
+            }
+        };
+
+        Ok(row_affected)
+    }
+}
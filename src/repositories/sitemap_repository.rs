@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+
+use crate::{db::WebError, entities::user::USER_ROLE_DELETED};
+
+/// Repository trait for the public pages listed in the sitemap
+pub trait SitemapRepository: Send + Sync + 'static {
+    /// Counts the published posts that are neither deleted nor hidden
+    fn count_sitemap_posts(&self) -> Result<i64, WebError>;
+
+    /// Lists the id, title and update time of published, visible posts, oldest first
+    ///
+    /// # Arguments
+    /// * `offset` - The posts to skip
+    /// * `limit` - The most posts to return
+    fn get_sitemap_posts(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, String, NaiveDateTime)>, WebError>;
+
+    /// Counts the users with a public profile, every user but the deleted user placeholder
+    fn count_sitemap_users(&self) -> Result<i64, WebError>;
+
+    /// Lists the id and update time of users with a public profile, oldest first
+    ///
+    /// # Arguments
+    /// * `offset` - The users to skip
+    /// * `limit` - The most users to return
+    fn get_sitemap_users(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, NaiveDateTime)>, WebError>;
+}
+
+pub struct PostgresSitemapRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresSitemapRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl SitemapRepository for PostgresSitemapRepository {
+    fn count_sitemap_posts(&self) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::posts::dsl::*;
+
+        let total = posts
+            .filter(published.eq(true))
+            .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn get_sitemap_posts(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, String, NaiveDateTime)>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::posts::dsl::*;
+
+        let rows = posts
+            .filter(published.eq(true))
+            .filter(deleted_at.is_null())
+            .filter(hidden_at.is_null())
+            .order(id.asc())
+            .offset(offset)
+            .limit(limit)
+            .select((id, title, updated_at))
+            .load(&mut conn)?;
+
+        Ok(rows)
+    }
+
+    fn count_sitemap_users(&self) -> Result<i64, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        // the placeholder holding the content of deleted users is not a profile to index
+        let total = users
+            .filter(role.ne(USER_ROLE_DELETED))
+            .count()
+            .get_result(&mut conn)?;
+
+        Ok(total)
+    }
+
+    fn get_sitemap_users(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, NaiveDateTime)>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        let rows = users
+            .filter(role.ne(USER_ROLE_DELETED))
+            .order(id.asc())
+            .offset(offset)
+            .limit(limit)
+            .select((id, updated_at))
+            .load(&mut conn)?;
+
+        Ok(rows)
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{NewUserBan, UserBan},
+};
+
+/// Repository trait for the ban history of users
+///
+/// Entries are only ever added, a lifted ban is recorded as a new entry without an end.
+pub trait UserBanRepository: Send + Sync + 'static {
+    /// Records a ban or the lift of one
+    ///
+    /// # Arguments
+    /// * `new_user_ban` - The entry to store
+    fn create_user_ban(&self, new_user_ban: &NewUserBan) -> Result<UserBan, WebError>;
+
+    /// Lists the ban history of a user, newest first
+    ///
+    /// # Arguments
+    /// * `target_user_id` - The user to list the history of
+    fn get_user_bans_by_user(&self, target_user_id: i32) -> Result<Vec<UserBan>, WebError>;
+}
+
+pub struct PostgresUserBanRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresUserBanRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl UserBanRepository for PostgresUserBanRepository {
+    fn create_user_ban(&self, new_user_ban: &NewUserBan) -> Result<UserBan, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_bans::dsl::*;
+
+        let user_ban = diesel::insert_into(user_bans)
+            .values(new_user_ban)
+            .returning(UserBan::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(user_ban)
+    }
+
+    fn get_user_bans_by_user(&self, target_user_id: i32) -> Result<Vec<UserBan>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_bans::dsl::*;
+
+        let bans = user_bans
+            .filter(user_id.eq(target_user_id))
+            .order(id.desc())
+            .select(UserBan::as_select())
+            .load(&mut conn)?;
+
+        Ok(bans)
+    }
+}
+
+/// Ban history kept in process memory, paired with `InMemoryUserRepository`
+#[derive(Default)]
+pub struct InMemoryUserBanRepository {
+    user_bans: Mutex<Vec<UserBan>>,
+}
+
+impl InMemoryUserBanRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UserBanRepository for InMemoryUserBanRepository {
+    fn create_user_ban(&self, new_user_ban: &NewUserBan) -> Result<UserBan, WebError> {
+        let mut user_bans = self.user_bans.lock().unwrap();
+
+        let user_ban = UserBan {
+            id: user_bans.iter().map(|b| b.id).max().unwrap_or(0) + 1,
+            user_id: new_user_ban.user_id,
+            moderator_user_id: new_user_ban.moderator_user_id,
+            banned_until: new_user_ban.banned_until,
+            reason: new_user_ban.reason.to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        user_bans.push(user_ban.clone());
+
+        Ok(user_ban)
+    }
+
+    fn get_user_bans_by_user(&self, target_user_id: i32) -> Result<Vec<UserBan>, WebError> {
+        let user_bans = self.user_bans.lock().unwrap();
+
+        Ok(user_bans
+            .iter()
+            .rev()
+            .filter(|b| b.user_id == target_user_id)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::{Arc, Mutex};
+
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    db::WebError,
+    models::{NewUserIdentity, UserIdentity},
+};
+
+/// Repository trait for identities linked from external OAuth2 / OpenID Connect providers
+///
+/// A user has at most one identity per provider, and a provider subject belongs to one user.
+pub trait UserIdentityRepository: Send + Sync + 'static {
+    /// Links a provider identity to a user
+    ///
+    /// # Arguments
+    /// * `new_identity` - The identity to store
+    fn create_user_identity(
+        &self,
+        new_identity: &NewUserIdentity,
+    ) -> Result<UserIdentity, WebError>;
+
+    /// Retrieves the identity for a provider subject, if any
+    ///
+    /// # Arguments
+    /// * `identity_provider` - The provider name, e.g. `github`
+    /// * `subject` - The stable user id issued by the provider
+    fn get_user_identity(
+        &self,
+        identity_provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, WebError>;
+
+    /// Lists every identity linked to a user
+    ///
+    /// # Arguments
+    /// * `target_user_id` - The user to list identities for
+    fn get_user_identities_by_user(
+        &self,
+        target_user_id: i32,
+    ) -> Result<Vec<UserIdentity>, WebError>;
+
+    /// Unlinks a provider from a user
+    ///
+    /// # Arguments
+    /// * `target_user_id` - The user owning the identity
+    /// * `identity_provider` - The provider name to unlink
+    fn delete_user_identity(
+        &self,
+        target_user_id: i32,
+        identity_provider: &str,
+    ) -> Result<usize, WebError>;
+}
+
+pub struct PostgresUserIdentityRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresUserIdentityRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl UserIdentityRepository for PostgresUserIdentityRepository {
+    fn create_user_identity(
+        &self,
+        new_identity: &NewUserIdentity,
+    ) -> Result<UserIdentity, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_identities::dsl::*;
+
+        let user_identity = diesel::insert_into(user_identities)
+            .values(new_identity)
+            .returning(UserIdentity::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(user_identity)
+    }
+
+    fn get_user_identity(
+        &self,
+        identity_provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_identities::dsl::*;
+
+        let user_identity = user_identities
+            .filter(provider.eq(identity_provider))
+            .filter(provider_subject.eq(subject))
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(user_identity)
+    }
+
+    fn get_user_identities_by_user(
+        &self,
+        target_user_id: i32,
+    ) -> Result<Vec<UserIdentity>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_identities::dsl::*;
+
+        let identities = user_identities
+            .filter(user_id.eq(target_user_id))
+            .order(created_at.asc())
+            .load(&mut conn)?;
+
+        Ok(identities)
+    }
+
+    fn delete_user_identity(
+        &self,
+        target_user_id: i32,
+        identity_provider: &str,
+    ) -> Result<usize, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::user_identities::dsl::*;
+
+        let row_affected = diesel::delete(
+            user_identities
+                .filter(user_id.eq(target_user_id))
+                .filter(provider.eq(identity_provider)),
+        )
+        .execute(&mut conn)?;
+
+        Ok(row_affected)
+    }
+}
+
+/// Identities kept in process memory, paired with `InMemoryUserRepository`
+#[derive(Default)]
+pub struct InMemoryUserIdentityRepository {
+    identities: Mutex<Vec<UserIdentity>>,
+}
+
+impl InMemoryUserIdentityRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UserIdentityRepository for InMemoryUserIdentityRepository {
+    fn create_user_identity(
+        &self,
+        new_identity: &NewUserIdentity,
+    ) -> Result<UserIdentity, WebError> {
+        let mut identities = self.identities.lock().unwrap();
+
+        // mirror the unique constraints of the user_identities table
+        let is_duplicate = identities.iter().any(|i| {
+            i.provider == new_identity.provider
+                && (i.provider_subject == new_identity.provider_subject
+                    || i.user_id == new_identity.user_id)
+        });
+        if is_duplicate {
+            return Err(WebError::from("user identity already exists"));
+        }
+
+        let user_identity = UserIdentity {
+            id: identities.iter().map(|i| i.id).max().unwrap_or(0) + 1,
+            user_id: new_identity.user_id,
+            provider: new_identity.provider.to_string(),
+            provider_subject: new_identity.provider_subject.to_string(),
+            email: new_identity.email.map(|e| e.to_string()),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        identities.push(user_identity.clone());
+
+        Ok(user_identity)
+    }
+
+    fn get_user_identity(
+        &self,
+        identity_provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, WebError> {
+        let identities = self.identities.lock().unwrap();
+
+        Ok(identities
+            .iter()
+            .find(|i| i.provider == identity_provider && i.provider_subject == subject)
+            .cloned())
+    }
+
+    fn get_user_identities_by_user(
+        &self,
+        target_user_id: i32,
+    ) -> Result<Vec<UserIdentity>, WebError> {
+        let identities = self.identities.lock().unwrap();
+
+        Ok(identities
+            .iter()
+            .filter(|i| i.user_id == target_user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn delete_user_identity(
+        &self,
+        target_user_id: i32,
+        identity_provider: &str,
+    ) -> Result<usize, WebError> {
+        let mut identities = self.identities.lock().unwrap();
+
+        let count_before = identities.len();
+        identities.retain(|i| !(i.user_id == target_user_id && i.provider == identity_provider));
+
+        Ok(count_before - identities.len())
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     db::WebError,
     entities::user::UserPublic,
-    models::{UpdateUserNameAndProfilePicture, User},
+    models::{UpdateUserNameAndProfilePicture, UpdateUserProfile, User},
 };
 
 /// Trait defining the interface for user-related operations in the repository.
@@ -10,8 +10,18 @@ pub trait UserRepository: Send + Sync + 'static {
     /// The error type that will be returned by operations in this repository
     type Error;
 
-    /// Creates a new user with the given name, email, and password
-    fn create_user(&self, name: &str, email: &str, password: &str) -> Result<User, Self::Error>;
+    /// Creates a new user with the given name, handle, email, and password
+    fn create_user(
+        &self,
+        name: &str,
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<User, Self::Error>;
+
+    /// Creates a new user without a password, for sign-ups through an identity provider.
+    /// The handle is generated from the name and can be changed later
+    fn create_user_without_password(&self, name: &str, email: &str) -> Result<User, Self::Error>;
 
     /// Authenticates a user with email and password
     fn login_user(&self, email: &str, password: &str) -> Result<User, Self::Error>;
@@ -22,6 +32,15 @@ pub trait UserRepository: Send + Sync + 'static {
     /// Retrieves a user by their email address
     fn get_user_by_email(&self, email: &str) -> Result<User, Self::Error>;
 
+    /// Retrieves a user by their current handle, stored in lowercase
+    fn get_user_by_username(&self, username: &str) -> Result<User, Self::Error>;
+
+    /// The user who used to go by a handle, `None` when nobody gave it up
+    fn get_previous_username_owner(&self, username: &str) -> Result<Option<i32>, Self::Error>;
+
+    /// Retrieves the deleted user placeholder, found by its role
+    fn get_deleted_user(&self) -> Result<User, Self::Error>;
+
     /// Gets a sanitized (public) version of a user by their ID
     fn get_user_sanitized_by_id(&self, user_id: i32) -> Result<UserPublic, Self::Error>;
 
@@ -38,6 +57,27 @@ pub trait UserRepository: Send + Sync + 'static {
         new_data: &UpdateUserNameAndProfilePicture,
     ) -> Result<(), Self::Error>;
 
+    /// Updates the bio, website, location, pronouns and signature of a user
+    fn update_user_profile(
+        &self,
+        user: &User,
+        new_profile: &UpdateUserProfile,
+    ) -> Result<(), Self::Error>;
+
+    /// Gives a user a new handle, the old one is kept as a previous handle of the user
+    fn update_user_username(&self, user: &User, new_username: &str) -> Result<(), Self::Error>;
+
+    /// Updates a user's role
+    fn update_user_role(&self, user: &User, new_role: &str) -> Result<(), Self::Error>;
+
+    /// Sets or lifts the ban of a user, `None` lifts it
+    fn update_user_ban(
+        &self,
+        user: &User,
+        new_banned_until: Option<chrono::NaiveDateTime>,
+        new_ban_reason: Option<&str>,
+    ) -> Result<(), Self::Error>;
+
     /// Deletes a user from the system
     fn delete_user(&self, user: &User) -> Result<(), Self::Error>;
 }
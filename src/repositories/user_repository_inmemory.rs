@@ -5,14 +5,20 @@ use bcrypt::{hash, DEFAULT_COST};
 
 use crate::{
     db::WebError,
-    entities::user::{user_to_user_public, validate_user_password, UserPublic},
-    models::{UpdateUserNameAndProfilePicture, User},
+    entities::user::{
+        user_to_user_public, username_from_name, validate_user_password, UserPublic,
+        DELETED_USER_EMAIL, DELETED_USER_NAME, DELETED_USER_USERNAME, USER_ROLE_DELETED,
+        USER_ROLE_USER,
+    },
+    models::{UpdateUserNameAndProfilePicture, UpdateUserProfile, User},
 };
 
 use super::user_repository::UserRepository;
 
 pub struct InMemoryUserRepository {
     users: Arc<Mutex<HashMap<i32, User>>>,
+    /// Handles given up, by the id of the user who had them
+    previous_usernames: Arc<Mutex<HashMap<String, i32>>>,
     next_id: Arc<Mutex<i32>>,
 }
 
@@ -26,16 +32,30 @@ impl InMemoryUserRepository {
     pub fn new() -> Self {
         Self {
             users: Arc::new(Mutex::new(HashMap::new())),
+            previous_usernames: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1)),
         }
     }
-}
 
-impl UserRepository for InMemoryUserRepository {
-    type Error = WebError;
+    fn insert_user(
+        &self,
+        name: &str,
+        username: &str,
+        email: &str,
+        password: Option<String>,
+    ) -> Result<User, WebError> {
+        let mut users = self.users.lock().unwrap();
 
-    fn create_user(&self, name: &str, email: &str, password: &str) -> Result<User, WebError> {
-        let hashed = hash(password, DEFAULT_COST).unwrap();
+        // mirror the unique constraints of the users table
+        if users.values().any(|u| u.email == email) {
+            return Err(WebError::from("user email already exists"));
+        }
+        if users.values().any(|u| u.username == username) {
+            return Err(WebError::from("username already exists"));
+        }
+        if username != username.to_lowercase() {
+            return Err(WebError::from("username must be lowercase"));
+        }
 
         let mut id_guard = self.next_id.lock().unwrap();
         let user_id = *id_guard;
@@ -46,15 +66,44 @@ impl UserRepository for InMemoryUserRepository {
             email: email.to_string(),
             name: name.to_string(),
             created_at: chrono::Utc::now().naive_utc(),
-            role: "user".to_string(),
+            role: USER_ROLE_USER.to_string(),
             updated_at: chrono::Utc::now().naive_utc(),
             user_profile_picture_url: Some("".to_string()),
-            password: hashed,
+            password,
+            banned_until: None,
+            ban_reason: None,
+            bio: String::new(),
+            website: String::new(),
+            location: String::new(),
+            pronouns: String::new(),
+            signature: String::new(),
+            username: username.to_string(),
+            username_changed_at: None,
         };
 
-        self.users.lock().unwrap().insert(user_id, new_user.clone());
+        users.insert(user_id, new_user.clone());
         Ok(new_user)
     }
+}
+
+impl UserRepository for InMemoryUserRepository {
+    type Error = WebError;
+
+    fn create_user(
+        &self,
+        name: &str,
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<User, WebError> {
+        let hashed = hash(password, DEFAULT_COST).unwrap();
+
+        self.insert_user(name, username, email, Some(hashed))
+    }
+
+    fn create_user_without_password(&self, name: &str, email: &str) -> Result<User, WebError> {
+        self.insert_user(name, &username_from_name(name), email, None)
+    }
 
     fn login_user(&self, user_email: &str, user_password: &str) -> Result<User, WebError> {
         let users = self.users.lock().unwrap();
@@ -91,6 +140,51 @@ impl UserRepository for InMemoryUserRepository {
         Ok(user)
     }
 
+    fn get_user_by_username(&self, user_username: &str) -> Result<User, WebError> {
+        let users = self.users.lock().unwrap();
+
+        let user = users
+            .values()
+            .find(|u| u.username == user_username)
+            .cloned()
+            .ok_or_else(|| Box::new(diesel::result::Error::NotFound))?;
+
+        Ok(user)
+    }
+
+    fn get_deleted_user(&self) -> Result<User, WebError> {
+        if let Some(user) = self
+            .users
+            .lock()
+            .unwrap()
+            .values()
+            .find(|u| u.role == USER_ROLE_DELETED)
+        {
+            return Ok(user.clone());
+        }
+
+        // the migration creates it for postgres, here it appears on first use
+        let mut user = self.insert_user(
+            DELETED_USER_NAME,
+            DELETED_USER_USERNAME,
+            DELETED_USER_EMAIL,
+            None,
+        )?;
+        user.role = USER_ROLE_DELETED.to_string();
+        self.users.lock().unwrap().insert(user.id, user.clone());
+
+        Ok(user)
+    }
+
+    fn get_previous_username_owner(&self, old_username: &str) -> Result<Option<i32>, WebError> {
+        Ok(self
+            .previous_usernames
+            .lock()
+            .unwrap()
+            .get(old_username)
+            .copied())
+    }
+
     fn get_user_sanitized_by_id(&self, target_user_id: i32) -> Result<UserPublic, WebError> {
         let user = self.get_user_by_id(target_user_id)?;
         Ok(user_to_user_public(&user))
@@ -100,7 +194,7 @@ impl UserRepository for InMemoryUserRepository {
         let hashed = hash(new_password, DEFAULT_COST).unwrap();
         let mut users = self.users.lock().unwrap();
         if let Some(u) = users.get_mut(&user.id) {
-            u.password = hashed;
+            u.password = Some(hashed);
             return Ok(());
         }
         Err(Box::new(diesel::result::Error::NotFound))
@@ -115,6 +209,54 @@ impl UserRepository for InMemoryUserRepository {
         Err(Box::new(diesel::result::Error::NotFound))
     }
 
+    fn update_user_username(&self, user: &User, new_username: &str) -> Result<(), WebError> {
+        // like the check constraints of users and previous_usernames
+        if new_username != new_username.to_lowercase() {
+            return Err(WebError::from("username must be lowercase"));
+        }
+
+        let mut users = self.users.lock().unwrap();
+        let mut previous_usernames = self.previous_usernames.lock().unwrap();
+
+        if let Some(u) = users.get_mut(&user.id) {
+            if previous_usernames.get(new_username) == Some(&user.id) {
+                previous_usernames.remove(new_username);
+            }
+            previous_usernames
+                .entry(u.username.clone())
+                .or_insert(user.id);
+
+            u.username = new_username.to_string();
+            u.username_changed_at = Some(chrono::Utc::now().naive_utc());
+            return Ok(());
+        }
+        Err(Box::new(diesel::result::Error::NotFound))
+    }
+
+    fn update_user_role(&self, user: &User, new_role: &str) -> Result<(), WebError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(u) = users.get_mut(&user.id) {
+            u.role = new_role.to_string();
+            return Ok(());
+        }
+        Err(Box::new(diesel::result::Error::NotFound))
+    }
+
+    fn update_user_ban(
+        &self,
+        user: &User,
+        new_banned_until: Option<chrono::NaiveDateTime>,
+        new_ban_reason: Option<&str>,
+    ) -> Result<(), WebError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(u) = users.get_mut(&user.id) {
+            u.banned_until = new_banned_until;
+            u.ban_reason = new_ban_reason.map(|reason| reason.to_string());
+            return Ok(());
+        }
+        Err(Box::new(diesel::result::Error::NotFound))
+    }
+
     fn update_user_data(
         &self,
         user: &User,
@@ -122,7 +264,30 @@ impl UserRepository for InMemoryUserRepository {
     ) -> Result<(), WebError> {
         let mut users = self.users.lock().unwrap();
         if let Some(u) = users.get_mut(&user.id) {
-            u.name = new_data.name.unwrap().to_string();
+            // like the diesel changeset, None leaves the column unchanged
+            if let Some(name) = new_data.name {
+                u.name = name.to_string();
+            }
+            if let Some(url) = new_data.user_profile_picture_url {
+                u.user_profile_picture_url = Some(url.to_string());
+            }
+            return Ok(());
+        }
+        Err(Box::new(diesel::result::Error::NotFound))
+    }
+
+    fn update_user_profile(
+        &self,
+        user: &User,
+        new_profile: &UpdateUserProfile,
+    ) -> Result<(), WebError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(u) = users.get_mut(&user.id) {
+            u.bio = new_profile.bio.to_string();
+            u.website = new_profile.website.to_string();
+            u.location = new_profile.location.to_string();
+            u.pronouns = new_profile.pronouns.to_string();
+            u.signature = new_profile.signature.to_string();
             return Ok(());
         }
         Err(Box::new(diesel::result::Error::NotFound))
@@ -131,6 +296,11 @@ impl UserRepository for InMemoryUserRepository {
     fn delete_user(&self, user: &User) -> Result<(), WebError> {
         let mut users = self.users.lock().unwrap();
         if users.remove(&user.id).is_some() {
+            // like the cascade on previous_usernames
+            self.previous_usernames
+                .lock()
+                .unwrap()
+                .retain(|_, owner_user_id| *owner_user_id != user.id);
             return Ok(());
         }
         Err(Box::new(diesel::result::Error::NotFound))
@@ -2,15 +2,18 @@ use std::sync::Arc;
 
 use bcrypt::{hash, DEFAULT_COST};
 use diesel::{
-    query_dsl::methods::FilterDsl,
+    query_dsl::methods::{FilterDsl, SelectDsl},
     r2d2::{ConnectionManager, Pool},
-    ExpressionMethods, PgConnection, RunQueryDsl, SelectableHelper,
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, RunQueryDsl, SelectableHelper,
 };
 
 use crate::{
     db::WebError,
-    entities::user::{user_to_user_public, validate_user_password, UserPublic},
-    models::{NewUser, UpdateUserNameAndProfilePicture, User},
+    entities::user::{
+        user_to_user_public, username_from_name, validate_user_password, UserPublic,
+        USER_ROLE_DELETED,
+    },
+    models::{NewUser, UpdateUserNameAndProfilePicture, UpdateUserProfile, User},
 };
 
 use super::user_repository::UserRepository;
@@ -28,7 +31,13 @@ impl PostgresUserRepository {
 impl UserRepository for PostgresUserRepository {
     type Error = WebError;
 
-    fn create_user(&self, name: &str, email: &str, password: &str) -> Result<User, WebError> {
+    fn create_user(
+        &self,
+        name: &str,
+        username: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<User, WebError> {
         use crate::schema::users::table as users_table;
 
         let mut conn = self.pool.get()?;
@@ -38,7 +47,28 @@ impl UserRepository for PostgresUserRepository {
         let new_user_data = NewUser {
             email,
             name,
-            password: &hashed,
+            username,
+            password: Some(&hashed),
+        };
+
+        let new_user = diesel::insert_into(users_table)
+            .values(&new_user_data)
+            .returning(User::as_returning())
+            .get_result(&mut conn)?;
+
+        Ok(new_user)
+    }
+
+    fn create_user_without_password(&self, name: &str, email: &str) -> Result<User, WebError> {
+        use crate::schema::users::table as users_table;
+
+        let mut conn = self.pool.get()?;
+
+        let new_user_data = NewUser {
+            email,
+            name,
+            username: &username_from_name(name),
+            password: None,
         };
 
         let new_user = diesel::insert_into(users_table)
@@ -83,6 +113,38 @@ impl UserRepository for PostgresUserRepository {
         Ok(user)
     }
 
+    fn get_deleted_user(&self) -> Result<User, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        let user = users.filter(role.eq(USER_ROLE_DELETED)).first(&mut conn)?;
+        Ok(user)
+    }
+
+    fn get_user_by_username(&self, user_username: &str) -> Result<User, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        let user = users.filter(username.eq(user_username)).first(&mut conn)?;
+        Ok(user)
+    }
+
+    fn get_previous_username_owner(&self, old_username: &str) -> Result<Option<i32>, WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::previous_usernames::dsl::*;
+
+        let owner_user_id = previous_usernames
+            .filter(username.eq(old_username))
+            .select(user_id)
+            .first(&mut conn)
+            .optional()?;
+
+        Ok(owner_user_id)
+    }
+
     fn get_user_sanitized_by_id(&self, target_user_id: i32) -> Result<UserPublic, WebError> {
         let non_sanitized_user = self.get_user_by_id(target_user_id)?;
 
@@ -116,6 +178,72 @@ impl UserRepository for PostgresUserRepository {
         Ok(())
     }
 
+    fn update_user_username(&self, user: &User, new_username: &str) -> Result<(), WebError> {
+        use crate::schema::previous_usernames::dsl as previous_dsl;
+        use crate::schema::users::dsl::*;
+
+        let mut conn = self.pool.get()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // taking back one of your own old handles releases it
+            diesel::delete(
+                previous_dsl::previous_usernames
+                    .filter(previous_dsl::username.eq(new_username))
+                    .filter(previous_dsl::user_id.eq(user.id)),
+            )
+            .execute(conn)?;
+
+            diesel::insert_into(previous_dsl::previous_usernames)
+                .values((
+                    previous_dsl::username.eq(&user.username),
+                    previous_dsl::user_id.eq(user.id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            diesel::update(users.filter(id.eq(user.id)))
+                .set((
+                    username.eq(new_username),
+                    username_changed_at.eq(diesel::dsl::now),
+                ))
+                .execute(conn)?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn update_user_role(&self, user: &User, new_role: &str) -> Result<(), WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.filter(id.eq(user.id)))
+            .set(role.eq(new_role))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    fn update_user_ban(
+        &self,
+        user: &User,
+        new_banned_until: Option<chrono::NaiveDateTime>,
+        new_ban_reason: Option<&str>,
+    ) -> Result<(), WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.filter(id.eq(user.id)))
+            .set((
+                banned_until.eq(new_banned_until),
+                ban_reason.eq(new_ban_reason),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
     fn update_user_data(
         &self,
         user: &User,
@@ -132,6 +260,22 @@ impl UserRepository for PostgresUserRepository {
         Ok(())
     }
 
+    fn update_user_profile(
+        &self,
+        user: &User,
+        new_profile: &UpdateUserProfile,
+    ) -> Result<(), WebError> {
+        let mut conn = self.pool.get()?;
+
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.filter(id.eq(user.id)))
+            .set(new_profile)
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+
     fn delete_user(&self, user: &User) -> Result<(), WebError> {
         let mut conn = self.pool.get()?;
 
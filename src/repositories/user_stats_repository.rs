@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    dsl::max,
+    r2d2::{ConnectionManager, Pool},
+    ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+
+use crate::{
+    db::WebError,
+    entities::user::UserStats,
+    schema::{bookmarks, comments, follows, messages, post_subscriptions, posts},
+    utils::time::time_to_human_readable,
+};
+
+/// Repository trait for the activity counters shown on profiles
+pub trait UserStatsRepository: Send + Sync + 'static {
+    /// Counts the visible posts and comments of a user, their reputation and when they were last active
+    ///
+    /// # Arguments
+    /// * `user_id` - The user whose profile is shown
+    fn get_user_stats(&self, user_id: i32) -> Result<UserStats, WebError>;
+}
+
+pub struct PostgresUserStatsRepository {
+    pool: Arc<Pool<ConnectionManager<PgConnection>>>,
+}
+
+impl PostgresUserStatsRepository {
+    pub fn new(pool: Arc<Pool<ConnectionManager<PgConnection>>>) -> Self {
+        Self { pool }
+    }
+}
+
+impl UserStatsRepository for PostgresUserStatsRepository {
+    fn get_user_stats(&self, user_id: i32) -> Result<UserStats, WebError> {
+        let mut conn = self.pool.get()?;
+
+        let post_count = posts::table
+            .filter(posts::user_id.eq(user_id))
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        // like the comments tab, comments under a deleted or hidden post are not shown
+        let comment_count = comments::table
+            .inner_join(posts::table)
+            .filter(comments::user_id.eq(user_id))
+            .filter(comments::deleted_at.is_null())
+            .filter(comments::hidden_at.is_null())
+            .filter(posts::deleted_at.is_null())
+            .filter(posts::hidden_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        // reputation is what other users gave, saving your own content earns nothing
+        let follower_count = follows::table
+            .filter(follows::followed_user_id.eq(user_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        // bookmarks point at posts and comments through nullable columns
+        let user_post_ids = posts::table
+            .filter(posts::user_id.eq(user_id))
+            .select(posts::id.nullable());
+        let user_comment_ids = comments::table
+            .filter(comments::user_id.eq(user_id))
+            .select(comments::id.nullable());
+
+        let post_save_count = bookmarks::table
+            .filter(bookmarks::post_id.eq_any(user_post_ids))
+            .filter(bookmarks::user_id.ne(user_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        let comment_save_count = bookmarks::table
+            .filter(bookmarks::comment_id.eq_any(user_comment_ids))
+            .filter(bookmarks::user_id.ne(user_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        let subscriber_count = post_subscriptions::table
+            .filter(
+                post_subscriptions::post_id.eq_any(
+                    posts::table
+                        .filter(posts::user_id.eq(user_id))
+                        .select(posts::id),
+                ),
+            )
+            .filter(post_subscriptions::user_id.ne(user_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        let last_post_at = posts::table
+            .filter(posts::user_id.eq(user_id))
+            .select(max(posts::created_at))
+            .get_result::<Option<NaiveDateTime>>(&mut conn)?;
+
+        let last_comment_at = comments::table
+            .filter(comments::user_id.eq(user_id))
+            .select(max(comments::created_at))
+            .get_result::<Option<NaiveDateTime>>(&mut conn)?;
+
+        let last_message_at = messages::table
+            .filter(messages::user_id.eq(user_id))
+            .select(max(messages::created_at))
+            .get_result::<Option<NaiveDateTime>>(&mut conn)?;
+
+        let last_seen_at = [last_post_at, last_comment_at, last_message_at]
+            .into_iter()
+            .flatten()
+            .max();
+
+        Ok(UserStats {
+            post_count,
+            comment_count,
+            reputation: follower_count + post_save_count + comment_save_count + subscriber_count,
+            last_seen_at,
+            last_seen_human: last_seen_at.map(time_to_human_readable),
+        })
+    }
+}
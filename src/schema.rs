@@ -1,5 +1,98 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    attachments (id) {
+        id -> Int4,
+        user_id -> Int4,
+        post_id -> Nullable<Int4>,
+        comment_id -> Nullable<Int4>,
+        #[max_length = 20]
+        kind -> Varchar,
+        #[max_length = 255]
+        file_name -> Varchar,
+        #[max_length = 255]
+        content_type -> Varchar,
+        size_bytes -> Int8,
+        #[max_length = 255]
+        storage_key -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Int4,
+        actor_user_id -> Int4,
+        #[max_length = 32]
+        action -> Varchar,
+        #[max_length = 20]
+        target_type -> Varchar,
+        target_id -> Int4,
+        reason -> Text,
+        #[max_length = 64]
+        ip_address -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    bookmarks (id) {
+        id -> Int4,
+        user_id -> Int4,
+        post_id -> Nullable<Int4>,
+        comment_id -> Nullable<Int4>,
+        #[max_length = 50]
+        folder -> Varchar,
+        #[max_length = 500]
+        note -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    comment_revisions (id) {
+        id -> Int4,
+        comment_id -> Int4,
+        user_id -> Nullable<Int4>,
+        content -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    content_filter_rules (id) {
+        id -> Int4,
+        pattern -> Text,
+        #[max_length = 16]
+        action -> Varchar,
+        description -> Text,
+        created_by_user_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    conversation_members (conversation_id, user_id) {
+        conversation_id -> Int4,
+        user_id -> Int4,
+        joined_at -> Timestamp,
+        last_read_at -> Nullable<Timestamp>,
+        left_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    conversations (id) {
+        id -> Int4,
+        #[max_length = 100]
+        title -> Varchar,
+        is_group -> Bool,
+        created_by_user_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     comments (id) {
         id -> Int4,
@@ -9,6 +102,50 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         deleted_at -> Nullable<Timestamp>,
+        hidden_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    follows (follower_user_id, followed_user_id) {
+        follower_user_id -> Int4,
+        followed_user_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    login_attempts (id) {
+        id -> Int4,
+        #[max_length = 255]
+        attempt_key -> Varchar,
+        failed_count -> Int4,
+        last_failed_at -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    messages (id) {
+        id -> Int4,
+        conversation_id -> Int4,
+        user_id -> Int4,
+        body -> Text,
+        created_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Int4,
+        user_id -> Int4,
+        post_id -> Int4,
+        comment_id -> Int4,
+        #[max_length = 20]
+        kind -> Varchar,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
     }
 }
 
@@ -23,6 +160,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    post_revisions (id) {
+        id -> Int4,
+        post_id -> Int4,
+        user_id -> Nullable<Int4>,
+        title -> Varchar,
+        body -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    post_subscriptions (user_id, post_id) {
+        user_id -> Int4,
+        post_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Int4,
@@ -33,6 +189,71 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         deleted_at -> Nullable<Timestamp>,
+        hidden_at -> Nullable<Timestamp>,
+        pinned -> Bool,
+        locked -> Bool,
+    }
+}
+
+diesel::table! {
+    previous_usernames (username) {
+        #[max_length = 32]
+        username -> Varchar,
+        user_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    reports (id) {
+        id -> Int4,
+        reporter_user_id -> Nullable<Int4>,
+        post_id -> Nullable<Int4>,
+        comment_id -> Nullable<Int4>,
+        reported_user_id -> Nullable<Int4>,
+        #[max_length = 32]
+        category -> Varchar,
+        details -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        #[max_length = 32]
+        resolution -> Nullable<Varchar>,
+        resolved_by_user_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    user_bans (id) {
+        id -> Int4,
+        user_id -> Int4,
+        moderator_user_id -> Nullable<Int4>,
+        banned_until -> Nullable<Timestamp>,
+        reason -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_blocks (blocker_user_id, blocked_user_id) {
+        blocker_user_id -> Int4,
+        blocked_user_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 50]
+        provider -> Varchar,
+        #[max_length = 255]
+        provider_subject -> Varchar,
+        #[max_length = 255]
+        email -> Nullable<Varchar>,
+        created_at -> Timestamp,
     }
 }
 
@@ -44,23 +265,80 @@ diesel::table! {
         #[max_length = 255]
         email -> Varchar,
         #[max_length = 255]
-        password -> Varchar,
+        password -> Nullable<Varchar>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
         user_profile_picture_url -> Nullable<Varchar>,
         #[max_length = 255]
         role -> Varchar,
+        banned_until -> Nullable<Timestamp>,
+        ban_reason -> Nullable<Text>,
+        bio -> Text,
+        #[max_length = 255]
+        website -> Varchar,
+        #[max_length = 100]
+        location -> Varchar,
+        #[max_length = 40]
+        pronouns -> Varchar,
+        #[max_length = 300]
+        signature -> Varchar,
+        #[max_length = 32]
+        username -> Varchar,
+        username_changed_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(attachments -> comments (comment_id));
+diesel::joinable!(attachments -> posts (post_id));
+diesel::joinable!(attachments -> users (user_id));
+diesel::joinable!(bookmarks -> comments (comment_id));
+diesel::joinable!(bookmarks -> posts (post_id));
+diesel::joinable!(bookmarks -> users (user_id));
+diesel::joinable!(comment_revisions -> comments (comment_id));
+diesel::joinable!(comment_revisions -> users (user_id));
 diesel::joinable!(comments -> posts (post_id));
 diesel::joinable!(comments -> users (user_id));
+diesel::joinable!(content_filter_rules -> users (created_by_user_id));
+diesel::joinable!(conversation_members -> conversations (conversation_id));
+diesel::joinable!(conversation_members -> users (user_id));
+diesel::joinable!(conversations -> users (created_by_user_id));
+diesel::joinable!(messages -> conversations (conversation_id));
+diesel::joinable!(messages -> users (user_id));
+diesel::joinable!(notifications -> comments (comment_id));
+diesel::joinable!(notifications -> posts (post_id));
+diesel::joinable!(notifications -> users (user_id));
 diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(post_revisions -> posts (post_id));
+diesel::joinable!(post_revisions -> users (user_id));
+diesel::joinable!(post_subscriptions -> posts (post_id));
+diesel::joinable!(post_subscriptions -> users (user_id));
 diesel::joinable!(posts -> users (user_id));
+diesel::joinable!(previous_usernames -> users (user_id));
+diesel::joinable!(reports -> comments (comment_id));
+diesel::joinable!(reports -> posts (post_id));
+diesel::joinable!(user_identities -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    attachments,
+    audit_log,
+    bookmarks,
+    comment_revisions,
     comments,
+    content_filter_rules,
+    conversation_members,
+    conversations,
+    follows,
+    login_attempts,
+    messages,
+    notifications,
     password_resets,
+    post_revisions,
+    post_subscriptions,
     posts,
+    previous_usernames,
+    reports,
+    user_bans,
+    user_blocks,
+    user_identities,
     users,
 );
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use actix_multipart::{Multipart, MultipartError};
+use actix_session::SessionExt;
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::PayloadError,
+    http::{
+        header::{self, HeaderMap},
+        Method,
+    },
+    web::{self, Bytes, BytesMut},
+    Error, HttpMessage,
+};
+use futures::{
+    future::{ready, LocalBoxFuture, Ready},
+    stream, Stream, StreamExt,
+};
+
+use crate::{
+    errors::csrf::CsrfError,
+    utils::csrf::{csrf_token_equal, CSRF_FORM_FIELD, CSRF_HEADER, SESSION_KEY_CSRF_TOKEN},
+};
+
+/// Most bytes read from the start of a multipart body looking for the token field
+const CSRF_MULTIPART_PEEK_BYTES: usize = 16 * 1024;
+
+type BoxedPayloadStream = Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>>;
+
+/// Synchronizer token CSRF protection for every state-changing request
+///
+/// The token is read from the `x-csrf-token` header or the `csrf_token` field of an
+/// url-encoded or multipart body, and compared with the token stored in the session.
+/// It is never read from the query string, urls end up in logs, history and referers.
+///
+/// Must be wrapped inside the session middleware and the `ErrorHandlers`
+/// so a mismatch ends up as a flash error.
+#[derive(Default)]
+pub struct Csrf;
+
+impl Csrf {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Csrf
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let is_safe_method = matches!(
+                *req.method(),
+                Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+            );
+
+            if is_safe_method {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            }
+
+            let expected_token = req
+                .get_session()
+                .get::<String>(SESSION_KEY_CSRF_TOKEN)
+                .ok()
+                .flatten();
+
+            let provided_token = extract_csrf_token(&mut req).await?;
+
+            let valid = match (expected_token, provided_token) {
+                (Some(expected), Some(provided)) => csrf_token_equal(&expected, &provided),
+                _ => false,
+            };
+
+            if !valid {
+                let csrf_error = CsrfError {
+                    message: "Invalid or missing form token, please reload the page and try again"
+                        .to_string(),
+                };
+
+                return Ok(req.error_response(csrf_error).map_into_right_body());
+            }
+
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+async fn extract_csrf_token(req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+    if let Some(token) = req.headers().get(CSRF_HEADER).and_then(|h| h.to_str().ok()) {
+        return Ok(Some(token.to_string()));
+    }
+
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+
+    if content_type.starts_with("multipart/form-data") {
+        return Ok(extract_multipart_csrf_token(req).await);
+    }
+
+    if !content_type.starts_with("application/x-www-form-urlencoded") {
+        return Ok(None);
+    }
+
+    // read the body then put it back for the route extractor
+    let body = req.extract::<web::Bytes>().await?;
+    req.set_payload(Payload::from(body.clone()));
+
+    let form_token = serde_urlencoded::from_bytes::<HashMap<String, String>>(&body)
+        .ok()
+        .and_then(|mut form| form.remove(CSRF_FORM_FIELD));
+
+    Ok(form_token)
+}
+
+/// The first field of a multipart body as far as it was read
+enum FirstFormField {
+    Incomplete,
+    Token(String),
+    Other,
+}
+
+/// Reads the token from the first field of a multipart body, forms render it ahead of their
+/// uploads so only the start of the body is held. What was read is put back for the route
+/// extractor
+async fn extract_multipart_csrf_token(req: &mut ServiceRequest) -> Option<String> {
+    let mut payload = req.take_payload();
+    let mut read_chunks: Vec<Result<Bytes, PayloadError>> = vec![];
+    let mut prefix = BytesMut::new();
+    let mut token = None;
+
+    while prefix.len() < CSRF_MULTIPART_PEEK_BYTES {
+        match payload.next().await {
+            Some(Ok(chunk)) => {
+                prefix.extend_from_slice(&chunk);
+                read_chunks.push(Ok(chunk));
+            }
+            Some(Err(e)) => {
+                read_chunks.push(Err(e));
+                break;
+            }
+            None => break,
+        }
+
+        match read_first_form_field(req.headers(), prefix.clone().freeze()).await {
+            FirstFormField::Incomplete => continue,
+            FirstFormField::Token(found) => token = Some(found),
+            FirstFormField::Other => {}
+        }
+        break;
+    }
+
+    let body: BoxedPayloadStream = Box::pin(stream::iter(read_chunks).chain(payload));
+    req.set_payload(Payload::from(body));
+
+    token
+}
+
+async fn read_first_form_field(headers: &HeaderMap, prefix: Bytes) -> FirstFormField {
+    let mut multipart = Multipart::new(headers, stream::once(ready(Ok(prefix))));
+
+    let mut field = match multipart.next().await {
+        Some(Ok(field)) => field,
+        Some(Err(MultipartError::Incomplete)) => return FirstFormField::Incomplete,
+        _ => return FirstFormField::Other,
+    };
+
+    if field.name() != Some(CSRF_FORM_FIELD) {
+        return FirstFormField::Other;
+    }
+
+    let mut value = BytesMut::new();
+    while let Some(chunk) = field.next().await {
+        match chunk {
+            Ok(chunk) => value.extend_from_slice(&chunk),
+            Err(MultipartError::Incomplete) => return FirstFormField::Incomplete,
+            Err(_) => return FirstFormField::Other,
+        }
+    }
+
+    match String::from_utf8(value.to_vec()) {
+        Ok(token) => FirstFormField::Token(token),
+        Err(_) => FirstFormField::Other,
+    }
+}
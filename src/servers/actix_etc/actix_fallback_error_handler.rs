@@ -1,3 +1,4 @@
+use crate::errors::csrf::CsrfError;
 use crate::utils::flash::{set_flash_message, FLASH_ERROR};
 use actix_session::SessionExt;
 use actix_web::error::UrlencodedError;
@@ -32,6 +33,8 @@ pub fn actix_fallback_error_handler<B>(
 
         let response_error_message = if response_error.as_error::<UrlencodedError>().is_some() {
             "url encode error".to_string()
+        } else if let Some(csrf_error) = response_error.as_error::<CsrfError>() {
+            csrf_error.message.clone()
         } else {
             response_error.to_string()
         };
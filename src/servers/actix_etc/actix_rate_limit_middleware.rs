@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use actix_session::SessionExt;
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+use crate::{
+    services::rate_limit_service::{RateLimitKey, RateLimitPolicy, RateLimitServiceError},
+    utils::{http::get_client_ip, users::get_session_user},
+    AppKit,
+};
+
+/// Applies a named `RateLimitPolicy` to the wrapped app, scope or route
+///
+/// Counters live in `AppKit::rate_limit_service`. Exceeding the limit responds with
+/// `429 Too Many Requests`, which the fallback error handler turns into a flash message
+/// when the middleware is wrapped inside the `ErrorHandlers`.
+///
+/// ```text
+/// #[post("/create", wrap = "RateLimit::new(CREATE_POST_RATE_LIMIT_POLICY)")]
+/// ```
+pub struct RateLimit {
+    policy: RateLimitPolicy,
+}
+
+impl RateLimit {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            policy: self.policy,
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    policy: RateLimitPolicy,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let policy = self.policy;
+
+        Box::pin(async move {
+            let app_kit = match req.app_data::<web::Data<AppKit>>() {
+                Some(app_kit) => app_kit.clone(),
+                None => {
+                    return service
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_left_body)
+                }
+            };
+
+            let key = build_rate_limit_key(&mut req, &policy).await?;
+
+            match app_kit.rate_limit_service.hit(&policy, &key).await {
+                Ok(_) => {}
+
+                Err(e @ RateLimitServiceError::LimitExceeded { .. }) => {
+                    println!("rate limit \"{}\" exceeded for {}", policy.name, key);
+                    return Ok(req.error_response(e).map_into_right_body());
+                }
+
+                // fail open, a broken limiter backend should not take the forum down
+                Err(e) => println!("rate limit \"{}\" skipped: {}", policy.name, e),
+            }
+
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+async fn build_rate_limit_key(
+    req: &mut ServiceRequest,
+    policy: &RateLimitPolicy,
+) -> Result<String, Error> {
+    let client_ip_key = format!("ip:{}", get_client_ip(req.request()));
+
+    match policy.key {
+        RateLimitKey::ClientIp => Ok(client_ip_key),
+
+        RateLimitKey::SessionUserOrIp => Ok(get_session_user(&req.get_session())
+            .map(|user| format!("user:{}", user.id))
+            .unwrap_or(client_ip_key)),
+
+        RateLimitKey::FormField(field_name) => {
+            // read the body then put it back for the route extractor
+            let body = req.extract::<web::Bytes>().await?;
+            req.set_payload(Payload::from(body.clone()));
+
+            let field_value = serde_urlencoded::from_bytes::<HashMap<String, String>>(&body)
+                .ok()
+                .and_then(|form| form.get(field_name).map(|v| v.trim().to_lowercase()))
+                .filter(|v| !v.is_empty());
+
+            Ok(field_value
+                .map(|v| format!("{}:{}", field_name, v))
+                .unwrap_or(client_ip_key))
+        }
+    }
+}
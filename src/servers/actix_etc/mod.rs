@@ -1,2 +1,4 @@
+pub mod actix_csrf_middleware;
 pub mod actix_fallback_error_handler;
 pub mod actix_multipart_error_handler;
+pub mod actix_rate_limit_middleware;
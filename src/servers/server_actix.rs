@@ -1,5 +1,4 @@
 use actix_cors::Cors;
-use actix_limitation::{Limiter, RateLimiter};
 use actix_multipart::form::MultipartFormConfig;
 use actix_session::config::PersistentSession;
 use actix_session::{storage::CookieSessionStore, SessionMiddleware};
@@ -13,20 +12,62 @@ use actix_web::{
 };
 use handlebars::{DirectorySourceOptions, Handlebars};
 
+use crate::controllers::admin_controller::{
+    admin_audit_log_export_route, admin_audit_log_route, admin_content_filter_create_route,
+    admin_content_filter_delete_route, admin_content_filter_route, admin_user_role_route,
+};
+use crate::controllers::attachment_controller::{delete_attachment_route, view_attachment_route};
+use crate::controllers::auth_controller::{
+    auth_provider_callback_route, auth_provider_link_route, auth_provider_login_route,
+    auth_provider_unlink_route,
+};
+use crate::controllers::bookmark_controller::{
+    bookmark_delete_route, bookmark_remove_target_route, bookmark_save_route,
+    bookmark_update_route,
+};
 use crate::controllers::comment_controller::{
-    create_comment_submit_route, delete_comment_route, update_comment_post_route,
-    update_comment_route,
+    comment_revisions_route, create_comment_submit_route, delete_comment_route,
+    restore_comment_route, rollback_comment_route, update_comment_post_route, update_comment_route,
+};
+use crate::controllers::feed_controller::{
+    feed_route, post_comments_feed_route, profile_feed_route,
+};
+use crate::controllers::follow_controller::{
+    following_posts_route, post_subscribe_route, post_unsubscribe_route, profile_follow_route,
+    profile_unfollow_route,
+};
+use crate::controllers::live_controller::{post_events_route, user_events_route};
+use crate::controllers::moderation_controller::{
+    moderation_ban_user_route, moderation_ban_user_submit_route, moderation_post_action_route,
+    moderation_report_action_route, moderation_report_ban_route, moderation_reports_route,
+    moderation_unban_user_route,
 };
 use crate::controllers::post_controller::{
     create_post_route, create_post_submit_route, delete_post_route, index_list_posts_route,
-    update_post_route, update_post_submit_route, view_post_route,
+    post_revisions_route, restore_post_route, rollback_post_route, update_post_route,
+    update_post_submit_route, view_post_route,
+};
+use crate::controllers::message_controller::{
+    messages_block_user_route, messages_conversation_route, messages_delete_route,
+    messages_inbox_route, messages_leave_route, messages_new_route, messages_new_submit_route,
+    messages_send_route, messages_unblock_user_route,
+};
+use crate::controllers::notification_controller::{
+    notification_open_route, notifications_mark_read_route, notifications_route,
 };
 use crate::controllers::profile_controller::profile_view_route;
+use crate::controllers::report_controller::{report_route, report_submit_route};
+use crate::controllers::sitemap_controller::{
+    robots_route, sitemap_chunk_route, sitemap_index_route,
+};
+use crate::controllers::trash_controller::trash_view_route;
 
 use crate::controllers::user_controller::{
-    users_changepassword_post_route, users_profile_picture_upload_post_route,
-    users_resetpassword_post_route, users_resetpassword_route, users_resetpasswordtoken_post_route,
-    users_resetpasswordtoken_route, users_settings_route, users_update_data_post_route,
+    users_changepassword_post_route, users_delete_account_post_route, users_export_route,
+    users_profile_picture_upload_post_route, users_resetpassword_post_route,
+    users_resetpassword_route, users_resetpasswordtoken_post_route, users_resetpasswordtoken_route,
+    users_setpassword_post_route, users_settings_route, users_update_data_post_route,
+    users_update_profile_post_route, users_update_username_post_route,
 };
 use crate::controllers::user_controller::{
     users_login_post_route, users_login_route, users_logout, users_register_post_route,
@@ -34,16 +75,23 @@ use crate::controllers::user_controller::{
 };
 
 use crate::handlebars_helper::pagination::handlebars_pagination_helper;
+use crate::handlebars_helper::post_path::handlebars_post_path_helper;
+use crate::handlebars_helper::url_encode::handlebars_url_encode_helper;
 use crate::servers::actix_etc::actix_fallback_error_handler::actix_fallback_error_handler;
 use crate::servers::actix_etc::actix_multipart_error_handler::actix_multipart_error_handler;
+use crate::servers::actix_etc::actix_rate_limit_middleware::RateLimit;
+use crate::services::rate_limit_service::GLOBAL_RATE_LIMIT_POLICY;
 use crate::AppKit;
 
+use crate::handlebars_helper::csrf::handlebars_csrf_helper;
 use crate::handlebars_helper::turnstile::handlebars_turnstile_helper;
+use crate::servers::actix_etc::actix_csrf_middleware::Csrf;
 use actix_files as fs;
 use actix_web::middleware::TrailingSlash;
 use actix_web::middleware::{ErrorHandlers, NormalizePath};
 
-pub type NestedBody = EitherBody<EitherBody<EitherBody<BoxBody, BoxBody>, BoxBody>, BoxBody>;
+// csrf, error handlers, global rate limit and cors each wrap the body once
+pub type NestedBody = EitherBody<EitherBody<EitherBody<EitherBody<BoxBody>>>>;
 
 pub fn create_actix_app(
     app_kit: AppKit,
@@ -57,7 +105,6 @@ pub fn create_actix_app(
     >,
 > {
     let cors_origins = app_kit.cors_origins.clone();
-    let redis_ratelimit_url = app_kit.redis_ratelimit_url.clone();
     let static_file_dir_path = app_kit.static_file_dir_path.clone();
 
     let app_kit_web_data = web::Data::new(app_kit);
@@ -70,6 +117,12 @@ pub fn create_actix_app(
     handlebars.register_helper("pagination", Box::new(handlebars_pagination_helper));
     // turnstile helper
     handlebars.register_helper("turnstile", Box::new(handlebars_turnstile_helper));
+    // csrf hidden field helper
+    handlebars.register_helper("csrf_field", Box::new(handlebars_csrf_helper));
+    // canonical post path helper
+    handlebars.register_helper("post_path", Box::new(handlebars_post_path_helper));
+    // query string value helper
+    handlebars.register_helper("url_encode", Box::new(handlebars_url_encode_helper));
 
     // set handlebars options
     let mut handlebars_options = DirectorySourceOptions::default();
@@ -128,25 +181,6 @@ pub fn create_actix_app(
         ])
         .max_age(3600);
 
-    // -- setup rate limiter --
-    let limiter = web::Data::new(
-        Limiter::builder(redis_ratelimit_url)
-            .key_by(|req: &ServiceRequest| {
-                let conn_info = req.connection_info();
-
-                let forwarded_for = conn_info.realip_remote_addr();
-                if let Some(ip) = forwarded_for {
-                    return Some(ip.to_string());
-                }
-
-                req.peer_addr().map(|addr| addr.ip().to_string())
-            })
-            .limit(5000)
-            .period(std::time::Duration::from_secs(3600)) // 60 minutes
-            .build()
-            .unwrap(),
-    );
-
     // --- setup routes ---
     let users_scope = web::scope("/users")
         .service(users_login_route)
@@ -156,60 +190,145 @@ pub fn create_actix_app(
         .service(users_logout)
         .service(users_changepassword_post_route)
         .service(users_update_data_post_route)
+        .service(users_update_profile_post_route)
+        .service(users_update_username_post_route)
         .service(users_profile_picture_upload_post_route)
         .service(users_settings_route)
         .service(users_resetpassword_route)
         .service(users_resetpassword_post_route)
         .service(users_resetpasswordtoken_route)
-        .service(users_resetpasswordtoken_post_route);
+        .service(users_resetpasswordtoken_post_route)
+        .service(users_setpassword_post_route)
+        .service(users_export_route)
+        .service(users_delete_account_post_route);
+
+    let auth_scope = web::scope("/auth")
+        .service(auth_provider_login_route)
+        .service(auth_provider_link_route)
+        .service(auth_provider_callback_route)
+        .service(auth_provider_unlink_route);
 
     let posts_scope = web::scope("/posts")
         .service(create_post_route)
         .service(create_post_submit_route)
+        // registered ahead of the post route, which would also match `following`
+        .service(following_posts_route)
         .service(view_post_route)
+        .service(post_comments_feed_route)
+        .service(post_events_route)
+        .service(post_subscribe_route)
+        .service(post_unsubscribe_route)
         .service(update_post_route)
         .service(update_post_submit_route)
         .service(delete_post_route)
+        .service(post_revisions_route)
+        .service(rollback_post_route)
+        .service(restore_post_route)
         .route("", web::to(index_list_posts_route));
 
     let comments_scope = web::scope("/comments")
         .service(create_comment_submit_route)
         .service(update_comment_route)
         .service(update_comment_post_route)
-        .service(delete_comment_route);
+        .service(delete_comment_route)
+        .service(comment_revisions_route)
+        .service(rollback_comment_route)
+        .service(restore_comment_route);
+
+    let attachments_scope = web::scope("/attachments")
+        .service(view_attachment_route)
+        .service(delete_attachment_route);
 
     let profile_scope = web::scope("/profile")
+        // registered ahead of the fetch mode route, which would also match the feed
+        .service(profile_feed_route)
+        .service(profile_follow_route)
+        .service(profile_unfollow_route)
         .route("/{user_id}", web::get().to(profile_view_route))
         .route(
             "/{user_id}/{fetch_mode:.*}",
             web::get().to(profile_view_route),
         );
 
+    // the same profile pages, addressed by handle
+    let handle_scope = web::scope("/u")
+        .route("/{username}", web::get().to(profile_view_route))
+        .route(
+            "/{username}/{fetch_mode:.*}",
+            web::get().to(profile_view_route),
+        );
+
+    let messages_scope = web::scope("/messages")
+        .route("", web::get().to(messages_inbox_route))
+        // registered ahead of the conversation routes, which would also match `new`
+        .service(messages_new_route)
+        .service(messages_new_submit_route)
+        .service(messages_block_user_route)
+        .service(messages_unblock_user_route)
+        .service(messages_conversation_route)
+        .service(messages_send_route)
+        .service(messages_delete_route)
+        .service(messages_leave_route);
+
+    let notifications_scope = web::scope("/notifications")
+        .route("", web::get().to(notifications_route))
+        .service(notification_open_route)
+        .service(notifications_mark_read_route);
+
+    let trash_scope = web::scope("/trash")
+        .route("", web::get().to(trash_view_route))
+        .route("/{fetch_mode}", web::get().to(trash_view_route));
+
+    let bookmarks_scope = web::scope("/bookmarks")
+        .service(bookmark_save_route)
+        .service(bookmark_remove_target_route)
+        .service(bookmark_update_route)
+        .service(bookmark_delete_route);
+
+    let reports_scope = web::scope("/reports")
+        .service(report_route)
+        .service(report_submit_route);
+
+    let moderation_scope = web::scope("/moderation")
+        .service(moderation_reports_route)
+        // registered ahead of the generic action route, which would also match `ban`
+        .service(moderation_report_ban_route)
+        .service(moderation_report_action_route)
+        .service(moderation_post_action_route)
+        .service(moderation_ban_user_route)
+        .service(moderation_ban_user_submit_route)
+        .service(moderation_unban_user_route);
+
+    let admin_scope = web::scope("/admin")
+        .service(admin_audit_log_route)
+        .service(admin_audit_log_export_route)
+        .service(admin_user_role_route)
+        .service(admin_content_filter_route)
+        .service(admin_content_filter_create_route)
+        .service(admin_content_filter_delete_route);
+
     // --- init app ---
 
     App::new()
+        // csrf check, inside error handlers so a mismatch becomes a flash error
+        .wrap(Csrf::new())
         // error handlers
         .wrap(ErrorHandlers::new().default_handler(actix_fallback_error_handler))
         .app_data(MultipartFormConfig::default().error_handler(actix_multipart_error_handler))
         // handlebars
         .app_data(handlebars_ref.clone())
         // limiter
-        .wrap(RateLimiter::default())
+        .wrap(RateLimit::new(GLOBAL_RATE_LIMIT_POLICY))
         // TEST MIDDLEWARE
         // .wrap_fn(|req, srv| {
         //         let session = req.get_session();
-
         //         dbg!(&session.entries());
-
         //         let fut = srv.call(req);
-
         //         async {
         //             let res = fut.await.map_err(|e| {
         //                 dbg!(&e);
-
         //                 e
         //             })?;
- 
         //             Ok(res)
         //         }
         //     })
@@ -226,7 +345,6 @@ pub fn create_actix_app(
         //             Ok(res)
         //         }
         //     })
-        .app_data(limiter.clone())
         // path fix
         .wrap(NormalizePath::new(TrailingSlash::Trim))
         // static file serving
@@ -241,9 +359,24 @@ pub fn create_actix_app(
         .app_data(app_kit_web_data.clone())
         // --- route ---
         .service(users_scope)
+        .service(auth_scope)
         .service(posts_scope)
         .service(comments_scope)
+        .service(attachments_scope)
         .service(profile_scope)
+        .service(handle_scope)
+        .service(messages_scope)
+        .service(notifications_scope)
+        .service(trash_scope)
+        .service(bookmarks_scope)
+        .service(reports_scope)
+        .service(moderation_scope)
+        .service(admin_scope)
+        .service(feed_route)
+        .service(user_events_route)
+        .service(robots_route)
+        .service(sitemap_index_route)
+        .service(sitemap_chunk_route)
         // default to posts view route
         .route("/", web::to(index_list_posts_route))
 }
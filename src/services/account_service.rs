@@ -0,0 +1,206 @@
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+use crate::{
+    entities::{
+        account::{UserDataExport, UserProfileExport},
+        user::user_is_deleted_placeholder,
+    },
+    models::User,
+    repositories::{
+        account_repository::AccountRepository, comment_repository::CommentRepositoryWithError,
+        post_repository::PostRepositoryWithError, user_repository::UserRepositoryWithError,
+    },
+    services::{attachment_service::AttachmentService, file_storage_service::FileStorage},
+    utils::avatar::avatar_file_keys,
+};
+
+/// What happens to the posts and comments of a deleted account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountDeletionMode {
+    /// Content stays and is credited to the "deleted user" placeholder
+    Anonymize,
+    /// Content is deleted along with the account
+    Cascade,
+}
+
+impl AccountDeletionMode {
+    /// Reads `ACCOUNT_DELETION_MODE` (`anonymize` or `cascade`), defaults to anonymize
+    pub fn from_env() -> Self {
+        match std::env::var("ACCOUNT_DELETION_MODE").as_deref() {
+            Ok("cascade") => AccountDeletionMode::Cascade,
+            Ok("anonymize") | Err(_) => AccountDeletionMode::Anonymize,
+            Ok(other) => panic!(
+                "unknown ACCOUNT_DELETION_MODE {:?}, expected anonymize or cascade",
+                other
+            ),
+        }
+    }
+}
+
+pub trait AccountService: Send + Sync {
+    /// Collects the profile, linked identities, posts, comments, attachments and uploads of a user
+    fn export_user_data(&self, user_id: i32) -> Result<UserDataExport, AccountServiceError>;
+
+    /// Deletes a user and their uploads, anonymizing or deleting their content
+    fn delete_account(&self, user_id: i32) -> Result<(), AccountServiceError>;
+}
+
+#[derive(Debug)]
+pub enum AccountServiceError {
+    ErrorNotFound,
+    ErrorExport(String),
+    ErrorDelete(String),
+}
+
+impl Display for AccountServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountServiceError::ErrorNotFound => write!(f, "Account not found"),
+            AccountServiceError::ErrorExport(msg) => write!(f, "Data export failed: {}", msg),
+            AccountServiceError::ErrorDelete(msg) => write!(f, "Account deletion failed: {}", msg),
+        }
+    }
+}
+
+pub struct BasedAccountService {
+    user_repository: Arc<UserRepositoryWithError>,
+    post_repository: Arc<PostRepositoryWithError>,
+    comment_repository: Arc<CommentRepositoryWithError>,
+    account_repository: Arc<dyn AccountRepository>,
+    attachment_service: Arc<dyn AttachmentService>,
+    file_storage: Arc<dyn FileStorage>,
+    deletion_mode: AccountDeletionMode,
+}
+
+impl BasedAccountService {
+    pub fn new(
+        user_repository: Arc<UserRepositoryWithError>,
+        post_repository: Arc<PostRepositoryWithError>,
+        comment_repository: Arc<CommentRepositoryWithError>,
+        account_repository: Arc<dyn AccountRepository>,
+        attachment_service: Arc<dyn AttachmentService>,
+        file_storage: Arc<dyn FileStorage>,
+        deletion_mode: AccountDeletionMode,
+    ) -> Self {
+        Self {
+            user_repository,
+            post_repository,
+            comment_repository,
+            account_repository,
+            attachment_service,
+            file_storage,
+            deletion_mode,
+        }
+    }
+
+    /// Storage keys of the files uploaded by the user, with their archive path
+    fn get_user_upload_files(&self, user: &User) -> Vec<(String, String)> {
+        user.user_profile_picture_url
+            .as_deref()
+            .and_then(|url| self.file_storage.key_from_public_url(url))
+            .map(|key| avatar_file_keys(&key))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|key| self.file_storage.file_exists(key).unwrap_or(false))
+            .map(|key| (format!("uploads/{}", key), key))
+            .collect()
+    }
+}
+
+impl AccountService for BasedAccountService {
+    fn export_user_data(&self, user_id: i32) -> Result<UserDataExport, AccountServiceError> {
+        let export_error = |e: crate::db::WebError| AccountServiceError::ErrorExport(e.to_string());
+
+        let user = self
+            .user_repository
+            .get_user_by_id(user_id)
+            .map_err(|_| AccountServiceError::ErrorNotFound)?;
+
+        let identities = self
+            .account_repository
+            .get_user_identities(user_id)
+            .map_err(export_error)?;
+
+        let posts = self
+            .post_repository
+            .get_all_posts_by_user(user_id)
+            .map_err(export_error)?;
+
+        let comments = self
+            .comment_repository
+            .get_all_comments_by_user(user_id)
+            .map_err(export_error)?;
+
+        let attachments = self
+            .attachment_service
+            .get_user_attachments(user_id)
+            .map_err(|e| AccountServiceError::ErrorExport(e.to_string()))?;
+
+        let mut upload_files = self.get_user_upload_files(&user);
+        upload_files.extend(attachments.iter().map(|attachment| {
+            (
+                format!("uploads/{}", attachment.storage_key),
+                attachment.storage_key.clone(),
+            )
+        }));
+
+        Ok(UserDataExport {
+            exported_at: chrono::Utc::now().naive_utc(),
+            profile: UserProfileExport::from(&user),
+            identities,
+            posts,
+            comments,
+            attachments,
+            uploads: upload_files.iter().map(|(path, _)| path.clone()).collect(),
+            upload_files,
+        })
+    }
+
+    fn delete_account(&self, user_id: i32) -> Result<(), AccountServiceError> {
+        let delete_error = |e: crate::db::WebError| AccountServiceError::ErrorDelete(e.to_string());
+
+        let user = self
+            .user_repository
+            .get_user_by_id(user_id)
+            .map_err(|_| AccountServiceError::ErrorNotFound)?;
+
+        if user_is_deleted_placeholder(&user) {
+            return Err(AccountServiceError::ErrorDelete(
+                "the deleted user placeholder can not be deleted".to_string(),
+            ));
+        }
+
+        let upload_files = self.get_user_upload_files(&user);
+
+        // with cascade the users foreign keys remove posts and comments
+        let content_owner_user_id = match self.deletion_mode {
+            AccountDeletionMode::Anonymize => Some(
+                self.user_repository
+                    .get_deleted_user()
+                    .map_err(delete_error)?
+                    .id,
+            ),
+            AccountDeletionMode::Cascade => None,
+        };
+
+        let attachment_keys = self
+            .account_repository
+            .delete_account(user.id, content_owner_user_id)
+            .map_err(delete_error)?;
+
+        // files go only once the rows are gone, a failed deletion leaves everything in place
+        for key in attachment_keys
+            .into_iter()
+            .chain(upload_files.into_iter().map(|(_, key)| key))
+        {
+            if let Err(e) = self.file_storage.delete_file(&key) {
+                println!("failed to remove upload {}: {}", key, e);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+use image::ImageFormat;
+
+use crate::{
+    entities::attachment::{
+        AttachmentKind, AttachmentTarget, AttachmentUpload, PreparedAttachment,
+        ATTACHMENT_KEY_PREFIX, ATTACHMENT_MAX_PER_TARGET,
+    },
+    models::{Attachment, NewAttachment},
+    repositories::attachment_repository::AttachmentRepositoryWithError,
+    services::file_storage_service::FileStorage,
+    utils::avatar::{decode_upload_image, encode_jpeg, encode_webp},
+};
+
+const ATTACHMENT_FILE_NAME_MAX_CHARS: usize = 100;
+
+#[derive(Debug)]
+pub enum AttachmentServiceError {
+    ErrorTooManyAttachments,
+    ErrorUnsupportedType(String),
+    ErrorTooLarge(String),
+    ErrorInvalidImage(String),
+    ErrorNotFound,
+    ErrorStoreAttachment(String),
+    ErrorGetAttachment(String),
+    ErrorDeleteAttachment(String),
+}
+
+impl Display for AttachmentServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttachmentServiceError::ErrorTooManyAttachments => write!(
+                f,
+                "At most {} attachments are allowed",
+                ATTACHMENT_MAX_PER_TARGET
+            ),
+            AttachmentServiceError::ErrorUnsupportedType(name) => write!(
+                f,
+                "Unsupported attachment {}, use JPEG, PNG, WebP, PDF, ZIP or text files",
+                name
+            ),
+            AttachmentServiceError::ErrorTooLarge(name) => {
+                write!(f, "Attachment {} is too large", name)
+            }
+            AttachmentServiceError::ErrorInvalidImage(msg) => write!(f, "{}", msg),
+            AttachmentServiceError::ErrorNotFound => write!(f, "Attachment not found"),
+            AttachmentServiceError::ErrorStoreAttachment(msg) => {
+                write!(f, "Failed to store attachment: {}", msg)
+            }
+            AttachmentServiceError::ErrorGetAttachment(msg) => {
+                write!(f, "Failed to get attachment: {}", msg)
+            }
+            AttachmentServiceError::ErrorDeleteAttachment(msg) => {
+                write!(f, "Failed to delete attachment: {}", msg)
+            }
+        }
+    }
+}
+
+pub trait AttachmentService: Send + Sync {
+    /// Validates uploads without storing anything
+    ///
+    /// Images are re-encoded so their metadata is dropped, other files must match a known signature.
+    fn prepare_attachments(
+        &self,
+        uploads: Vec<AttachmentUpload>,
+    ) -> Result<Vec<PreparedAttachment>, AttachmentServiceError>;
+
+    /// Stores prepared attachments for a post or a comment
+    fn store_attachments(
+        &self,
+        owner_user_id: i32,
+        target: AttachmentTarget,
+        prepared: Vec<PreparedAttachment>,
+    ) -> Result<Vec<Attachment>, AttachmentServiceError>;
+
+    /// Retrieves an attachment by its ID
+    fn get_attachment(&self, attachment_id: i32) -> Result<Attachment, AttachmentServiceError>;
+
+    /// Reads the stored file of an attachment
+    fn get_attachment_file(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<Vec<u8>, AttachmentServiceError>;
+
+    /// Retrieves the attachments of a post, without those of its comments
+    fn get_post_attachments(&self, post_id: i32)
+        -> Result<Vec<Attachment>, AttachmentServiceError>;
+
+    /// Retrieves the attachments of several comments
+    fn get_comments_attachments(
+        &self,
+        comment_ids: &[i32],
+    ) -> Result<Vec<Attachment>, AttachmentServiceError>;
+
+    /// Retrieves every attachment uploaded by a user
+    fn get_user_attachments(&self, user_id: i32)
+        -> Result<Vec<Attachment>, AttachmentServiceError>;
+
+    /// Deletes an attachment and its file
+    fn delete_attachment(&self, attachment_id: i32) -> Result<usize, AttachmentServiceError>;
+
+    /// Deletes the attachments of a post and of all its comments
+    fn delete_post_attachments(&self, post_id: i32) -> Result<usize, AttachmentServiceError>;
+
+    /// Deletes the attachments of a comment
+    fn delete_comment_attachments(&self, comment_id: i32) -> Result<usize, AttachmentServiceError>;
+
+    /// Deletes every attachment uploaded by a user
+    fn delete_user_attachments(&self, user_id: i32) -> Result<usize, AttachmentServiceError>;
+}
+
+pub struct BasedAttachmentService {
+    attachment_repository: Arc<AttachmentRepositoryWithError>,
+    file_storage: Arc<dyn FileStorage>,
+}
+
+impl BasedAttachmentService {
+    pub fn new(
+        attachment_repository: Arc<AttachmentRepositoryWithError>,
+        file_storage: Arc<dyn FileStorage>,
+    ) -> Self {
+        Self {
+            attachment_repository,
+            file_storage,
+        }
+    }
+
+    fn prepare_attachment(
+        &self,
+        upload: AttachmentUpload,
+    ) -> Result<PreparedAttachment, AttachmentServiceError> {
+        let file_name = sanitize_file_name(&upload.file_name);
+
+        let format = image::guess_format(&upload.bytes).ok();
+        if let Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP) = format {
+            return prepare_image(file_name, &upload.bytes);
+        }
+
+        let (content_type, extension) = match sniff_file_type(&file_name, &upload.bytes) {
+            Some(file_type) => file_type,
+            None => return Err(AttachmentServiceError::ErrorUnsupportedType(file_name)),
+        };
+
+        if upload.bytes.len() > AttachmentKind::File.max_bytes() {
+            return Err(AttachmentServiceError::ErrorTooLarge(file_name));
+        }
+
+        Ok(PreparedAttachment {
+            kind: AttachmentKind::File,
+            file_name,
+            content_type,
+            extension,
+            bytes: upload.bytes,
+        })
+    }
+
+    /// Deletes the records first, a file left behind is only logged
+    fn remove_attachments(
+        &self,
+        attachments: Vec<Attachment>,
+    ) -> Result<usize, AttachmentServiceError> {
+        if attachments.is_empty() {
+            return Ok(0);
+        }
+
+        let attachment_ids: Vec<i32> = attachments.iter().map(|a| a.id).collect();
+
+        let row_affected = self
+            .attachment_repository
+            .delete_attachments(&attachment_ids)
+            .map_err(|e| AttachmentServiceError::ErrorDeleteAttachment(e.to_string()))?;
+
+        for attachment in attachments {
+            if let Err(e) = self.file_storage.delete_file(&attachment.storage_key) {
+                println!(
+                    "failed to remove attachment file {}: {}",
+                    attachment.storage_key, e
+                );
+            }
+        }
+
+        Ok(row_affected)
+    }
+
+    fn rollback_stored(&self, stored: &[Attachment], stored_keys: &[String]) {
+        let attachment_ids: Vec<i32> = stored.iter().map(|a| a.id).collect();
+        if let Err(e) = self
+            .attachment_repository
+            .delete_attachments(&attachment_ids)
+        {
+            println!("failed to roll back attachments: {}", e);
+        }
+
+        for key in stored_keys {
+            if let Err(e) = self.file_storage.delete_file(key) {
+                println!("failed to remove attachment file {}: {}", key, e);
+            }
+        }
+    }
+}
+
+impl AttachmentService for BasedAttachmentService {
+    fn prepare_attachments(
+        &self,
+        uploads: Vec<AttachmentUpload>,
+    ) -> Result<Vec<PreparedAttachment>, AttachmentServiceError> {
+        if uploads.len() > ATTACHMENT_MAX_PER_TARGET {
+            return Err(AttachmentServiceError::ErrorTooManyAttachments);
+        }
+
+        uploads
+            .into_iter()
+            .map(|upload| self.prepare_attachment(upload))
+            .collect()
+    }
+
+    fn store_attachments(
+        &self,
+        owner_user_id: i32,
+        target: AttachmentTarget,
+        prepared: Vec<PreparedAttachment>,
+    ) -> Result<Vec<Attachment>, AttachmentServiceError> {
+        let (target_post_id, target_comment_id) = match target {
+            AttachmentTarget::Post(post_id) => (Some(post_id), None),
+            AttachmentTarget::Comment(comment_id) => (None, Some(comment_id)),
+        };
+
+        let mut stored = vec![];
+        let mut stored_keys = vec![];
+
+        for attachment in prepared {
+            let storage_key = format!(
+                "{}{}.{}",
+                ATTACHMENT_KEY_PREFIX,
+                uuid::Uuid::new_v4().simple(),
+                attachment.extension
+            );
+
+            if let Err(e) =
+                self.file_storage
+                    .put_file(&storage_key, &attachment.bytes, attachment.content_type)
+            {
+                self.rollback_stored(&stored, &stored_keys);
+                return Err(AttachmentServiceError::ErrorStoreAttachment(e.to_string()));
+            }
+            stored_keys.push(storage_key.clone());
+
+            let create_result = self
+                .attachment_repository
+                .create_attachment(&NewAttachment {
+                    user_id: owner_user_id,
+                    post_id: target_post_id,
+                    comment_id: target_comment_id,
+                    kind: attachment.kind.as_str(),
+                    file_name: &attachment.file_name,
+                    content_type: attachment.content_type,
+                    size_bytes: attachment.bytes.len() as i64,
+                    storage_key: &storage_key,
+                });
+
+            match create_result {
+                Ok(created) => stored.push(created),
+                Err(e) => {
+                    self.rollback_stored(&stored, &stored_keys);
+                    return Err(AttachmentServiceError::ErrorStoreAttachment(e.to_string()));
+                }
+            }
+        }
+
+        Ok(stored)
+    }
+
+    fn get_attachment(&self, attachment_id: i32) -> Result<Attachment, AttachmentServiceError> {
+        self.attachment_repository
+            .get_attachment(attachment_id)
+            .map_err(|_| AttachmentServiceError::ErrorNotFound)
+    }
+
+    fn get_attachment_file(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<Vec<u8>, AttachmentServiceError> {
+        self.file_storage
+            .get_file(&attachment.storage_key)
+            .map_err(|e| AttachmentServiceError::ErrorGetAttachment(e.to_string()))
+    }
+
+    fn get_post_attachments(
+        &self,
+        post_id: i32,
+    ) -> Result<Vec<Attachment>, AttachmentServiceError> {
+        self.attachment_repository
+            .get_attachments_by_post(post_id)
+            .map_err(|e| AttachmentServiceError::ErrorGetAttachment(e.to_string()))
+    }
+
+    fn get_comments_attachments(
+        &self,
+        comment_ids: &[i32],
+    ) -> Result<Vec<Attachment>, AttachmentServiceError> {
+        self.attachment_repository
+            .get_attachments_by_comments(comment_ids)
+            .map_err(|e| AttachmentServiceError::ErrorGetAttachment(e.to_string()))
+    }
+
+    fn get_user_attachments(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<Attachment>, AttachmentServiceError> {
+        self.attachment_repository
+            .get_attachments_by_user(user_id)
+            .map_err(|e| AttachmentServiceError::ErrorGetAttachment(e.to_string()))
+    }
+
+    fn delete_attachment(&self, attachment_id: i32) -> Result<usize, AttachmentServiceError> {
+        let attachment = self.get_attachment(attachment_id)?;
+
+        self.remove_attachments(vec![attachment])
+    }
+
+    fn delete_post_attachments(&self, post_id: i32) -> Result<usize, AttachmentServiceError> {
+        let attachments = self
+            .attachment_repository
+            .get_all_attachments_by_post(post_id)
+            .map_err(|e| AttachmentServiceError::ErrorDeleteAttachment(e.to_string()))?;
+
+        self.remove_attachments(attachments)
+    }
+
+    fn delete_comment_attachments(&self, comment_id: i32) -> Result<usize, AttachmentServiceError> {
+        let attachments = self
+            .attachment_repository
+            .get_attachments_by_comments(&[comment_id])
+            .map_err(|e| AttachmentServiceError::ErrorDeleteAttachment(e.to_string()))?;
+
+        self.remove_attachments(attachments)
+    }
+
+    fn delete_user_attachments(&self, user_id: i32) -> Result<usize, AttachmentServiceError> {
+        let attachments = self
+            .attachment_repository
+            .get_attachments_by_user(user_id)
+            .map_err(|e| AttachmentServiceError::ErrorDeleteAttachment(e.to_string()))?;
+
+        self.remove_attachments(attachments)
+    }
+}
+
+/// Re-encodes an image, JPEG stays JPEG and PNG or WebP become lossless WebP to keep transparency
+fn prepare_image(
+    file_name: String,
+    bytes: &[u8],
+) -> Result<PreparedAttachment, AttachmentServiceError> {
+    if bytes.len() > AttachmentKind::Image.max_bytes() {
+        return Err(AttachmentServiceError::ErrorTooLarge(file_name));
+    }
+
+    let (image, format) = decode_upload_image(bytes)
+        .map_err(|e| AttachmentServiceError::ErrorInvalidImage(e.to_string()))?;
+
+    let (encoded, content_type, extension) = match format {
+        ImageFormat::Jpeg => (encode_jpeg(&image), "image/jpeg", "jpg"),
+        _ => (encode_webp(&image), "image/webp", "webp"),
+    };
+
+    Ok(PreparedAttachment {
+        kind: AttachmentKind::Image,
+        file_name,
+        content_type,
+        extension,
+        bytes: encoded.map_err(|e| AttachmentServiceError::ErrorInvalidImage(e.to_string()))?,
+    })
+}
+
+/// Content type and extension of an allowed non-image file, judged by its bytes
+fn sniff_file_type(file_name: &str, bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"%PDF-") {
+        return Some(("application/pdf", "pdf"));
+    }
+
+    if bytes.starts_with(b"PK\x03\x04") {
+        return Some(("application/zip", "zip"));
+    }
+
+    // text has no signature, the name must say so and the bytes must be UTF-8 without NUL
+    let extension = file_name.rsplit_once('.').map(|(_, e)| e.to_lowercase());
+    let text_extension = match extension.as_deref() {
+        Some("txt") => "txt",
+        Some("md") => "md",
+        _ => return None,
+    };
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => Some(("text/plain; charset=utf-8", text_extension)),
+        _ => None,
+    }
+}
+
+/// Keeps the last path segment of a client file name, without control characters
+fn sanitize_file_name(file_name: &str) -> String {
+    let base_name = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(ATTACHMENT_FILE_NAME_MAX_CHARS)
+        .collect::<String>();
+
+    match base_name.trim() {
+        "" | "." | ".." => "attachment".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::audit::{AuditLogFilter, AuditRecord, ListAuditLogResult, AUDIT_LOG_EXPORT_LIMIT},
+    models::{AuditLogEntry, NewAuditLogEntry},
+    repositories::audit_log_repository::AuditLogRepository,
+    utils::pagination::QueryPagination,
+};
+
+/// Longest reason kept in an entry, longer ones are cut
+const AUDIT_REASON_MAX_CHARS: usize = 1000;
+
+#[derive(Debug)]
+pub enum AuditServiceError {
+    ErrorRecord(String),
+    ErrorGetEntries(String),
+}
+
+impl Display for AuditServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditServiceError::ErrorRecord(msg) => {
+                write!(f, "Failed to record audit log entry: {}", msg)
+            }
+            AuditServiceError::ErrorGetEntries(msg) => {
+                write!(f, "Failed to get audit log entries: {}", msg)
+            }
+        }
+    }
+}
+
+pub trait AuditService: Send + Sync {
+    /// Appends a privileged action to the audit log
+    fn record(&self, record: &AuditRecord) -> Result<AuditLogEntry, AuditServiceError>;
+
+    /// Retrieves a page of the entries matching a filter, newest first
+    fn get_entries(
+        &self,
+        filter: &AuditLogFilter,
+        pagination: &QueryPagination,
+    ) -> Result<ListAuditLogResult, AuditServiceError>;
+
+    /// Retrieves the newest entries matching a filter for an export, up to the export limit
+    fn export_entries(
+        &self,
+        filter: &AuditLogFilter,
+    ) -> Result<Vec<AuditLogEntry>, AuditServiceError>;
+}
+
+pub struct BasedAuditService {
+    audit_log_repository: Arc<dyn AuditLogRepository>,
+}
+
+impl BasedAuditService {
+    pub fn new(audit_log_repository: Arc<dyn AuditLogRepository>) -> Self {
+        Self {
+            audit_log_repository,
+        }
+    }
+}
+
+impl AuditService for BasedAuditService {
+    fn record(&self, record: &AuditRecord) -> Result<AuditLogEntry, AuditServiceError> {
+        let reason = record
+            .reason
+            .trim()
+            .chars()
+            .take(AUDIT_REASON_MAX_CHARS)
+            .collect::<String>();
+
+        let new_entry = NewAuditLogEntry {
+            actor_user_id: record.actor_user_id,
+            action: record.action.as_str(),
+            target_type: record.target.kind(),
+            target_id: record.target.id(),
+            reason: &reason,
+            ip_address: record.ip_address,
+        };
+
+        self.audit_log_repository
+            .create_audit_log_entry(&new_entry)
+            .map_err(|e| AuditServiceError::ErrorRecord(e.to_string()))
+    }
+
+    fn get_entries(
+        &self,
+        filter: &AuditLogFilter,
+        pagination: &QueryPagination,
+    ) -> Result<ListAuditLogResult, AuditServiceError> {
+        let entries = self
+            .audit_log_repository
+            .get_audit_log_entries(filter, pagination)
+            .map_err(|e| AuditServiceError::ErrorGetEntries(e.to_string()))?;
+
+        let total = self
+            .audit_log_repository
+            .count_audit_log_entries(filter)
+            .map_err(|e| AuditServiceError::ErrorGetEntries(e.to_string()))?;
+
+        Ok(ListAuditLogResult { entries, total })
+    }
+
+    fn export_entries(
+        &self,
+        filter: &AuditLogFilter,
+    ) -> Result<Vec<AuditLogEntry>, AuditServiceError> {
+        let pagination = QueryPagination {
+            page: 1,
+            limit: AUDIT_LOG_EXPORT_LIMIT,
+            ..Default::default()
+        };
+
+        self.audit_log_repository
+            .get_audit_log_entries(filter, &pagination)
+            .map_err(|e| AuditServiceError::ErrorGetEntries(e.to_string()))
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::bookmark::{BookmarkPublic, BookmarkTarget, ListBookmarkResult},
+    models::{Bookmark, NewBookmark},
+    repositories::{
+        bookmark_repository::BookmarkRepository, comment_repository::CommentRepositoryWithError,
+        post_repository::PostRepositoryWithError,
+    },
+    utils::{excerpt::excerpt, pagination::QueryPagination, time::time_to_human_readable},
+};
+
+#[derive(Debug)]
+pub enum BookmarkServiceError {
+    ErrorTargetNotFound,
+    ErrorBookmarkNotFound,
+    ErrorSaveBookmark,
+    ErrorRemoveBookmark,
+    ErrorGetBookmarks,
+}
+
+impl Display for BookmarkServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookmarkServiceError::ErrorTargetNotFound => write!(f, "Saved content not found"),
+            BookmarkServiceError::ErrorBookmarkNotFound => write!(f, "Bookmark not found"),
+            BookmarkServiceError::ErrorSaveBookmark => write!(f, "Failed to save bookmark"),
+            BookmarkServiceError::ErrorRemoveBookmark => write!(f, "Failed to remove bookmark"),
+            BookmarkServiceError::ErrorGetBookmarks => write!(f, "Failed to get bookmarks"),
+        }
+    }
+}
+
+pub trait BookmarkService: Send + Sync {
+    /// Saves a visible post or comment for a user, saving it again only moves it to `folder` and replaces its note
+    fn save_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+        folder: &str,
+        note: &str,
+    ) -> Result<Bookmark, BookmarkServiceError>;
+
+    /// Files a bookmark of a user in another folder and replaces its note
+    fn update_bookmark(
+        &self,
+        user_id: i32,
+        bookmark_id: i32,
+        folder: &str,
+        note: &str,
+    ) -> Result<usize, BookmarkServiceError>;
+
+    /// Removes a bookmark of a user
+    fn remove_bookmark(
+        &self,
+        user_id: i32,
+        bookmark_id: i32,
+    ) -> Result<usize, BookmarkServiceError>;
+
+    /// Removes the bookmark a user has on a post or a comment
+    fn remove_target_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<usize, BookmarkServiceError>;
+
+    /// Retrieves a page of the bookmarks of a user, most recent first, `folder` narrows to one folder
+    fn get_bookmarks(
+        &self,
+        user_id: i32,
+        folder: Option<&str>,
+        pagination: &QueryPagination,
+    ) -> Result<ListBookmarkResult, BookmarkServiceError>;
+
+    /// The folders a user filed bookmarks in, sorted by name
+    fn get_folders(&self, user_id: i32) -> Result<Vec<String>, BookmarkServiceError>;
+
+    /// Whether a user saved a post or a comment
+    fn is_bookmarked(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<bool, BookmarkServiceError>;
+
+    /// The comments among `comment_ids` a user saved
+    fn get_bookmarked_comment_ids(
+        &self,
+        user_id: i32,
+        comment_ids: &[i32],
+    ) -> Result<Vec<i32>, BookmarkServiceError>;
+}
+
+pub struct BasedBookmarkService {
+    bookmark_repository: Arc<dyn BookmarkRepository>,
+    post_repository: Arc<PostRepositoryWithError>,
+    comment_repository: Arc<CommentRepositoryWithError>,
+}
+
+impl BasedBookmarkService {
+    pub fn new(
+        bookmark_repository: Arc<dyn BookmarkRepository>,
+        post_repository: Arc<PostRepositoryWithError>,
+        comment_repository: Arc<CommentRepositoryWithError>,
+    ) -> Self {
+        Self {
+            bookmark_repository,
+            post_repository,
+            comment_repository,
+        }
+    }
+
+    /// Describes the saved content, content hidden or deleted since it was saved keeps its
+    /// bookmark under the removed placeholder, without a link, title or excerpt
+    fn build_bookmark_public(&self, bookmark: Bookmark) -> BookmarkPublic {
+        let mut bookmark_public = BookmarkPublic {
+            target_kind: "post",
+            target_id: 0,
+            target_url: None,
+            target_title: "(removed)".to_string(),
+            target_excerpt: String::new(),
+            time_human: time_to_human_readable(bookmark.created_at),
+            bookmark,
+        };
+
+        match BookmarkTarget::of_bookmark(&bookmark_public.bookmark) {
+            Some(BookmarkTarget::Post(post_id)) => {
+                bookmark_public.target_id = post_id;
+                if let Ok(post) = self.post_repository.get_post_unfiltered(post_id) {
+                    if post.hidden_at.is_none() && post.deleted_at.is_none() {
+                        bookmark_public.target_url = Some(format!("/posts/{}", post.id));
+                        bookmark_public.target_title = post.title;
+                        bookmark_public.target_excerpt = excerpt(&post.body);
+                    }
+                }
+            }
+
+            Some(BookmarkTarget::Comment(comment_id)) => {
+                bookmark_public.target_kind = "comment";
+                bookmark_public.target_id = comment_id;
+                if let Ok(comment) = self.comment_repository.get_comment_unfiltered(comment_id) {
+                    // a comment under a removed post is gone with it
+                    let post = self
+                        .post_repository
+                        .get_post_unfiltered(comment.post_id)
+                        .ok()
+                        .filter(|post| post.hidden_at.is_none() && post.deleted_at.is_none());
+                    let comment_visible =
+                        comment.hidden_at.is_none() && comment.deleted_at.is_none();
+                    if let Some(post) = post.filter(|_| comment_visible) {
+                        bookmark_public.target_url =
+                            Some(format!("/posts/{}#{}", comment.post_id, comment.id));
+                        bookmark_public.target_title = format!("Comment on {}", post.title);
+                        bookmark_public.target_excerpt = excerpt(&comment.content);
+                    }
+                }
+            }
+
+            None => {}
+        }
+
+        bookmark_public
+    }
+}
+
+impl BookmarkService for BasedBookmarkService {
+    fn save_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+        folder: &str,
+        note: &str,
+    ) -> Result<Bookmark, BookmarkServiceError> {
+        let target_found = match target {
+            BookmarkTarget::Post(post_id) => self.post_repository.get_post(post_id).is_ok(),
+            BookmarkTarget::Comment(comment_id) => {
+                self.comment_repository.get_comment(comment_id).is_ok()
+            }
+        };
+        if !target_found {
+            return Err(BookmarkServiceError::ErrorTargetNotFound);
+        }
+
+        let folder = folder.trim();
+        let note = note.trim();
+
+        let existing = self
+            .bookmark_repository
+            .find_bookmark(user_id, target)
+            .map_err(|_| BookmarkServiceError::ErrorSaveBookmark)?;
+
+        if let Some(bookmark) = existing {
+            self.bookmark_repository
+                .update_bookmark(bookmark.id, user_id, folder, note)
+                .map_err(|_| BookmarkServiceError::ErrorSaveBookmark)?;
+
+            return Ok(Bookmark {
+                folder: folder.to_string(),
+                note: note.to_string(),
+                ..bookmark
+            });
+        }
+
+        let (post_id, comment_id) = match target {
+            BookmarkTarget::Post(post_id) => (Some(post_id), None),
+            BookmarkTarget::Comment(comment_id) => (None, Some(comment_id)),
+        };
+
+        self.bookmark_repository
+            .create_bookmark(&NewBookmark {
+                user_id,
+                post_id,
+                comment_id,
+                folder,
+                note,
+            })
+            .map_err(|_| BookmarkServiceError::ErrorSaveBookmark)
+    }
+
+    fn update_bookmark(
+        &self,
+        user_id: i32,
+        bookmark_id: i32,
+        folder: &str,
+        note: &str,
+    ) -> Result<usize, BookmarkServiceError> {
+        match self.bookmark_repository.update_bookmark(
+            bookmark_id,
+            user_id,
+            folder.trim(),
+            note.trim(),
+        ) {
+            Ok(0) | Err(_) => Err(BookmarkServiceError::ErrorBookmarkNotFound),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn remove_bookmark(
+        &self,
+        user_id: i32,
+        bookmark_id: i32,
+    ) -> Result<usize, BookmarkServiceError> {
+        match self
+            .bookmark_repository
+            .delete_bookmark(bookmark_id, user_id)
+        {
+            Ok(0) | Err(_) => Err(BookmarkServiceError::ErrorRemoveBookmark),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn remove_target_bookmark(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<usize, BookmarkServiceError> {
+        match self
+            .bookmark_repository
+            .delete_bookmark_by_target(user_id, target)
+        {
+            Ok(0) | Err(_) => Err(BookmarkServiceError::ErrorRemoveBookmark),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn get_bookmarks(
+        &self,
+        user_id: i32,
+        folder: Option<&str>,
+        pagination: &QueryPagination,
+    ) -> Result<ListBookmarkResult, BookmarkServiceError> {
+        let bookmarks = self
+            .bookmark_repository
+            .get_bookmarks(user_id, folder, pagination)
+            .map_err(|_| BookmarkServiceError::ErrorGetBookmarks)?;
+        let total = self
+            .bookmark_repository
+            .count_bookmarks(user_id, folder)
+            .map_err(|_| BookmarkServiceError::ErrorGetBookmarks)?;
+
+        Ok(ListBookmarkResult {
+            bookmarks: bookmarks
+                .into_iter()
+                .map(|bookmark| self.build_bookmark_public(bookmark))
+                .collect(),
+            total,
+        })
+    }
+
+    fn get_folders(&self, user_id: i32) -> Result<Vec<String>, BookmarkServiceError> {
+        self.bookmark_repository
+            .get_folders(user_id)
+            .map_err(|_| BookmarkServiceError::ErrorGetBookmarks)
+    }
+
+    fn is_bookmarked(
+        &self,
+        user_id: i32,
+        target: BookmarkTarget,
+    ) -> Result<bool, BookmarkServiceError> {
+        self.bookmark_repository
+            .find_bookmark(user_id, target)
+            .map(|bookmark| bookmark.is_some())
+            .map_err(|_| BookmarkServiceError::ErrorGetBookmarks)
+    }
+
+    fn get_bookmarked_comment_ids(
+        &self,
+        user_id: i32,
+        comment_ids: &[i32],
+    ) -> Result<Vec<i32>, BookmarkServiceError> {
+        self.bookmark_repository
+            .get_bookmarked_comment_ids(user_id, comment_ids)
+            .map_err(|_| BookmarkServiceError::ErrorGetBookmarks)
+    }
+}
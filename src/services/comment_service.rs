@@ -1,17 +1,43 @@
 use std::fmt::Display;
 use std::sync::Arc;
 
+use chrono::NaiveDateTime;
+
 use crate::{
-    entities::comment::ListCommentResult, models::Comment,
-    repositories::comment_repository::CommentRepositoryWithError,
+    entities::{
+        ban::ActiveBan,
+        comment::ListCommentResult,
+        content_filter::{ContentFilterVerdict, FilterAction, FilteredContent, FilteredTarget},
+        live::{LiveChannel, LiveComment, LiveEvent},
+        notification::{NOTIFICATION_KIND_POST_REPLY, NOTIFICATION_KIND_SUBSCRIPTION_REPLY},
+        report::ReportTarget,
+    },
+    models::{Comment, CommentRevision, User},
+    repositories::{
+        comment_repository::CommentRepositoryWithError, follow_repository::FollowRepository,
+        user_repository::UserRepositoryWithError,
+    },
+    services::{
+        attachment_service::AttachmentService, content_filter_service::ContentFilterService,
+        live_service::LiveService, notification_service::NotificationService,
+    },
     utils::pagination::QueryPagination,
 };
 
+#[derive(Debug)]
 pub enum CommentServiceError {
     ErrorCreateComment,
     ErrorGetComment,
     ErrorUpdateComment,
     ErrorDeleteComment,
+    ErrorGetRevision,
+    ErrorRollbackComment,
+    ErrorRestoreComment,
+    ErrorPurgeComment,
+    ErrorUserBanned(ActiveBan),
+    ErrorContentRejected(String),
+    ErrorPostLocked,
+    ErrorPostNotFound,
 }
 
 impl Display for CommentServiceError {
@@ -21,12 +47,25 @@ impl Display for CommentServiceError {
             CommentServiceError::ErrorGetComment => write!(f, "Failed to get comment"),
             CommentServiceError::ErrorUpdateComment => write!(f, "Failed to update comment"),
             CommentServiceError::ErrorDeleteComment => write!(f, "Failed to delete comment"),
+            CommentServiceError::ErrorGetRevision => write!(f, "Failed to get comment revision"),
+            CommentServiceError::ErrorRollbackComment => write!(f, "Failed to roll back comment"),
+            CommentServiceError::ErrorRestoreComment => write!(f, "Failed to restore comment"),
+            CommentServiceError::ErrorPurgeComment => write!(f, "Failed to purge comments"),
+            CommentServiceError::ErrorUserBanned(ban) => write!(f, "{}", ban),
+            CommentServiceError::ErrorContentRejected(reasons) => {
+                write!(f, "Your comment was rejected: {}", reasons)
+            }
+            CommentServiceError::ErrorPostLocked => {
+                write!(f, "The post is locked, it takes no new comments")
+            }
+            CommentServiceError::ErrorPostNotFound => write!(f, "Post not found"),
         }
     }
 }
 
 pub trait CommentService: Send + Sync {
-    /// Creates a new comment
+    /// Creates a new comment, refused on locked, deleted or hidden posts and held by the content filter when it
+    /// matches a hold rule
     fn create_comment(
         &self,
         comment_user_id: i32,
@@ -40,14 +79,15 @@ pub trait CommentService: Send + Sync {
     /// Retrieves all comments for a post
     fn get_comments(&self, parent_post_id: i32) -> Result<Vec<Comment>, CommentServiceError>;
 
-    /// Updates an existing comment
+    /// Updates an existing comment, keeping the previous content as a revision
     fn update_comment(
         &self,
         target_comment_id: i32,
+        editor_user_id: i32,
         new_body: &str,
     ) -> Result<Comment, CommentServiceError>;
 
-    /// Soft deletes a comment
+    /// Soft deletes a comment, it stays restorable until it is purged
     fn delete_comment(&self, target_comment_id: i32) -> Result<usize, CommentServiceError>;
 
     /// Retrieves comments with user information for a post
@@ -70,15 +110,206 @@ pub trait CommentService: Send + Sync {
         target_comment: &Comment,
         page_limit: i64,
     ) -> Result<i64, CommentServiceError>;
+
+    /// Retrieves the revisions of a comment, oldest first and empty if it was never edited
+    fn get_comment_revisions(
+        &self,
+        target_comment_id: i32,
+    ) -> Result<Vec<CommentRevision>, CommentServiceError>;
+
+    /// Restores the content of a previous revision as a new revision
+    fn rollback_comment(
+        &self,
+        target_comment_id: i32,
+        revision_id: i32,
+        editor_user_id: i32,
+    ) -> Result<Comment, CommentServiceError>;
+
+    /// Retrieves a soft deleted comment by its ID
+    fn get_deleted_comment(&self, comment_id: i32) -> Result<Comment, CommentServiceError>;
+
+    /// Retrieves the trash, the soft deleted comments of a user or of everyone
+    fn get_deleted_comments(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListCommentResult, CommentServiceError>;
+
+    /// Brings a soft deleted comment back
+    fn restore_comment(&self, target_comment_id: i32) -> Result<usize, CommentServiceError>;
+
+    /// Hard deletes the comments soft deleted before `deleted_before`, along with their attachments
+    fn purge_deleted_comments(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, CommentServiceError>;
+
+    /// Retrieves a comment by its ID whatever its state, for moderators
+    fn get_comment_unfiltered(&self, comment_id: i32) -> Result<Comment, CommentServiceError>;
+
+    /// Hides a comment from everyone but moderators, or lifts the hold
+    fn set_comment_hidden(
+        &self,
+        target_comment_id: i32,
+        hidden: bool,
+    ) -> Result<usize, CommentServiceError>;
 }
 
 pub struct BasedCommentService {
     comment_repository: Arc<CommentRepositoryWithError>,
+    user_repository: Arc<UserRepositoryWithError>,
+    attachment_service: Arc<dyn AttachmentService>,
+    content_filter_service: Arc<dyn ContentFilterService>,
+    live_service: Arc<dyn LiveService>,
+    follow_repository: Arc<dyn FollowRepository>,
+    notification_service: Arc<dyn NotificationService>,
 }
 
 impl BasedCommentService {
-    pub fn new(comment_repository: Arc<CommentRepositoryWithError>) -> Self {
-        Self { comment_repository }
+    pub fn new(
+        comment_repository: Arc<CommentRepositoryWithError>,
+        user_repository: Arc<UserRepositoryWithError>,
+        attachment_service: Arc<dyn AttachmentService>,
+        content_filter_service: Arc<dyn ContentFilterService>,
+        live_service: Arc<dyn LiveService>,
+        follow_repository: Arc<dyn FollowRepository>,
+        notification_service: Arc<dyn NotificationService>,
+    ) -> Self {
+        Self {
+            comment_repository,
+            user_repository,
+            attachment_service,
+            content_filter_service,
+            live_service,
+            follow_repository,
+            notification_service,
+        }
+    }
+
+    /// The post author and the subscribers of a post with the kind of notification each gets,
+    /// the one who commented left out
+    fn get_comment_recipients(&self, comment: &Comment) -> Vec<(i32, &'static str)> {
+        let mut recipients = vec![];
+
+        match self.comment_repository.get_post_user_id(comment.post_id) {
+            Ok(post_user_id) => recipients.push((post_user_id, NOTIFICATION_KIND_POST_REPLY)),
+            Err(e) => println!("failed to get author of post {}: {}", comment.post_id, e),
+        }
+
+        match self
+            .follow_repository
+            .get_post_subscriber_ids(comment.post_id)
+        {
+            // an author subscribed to their own post is told once, as the author
+            Ok(subscriber_ids) => {
+                for subscriber_id in subscriber_ids {
+                    if !recipients
+                        .iter()
+                        .any(|(user_id, _)| *user_id == subscriber_id)
+                    {
+                        recipients.push((subscriber_id, NOTIFICATION_KIND_SUBSCRIPTION_REPLY));
+                    }
+                }
+            }
+            Err(e) => println!(
+                "failed to get subscribers of post {}: {}",
+                comment.post_id, e
+            ),
+        }
+
+        recipients.retain(|(user_id, _)| *user_id != comment.user_id);
+        recipients
+    }
+
+    /// Pushes a new comment to the readers of its post and tells the post author and the
+    /// subscribers about it, comments held by the content filter stay quiet
+    fn publish_comment_created(&self, comment: &Comment, author: &User) {
+        if comment.hidden_at.is_some() {
+            return;
+        }
+
+        self.live_service.publish(
+            LiveChannel::Post(comment.post_id),
+            LiveEvent::CommentCreated {
+                comment: LiveComment::new(comment, &author.name),
+            },
+        );
+
+        let recipients = self.get_comment_recipients(comment);
+        if let Err(e) = self
+            .notification_service
+            .notify_comment(comment, &recipients)
+        {
+            println!("failed to notify about comment {}: {}", comment.id, e);
+        }
+    }
+
+    /// Pushes the new content of an edited comment to the readers of its post
+    fn publish_comment_updated(&self, comment: &Comment) {
+        if comment.hidden_at.is_some() {
+            return;
+        }
+
+        self.live_service.publish(
+            LiveChannel::Post(comment.post_id),
+            LiveEvent::CommentUpdated {
+                comment_id: comment.id,
+                content: comment.content.clone(),
+            },
+        );
+    }
+
+    /// Takes a deleted or hidden comment off the pages of the readers of its post
+    fn publish_comment_deleted(&self, comment: &Comment) {
+        self.live_service.publish(
+            LiveChannel::Post(comment.post_id),
+            LiveEvent::CommentDeleted {
+                comment_id: comment.id,
+            },
+        );
+    }
+
+    /// Refuses writes by banned users and returns the writing user, `lookup_error` is returned
+    /// when the user can not be read
+    fn check_user_not_banned(
+        &self,
+        user_id: i32,
+        lookup_error: CommentServiceError,
+    ) -> Result<User, CommentServiceError> {
+        let user = self
+            .user_repository
+            .get_user_by_id(user_id)
+            .map_err(|_| lookup_error)?;
+
+        match ActiveBan::of_user(&user) {
+            Some(ban) => Err(CommentServiceError::ErrorUserBanned(ban)),
+            None => Ok(user),
+        }
+    }
+
+    /// Hides a stored comment the filter held and reports held or flagged comments to the
+    /// moderators
+    fn apply_filter_verdict(&self, comment: Comment, verdict: &ContentFilterVerdict) -> Comment {
+        let Some(action) = verdict.action() else {
+            return comment;
+        };
+
+        if action == FilterAction::Hold {
+            if let Err(e) = self.comment_repository.set_comment_hidden(comment.id, true) {
+                println!("failed to hold filtered comment {}: {}", comment.id, e);
+            }
+        }
+
+        if let Err(e) = self
+            .content_filter_service
+            .report_content(ReportTarget::Comment(comment.id), verdict)
+        {
+            println!("failed to report filtered comment {}: {}", comment.id, e);
+        }
+
+        self.comment_repository
+            .get_comment_unfiltered(comment.id)
+            .unwrap_or(comment)
     }
 }
 
@@ -89,9 +320,43 @@ impl CommentService for BasedCommentService {
         parent_post_id: i32,
         comment_body: &str,
     ) -> Result<Comment, CommentServiceError> {
-        self.comment_repository
+        let author =
+            self.check_user_not_banned(comment_user_id, CommentServiceError::ErrorCreateComment)?;
+
+        let post_locked = self
+            .comment_repository
+            .is_post_locked(parent_post_id)
+            .map_err(|_| CommentServiceError::ErrorCreateComment)?;
+        // posts in the trash or hidden by moderation take no comments either
+        match post_locked {
+            None => return Err(CommentServiceError::ErrorPostNotFound),
+            Some(true) => return Err(CommentServiceError::ErrorPostLocked),
+            Some(false) => {}
+        }
+
+        let verdict = self
+            .content_filter_service
+            .check_content(&FilteredContent {
+                author: &author,
+                target: FilteredTarget::NewComment,
+                title: "",
+                body: comment_body,
+            })
+            .map_err(|_| CommentServiceError::ErrorCreateComment)?;
+
+        if verdict.action() == Some(FilterAction::Reject) {
+            return Err(CommentServiceError::ErrorContentRejected(verdict.reasons()));
+        }
+
+        let comment = self
+            .comment_repository
             .create_comment(comment_user_id, parent_post_id, comment_body)
-            .map_err(|_| CommentServiceError::ErrorCreateComment)
+            .map_err(|_| CommentServiceError::ErrorCreateComment)?;
+        let comment = self.apply_filter_verdict(comment, &verdict);
+
+        self.publish_comment_created(&comment, &author);
+
+        Ok(comment)
     }
 
     fn get_comment(&self, comment_id: i32) -> Result<Comment, CommentServiceError> {
@@ -109,17 +374,36 @@ impl CommentService for BasedCommentService {
     fn update_comment(
         &self,
         target_comment_id: i32,
+        editor_user_id: i32,
         new_body: &str,
     ) -> Result<Comment, CommentServiceError> {
-        self.comment_repository
-            .update_comment(target_comment_id, new_body)
-            .map_err(|_| CommentServiceError::ErrorUpdateComment)
+        self.check_user_not_banned(editor_user_id, CommentServiceError::ErrorUpdateComment)?;
+
+        let comment = self
+            .comment_repository
+            .update_comment(target_comment_id, editor_user_id, new_body)
+            .map_err(|_| CommentServiceError::ErrorUpdateComment)?;
+
+        self.publish_comment_updated(&comment);
+
+        Ok(comment)
     }
 
     fn delete_comment(&self, target_comment_id: i32) -> Result<usize, CommentServiceError> {
-        self.comment_repository
+        let comment = self
+            .comment_repository
+            .get_comment_unfiltered(target_comment_id);
+
+        let row_affected = self
+            .comment_repository
             .delete_comment(target_comment_id)
-            .map_err(|_| CommentServiceError::ErrorDeleteComment)
+            .map_err(|_| CommentServiceError::ErrorDeleteComment)?;
+
+        if let (Ok(comment), true) = (comment, row_affected > 0) {
+            self.publish_comment_deleted(&comment);
+        }
+
+        Ok(row_affected)
     }
 
     fn get_comments_with_user(
@@ -151,4 +435,121 @@ impl CommentService for BasedCommentService {
             .get_page_where_comment_at(target_comment, page_limit)
             .map_err(|_| CommentServiceError::ErrorGetComment)
     }
+
+    fn get_comment_revisions(
+        &self,
+        target_comment_id: i32,
+    ) -> Result<Vec<CommentRevision>, CommentServiceError> {
+        self.comment_repository
+            .get_comment_revisions(target_comment_id)
+            .map_err(|_| CommentServiceError::ErrorGetRevision)
+    }
+
+    fn rollback_comment(
+        &self,
+        target_comment_id: i32,
+        revision_id: i32,
+        editor_user_id: i32,
+    ) -> Result<Comment, CommentServiceError> {
+        self.check_user_not_banned(editor_user_id, CommentServiceError::ErrorRollbackComment)?;
+
+        let revision = self
+            .comment_repository
+            .get_comment_revision(revision_id)
+            .map_err(|_| CommentServiceError::ErrorGetRevision)?;
+
+        if revision.comment_id != target_comment_id {
+            return Err(CommentServiceError::ErrorGetRevision);
+        }
+
+        let comment = self
+            .comment_repository
+            .update_comment(target_comment_id, editor_user_id, &revision.content)
+            .map_err(|_| CommentServiceError::ErrorRollbackComment)?;
+
+        self.publish_comment_updated(&comment);
+
+        Ok(comment)
+    }
+
+    fn get_deleted_comment(&self, comment_id: i32) -> Result<Comment, CommentServiceError> {
+        self.comment_repository
+            .get_deleted_comment(comment_id)
+            .map_err(|_| CommentServiceError::ErrorGetComment)
+    }
+
+    fn get_deleted_comments(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListCommentResult, CommentServiceError> {
+        self.comment_repository
+            .get_deleted_comments(target_user_id, pagination)
+            .map_err(|_| CommentServiceError::ErrorGetComment)
+    }
+
+    fn restore_comment(&self, target_comment_id: i32) -> Result<usize, CommentServiceError> {
+        match self.comment_repository.restore_comment(target_comment_id) {
+            Ok(0) | Err(_) => Err(CommentServiceError::ErrorRestoreComment),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn purge_deleted_comments(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, CommentServiceError> {
+        let comment_ids = self
+            .comment_repository
+            .get_comment_ids_deleted_before(deleted_before)
+            .map_err(|_| CommentServiceError::ErrorPurgeComment)?;
+
+        if comment_ids.is_empty() {
+            return Ok(0);
+        }
+
+        for comment_id in &comment_ids {
+            if let Err(e) = self
+                .attachment_service
+                .delete_comment_attachments(*comment_id)
+            {
+                println!(
+                    "failed to remove attachments of comment {}: {}",
+                    comment_id, e
+                );
+            }
+        }
+
+        self.comment_repository
+            .purge_comments(&comment_ids)
+            .map_err(|_| CommentServiceError::ErrorPurgeComment)
+    }
+
+    fn get_comment_unfiltered(&self, comment_id: i32) -> Result<Comment, CommentServiceError> {
+        self.comment_repository
+            .get_comment_unfiltered(comment_id)
+            .map_err(|_| CommentServiceError::ErrorGetComment)
+    }
+
+    fn set_comment_hidden(
+        &self,
+        target_comment_id: i32,
+        hidden: bool,
+    ) -> Result<usize, CommentServiceError> {
+        let row_affected = self
+            .comment_repository
+            .set_comment_hidden(target_comment_id, hidden)
+            .map_err(|_| CommentServiceError::ErrorUpdateComment)?;
+
+        if hidden && row_affected > 0 {
+            if let Ok(comment) = self
+                .comment_repository
+                .get_comment_unfiltered(target_comment_id)
+            {
+                self.publish_comment_deleted(&comment);
+            }
+        }
+
+        Ok(row_affected)
+    }
 }
@@ -0,0 +1,290 @@
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, LazyLock};
+
+use chrono::{Duration, Utc};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    entities::{
+        content_filter::{
+            ContentFilterPolicy, ContentFilterVerdict, FilterAction, FilterHit, FilteredContent,
+            FilteredTarget,
+        },
+        report::ReportTarget,
+    },
+    models::{ContentFilterRule, NewContentFilterRule},
+    repositories::{
+        comment_repository::CommentRepositoryWithError,
+        content_filter_rule_repository::ContentFilterRuleRepository,
+        post_repository::PostRepositoryWithError,
+    },
+    services::report_service::ReportService,
+};
+
+static LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(?:https?://|www\.)").unwrap());
+
+/// Largest compiled size of an admin rule, keeps a careless pattern from slowing every post
+const RULE_SIZE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum ContentFilterServiceError {
+    ErrorInvalidPattern(String),
+    ErrorCheckContent,
+    ErrorReportContent,
+    ErrorGetRules,
+    ErrorCreateRule,
+    ErrorDeleteRule,
+}
+
+impl Display for ContentFilterServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentFilterServiceError::ErrorInvalidPattern(msg) => {
+                write!(f, "Invalid pattern: {}", msg)
+            }
+            ContentFilterServiceError::ErrorCheckContent => write!(f, "Failed to check content"),
+            ContentFilterServiceError::ErrorReportContent => {
+                write!(f, "Failed to report filtered content")
+            }
+            ContentFilterServiceError::ErrorGetRules => write!(f, "Failed to get filter rules"),
+            ContentFilterServiceError::ErrorCreateRule => write!(f, "Failed to create filter rule"),
+            ContentFilterServiceError::ErrorDeleteRule => write!(f, "Failed to delete filter rule"),
+        }
+    }
+}
+
+pub trait ContentFilterService: Send + Sync {
+    /// Runs new or edited content through the blocked words, link, duplicate and admin rules
+    fn check_content(
+        &self,
+        content: &FilteredContent,
+    ) -> Result<ContentFilterVerdict, ContentFilterServiceError>;
+
+    /// Files a report for stored content the filter held or flagged
+    fn report_content(
+        &self,
+        target: ReportTarget,
+        verdict: &ContentFilterVerdict,
+    ) -> Result<(), ContentFilterServiceError>;
+
+    /// The built-in rules in effect
+    fn get_policy(&self) -> ContentFilterPolicy;
+
+    /// Retrieves the admin rules, oldest first
+    fn get_rules(&self) -> Result<Vec<ContentFilterRule>, ContentFilterServiceError>;
+
+    /// Adds an admin rule, the pattern must be a valid regex
+    fn create_rule(
+        &self,
+        pattern: &str,
+        action: FilterAction,
+        description: &str,
+        admin_user_id: i32,
+    ) -> Result<ContentFilterRule, ContentFilterServiceError>;
+
+    /// Removes an admin rule
+    fn delete_rule(&self, rule_id: i32) -> Result<usize, ContentFilterServiceError>;
+}
+
+pub struct BasedContentFilterService {
+    rule_repository: Arc<dyn ContentFilterRuleRepository>,
+    post_repository: Arc<PostRepositoryWithError>,
+    comment_repository: Arc<CommentRepositoryWithError>,
+    report_service: Arc<dyn ReportService>,
+    policy: ContentFilterPolicy,
+    blocked_words_regex: Option<Regex>,
+}
+
+impl BasedContentFilterService {
+    pub fn new(
+        rule_repository: Arc<dyn ContentFilterRuleRepository>,
+        post_repository: Arc<PostRepositoryWithError>,
+        comment_repository: Arc<CommentRepositoryWithError>,
+        report_service: Arc<dyn ReportService>,
+        policy: ContentFilterPolicy,
+    ) -> Self {
+        let blocked_words_regex = (!policy.blocked_words.is_empty()).then(|| {
+            let words = policy
+                .blocked_words
+                .iter()
+                .map(|word| regex::escape(word))
+                .collect::<Vec<_>>()
+                .join("|");
+
+            Regex::new(&format!(r"(?i)\b(?:{})\b", words)).expect("blocked words are escaped")
+        });
+
+        Self {
+            rule_repository,
+            post_repository,
+            comment_repository,
+            report_service,
+            policy,
+            blocked_words_regex,
+        }
+    }
+
+    fn compile_rule(pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .size_limit(RULE_SIZE_LIMIT)
+            .build()
+    }
+
+    fn count_duplicates(
+        &self,
+        content: &FilteredContent,
+    ) -> Result<i64, ContentFilterServiceError> {
+        let created_since =
+            Utc::now().naive_utc() - Duration::minutes(self.policy.duplicate_window_minutes);
+
+        let result = match content.target {
+            FilteredTarget::NewPost => self.post_repository.count_duplicate_posts(
+                content.author.id,
+                content.body,
+                created_since,
+                None,
+            ),
+            FilteredTarget::EditedPost(post_id) => self.post_repository.count_duplicate_posts(
+                content.author.id,
+                content.body,
+                created_since,
+                Some(post_id),
+            ),
+            FilteredTarget::NewComment => self.comment_repository.count_duplicate_comments(
+                content.author.id,
+                content.body,
+                created_since,
+            ),
+        };
+
+        result.map_err(|_| ContentFilterServiceError::ErrorCheckContent)
+    }
+}
+
+impl ContentFilterService for BasedContentFilterService {
+    fn check_content(
+        &self,
+        content: &FilteredContent,
+    ) -> Result<ContentFilterVerdict, ContentFilterServiceError> {
+        let mut verdict = ContentFilterVerdict::default();
+        let text = format!("{}\n{}", content.title, content.body);
+
+        if let Some(blocked_words_regex) = &self.blocked_words_regex {
+            if let Some(found) = blocked_words_regex.find(&text) {
+                verdict.hits.push(FilterHit {
+                    action: self.policy.blocked_words_action,
+                    reason: format!("contains the blocked word \"{}\"", found.as_str()),
+                });
+            }
+        }
+
+        if self.policy.new_account_days > 0 {
+            let account_age = Utc::now().naive_utc() - content.author.created_at;
+            let links = LINK_REGEX.find_iter(&text).count();
+
+            if account_age < Duration::days(self.policy.new_account_days)
+                && links > self.policy.new_account_max_links
+            {
+                verdict.hits.push(FilterHit {
+                    action: self.policy.new_account_links_action,
+                    reason: format!(
+                        "new accounts can post at most {} links, found {}",
+                        self.policy.new_account_max_links, links
+                    ),
+                });
+            }
+        }
+
+        if self.policy.duplicate_window_minutes > 0 && self.count_duplicates(content)? > 0 {
+            verdict.hits.push(FilterHit {
+                action: self.policy.duplicate_action,
+                reason: format!(
+                    "same content was posted in the last {} minutes",
+                    self.policy.duplicate_window_minutes
+                ),
+            });
+        }
+
+        let rules = self
+            .rule_repository
+            .get_content_filter_rules()
+            .map_err(|_| ContentFilterServiceError::ErrorCheckContent)?;
+
+        for rule in rules {
+            // patterns are checked when they are added, a rule that no longer compiles is skipped
+            let (Some(action), Ok(rule_regex)) = (
+                FilterAction::parse(&rule.action),
+                Self::compile_rule(&rule.pattern),
+            ) else {
+                continue;
+            };
+
+            if rule_regex.is_match(&text) {
+                verdict.hits.push(FilterHit {
+                    action,
+                    reason: match rule.description.is_empty() {
+                        true => format!("matches filter rule #{}", rule.id),
+                        false => rule.description,
+                    },
+                });
+            }
+        }
+
+        Ok(verdict)
+    }
+
+    fn report_content(
+        &self,
+        target: ReportTarget,
+        verdict: &ContentFilterVerdict,
+    ) -> Result<(), ContentFilterServiceError> {
+        let details = match verdict.action() {
+            Some(FilterAction::Hold) => format!("Held: {}", verdict.reasons()),
+            _ => format!("Flagged: {}", verdict.reasons()),
+        };
+
+        self.report_service
+            .create_filter_report(target, &details)
+            .map(|_| ())
+            .map_err(|_| ContentFilterServiceError::ErrorReportContent)
+    }
+
+    fn get_policy(&self) -> ContentFilterPolicy {
+        self.policy.clone()
+    }
+
+    fn get_rules(&self) -> Result<Vec<ContentFilterRule>, ContentFilterServiceError> {
+        self.rule_repository
+            .get_content_filter_rules()
+            .map_err(|_| ContentFilterServiceError::ErrorGetRules)
+    }
+
+    fn create_rule(
+        &self,
+        pattern: &str,
+        action: FilterAction,
+        description: &str,
+        admin_user_id: i32,
+    ) -> Result<ContentFilterRule, ContentFilterServiceError> {
+        Self::compile_rule(pattern)
+            .map_err(|e| ContentFilterServiceError::ErrorInvalidPattern(e.to_string()))?;
+
+        self.rule_repository
+            .create_content_filter_rule(&NewContentFilterRule {
+                pattern,
+                action: action.as_str(),
+                description: description.trim(),
+                created_by_user_id: Some(admin_user_id),
+            })
+            .map_err(|_| ContentFilterServiceError::ErrorCreateRule)
+    }
+
+    fn delete_rule(&self, rule_id: i32) -> Result<usize, ContentFilterServiceError> {
+        match self.rule_repository.delete_content_filter_rule(rule_id) {
+            Ok(0) | Err(_) => Err(ContentFilterServiceError::ErrorDeleteRule),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+}
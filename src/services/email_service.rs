@@ -38,17 +38,28 @@ impl BasedEmailService {
 
 impl EmailService for BasedEmailService {
     fn send_email(&self, to: &str, header: &str, body: &str) -> Result<(), EmailServiceError> {
-        let smtp_host = std::env::var("SMTP_HOST").expect("SMTP_HOST must be set");
-        let smtp_email = std::env::var("SMTP_EMAIL").expect("SMTP_EMAIL must be set");
-        let smptp_password = std::env::var("SMPTP_PASSWORD").expect("SMPTP_PASSWORD must be set");
+        let smtp_env = |key: &str| {
+            std::env::var(key)
+                .map_err(|_| EmailServiceError::ErrorEmail(format!("{} must be set", key)))
+        };
+
+        let smtp_host = smtp_env("SMTP_HOST")?;
+        let smtp_email = smtp_env("SMTP_EMAIL")?;
+        let smptp_password = smtp_env("SMPTP_PASSWORD")?;
 
         let email = Message::builder()
-            .from(smtp_email.parse().unwrap())
-            .to(to.parse().unwrap())
+            .from(
+                smtp_email
+                    .parse()
+                    .map_err(|e| EmailServiceError::ErrorEmail(format!("invalid sender: {}", e)))?,
+            )
+            .to(to
+                .parse()
+                .map_err(|e| EmailServiceError::ErrorEmail(format!("invalid recipient: {}", e)))?)
             .subject(header)
             .header(ContentType::TEXT_PLAIN)
             .body(String::from(body))
-            .unwrap();
+            .map_err(|e| EmailServiceError::ErrorEmail(e.to_string()))?;
 
         let creds = Credentials::new(smtp_email.to_owned(), smptp_password.to_owned());
 
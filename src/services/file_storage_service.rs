@@ -0,0 +1,459 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::ErrorKind,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Where uploaded files live
+///
+/// Keys are relative paths like `avatar-<id>-256.jpg` or `attachments/<id>.png`,
+/// every replica resolves the same key to the same object.
+/// All methods block, call them from `web::block`.
+pub trait FileStorage: Send + Sync {
+    /// Stores a file, replacing any file with the same key
+    fn put_file(
+        &self,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+    ) -> Result<(), FileStorageError>;
+
+    fn get_file(&self, key: &str) -> Result<Vec<u8>, FileStorageError>;
+
+    /// Deletes a file, deleting a missing file is not an error
+    fn delete_file(&self, key: &str) -> Result<(), FileStorageError>;
+
+    fn file_exists(&self, key: &str) -> Result<bool, FileStorageError>;
+
+    /// URL browsers load the file from
+    fn public_url(&self, key: &str) -> String;
+
+    /// Reverses `public_url`, `None` for URLs not served by this storage
+    fn key_from_public_url(&self, url: &str) -> Option<String>;
+}
+
+#[derive(Debug)]
+pub enum FileStorageError {
+    InvalidKey(String),
+    NotFound(String),
+    Io(String),
+    Backend(String),
+}
+
+impl Display for FileStorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileStorageError::InvalidKey(key) => write!(f, "Invalid file key: {}", key),
+            FileStorageError::NotFound(key) => write!(f, "File not found: {}", key),
+            FileStorageError::Io(msg) => write!(f, "File storage I/O error: {}", msg),
+            FileStorageError::Backend(msg) => write!(f, "File storage backend error: {}", msg),
+        }
+    }
+}
+
+/// Rejects keys that could escape the storage root or need escaping
+pub fn validate_file_key(key: &str) -> Result<(), FileStorageError> {
+    let valid = !key.is_empty()
+        && key.len() <= 255
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+        && key
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+
+    match valid {
+        true => Ok(()),
+        false => Err(FileStorageError::InvalidKey(key.to_string())),
+    }
+}
+
+fn key_after_prefix(url: &str, prefix: &str) -> Option<String> {
+    url.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .filter(|key| validate_file_key(key).is_ok())
+        .map(|key| key.to_string())
+}
+
+/// Files in a local directory, served by `actix_files` under `/static`
+///
+/// Only usable by a single instance, or replicas sharing the directory.
+pub struct LocalFileStorage {
+    root_dir: PathBuf,
+    public_url_prefix: String,
+}
+
+impl LocalFileStorage {
+    pub fn new(root_dir: &str, public_url_prefix: &str) -> Self {
+        Self {
+            root_dir: PathBuf::from(root_dir),
+            public_url_prefix: public_url_prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn file_path(&self, key: &str) -> Result<PathBuf, FileStorageError> {
+        validate_file_key(key)?;
+
+        Ok(self.root_dir.join(key))
+    }
+}
+
+impl FileStorage for LocalFileStorage {
+    fn put_file(
+        &self,
+        key: &str,
+        content: &[u8],
+        _content_type: &str,
+    ) -> Result<(), FileStorageError> {
+        let file_path = self.file_path(key)?;
+        let io_error = |e: std::io::Error| FileStorageError::Io(e.to_string());
+
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        // readers never see a half written file
+        let tmp_file_path =
+            file_path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp_file_path, content).map_err(io_error)?;
+        std::fs::rename(&tmp_file_path, &file_path).map_err(|e| {
+            let _ = std::fs::remove_file(&tmp_file_path);
+            io_error(e)
+        })
+    }
+
+    fn get_file(&self, key: &str) -> Result<Vec<u8>, FileStorageError> {
+        std::fs::read(self.file_path(key)?).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => FileStorageError::NotFound(key.to_string()),
+            _ => FileStorageError::Io(e.to_string()),
+        })
+    }
+
+    fn delete_file(&self, key: &str) -> Result<(), FileStorageError> {
+        match std::fs::remove_file(self.file_path(key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(FileStorageError::Io(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn file_exists(&self, key: &str) -> Result<bool, FileStorageError> {
+        Ok(self.file_path(key)?.is_file())
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url_prefix, key)
+    }
+
+    fn key_from_public_url(&self, url: &str) -> Option<String> {
+        key_after_prefix(url, &self.public_url_prefix)
+    }
+}
+
+/// Objects in an S3 compatible bucket (AWS S3, MinIO, R2, ...)
+///
+/// Requests use path style addressing, `{endpoint}/{bucket}/{key}`, which every
+/// compatible server supports, and are signed with AWS Signature Version 4.
+pub struct S3FileStorage {
+    endpoint: reqwest::Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    public_base_url: String,
+    // created on first use, a blocking client can not be built inside the async runtime
+    client: OnceLock<reqwest::blocking::Client>,
+}
+
+impl S3FileStorage {
+    /// `public_base_url` defaults to `{endpoint}/{bucket}`, set it for a CDN or a public bucket domain
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+        public_base_url: Option<&str>,
+    ) -> Result<Self, FileStorageError> {
+        let endpoint = reqwest::Url::parse(endpoint)
+            .map_err(|e| FileStorageError::Backend(format!("invalid S3 endpoint: {}", e)))?;
+
+        let public_base_url = public_base_url
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or(format!(
+                "{}/{}",
+                endpoint.as_str().trim_end_matches('/'),
+                bucket
+            ));
+
+        Ok(Self {
+            endpoint,
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            public_base_url,
+            client: OnceLock::new(),
+        })
+    }
+
+    fn client(&self) -> &reqwest::blocking::Client {
+        self.client.get_or_init(reqwest::blocking::Client::new)
+    }
+
+    fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        content: Option<(&[u8], &str)>,
+    ) -> Result<reqwest::blocking::Response, FileStorageError> {
+        validate_file_key(key)?;
+
+        let canonical_uri = uri_encode_path(&format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket,
+            key
+        ));
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&canonical_uri);
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let payload_hash = hex_encode(&Sha256::digest(content.map_or(&[][..], |(b, _)| b)));
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some((_, content_type)) = content {
+            headers.push(("content-type", content_type));
+        }
+
+        let authorization = aws_sigv4_authorization(
+            &S3Credentials {
+                access_key_id: &self.access_key_id,
+                secret_access_key: &self.secret_access_key,
+                region: &self.region,
+            },
+            method.as_str(),
+            &canonical_uri,
+            &headers,
+            &payload_hash,
+        );
+
+        let mut request = self
+            .client()
+            .request(method, url)
+            .header("authorization", authorization);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, *value);
+        }
+        if let Some((bytes, _)) = content {
+            request = request.body(bytes.to_vec());
+        }
+
+        request
+            .send()
+            .map_err(|e| FileStorageError::Backend(e.to_string()))
+    }
+
+    fn check_status(
+        key: &str,
+        response: reqwest::blocking::Response,
+    ) -> Result<reqwest::blocking::Response, FileStorageError> {
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            reqwest::StatusCode::NOT_FOUND => Err(FileStorageError::NotFound(key.to_string())),
+            status => Err(FileStorageError::Backend(format!(
+                "{} for {}: {}",
+                status,
+                key,
+                response.text().unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+impl FileStorage for S3FileStorage {
+    fn put_file(
+        &self,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+    ) -> Result<(), FileStorageError> {
+        let response = self.send(reqwest::Method::PUT, key, Some((content, content_type)))?;
+        Self::check_status(key, response)?;
+
+        Ok(())
+    }
+
+    fn get_file(&self, key: &str) -> Result<Vec<u8>, FileStorageError> {
+        let response = self.send(reqwest::Method::GET, key, None)?;
+
+        Self::check_status(key, response)?
+            .bytes()
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| FileStorageError::Backend(e.to_string()))
+    }
+
+    fn delete_file(&self, key: &str) -> Result<(), FileStorageError> {
+        let response = self.send(reqwest::Method::DELETE, key, None)?;
+
+        match Self::check_status(key, response) {
+            Ok(_) | Err(FileStorageError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn file_exists(&self, key: &str) -> Result<bool, FileStorageError> {
+        let response = self.send(reqwest::Method::HEAD, key, None)?;
+
+        match Self::check_status(key, response) {
+            Ok(_) => Ok(true),
+            Err(FileStorageError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, key)
+    }
+
+    fn key_from_public_url(&self, url: &str) -> Option<String> {
+        key_after_prefix(url, &self.public_base_url)
+    }
+}
+
+/// Builds the storage selected by `FILE_STORAGE` (`local` or `s3`)
+///
+/// `local` stores into `static_file_dir_path`, `s3` reads the `S3_*` variables.
+pub fn file_storage_from_env(
+    static_file_dir_path: &str,
+) -> Result<Box<dyn FileStorage>, FileStorageError> {
+    let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+    match env("FILE_STORAGE").as_deref() {
+        None | Some("local") => Ok(Box::new(LocalFileStorage::new(
+            static_file_dir_path,
+            "/static",
+        ))),
+
+        Some("s3") => {
+            let required = |key: &str| {
+                env(key).ok_or_else(|| FileStorageError::Backend(format!("env {} is not set", key)))
+            };
+
+            Ok(Box::new(S3FileStorage::new(
+                &required("S3_ENDPOINT")?,
+                &required("S3_BUCKET")?,
+                &env("S3_REGION").unwrap_or("us-east-1".to_string()),
+                &required("S3_ACCESS_KEY_ID")?,
+                &required("S3_SECRET_ACCESS_KEY")?,
+                env("S3_PUBLIC_URL").as_deref(),
+            )?))
+        }
+
+        Some(other) => Err(FileStorageError::Backend(format!(
+            "unknown FILE_STORAGE {:?}, expected local or s3",
+            other
+        ))),
+    }
+}
+
+pub struct S3Credentials<'a> {
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+    pub region: &'a str,
+}
+
+/// `Authorization` header value of an AWS Signature Version 4 signed S3 request
+///
+/// `headers` are the signed headers with lowercase names and must include `host` and
+/// `x-amz-date` (`%Y%m%dT%H%M%SZ`). The request has no query string.
+pub fn aws_sigv4_authorization(
+    credentials: &S3Credentials,
+    method: &str,
+    canonical_uri: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let mut headers = headers.to_vec();
+    headers.sort_by_key(|(name, _)| *name);
+
+    let amz_date = headers
+        .iter()
+        .find(|(name, _)| *name == "x-amz-date")
+        .map(|(_, value)| *value)
+        .unwrap_or_default();
+    let date = &amz_date[..amz_date.len().min(8)];
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n\n{canonical_headers}\n{signed_headers}\n{payload_hash}"
+    );
+
+    let scope = format!("{}/{}/s3/aws4_request", date, credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex_encode(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = [credentials.region, "s3", "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    );
+    let signature = hex_encode(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent encodes everything but unreserved characters and `/`
+fn uri_encode_path(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
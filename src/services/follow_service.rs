@@ -0,0 +1,257 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::{
+        follow::{FollowCounts, ListFollowResult},
+        post::ListPostResult,
+        user::user_to_user_public,
+    },
+    repositories::{
+        follow_repository::FollowRepository, post_repository::PostRepositoryWithError,
+        user_repository::UserRepositoryWithError,
+    },
+    utils::pagination::QueryPagination,
+};
+
+#[derive(Debug)]
+pub enum FollowServiceError {
+    ErrorFollowYourself,
+    ErrorUserNotFound,
+    ErrorPostNotFound,
+    ErrorFollowUser,
+    ErrorUnfollowUser,
+    ErrorGetFollows,
+    ErrorGetPosts,
+    ErrorSubscribePost,
+    ErrorUnsubscribePost,
+}
+
+impl Display for FollowServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FollowServiceError::ErrorFollowYourself => write!(f, "You can not follow yourself"),
+            FollowServiceError::ErrorUserNotFound => write!(f, "User not found"),
+            FollowServiceError::ErrorPostNotFound => write!(f, "Post not found"),
+            FollowServiceError::ErrorFollowUser => write!(f, "Failed to follow user"),
+            FollowServiceError::ErrorUnfollowUser => write!(f, "Failed to unfollow user"),
+            FollowServiceError::ErrorGetFollows => write!(f, "Failed to get follows"),
+            FollowServiceError::ErrorGetPosts => write!(f, "Failed to get followed posts"),
+            FollowServiceError::ErrorSubscribePost => write!(f, "Failed to subscribe to post"),
+            FollowServiceError::ErrorUnsubscribePost => {
+                write!(f, "Failed to unsubscribe from post")
+            }
+        }
+    }
+}
+
+pub trait FollowService: Send + Sync {
+    /// Makes a user follow another, the posts of the followed user show up on the Following tab
+    fn follow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<(), FollowServiceError>;
+
+    /// Stops a user from following another
+    fn unfollow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<usize, FollowServiceError>;
+
+    /// Whether a user follows another
+    fn is_following(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<bool, FollowServiceError>;
+
+    /// Counts the followers of a user and the users it follows
+    fn get_follow_counts(&self, user_id: i32) -> Result<FollowCounts, FollowServiceError>;
+
+    /// Retrieves a page of the users following a user
+    fn get_followers(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListFollowResult, FollowServiceError>;
+
+    /// Retrieves a page of the users a user follows
+    fn get_following(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListFollowResult, FollowServiceError>;
+
+    /// Retrieves a page of the posts of the users a user follows, newest first
+    fn get_followed_posts(
+        &self,
+        follower_user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, FollowServiceError>;
+
+    /// Subscribes a user to the new comments of a post
+    fn subscribe_post(&self, user_id: i32, post_id: i32) -> Result<(), FollowServiceError>;
+
+    /// Stops the notifications of a post for a user
+    fn unsubscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, FollowServiceError>;
+
+    /// Whether a user is subscribed to a post
+    fn is_subscribed(&self, user_id: i32, post_id: i32) -> Result<bool, FollowServiceError>;
+}
+
+pub struct BasedFollowService {
+    follow_repository: Arc<dyn FollowRepository>,
+    user_repository: Arc<UserRepositoryWithError>,
+    post_repository: Arc<PostRepositoryWithError>,
+}
+
+impl BasedFollowService {
+    pub fn new(
+        follow_repository: Arc<dyn FollowRepository>,
+        user_repository: Arc<UserRepositoryWithError>,
+        post_repository: Arc<PostRepositoryWithError>,
+    ) -> Self {
+        Self {
+            follow_repository,
+            user_repository,
+            post_repository,
+        }
+    }
+}
+
+impl FollowService for BasedFollowService {
+    fn follow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<(), FollowServiceError> {
+        if follower_user_id == followed_user_id {
+            return Err(FollowServiceError::ErrorFollowYourself);
+        }
+
+        self.user_repository
+            .get_user_by_id(followed_user_id)
+            .map_err(|_| FollowServiceError::ErrorUserNotFound)?;
+
+        self.follow_repository
+            .follow_user(follower_user_id, followed_user_id)
+            .map(|_| ())
+            .map_err(|_| FollowServiceError::ErrorFollowUser)
+    }
+
+    fn unfollow_user(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<usize, FollowServiceError> {
+        match self
+            .follow_repository
+            .unfollow_user(follower_user_id, followed_user_id)
+        {
+            Ok(0) | Err(_) => Err(FollowServiceError::ErrorUnfollowUser),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn is_following(
+        &self,
+        follower_user_id: i32,
+        followed_user_id: i32,
+    ) -> Result<bool, FollowServiceError> {
+        self.follow_repository
+            .is_following(follower_user_id, followed_user_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)
+    }
+
+    fn get_follow_counts(&self, user_id: i32) -> Result<FollowCounts, FollowServiceError> {
+        let followers = self
+            .follow_repository
+            .count_followers(user_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+        let following = self
+            .follow_repository
+            .count_following(user_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+
+        Ok(FollowCounts {
+            followers,
+            following,
+        })
+    }
+
+    fn get_followers(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListFollowResult, FollowServiceError> {
+        let followers = self
+            .follow_repository
+            .get_followers(user_id, pagination)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+        let total = self
+            .follow_repository
+            .count_followers(user_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+
+        Ok(ListFollowResult {
+            users: followers.iter().map(user_to_user_public).collect(),
+            total,
+        })
+    }
+
+    fn get_following(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListFollowResult, FollowServiceError> {
+        let following = self
+            .follow_repository
+            .get_following(user_id, pagination)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+        let total = self
+            .follow_repository
+            .count_following(user_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)?;
+
+        Ok(ListFollowResult {
+            users: following.iter().map(user_to_user_public).collect(),
+            total,
+        })
+    }
+
+    fn get_followed_posts(
+        &self,
+        follower_user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, FollowServiceError> {
+        self.follow_repository
+            .get_followed_posts_with_user(follower_user_id, pagination)
+            .map_err(|_| FollowServiceError::ErrorGetPosts)
+    }
+
+    fn subscribe_post(&self, user_id: i32, post_id: i32) -> Result<(), FollowServiceError> {
+        self.post_repository
+            .get_post(post_id)
+            .map_err(|_| FollowServiceError::ErrorPostNotFound)?;
+
+        self.follow_repository
+            .subscribe_post(user_id, post_id)
+            .map(|_| ())
+            .map_err(|_| FollowServiceError::ErrorSubscribePost)
+    }
+
+    fn unsubscribe_post(&self, user_id: i32, post_id: i32) -> Result<usize, FollowServiceError> {
+        match self.follow_repository.unsubscribe_post(user_id, post_id) {
+            Ok(0) | Err(_) => Err(FollowServiceError::ErrorUnsubscribePost),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn is_subscribed(&self, user_id: i32, post_id: i32) -> Result<bool, FollowServiceError> {
+        self.follow_repository
+            .is_subscribed(user_id, post_id)
+            .map_err(|_| FollowServiceError::ErrorGetFollows)
+    }
+}
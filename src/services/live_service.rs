@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tokio::sync::broadcast;
+
+use crate::entities::live::{LiveChannel, LiveEvent};
+
+pub trait LiveService: Send + Sync {
+    /// Sends an event to everyone listening on a channel, it is dropped when nobody listens
+    fn publish(&self, channel: LiveChannel, event: LiveEvent);
+
+    /// Starts listening on a channel, the listener stops when the receiver is dropped
+    fn subscribe(&self, channel: LiveChannel) -> broadcast::Receiver<LiveEvent>;
+}
+
+/// Broadcast hub of a single process, listeners on other instances do not see the events
+pub struct InMemoryLiveService {
+    channels: Mutex<HashMap<LiveChannel, broadcast::Sender<LiveEvent>>>,
+    capacity: usize,
+}
+
+impl InMemoryLiveService {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            channels: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+}
+
+impl LiveService for InMemoryLiveService {
+    fn publish(&self, channel: LiveChannel, event: LiveEvent) {
+        let mut channels = self.channels.lock().unwrap();
+
+        if let Some(sender) = channels.get(&channel) {
+            // sending only fails once every receiver is gone
+            if sender.send(event).is_err() {
+                channels.remove(&channel);
+            }
+        }
+    }
+
+    fn subscribe(&self, channel: LiveChannel) -> broadcast::Receiver<LiveEvent> {
+        let mut channels = self.channels.lock().unwrap();
+
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+
+        channels
+            .entry(channel)
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .subscribe()
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::{
+        ban::ActiveBan,
+        live::{LiveChannel, LiveEvent},
+        message::{
+            ConversationSummary, ListConversationResult, ListMessageResult, MessagePublic,
+            CONVERSATION_MAX_MEMBERS,
+        },
+        user::{user_to_user_public, UserPublic},
+    },
+    models::{Conversation, Message, NewConversation, NewMessage, User},
+    repositories::{
+        message_repository::MessageRepository, user_repository::UserRepositoryWithError,
+    },
+    services::live_service::LiveService,
+    utils::pagination::QueryPagination,
+};
+
+#[derive(Debug)]
+pub enum MessageServiceError {
+    ErrorNoRecipients,
+    ErrorMessageYourself,
+    ErrorTooManyMembers,
+    ErrorRecipientNotFound(i32),
+    ErrorBlocked,
+    ErrorNotMember,
+    ErrorUserBanned(ActiveBan),
+    ErrorSendMessage,
+    ErrorGetConversations,
+    ErrorGetMessages,
+    ErrorDeleteMessage,
+    ErrorLeaveConversation,
+    ErrorBlockUser,
+    ErrorUnblockUser,
+}
+
+impl Display for MessageServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageServiceError::ErrorNoRecipients => write!(f, "Add at least one recipient"),
+            MessageServiceError::ErrorMessageYourself => write!(f, "You can not message yourself"),
+            MessageServiceError::ErrorTooManyMembers => write!(
+                f,
+                "A conversation has at most {} members",
+                CONVERSATION_MAX_MEMBERS
+            ),
+            MessageServiceError::ErrorRecipientNotFound(user_id) => {
+                write!(f, "There is no user #{}", user_id)
+            }
+            MessageServiceError::ErrorBlocked => {
+                write!(
+                    f,
+                    "Someone in this conversation does not accept your messages"
+                )
+            }
+            MessageServiceError::ErrorNotMember => write!(f, "Conversation not found"),
+            MessageServiceError::ErrorUserBanned(ban) => write!(f, "{}", ban),
+            MessageServiceError::ErrorSendMessage => write!(f, "Failed to send message"),
+            MessageServiceError::ErrorGetConversations => write!(f, "Failed to get conversations"),
+            MessageServiceError::ErrorGetMessages => write!(f, "Failed to get messages"),
+            MessageServiceError::ErrorDeleteMessage => write!(f, "Failed to delete message"),
+            MessageServiceError::ErrorLeaveConversation => {
+                write!(f, "Failed to leave conversation")
+            }
+            MessageServiceError::ErrorBlockUser => write!(f, "Failed to block user"),
+            MessageServiceError::ErrorUnblockUser => write!(f, "Failed to unblock user"),
+        }
+    }
+}
+
+pub trait MessageService: Send + Sync {
+    /// Sends a first message to one or more users, a single recipient without a title reuses
+    /// the one-to-one conversation the two already have
+    fn start_conversation(
+        &self,
+        sender_user_id: i32,
+        recipient_user_ids: &[i32],
+        title: &str,
+        body: &str,
+    ) -> Result<Conversation, MessageServiceError>;
+
+    /// Adds a message to a conversation the sender is a member of
+    fn send_message(
+        &self,
+        conversation_id: i32,
+        sender_user_id: i32,
+        body: &str,
+    ) -> Result<Message, MessageServiceError>;
+
+    /// Retrieves a page of the conversations of a user, most recent first
+    fn get_inbox(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListConversationResult, MessageServiceError>;
+
+    /// Retrieves a conversation the user is a member of
+    fn get_conversation(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<ConversationSummary, MessageServiceError>;
+
+    /// Retrieves a page of messages, the first page holds the newest ones
+    fn get_messages(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListMessageResult, MessageServiceError>;
+
+    /// Marks the messages of a conversation read
+    fn mark_conversation_read(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<(), MessageServiceError>;
+
+    /// Counts the unread messages of a user over every conversation
+    fn count_unread_messages(&self, user_id: i32) -> Result<i64, MessageServiceError>;
+
+    /// Soft deletes a message of its author
+    fn delete_message(
+        &self,
+        conversation_id: i32,
+        message_id: i32,
+        user_id: i32,
+    ) -> Result<usize, MessageServiceError>;
+
+    /// Takes a user out of a group conversation
+    fn leave_conversation(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<usize, MessageServiceError>;
+
+    /// Stops a user from messaging the blocker
+    fn block_user(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<(), MessageServiceError>;
+
+    /// Lets a blocked user message the blocker again
+    fn unblock_user(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<usize, MessageServiceError>;
+
+    /// Lists the users a user blocked
+    fn get_blocked_users(&self, user_id: i32) -> Result<Vec<UserPublic>, MessageServiceError>;
+
+    /// Whether a user blocked another
+    fn is_blocked(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<bool, MessageServiceError>;
+}
+
+pub struct BasedMessageService {
+    message_repository: Arc<dyn MessageRepository>,
+    user_repository: Arc<UserRepositoryWithError>,
+    live_service: Arc<dyn LiveService>,
+}
+
+impl BasedMessageService {
+    pub fn new(
+        message_repository: Arc<dyn MessageRepository>,
+        user_repository: Arc<UserRepositoryWithError>,
+        live_service: Arc<dyn LiveService>,
+    ) -> Self {
+        Self {
+            message_repository,
+            user_repository,
+            live_service,
+        }
+    }
+
+    /// Pushes the unread message count of each user to their open pages
+    fn publish_unread_counts(&self, user_ids: &[i32]) {
+        for user_id in user_ids {
+            match self.count_unread_messages(*user_id) {
+                Ok(count) => self.live_service.publish(
+                    LiveChannel::User(*user_id),
+                    LiveEvent::UnreadMessages { count },
+                ),
+                Err(e) => println!("failed to count unread messages of {}: {}", user_id, e),
+            }
+        }
+    }
+
+    /// Refuses messages by banned users
+    fn check_user_not_banned(&self, user_id: i32) -> Result<User, MessageServiceError> {
+        let user = self
+            .user_repository
+            .get_user_by_id(user_id)
+            .map_err(|_| MessageServiceError::ErrorSendMessage)?;
+
+        match ActiveBan::of_user(&user) {
+            Some(ban) => Err(MessageServiceError::ErrorUserBanned(ban)),
+            None => Ok(user),
+        }
+    }
+
+    /// Refuses messages to users who blocked the sender
+    fn check_not_blocked(
+        &self,
+        sender_user_id: i32,
+        member_user_ids: &[i32],
+    ) -> Result<(), MessageServiceError> {
+        let blocker_ids = self
+            .message_repository
+            .get_blockers_of(sender_user_id, member_user_ids)
+            .map_err(|_| MessageServiceError::ErrorSendMessage)?;
+
+        match blocker_ids.is_empty() {
+            true => Ok(()),
+            false => Err(MessageServiceError::ErrorBlocked),
+        }
+    }
+
+    /// Members of the conversations who did not leave, by conversation
+    fn get_members(
+        &self,
+        conversation_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<UserPublic>>, MessageServiceError> {
+        let members = self
+            .message_repository
+            .get_conversation_members(conversation_ids)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?;
+
+        let mut members_by_conversation: HashMap<i32, Vec<UserPublic>> = HashMap::new();
+        for (member, user) in members {
+            members_by_conversation
+                .entry(member.conversation_id)
+                .or_default()
+                .push(user_to_user_public(&user));
+        }
+
+        Ok(members_by_conversation)
+    }
+
+    /// Refuses users who are not, or no longer, members of the conversation
+    fn check_member(&self, conversation_id: i32, user_id: i32) -> Result<(), MessageServiceError> {
+        match self
+            .message_repository
+            .get_conversation_member(conversation_id, user_id)
+        {
+            Ok(Some(member)) if member.left_at.is_none() => Ok(()),
+            Ok(_) => Err(MessageServiceError::ErrorNotMember),
+            Err(_) => Err(MessageServiceError::ErrorGetMessages),
+        }
+    }
+}
+
+impl MessageService for BasedMessageService {
+    fn start_conversation(
+        &self,
+        sender_user_id: i32,
+        recipient_user_ids: &[i32],
+        title: &str,
+        body: &str,
+    ) -> Result<Conversation, MessageServiceError> {
+        if recipient_user_ids.is_empty() {
+            return Err(MessageServiceError::ErrorNoRecipients);
+        }
+        if recipient_user_ids.contains(&sender_user_id) {
+            return Err(MessageServiceError::ErrorMessageYourself);
+        }
+        if recipient_user_ids.len() + 1 > CONVERSATION_MAX_MEMBERS {
+            return Err(MessageServiceError::ErrorTooManyMembers);
+        }
+
+        self.check_user_not_banned(sender_user_id)?;
+        for recipient_user_id in recipient_user_ids {
+            self.user_repository
+                .get_user_by_id(*recipient_user_id)
+                .map_err(|_| MessageServiceError::ErrorRecipientNotFound(*recipient_user_id))?;
+        }
+        self.check_not_blocked(sender_user_id, recipient_user_ids)?;
+
+        let title = title.trim();
+        let is_group = recipient_user_ids.len() > 1 || !title.is_empty();
+
+        let existing_conversation = match is_group {
+            true => None,
+            false => self
+                .message_repository
+                .find_direct_conversation(sender_user_id, recipient_user_ids[0])
+                .map_err(|_| MessageServiceError::ErrorSendMessage)?,
+        };
+
+        let conversation = match existing_conversation {
+            Some(conversation) => conversation,
+            None => {
+                let mut member_ids = vec![sender_user_id];
+                member_ids.extend_from_slice(recipient_user_ids);
+
+                self.message_repository
+                    .create_conversation(
+                        &NewConversation {
+                            title,
+                            is_group,
+                            created_by_user_id: Some(sender_user_id),
+                        },
+                        &member_ids,
+                    )
+                    .map_err(|_| MessageServiceError::ErrorSendMessage)?
+            }
+        };
+
+        self.message_repository
+            .create_message(&NewMessage {
+                conversation_id: conversation.id,
+                user_id: sender_user_id,
+                body,
+            })
+            .map_err(|_| MessageServiceError::ErrorSendMessage)?;
+
+        self.publish_unread_counts(recipient_user_ids);
+
+        Ok(conversation)
+    }
+
+    fn send_message(
+        &self,
+        conversation_id: i32,
+        sender_user_id: i32,
+        body: &str,
+    ) -> Result<Message, MessageServiceError> {
+        self.check_member(conversation_id, sender_user_id)?;
+        self.check_user_not_banned(sender_user_id)?;
+
+        let member_ids: Vec<i32> = self
+            .message_repository
+            .get_conversation_members(&[conversation_id])
+            .map_err(|_| MessageServiceError::ErrorSendMessage)?
+            .into_iter()
+            .map(|(member, _)| member.user_id)
+            .filter(|member_id| *member_id != sender_user_id)
+            .collect();
+        self.check_not_blocked(sender_user_id, &member_ids)?;
+
+        let message = self
+            .message_repository
+            .create_message(&NewMessage {
+                conversation_id,
+                user_id: sender_user_id,
+                body,
+            })
+            .map_err(|_| MessageServiceError::ErrorSendMessage)?;
+
+        self.publish_unread_counts(&member_ids);
+
+        Ok(message)
+    }
+
+    fn get_inbox(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListConversationResult, MessageServiceError> {
+        let conversations = self
+            .message_repository
+            .get_user_conversations(user_id, pagination)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?;
+        let total = self
+            .message_repository
+            .count_user_conversations(user_id)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?;
+
+        let conversation_ids: Vec<i32> = conversations.iter().map(|c| c.id).collect();
+        let mut members = self.get_members(&conversation_ids)?;
+        let last_messages = self
+            .message_repository
+            .get_last_messages(&conversation_ids)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?;
+        let unread_counts: HashMap<i32, i64> = self
+            .message_repository
+            .get_unread_counts(user_id)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?
+            .into_iter()
+            .collect();
+
+        let conversations = conversations
+            .into_iter()
+            .map(|conversation| {
+                let conversation_id = conversation.id;
+                ConversationSummary::new(
+                    conversation,
+                    members.remove(&conversation_id).unwrap_or_default(),
+                    last_messages
+                        .iter()
+                        .find(|message| message.conversation_id == conversation_id),
+                    unread_counts.get(&conversation_id).copied().unwrap_or(0),
+                    user_id,
+                )
+            })
+            .collect();
+
+        Ok(ListConversationResult {
+            conversations,
+            total,
+        })
+    }
+
+    fn get_conversation(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<ConversationSummary, MessageServiceError> {
+        self.check_member(conversation_id, user_id)?;
+
+        let conversation = self
+            .message_repository
+            .get_conversation(conversation_id)
+            .map_err(|_| MessageServiceError::ErrorGetConversations)?;
+        let members = self
+            .get_members(&[conversation_id])?
+            .remove(&conversation_id)
+            .unwrap_or_default();
+
+        Ok(ConversationSummary::new(
+            conversation,
+            members,
+            None,
+            0,
+            user_id,
+        ))
+    }
+
+    fn get_messages(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListMessageResult, MessageServiceError> {
+        self.check_member(conversation_id, user_id)?;
+
+        let mut messages: Vec<MessagePublic> = self
+            .message_repository
+            .get_messages_with_user(conversation_id, pagination)
+            .map_err(|_| MessageServiceError::ErrorGetMessages)?
+            .into_iter()
+            .map(|(mut message, user)| {
+                // the text of a deleted message is gone for everyone
+                if message.deleted_at.is_some() {
+                    message.body.clear();
+                }
+                MessagePublic::new(message, user_to_user_public(&user), user_id)
+            })
+            .collect();
+        messages.reverse();
+
+        let total = self
+            .message_repository
+            .count_messages(conversation_id)
+            .map_err(|_| MessageServiceError::ErrorGetMessages)?;
+
+        Ok(ListMessageResult { messages, total })
+    }
+
+    fn mark_conversation_read(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<(), MessageServiceError> {
+        self.message_repository
+            .mark_conversation_read(conversation_id, user_id)
+            .map_err(|_| MessageServiceError::ErrorGetMessages)?;
+
+        self.publish_unread_counts(&[user_id]);
+
+        Ok(())
+    }
+
+    fn count_unread_messages(&self, user_id: i32) -> Result<i64, MessageServiceError> {
+        self.message_repository
+            .get_unread_counts(user_id)
+            .map(|unread_counts| unread_counts.iter().map(|(_, count)| count).sum())
+            .map_err(|_| MessageServiceError::ErrorGetConversations)
+    }
+
+    fn delete_message(
+        &self,
+        conversation_id: i32,
+        message_id: i32,
+        user_id: i32,
+    ) -> Result<usize, MessageServiceError> {
+        self.check_member(conversation_id, user_id)?;
+
+        match self.message_repository.delete_message(message_id, user_id) {
+            Ok(0) | Err(_) => Err(MessageServiceError::ErrorDeleteMessage),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn leave_conversation(
+        &self,
+        conversation_id: i32,
+        user_id: i32,
+    ) -> Result<usize, MessageServiceError> {
+        // a one-to-one conversation is the only way the two can talk, it is kept
+        match self.message_repository.get_conversation(conversation_id) {
+            Ok(conversation) if conversation.is_group => {}
+            _ => return Err(MessageServiceError::ErrorLeaveConversation),
+        }
+
+        match self
+            .message_repository
+            .leave_conversation(conversation_id, user_id)
+        {
+            Ok(0) | Err(_) => Err(MessageServiceError::ErrorLeaveConversation),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn block_user(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<(), MessageServiceError> {
+        if blocker_user_id == blocked_user_id {
+            return Err(MessageServiceError::ErrorBlockUser);
+        }
+
+        self.message_repository
+            .block_user(blocker_user_id, blocked_user_id)
+            .map(|_| ())
+            .map_err(|_| MessageServiceError::ErrorBlockUser)
+    }
+
+    fn unblock_user(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<usize, MessageServiceError> {
+        match self
+            .message_repository
+            .unblock_user(blocker_user_id, blocked_user_id)
+        {
+            Ok(0) | Err(_) => Err(MessageServiceError::ErrorUnblockUser),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn get_blocked_users(&self, user_id: i32) -> Result<Vec<UserPublic>, MessageServiceError> {
+        self.message_repository
+            .get_blocked_users(user_id)
+            .map(|users| users.iter().map(user_to_user_public).collect())
+            .map_err(|_| MessageServiceError::ErrorGetConversations)
+    }
+
+    fn is_blocked(
+        &self,
+        blocker_user_id: i32,
+        blocked_user_id: i32,
+    ) -> Result<bool, MessageServiceError> {
+        self.message_repository
+            .get_blockers_of(blocked_user_id, &[blocker_user_id])
+            .map(|blocker_ids| !blocker_ids.is_empty())
+            .map_err(|_| MessageServiceError::ErrorGetConversations)
+    }
+}
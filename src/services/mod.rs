@@ -3,3 +3,17 @@ pub mod user_service;
 pub mod token_service;
 pub mod post_service;
 pub mod comment_service;
+pub mod rate_limit_service;
+pub mod oauth_service;
+pub mod account_service;
+pub mod file_storage_service;
+pub mod attachment_service;
+pub mod report_service;
+pub mod audit_service;
+pub mod content_filter_service;
+pub mod sitemap_service;
+pub mod message_service;
+pub mod live_service;
+pub mod follow_service;
+pub mod bookmark_service;
+pub mod notification_service;
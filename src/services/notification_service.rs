@@ -0,0 +1,188 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::{
+        live::{LiveChannel, LiveEvent},
+        notification::{ListNotificationResult, NotificationPublic},
+    },
+    models::{Comment, NewNotification, Notification},
+    repositories::notification_repository::NotificationRepository,
+    services::live_service::LiveService,
+    utils::pagination::QueryPagination,
+};
+
+#[derive(Debug)]
+pub enum NotificationServiceError {
+    ErrorNotificationNotFound,
+    ErrorCreateNotifications,
+    ErrorGetNotifications,
+    ErrorMarkRead,
+}
+
+impl Display for NotificationServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationServiceError::ErrorNotificationNotFound => {
+                write!(f, "Notification not found")
+            }
+            NotificationServiceError::ErrorCreateNotifications => {
+                write!(f, "Failed to create notifications")
+            }
+            NotificationServiceError::ErrorGetNotifications => {
+                write!(f, "Failed to get notifications")
+            }
+            NotificationServiceError::ErrorMarkRead => {
+                write!(f, "Failed to mark notifications read")
+            }
+        }
+    }
+}
+
+pub trait NotificationService: Send + Sync {
+    /// Tells users about a new comment, `recipients` pairs each user with the kind of
+    /// notification they get. The new unread count is pushed to the open pages of each user
+    fn notify_comment(
+        &self,
+        comment: &Comment,
+        recipients: &[(i32, &str)],
+    ) -> Result<usize, NotificationServiceError>;
+
+    /// Retrieves a page of the notifications of a user, most recent first
+    fn get_notifications(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListNotificationResult, NotificationServiceError>;
+
+    /// Counts the unread notifications of a user
+    fn count_unread_notifications(&self, user_id: i32) -> Result<i64, NotificationServiceError>;
+
+    /// Marks a notification of a user read and returns it
+    fn read_notification(
+        &self,
+        user_id: i32,
+        notification_id: i32,
+    ) -> Result<Notification, NotificationServiceError>;
+
+    /// Marks every notification of a user read
+    fn mark_all_read(&self, user_id: i32) -> Result<usize, NotificationServiceError>;
+}
+
+pub struct BasedNotificationService {
+    notification_repository: Arc<dyn NotificationRepository>,
+    live_service: Arc<dyn LiveService>,
+}
+
+impl BasedNotificationService {
+    pub fn new(
+        notification_repository: Arc<dyn NotificationRepository>,
+        live_service: Arc<dyn LiveService>,
+    ) -> Self {
+        Self {
+            notification_repository,
+            live_service,
+        }
+    }
+
+    /// Pushes the unread notification count of a user to their open pages
+    fn publish_unread_count(&self, user_id: i32) {
+        match self.count_unread_notifications(user_id) {
+            Ok(count) => self.live_service.publish(
+                LiveChannel::User(user_id),
+                LiveEvent::UnreadNotifications { count },
+            ),
+            Err(e) => println!("failed to count unread notifications of {}: {}", user_id, e),
+        }
+    }
+}
+
+impl NotificationService for BasedNotificationService {
+    fn notify_comment(
+        &self,
+        comment: &Comment,
+        recipients: &[(i32, &str)],
+    ) -> Result<usize, NotificationServiceError> {
+        if recipients.is_empty() {
+            return Ok(0);
+        }
+
+        let new_notifications: Vec<NewNotification> = recipients
+            .iter()
+            .map(|(user_id, kind)| NewNotification {
+                user_id: *user_id,
+                post_id: comment.post_id,
+                comment_id: comment.id,
+                kind,
+            })
+            .collect();
+
+        let row_affected = self
+            .notification_repository
+            .create_notifications(&new_notifications)
+            .map_err(|_| NotificationServiceError::ErrorCreateNotifications)?;
+
+        for (user_id, _) in recipients {
+            self.publish_unread_count(*user_id);
+        }
+
+        Ok(row_affected)
+    }
+
+    fn get_notifications(
+        &self,
+        user_id: i32,
+        pagination: &QueryPagination,
+    ) -> Result<ListNotificationResult, NotificationServiceError> {
+        let notifications = self
+            .notification_repository
+            .get_notifications(user_id, pagination)
+            .map_err(|_| NotificationServiceError::ErrorGetNotifications)?;
+        let total = self
+            .notification_repository
+            .count_notifications(user_id)
+            .map_err(|_| NotificationServiceError::ErrorGetNotifications)?;
+
+        Ok(ListNotificationResult {
+            notifications: notifications
+                .into_iter()
+                .map(|(notification, post_title, actor_name)| {
+                    NotificationPublic::new(notification, post_title, actor_name)
+                })
+                .collect(),
+            total,
+        })
+    }
+
+    fn count_unread_notifications(&self, user_id: i32) -> Result<i64, NotificationServiceError> {
+        self.notification_repository
+            .count_unread_notifications(user_id)
+            .map_err(|_| NotificationServiceError::ErrorGetNotifications)
+    }
+
+    fn read_notification(
+        &self,
+        user_id: i32,
+        notification_id: i32,
+    ) -> Result<Notification, NotificationServiceError> {
+        let notification = self
+            .notification_repository
+            .mark_notification_read(user_id, notification_id)
+            .map_err(|_| NotificationServiceError::ErrorNotificationNotFound)?;
+
+        self.publish_unread_count(user_id);
+
+        Ok(notification)
+    }
+
+    fn mark_all_read(&self, user_id: i32) -> Result<usize, NotificationServiceError> {
+        let row_affected = self
+            .notification_repository
+            .mark_all_notifications_read(user_id)
+            .map_err(|_| NotificationServiceError::ErrorMarkRead)?;
+
+        self.publish_unread_count(user_id);
+
+        Ok(row_affected)
+    }
+}
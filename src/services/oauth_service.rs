@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use reqwest::{header, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const GITHUB_AUTHORIZATION_ENDPOINT: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN_ENDPOINT: &str = "https://github.com/login/oauth/access_token";
+const GITHUB_USERINFO_ENDPOINT: &str = "https://api.github.com/user";
+const GOOGLE_ISSUER_URL: &str = "https://accounts.google.com";
+
+/// GitHub's API rejects requests without a user agent
+const HTTP_USER_AGENT: &str = "rust-forum";
+
+/// How a provider exposes the signed-in user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthProviderKind {
+    /// Plain OAuth2 with GitHub's `/user` and `/user/emails` API
+    Github,
+    /// OpenID Connect, the user is read from the standard userinfo endpoint
+    OpenIdConnect,
+}
+
+/// Endpoints used by the authorization code flow
+///
+/// Field names follow the OpenID Connect discovery document so it deserializes directly.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthEndpoints {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum OAuthEndpointSource {
+    Static(OAuthEndpoints),
+    /// Read from `<issuer_url>/.well-known/openid-configuration` on first use
+    Discovery {
+        issuer_url: String,
+    },
+}
+
+/// A configured identity provider
+#[derive(Debug, Clone)]
+pub struct OAuthProvider {
+    /// Used in the `/auth/{provider}` routes and stored in `user_identities.provider`
+    pub name: String,
+    pub display_name: String,
+    pub kind: OAuthProviderKind,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: String,
+    pub endpoints: OAuthEndpointSource,
+}
+
+impl OAuthProvider {
+    pub fn github(client_id: &str, client_secret: &str) -> Self {
+        Self {
+            name: "github".to_string(),
+            display_name: "GitHub".to_string(),
+            kind: OAuthProviderKind::Github,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: "read:user user:email".to_string(),
+            endpoints: OAuthEndpointSource::Static(OAuthEndpoints {
+                authorization_endpoint: GITHUB_AUTHORIZATION_ENDPOINT.to_string(),
+                token_endpoint: GITHUB_TOKEN_ENDPOINT.to_string(),
+                userinfo_endpoint: GITHUB_USERINFO_ENDPOINT.to_string(),
+            }),
+        }
+    }
+
+    pub fn google(client_id: &str, client_secret: &str) -> Self {
+        Self::openid_connect(
+            "google",
+            "Google",
+            GOOGLE_ISSUER_URL,
+            client_id,
+            client_secret,
+        )
+    }
+
+    pub fn openid_connect(
+        name: &str,
+        display_name: &str,
+        issuer_url: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            kind: OAuthProviderKind::OpenIdConnect,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: "openid email profile".to_string(),
+            endpoints: OAuthEndpointSource::Discovery {
+                issuer_url: issuer_url.trim_end_matches('/').to_string(),
+            },
+        }
+    }
+}
+
+/// Provider data safe to hand to templates
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct OAuthProviderPublic {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// The signed-in user as reported by the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthUserInfo {
+    /// Stable user id issued by the provider
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum OAuthServiceError {
+    UnknownProvider(String),
+    ErrorDiscovery(String),
+    ErrorTokenExchange(String),
+    ErrorUserInfo(String),
+}
+
+impl Display for OAuthServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OAuthServiceError::UnknownProvider(name) => {
+                write!(f, "Unknown sign in provider: {}", name)
+            }
+            OAuthServiceError::ErrorDiscovery(msg) => {
+                write!(f, "Failed to discover provider configuration: {}", msg)
+            }
+            OAuthServiceError::ErrorTokenExchange(msg) => {
+                write!(f, "Failed to exchange authorization code: {}", msg)
+            }
+            OAuthServiceError::ErrorUserInfo(msg) => {
+                write!(f, "Failed to get user info from provider: {}", msg)
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+pub trait OAuthService: Send + Sync {
+    /// Lists the configured providers
+    fn get_providers(&self) -> Vec<OAuthProviderPublic>;
+
+    /// Builds the provider URL the user is sent to for signing in
+    ///
+    /// # Arguments
+    /// * `provider_name` - The provider name
+    /// * `state` - The opaque value echoed back to the callback, checked against the session
+    async fn get_authorization_url(
+        &self,
+        provider_name: &str,
+        state: &str,
+    ) -> Result<String, OAuthServiceError>;
+
+    /// Exchanges the callback authorization code and fetches the signed-in user
+    ///
+    /// # Arguments
+    /// * `provider_name` - The provider name
+    /// * `code` - The authorization code received on the callback
+    async fn fetch_user_info(
+        &self,
+        provider_name: &str,
+        code: &str,
+    ) -> Result<OAuthUserInfo, OAuthServiceError>;
+}
+
+/// Authorization code flow over `reqwest`
+///
+/// OpenID Connect users are read from the userinfo endpoint with the access token received
+/// directly from the token endpoint, so the ID token signature is not needed.
+pub struct BasedOAuthService {
+    providers: Vec<OAuthProvider>,
+    app_domain_url: String,
+    http_client: reqwest::Client,
+    discovered_endpoints: Mutex<HashMap<String, OAuthEndpoints>>,
+}
+
+#[derive(Deserialize)]
+struct OAuthTokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenIdDiscoveryDocument {
+    issuer: String,
+    #[serde(flatten)]
+    endpoints: OAuthEndpoints,
+}
+
+#[derive(Deserialize)]
+struct GithubUser {
+    id: i64,
+    login: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+impl BasedOAuthService {
+    /// # Arguments
+    /// * `providers` - The providers users can sign in with
+    /// * `app_domain_url` - The public base URL, callbacks go to `<app_domain_url>/auth/{provider}/callback`
+    pub fn new(providers: Vec<OAuthProvider>, app_domain_url: &str) -> Self {
+        Self {
+            providers,
+            app_domain_url: app_domain_url.trim_end_matches('/').to_string(),
+            http_client: reqwest::Client::new(),
+            discovered_endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Configures the providers whose client credentials are set
+    ///
+    /// * GitHub: `OAUTH_GITHUB_CLIENT_ID`, `OAUTH_GITHUB_CLIENT_SECRET`
+    /// * Google: `OAUTH_GOOGLE_CLIENT_ID`, `OAUTH_GOOGLE_CLIENT_SECRET`
+    /// * Any OpenID Connect issuer: `OIDC_ISSUER_URL`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`
+    ///   and optionally `OIDC_PROVIDER_NAME`
+    pub fn from_env() -> Self {
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+        let mut providers = vec![];
+
+        if let (Some(client_id), Some(client_secret)) = (
+            env("OAUTH_GITHUB_CLIENT_ID"),
+            env("OAUTH_GITHUB_CLIENT_SECRET"),
+        ) {
+            providers.push(OAuthProvider::github(&client_id, &client_secret));
+        }
+
+        if let (Some(client_id), Some(client_secret)) = (
+            env("OAUTH_GOOGLE_CLIENT_ID"),
+            env("OAUTH_GOOGLE_CLIENT_SECRET"),
+        ) {
+            providers.push(OAuthProvider::google(&client_id, &client_secret));
+        }
+
+        if let (Some(issuer_url), Some(client_id), Some(client_secret)) = (
+            env("OIDC_ISSUER_URL"),
+            env("OIDC_CLIENT_ID"),
+            env("OIDC_CLIENT_SECRET"),
+        ) {
+            let display_name = env("OIDC_PROVIDER_NAME").unwrap_or("OpenID Connect".to_string());
+
+            providers.push(OAuthProvider::openid_connect(
+                "oidc",
+                &display_name,
+                &issuer_url,
+                &client_id,
+                &client_secret,
+            ));
+        }
+
+        let app_domain_url = env("APP_DOMAIN_URL").unwrap_or("http://localhost:3000".to_string());
+
+        Self::new(providers, &app_domain_url)
+    }
+
+    fn get_provider(&self, provider_name: &str) -> Result<&OAuthProvider, OAuthServiceError> {
+        self.providers
+            .iter()
+            .find(|p| p.name == provider_name)
+            .ok_or_else(|| OAuthServiceError::UnknownProvider(provider_name.to_string()))
+    }
+
+    fn get_redirect_uri(&self, provider: &OAuthProvider) -> String {
+        format!("{}/auth/{}/callback", self.app_domain_url, provider.name)
+    }
+
+    async fn get_endpoints(
+        &self,
+        provider: &OAuthProvider,
+    ) -> Result<OAuthEndpoints, OAuthServiceError> {
+        let issuer_url = match &provider.endpoints {
+            OAuthEndpointSource::Static(endpoints) => return Ok(endpoints.clone()),
+            OAuthEndpointSource::Discovery { issuer_url } => issuer_url,
+        };
+
+        if let Some(endpoints) = self
+            .discovered_endpoints
+            .lock()
+            .unwrap()
+            .get(&provider.name)
+        {
+            return Ok(endpoints.clone());
+        }
+
+        let discovery_error = |e: reqwest::Error| OAuthServiceError::ErrorDiscovery(e.to_string());
+
+        let discovery_document = self
+            .http_client
+            .get(format!("{}/.well-known/openid-configuration", issuer_url))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(discovery_error)?
+            .json::<OpenIdDiscoveryDocument>()
+            .await
+            .map_err(discovery_error)?;
+
+        if discovery_document.issuer.trim_end_matches('/') != issuer_url {
+            return Err(OAuthServiceError::ErrorDiscovery(format!(
+                "issuer mismatch, expected {} got {}",
+                issuer_url, discovery_document.issuer
+            )));
+        }
+
+        self.discovered_endpoints
+            .lock()
+            .unwrap()
+            .insert(provider.name.clone(), discovery_document.endpoints.clone());
+
+        Ok(discovery_document.endpoints)
+    }
+
+    async fn exchange_code(
+        &self,
+        provider: &OAuthProvider,
+        endpoints: &OAuthEndpoints,
+        code: &str,
+    ) -> Result<String, OAuthServiceError> {
+        let token_error = |e: reqwest::Error| OAuthServiceError::ErrorTokenExchange(e.to_string());
+
+        let redirect_uri = self.get_redirect_uri(provider);
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &redirect_uri),
+            ("client_id", &provider.client_id),
+            ("client_secret", &provider.client_secret),
+        ];
+
+        let token_response = self
+            .http_client
+            .post(&endpoints.token_endpoint)
+            .header(header::ACCEPT, "application/json")
+            .form(&params)
+            .send()
+            .await
+            .map_err(token_error)?
+            .json::<OAuthTokenResponse>()
+            .await
+            .map_err(token_error)?;
+
+        // github answers 200 with an error body
+        match token_response {
+            OAuthTokenResponse {
+                access_token: Some(access_token),
+                ..
+            } => Ok(access_token),
+
+            OAuthTokenResponse {
+                error,
+                error_description,
+                ..
+            } => Err(OAuthServiceError::ErrorTokenExchange(
+                error_description
+                    .or(error)
+                    .unwrap_or("no access token returned".to_string()),
+            )),
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        access_token: &str,
+    ) -> Result<T, OAuthServiceError> {
+        let userinfo_error = |e: reqwest::Error| OAuthServiceError::ErrorUserInfo(e.to_string());
+
+        self.http_client
+            .get(url)
+            .bearer_auth(access_token)
+            .header(header::ACCEPT, "application/json")
+            .header(header::USER_AGENT, HTTP_USER_AGENT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(userinfo_error)?
+            .json::<T>()
+            .await
+            .map_err(userinfo_error)
+    }
+
+    async fn fetch_github_user_info(
+        &self,
+        endpoints: &OAuthEndpoints,
+        access_token: &str,
+    ) -> Result<OAuthUserInfo, OAuthServiceError> {
+        let github_user: GithubUser = self
+            .get_json(&endpoints.userinfo_endpoint, access_token)
+            .await?;
+
+        // the profile email may be hidden, the emails API reports the verified primary one
+        let github_emails: Vec<GithubEmail> = self
+            .get_json(
+                &format!("{}/emails", endpoints.userinfo_endpoint),
+                access_token,
+            )
+            .await?;
+
+        let primary_email = github_emails.into_iter().find(|e| e.primary && e.verified);
+
+        Ok(OAuthUserInfo {
+            subject: github_user.id.to_string(),
+            email_verified: primary_email.is_some(),
+            email: primary_email.map(|e| e.email),
+            name: github_user.name.or(Some(github_user.login)),
+        })
+    }
+
+    async fn fetch_openid_user_info(
+        &self,
+        endpoints: &OAuthEndpoints,
+        access_token: &str,
+    ) -> Result<OAuthUserInfo, OAuthServiceError> {
+        let claims: Value = self
+            .get_json(&endpoints.userinfo_endpoint, access_token)
+            .await?;
+
+        let claim = |key: &str| claims.get(key).and_then(|v| v.as_str()).map(String::from);
+
+        let subject = claim("sub").ok_or_else(|| {
+            OAuthServiceError::ErrorUserInfo("userinfo response has no sub claim".to_string())
+        })?;
+
+        // some issuers send the boolean as a string
+        let email_verified = match claims.get("email_verified") {
+            Some(Value::Bool(verified)) => *verified,
+            Some(Value::String(verified)) => verified == "true",
+            _ => false,
+        };
+
+        Ok(OAuthUserInfo {
+            subject,
+            email: claim("email"),
+            email_verified,
+            name: claim("name").or_else(|| claim("preferred_username")),
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl OAuthService for BasedOAuthService {
+    fn get_providers(&self) -> Vec<OAuthProviderPublic> {
+        self.providers
+            .iter()
+            .map(|p| OAuthProviderPublic {
+                name: p.name.clone(),
+                display_name: p.display_name.clone(),
+            })
+            .collect()
+    }
+
+    async fn get_authorization_url(
+        &self,
+        provider_name: &str,
+        state: &str,
+    ) -> Result<String, OAuthServiceError> {
+        let provider = self.get_provider(provider_name)?;
+        let endpoints = self.get_endpoints(provider).await?;
+
+        let authorization_url = Url::parse_with_params(
+            &endpoints.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &provider.client_id),
+                ("redirect_uri", &self.get_redirect_uri(provider)),
+                ("scope", &provider.scopes),
+                ("state", state),
+            ],
+        )
+        .map_err(|e| OAuthServiceError::ErrorDiscovery(e.to_string()))?;
+
+        Ok(authorization_url.to_string())
+    }
+
+    async fn fetch_user_info(
+        &self,
+        provider_name: &str,
+        code: &str,
+    ) -> Result<OAuthUserInfo, OAuthServiceError> {
+        let provider = self.get_provider(provider_name)?;
+        let endpoints = self.get_endpoints(provider).await?;
+
+        let access_token = self.exchange_code(provider, &endpoints, code).await?;
+
+        match provider.kind {
+            OAuthProviderKind::Github => {
+                self.fetch_github_user_info(&endpoints, &access_token).await
+            }
+            OAuthProviderKind::OpenIdConnect => {
+                self.fetch_openid_user_info(&endpoints, &access_token).await
+            }
+        }
+    }
+}
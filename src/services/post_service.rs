@@ -1,18 +1,41 @@
 use std::fmt::{Display, Formatter};
 use std::sync::Arc;
 
+use chrono::NaiveDateTime;
+
 use crate::{
-    entities::post::{ListPostResult, PostPublic},
-    models::Post,
-    repositories::post_repository::PostRepositoryWithError,
+    entities::{
+        ban::ActiveBan,
+        content_filter::{ContentFilterVerdict, FilterAction, FilteredContent, FilteredTarget},
+        live::{LiveChannel, LiveEvent},
+        post::{ListPostResult, PostPublic},
+        report::ReportTarget,
+    },
+    models::{Post, PostRevision, User},
+    repositories::{
+        post_repository::PostRepositoryWithError, user_repository::UserRepositoryWithError,
+    },
+    services::{
+        attachment_service::AttachmentService, content_filter_service::ContentFilterService,
+        live_service::LiveService,
+    },
     utils::pagination::QueryPagination,
 };
 
+#[derive(Debug)]
 pub enum PostServiceError {
     ErrorCreatePost,
     ErrorGetPost,
     ErrorUpdatePost,
     ErrorDeletePost,
+    ErrorGetRevision,
+    ErrorRollbackPost,
+    ErrorRestorePost,
+    ErrorPurgePost,
+    ErrorUserBanned(ActiveBan),
+    ErrorContentRejected(String),
+    ErrorPinPost,
+    ErrorLockPost,
 }
 
 impl Display for PostServiceError {
@@ -22,12 +45,22 @@ impl Display for PostServiceError {
             PostServiceError::ErrorGetPost => write!(f, "Failed to get post"),
             PostServiceError::ErrorUpdatePost => write!(f, "Failed to update post"),
             PostServiceError::ErrorDeletePost => write!(f, "Failed to delete post"),
+            PostServiceError::ErrorGetRevision => write!(f, "Failed to get post revision"),
+            PostServiceError::ErrorRollbackPost => write!(f, "Failed to roll back post"),
+            PostServiceError::ErrorRestorePost => write!(f, "Failed to restore post"),
+            PostServiceError::ErrorPurgePost => write!(f, "Failed to purge posts"),
+            PostServiceError::ErrorUserBanned(ban) => write!(f, "{}", ban),
+            PostServiceError::ErrorContentRejected(reasons) => {
+                write!(f, "Your post was rejected: {}", reasons)
+            }
+            PostServiceError::ErrorPinPost => write!(f, "Failed to pin post"),
+            PostServiceError::ErrorLockPost => write!(f, "Failed to lock post"),
         }
     }
 }
 
 pub trait PostService: Send + Sync {
-    /// Creates a new post
+    /// Creates a new post, held by the content filter when it matches a hold rule
     fn create_post(
         &self,
         owner_user_id: i32,
@@ -41,15 +74,17 @@ pub trait PostService: Send + Sync {
     /// Retrieves a paginated list of posts
     fn get_posts(&self, pagination: &QueryPagination) -> Result<Vec<Post>, PostServiceError>;
 
-    /// Updates an existing post
+    /// Updates an existing post, keeping the previous content as a revision, the new content
+    /// goes through the content filter
     fn update_post(
         &self,
         post_id: i32,
+        editor_user_id: i32,
         post_title: &str,
         post_body: &str,
     ) -> Result<Post, PostServiceError>;
 
-    /// Soft deletes a post
+    /// Soft deletes a post, it stays restorable until it is purged
     fn delete_post(&self, post_id: i32) -> Result<usize, PostServiceError>;
 
     /// Retrieves a paginated list of posts with user information
@@ -67,15 +102,149 @@ pub trait PostService: Send + Sync {
 
     /// Retrieves a single post with user information
     fn get_post_with_user(&self, post_id: i32) -> Result<PostPublic, PostServiceError>;
+
+    /// Retrieves the revisions of a post, oldest first and empty if it was never edited
+    fn get_post_revisions(&self, post_id: i32) -> Result<Vec<PostRevision>, PostServiceError>;
+
+    /// Restores the content of a previous revision as a new revision
+    fn rollback_post(
+        &self,
+        post_id: i32,
+        revision_id: i32,
+        editor_user_id: i32,
+    ) -> Result<Post, PostServiceError>;
+
+    /// Retrieves a soft deleted post by its ID
+    fn get_deleted_post(&self, post_id: i32) -> Result<Post, PostServiceError>;
+
+    /// Retrieves the trash, the soft deleted posts of a user or of everyone
+    fn get_deleted_posts(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, PostServiceError>;
+
+    /// Brings a soft deleted post back
+    fn restore_post(&self, post_id: i32) -> Result<usize, PostServiceError>;
+
+    /// Hard deletes the posts soft deleted before `deleted_before`, along with their attachments
+    fn purge_deleted_posts(&self, deleted_before: NaiveDateTime)
+        -> Result<usize, PostServiceError>;
+
+    /// Retrieves a post by its ID whatever its state, for moderators
+    fn get_post_unfiltered(&self, post_id: i32) -> Result<Post, PostServiceError>;
+
+    /// Hides a post from everyone but moderators, or lifts the hold
+    fn set_post_hidden(&self, post_id: i32, hidden: bool) -> Result<usize, PostServiceError>;
+
+    /// Retrieves the pinned posts listed above the others, newest first
+    fn get_pinned_posts_with_user(&self) -> Result<Vec<PostPublic>, PostServiceError>;
+
+    /// Pins a post above the other posts, or unpins it
+    fn set_post_pinned(&self, post_id: i32, pinned: bool) -> Result<usize, PostServiceError>;
+
+    /// Locks a post against new comments, or unlocks it
+    fn set_post_locked(&self, post_id: i32, locked: bool) -> Result<usize, PostServiceError>;
 }
 
 pub struct BasedPostService {
     post_repository: Arc<PostRepositoryWithError>,
+    user_repository: Arc<UserRepositoryWithError>,
+    attachment_service: Arc<dyn AttachmentService>,
+    content_filter_service: Arc<dyn ContentFilterService>,
+    live_service: Arc<dyn LiveService>,
 }
 
 impl BasedPostService {
-    pub fn new(post_repository: Arc<PostRepositoryWithError>) -> Self {
-        Self { post_repository }
+    pub fn new(
+        post_repository: Arc<PostRepositoryWithError>,
+        user_repository: Arc<UserRepositoryWithError>,
+        attachment_service: Arc<dyn AttachmentService>,
+        content_filter_service: Arc<dyn ContentFilterService>,
+        live_service: Arc<dyn LiveService>,
+    ) -> Self {
+        Self {
+            post_repository,
+            user_repository,
+            attachment_service,
+            content_filter_service,
+            live_service,
+        }
+    }
+
+    /// Pushes the new title and body of an edited post to its readers, a post held by the
+    /// content filter is taken off their pages instead
+    fn publish_post_updated(&self, post: &Post) {
+        let event = match post.hidden_at {
+            Some(_) => LiveEvent::PostDeleted,
+            None => LiveEvent::PostUpdated {
+                title: post.title.clone(),
+                body: post.body.clone(),
+            },
+        };
+
+        self.live_service.publish(LiveChannel::Post(post.id), event);
+    }
+
+    /// Refuses writes by banned users and returns the writing user, `lookup_error` is returned
+    /// when the user can not be read
+    fn check_user_not_banned(
+        &self,
+        user_id: i32,
+        lookup_error: PostServiceError,
+    ) -> Result<User, PostServiceError> {
+        let user = self
+            .user_repository
+            .get_user_by_id(user_id)
+            .map_err(|_| lookup_error)?;
+
+        match ActiveBan::of_user(&user) {
+            Some(ban) => Err(PostServiceError::ErrorUserBanned(ban)),
+            None => Ok(user),
+        }
+    }
+
+    /// Runs content through the content filter, refusing it when a reject rule matched
+    fn filter_content(
+        &self,
+        content: &FilteredContent,
+        check_error: PostServiceError,
+    ) -> Result<ContentFilterVerdict, PostServiceError> {
+        let verdict = self
+            .content_filter_service
+            .check_content(content)
+            .map_err(|_| check_error)?;
+
+        match verdict.action() {
+            Some(FilterAction::Reject) => {
+                Err(PostServiceError::ErrorContentRejected(verdict.reasons()))
+            }
+            _ => Ok(verdict),
+        }
+    }
+
+    /// Hides a stored post the filter held and reports held or flagged posts to the moderators
+    fn apply_filter_verdict(&self, post: Post, verdict: &ContentFilterVerdict) -> Post {
+        let Some(action) = verdict.action() else {
+            return post;
+        };
+
+        if action == FilterAction::Hold {
+            if let Err(e) = self.post_repository.set_post_hidden(post.id, true) {
+                println!("failed to hold filtered post {}: {}", post.id, e);
+            }
+        }
+
+        if let Err(e) = self
+            .content_filter_service
+            .report_content(ReportTarget::Post(post.id), verdict)
+        {
+            println!("failed to report filtered post {}: {}", post.id, e);
+        }
+
+        self.post_repository
+            .get_post_unfiltered(post.id)
+            .unwrap_or(post)
     }
 }
 
@@ -86,9 +255,25 @@ impl PostService for BasedPostService {
         post_title: &str,
         post_body: &str,
     ) -> Result<Post, PostServiceError> {
-        self.post_repository
+        let author =
+            self.check_user_not_banned(owner_user_id, PostServiceError::ErrorCreatePost)?;
+
+        let verdict = self.filter_content(
+            &FilteredContent {
+                author: &author,
+                target: FilteredTarget::NewPost,
+                title: post_title,
+                body: post_body,
+            },
+            PostServiceError::ErrorCreatePost,
+        )?;
+
+        let post = self
+            .post_repository
             .create_post(owner_user_id, post_title, post_body)
-            .map_err(|_| PostServiceError::ErrorCreatePost)
+            .map_err(|_| PostServiceError::ErrorCreatePost)?;
+
+        Ok(self.apply_filter_verdict(post, &verdict))
     }
 
     fn get_post(&self, post_id: i32) -> Result<Post, PostServiceError> {
@@ -106,18 +291,46 @@ impl PostService for BasedPostService {
     fn update_post(
         &self,
         post_id: i32,
+        editor_user_id: i32,
         post_title: &str,
         post_body: &str,
     ) -> Result<Post, PostServiceError> {
-        self.post_repository
-            .update_post(post_id, post_title, post_body)
-            .map_err(|_| PostServiceError::ErrorUpdatePost)
+        let editor =
+            self.check_user_not_banned(editor_user_id, PostServiceError::ErrorUpdatePost)?;
+
+        let verdict = self.filter_content(
+            &FilteredContent {
+                author: &editor,
+                target: FilteredTarget::EditedPost(post_id),
+                title: post_title,
+                body: post_body,
+            },
+            PostServiceError::ErrorUpdatePost,
+        )?;
+
+        let post = self
+            .post_repository
+            .update_post(post_id, editor_user_id, post_title, post_body)
+            .map_err(|_| PostServiceError::ErrorUpdatePost)?;
+        let post = self.apply_filter_verdict(post, &verdict);
+
+        self.publish_post_updated(&post);
+
+        Ok(post)
     }
 
     fn delete_post(&self, post_id: i32) -> Result<usize, PostServiceError> {
-        self.post_repository
+        let row_affected = self
+            .post_repository
             .delete_post(post_id)
-            .map_err(|_| PostServiceError::ErrorDeletePost)
+            .map_err(|_| PostServiceError::ErrorDeletePost)?;
+
+        if row_affected > 0 {
+            self.live_service
+                .publish(LiveChannel::Post(post_id), LiveEvent::PostDeleted);
+        }
+
+        Ok(row_affected)
     }
 
     fn get_posts_with_user(
@@ -144,4 +357,129 @@ impl PostService for BasedPostService {
             .get_post_with_user(post_id)
             .map_err(|_| PostServiceError::ErrorGetPost)
     }
+
+    fn get_post_revisions(&self, post_id: i32) -> Result<Vec<PostRevision>, PostServiceError> {
+        self.post_repository
+            .get_post_revisions(post_id)
+            .map_err(|_| PostServiceError::ErrorGetRevision)
+    }
+
+    fn rollback_post(
+        &self,
+        post_id: i32,
+        revision_id: i32,
+        editor_user_id: i32,
+    ) -> Result<Post, PostServiceError> {
+        self.check_user_not_banned(editor_user_id, PostServiceError::ErrorRollbackPost)?;
+
+        let revision = self
+            .post_repository
+            .get_post_revision(revision_id)
+            .map_err(|_| PostServiceError::ErrorGetRevision)?;
+
+        if revision.post_id != post_id {
+            return Err(PostServiceError::ErrorGetRevision);
+        }
+
+        let post = self
+            .post_repository
+            .update_post(post_id, editor_user_id, &revision.title, &revision.body)
+            .map_err(|_| PostServiceError::ErrorRollbackPost)?;
+
+        self.publish_post_updated(&post);
+
+        Ok(post)
+    }
+
+    fn get_deleted_post(&self, post_id: i32) -> Result<Post, PostServiceError> {
+        self.post_repository
+            .get_deleted_post(post_id)
+            .map_err(|_| PostServiceError::ErrorGetPost)
+    }
+
+    fn get_deleted_posts(
+        &self,
+        target_user_id: Option<i32>,
+        pagination: &QueryPagination,
+    ) -> Result<ListPostResult, PostServiceError> {
+        self.post_repository
+            .get_deleted_posts(target_user_id, pagination)
+            .map_err(|_| PostServiceError::ErrorGetPost)
+    }
+
+    fn restore_post(&self, post_id: i32) -> Result<usize, PostServiceError> {
+        match self.post_repository.restore_post(post_id) {
+            Ok(0) | Err(_) => Err(PostServiceError::ErrorRestorePost),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn purge_deleted_posts(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, PostServiceError> {
+        let post_ids = self
+            .post_repository
+            .get_post_ids_deleted_before(deleted_before)
+            .map_err(|_| PostServiceError::ErrorPurgePost)?;
+
+        if post_ids.is_empty() {
+            return Ok(0);
+        }
+
+        // the foreign keys drop the attachment rows but would leave their files behind
+        for post_id in &post_ids {
+            if let Err(e) = self.attachment_service.delete_post_attachments(*post_id) {
+                println!("failed to remove attachments of post {}: {}", post_id, e);
+            }
+        }
+
+        self.post_repository
+            .purge_posts(&post_ids)
+            .map_err(|_| PostServiceError::ErrorPurgePost)
+    }
+
+    fn get_post_unfiltered(&self, post_id: i32) -> Result<Post, PostServiceError> {
+        self.post_repository
+            .get_post_unfiltered(post_id)
+            .map_err(|_| PostServiceError::ErrorGetPost)
+    }
+
+    fn set_post_hidden(&self, post_id: i32, hidden: bool) -> Result<usize, PostServiceError> {
+        let row_affected = self
+            .post_repository
+            .set_post_hidden(post_id, hidden)
+            .map_err(|_| PostServiceError::ErrorUpdatePost)?;
+
+        if hidden && row_affected > 0 {
+            self.live_service
+                .publish(LiveChannel::Post(post_id), LiveEvent::PostDeleted);
+        }
+
+        Ok(row_affected)
+    }
+
+    fn get_pinned_posts_with_user(&self) -> Result<Vec<PostPublic>, PostServiceError> {
+        self.post_repository
+            .get_pinned_posts_with_user()
+            .map_err(|_| PostServiceError::ErrorGetPost)
+    }
+
+    fn set_post_pinned(&self, post_id: i32, pinned: bool) -> Result<usize, PostServiceError> {
+        match self.post_repository.set_post_pinned(post_id, pinned) {
+            Ok(0) | Err(_) => Err(PostServiceError::ErrorPinPost),
+            Ok(row_affected) => Ok(row_affected),
+        }
+    }
+
+    fn set_post_locked(&self, post_id: i32, locked: bool) -> Result<usize, PostServiceError> {
+        match self.post_repository.set_post_locked(post_id, locked) {
+            Ok(0) | Err(_) => Err(PostServiceError::ErrorLockPost),
+            Ok(row_affected) => {
+                self.live_service
+                    .publish(LiveChannel::Post(post_id), LiveEvent::PostLocked { locked });
+                Ok(row_affected)
+            }
+        }
+    }
 }
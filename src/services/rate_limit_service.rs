@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_limitation::Limiter;
+use actix_web::{http::header, http::StatusCode, HttpResponse, ResponseError};
+use async_trait::async_trait;
+
+/// How a policy identifies the client it is counting requests for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// The session user, falling back to the client IP for guests
+    SessionUserOrIp,
+    /// The client IP
+    ClientIp,
+    /// A field of the url-encoded form body, falling back to the client IP
+    FormField(&'static str),
+}
+
+/// A named request limit attached to one or more routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    pub limit: usize,
+    pub period: Duration,
+    pub key: RateLimitKey,
+}
+
+pub const GLOBAL_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "global",
+    limit: 5000,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::ClientIp,
+};
+
+pub const CREATE_POST_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "create post",
+    limit: 10,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::SessionUserOrIp,
+};
+
+pub const CREATE_COMMENT_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "comment",
+    limit: 60,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::SessionUserOrIp,
+};
+
+pub const SEND_MESSAGE_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "message",
+    limit: 60,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::SessionUserOrIp,
+};
+
+pub const REPORT_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "report",
+    limit: 20,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::SessionUserOrIp,
+};
+
+pub const RESET_PASSWORD_RATE_LIMIT_POLICY: RateLimitPolicy = RateLimitPolicy {
+    name: "reset password",
+    limit: 3,
+    period: Duration::from_secs(3600),
+    key: RateLimitKey::FormField("email"),
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub remaining: usize,
+    pub reset_after_secs: u64,
+}
+
+#[derive(Debug)]
+pub enum RateLimitServiceError {
+    LimitExceeded {
+        policy_name: &'static str,
+        retry_after_secs: u64,
+    },
+    ErrorBackend(String),
+}
+
+impl Display for RateLimitServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitServiceError::LimitExceeded {
+                policy_name,
+                retry_after_secs,
+            } => write!(
+                f,
+                "Too many requests ({}). Try again in {} seconds",
+                policy_name, retry_after_secs
+            ),
+            RateLimitServiceError::ErrorBackend(msg) => {
+                write!(f, "Rate limit backend error: {}", msg)
+            }
+        }
+    }
+}
+
+impl ResponseError for RateLimitServiceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RateLimitServiceError::LimitExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            RateLimitServiceError::ErrorBackend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+
+        if let RateLimitServiceError::LimitExceeded {
+            retry_after_secs, ..
+        } = self
+        {
+            response.insert_header((header::RETRY_AFTER, retry_after_secs.to_string()));
+        }
+
+        response.body(self.to_string())
+    }
+}
+
+#[async_trait(?Send)]
+pub trait RateLimitService: Send + Sync {
+    /// Counts one request for `key` under `policy`
+    ///
+    /// Returns `RateLimitServiceError::LimitExceeded` once the policy limit is reached
+    async fn hit(
+        &self,
+        policy: &RateLimitPolicy,
+        key: &str,
+    ) -> Result<RateLimitStatus, RateLimitServiceError>;
+}
+
+fn rate_limit_storage_key(policy: &RateLimitPolicy, key: &str) -> String {
+    format!("ratelimit:{}:{}", policy.name.replace(' ', "_"), key)
+}
+
+/// Fixed-window counters kept in process memory, for dev and tests without Redis
+pub struct InMemoryRateLimitService {
+    windows: Mutex<HashMap<String, (usize, Instant)>>,
+}
+
+impl Default for InMemoryRateLimitService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryRateLimitService {
+    /// Expired windows are dropped once this many keys are tracked
+    const PRUNE_AFTER_KEYS: usize = 10_000;
+
+    pub fn new() -> Self {
+        Self {
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl RateLimitService for InMemoryRateLimitService {
+    async fn hit(
+        &self,
+        policy: &RateLimitPolicy,
+        key: &str,
+    ) -> Result<RateLimitStatus, RateLimitServiceError> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+
+        if windows.len() > Self::PRUNE_AFTER_KEYS {
+            windows.retain(|_, (_, reset_at)| *reset_at > now);
+        }
+
+        let (count, reset_at) = windows
+            .entry(rate_limit_storage_key(policy, key))
+            .or_insert((0, now + policy.period));
+
+        // window elapsed, start a new one
+        if *reset_at <= now {
+            *count = 0;
+            *reset_at = now + policy.period;
+        }
+
+        *count += 1;
+
+        let reset_after_secs = reset_at.duration_since(now).as_secs().max(1);
+
+        if *count > policy.limit {
+            return Err(RateLimitServiceError::LimitExceeded {
+                policy_name: policy.name,
+                retry_after_secs: reset_after_secs,
+            });
+        }
+
+        Ok(RateLimitStatus {
+            remaining: policy.limit - *count,
+            reset_after_secs,
+        })
+    }
+}
+
+/// Counters stored in Redis so every replica shares the same limits
+pub struct RedisRateLimitService {
+    redis_url: String,
+    limiters: Mutex<HashMap<&'static str, Limiter>>,
+}
+
+impl RedisRateLimitService {
+    pub fn new(redis_url: &str) -> Self {
+        Self {
+            redis_url: redis_url.to_string(),
+            limiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_limiter(&self, policy: &RateLimitPolicy) -> Result<Limiter, RateLimitServiceError> {
+        let mut limiters = self.limiters.lock().unwrap();
+
+        if let Some(limiter) = limiters.get(policy.name) {
+            return Ok(limiter.clone());
+        }
+
+        // keys are passed explicitly, the limiter's own key resolver is never used
+        let limiter = Limiter::builder(self.redis_url.clone())
+            .key_by(|_| None)
+            .limit(policy.limit)
+            .period(policy.period)
+            .build()
+            .map_err(|e| RateLimitServiceError::ErrorBackend(e.to_string()))?;
+
+        limiters.insert(policy.name, limiter.clone());
+
+        Ok(limiter)
+    }
+}
+
+#[async_trait(?Send)]
+impl RateLimitService for RedisRateLimitService {
+    async fn hit(
+        &self,
+        policy: &RateLimitPolicy,
+        key: &str,
+    ) -> Result<RateLimitStatus, RateLimitServiceError> {
+        let limiter = self.get_limiter(policy)?;
+
+        let reset_after_secs = |reset_epoch_utc: usize| {
+            (reset_epoch_utc as i64 - chrono::Utc::now().timestamp()).max(1) as u64
+        };
+
+        match limiter.count(rate_limit_storage_key(policy, key)).await {
+            Ok(status) => Ok(RateLimitStatus {
+                remaining: status.remaining(),
+                reset_after_secs: reset_after_secs(status.reset_epoch_utc()),
+            }),
+
+            Err(actix_limitation::Error::LimitExceeded(status)) => {
+                Err(RateLimitServiceError::LimitExceeded {
+                    policy_name: policy.name,
+                    retry_after_secs: reset_after_secs(status.reset_epoch_utc()),
+                })
+            }
+
+            Err(e) => Err(RateLimitServiceError::ErrorBackend(e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::{
+        report::{
+            ListReportQueueResult, ReportCategory, ReportPublic, ReportQueueItemPublic,
+            ReportTarget, REPORT_CONTENT_FILTER_REPORTER_NAME, REPORT_RESOLUTION_CONTENT_DELETED,
+            REPORT_RESOLUTION_DISMISSED, REPORT_RESOLUTION_USER_BANNED,
+            REPORT_RESOLUTION_USER_WARNED, REPORT_STATUS_DISMISSED, REPORT_STATUS_RESOLVED,
+        },
+        user::DELETED_USER_NAME,
+    },
+    models::{NewReport, Report},
+    repositories::{
+        comment_repository::CommentRepositoryWithError, post_repository::PostRepositoryWithError,
+        report_repository::ReportRepositoryWithError, user_repository::UserRepositoryWithError,
+    },
+    services::email_service::EmailService,
+    utils::{excerpt::excerpt, pagination::QueryPagination, time::time_to_human_readable},
+};
+
+#[derive(Debug)]
+pub enum ReportServiceError {
+    ErrorTargetNotFound,
+    ErrorOwnContent,
+    ErrorAlreadyReported,
+    ErrorUnsupportedAction,
+    ErrorNoOpenReports,
+    ErrorCreateReport,
+    ErrorGetReport,
+    ErrorResolveReport,
+    ErrorWarnUser(String),
+}
+
+impl Display for ReportServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportServiceError::ErrorTargetNotFound => write!(f, "Reported content not found"),
+            ReportServiceError::ErrorOwnContent => write!(f, "You can not report yourself"),
+            ReportServiceError::ErrorAlreadyReported => {
+                write!(
+                    f,
+                    "You already reported this, the moderators will look at it"
+                )
+            }
+            ReportServiceError::ErrorUnsupportedAction => {
+                write!(f, "This action does not apply to a profile")
+            }
+            ReportServiceError::ErrorNoOpenReports => write!(f, "No open reports left"),
+            ReportServiceError::ErrorCreateReport => write!(f, "Failed to create report"),
+            ReportServiceError::ErrorGetReport => write!(f, "Failed to get reports"),
+            ReportServiceError::ErrorResolveReport => write!(f, "Failed to resolve reports"),
+            ReportServiceError::ErrorWarnUser(msg) => write!(f, "Failed to warn user: {}", msg),
+        }
+    }
+}
+
+pub trait ReportService: Send + Sync {
+    /// Files a report, hiding the content once enough distinct users reported it
+    fn create_report(
+        &self,
+        reporter_user_id: i32,
+        target: ReportTarget,
+        category: ReportCategory,
+        details: &str,
+    ) -> Result<Report, ReportServiceError>;
+
+    /// Files a report without a reporter for content the content filter held or flagged
+    fn create_filter_report(
+        &self,
+        target: ReportTarget,
+        details: &str,
+    ) -> Result<Report, ReportServiceError>;
+
+    /// Retrieves the open reports grouped by target, the target reported first comes first
+    fn get_report_queue(
+        &self,
+        pagination: &QueryPagination,
+    ) -> Result<ListReportQueueResult, ReportServiceError>;
+
+    /// Closes the open reports of a target without action and lifts an automatic hide
+    fn dismiss_reports(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError>;
+
+    /// Moves a reported post or comment to the trash, held so its author can not restore it
+    fn delete_reported_content(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError>;
+
+    /// Emails a warning to the author of the reported content or the reported user
+    fn warn_user(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+        message: &str,
+    ) -> Result<usize, ReportServiceError>;
+
+    /// Closes the open reports of a target once its user was banned
+    fn resolve_reports_with_ban(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError>;
+
+    /// The user behind a report target, the author for posts and comments
+    fn get_target_user_id(&self, target: ReportTarget) -> Result<i32, ReportServiceError>;
+}
+
+pub struct BasedReportService {
+    report_repository: Arc<ReportRepositoryWithError>,
+    post_repository: Arc<PostRepositoryWithError>,
+    comment_repository: Arc<CommentRepositoryWithError>,
+    user_repository: Arc<UserRepositoryWithError>,
+    email_service: Arc<dyn EmailService>,
+    auto_hide_threshold: i64,
+}
+
+impl BasedReportService {
+    pub fn new(
+        report_repository: Arc<ReportRepositoryWithError>,
+        post_repository: Arc<PostRepositoryWithError>,
+        comment_repository: Arc<CommentRepositoryWithError>,
+        user_repository: Arc<UserRepositoryWithError>,
+        email_service: Arc<dyn EmailService>,
+        auto_hide_threshold: i64,
+    ) -> Self {
+        Self {
+            report_repository,
+            post_repository,
+            comment_repository,
+            user_repository,
+            email_service,
+            auto_hide_threshold,
+        }
+    }
+
+    fn set_target_hidden(
+        &self,
+        target: ReportTarget,
+        hidden: bool,
+    ) -> Result<(), ReportServiceError> {
+        let result = match target {
+            ReportTarget::Post(post_id) => self.post_repository.set_post_hidden(post_id, hidden),
+            ReportTarget::Comment(comment_id) => self
+                .comment_repository
+                .set_comment_hidden(comment_id, hidden),
+            ReportTarget::User(_) => return Ok(()),
+        };
+
+        result
+            .map(|_| ())
+            .map_err(|_| ReportServiceError::ErrorResolveReport)
+    }
+
+    fn resolve(
+        &self,
+        target: ReportTarget,
+        status: &str,
+        resolution: &str,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError> {
+        match self
+            .report_repository
+            .resolve_reports(target, status, resolution, moderator_user_id)
+        {
+            Ok(0) => Err(ReportServiceError::ErrorNoOpenReports),
+            Ok(row_affected) => Ok(row_affected),
+            Err(_) => Err(ReportServiceError::ErrorResolveReport),
+        }
+    }
+
+    fn user_name(&self, user_names: &mut HashMap<i32, String>, user_id: i32) -> String {
+        user_names
+            .entry(user_id)
+            .or_insert_with(|| {
+                self.user_repository
+                    .get_user_by_id(user_id)
+                    .map(|user| user.name)
+                    .unwrap_or(DELETED_USER_NAME.to_string())
+            })
+            .clone()
+    }
+
+    fn build_queue_item(
+        &self,
+        target: ReportTarget,
+        reports: Vec<Report>,
+        user_names: &mut HashMap<i32, String>,
+    ) -> ReportQueueItemPublic {
+        let mut item = ReportQueueItemPublic {
+            target_kind: target.kind(),
+            target_id: target.id(),
+            target_url: None,
+            target_title: "(removed)".to_string(),
+            target_excerpt: String::new(),
+            target_user_id: None,
+            target_user_name: DELETED_USER_NAME.to_string(),
+            is_hidden: false,
+            is_deleted: false,
+            allow_delete: false,
+            reporter_count: 0,
+            reports: vec![],
+        };
+
+        match target {
+            ReportTarget::Post(post_id) => {
+                if let Ok(post) = self.post_repository.get_post_unfiltered(post_id) {
+                    item.is_hidden = post.hidden_at.is_some();
+                    item.is_deleted = post.deleted_at.is_some();
+                    item.target_url = (!item.is_hidden && !item.is_deleted)
+                        .then(|| format!("/posts/{}", post.id));
+                    item.target_title = post.title;
+                    item.target_excerpt = excerpt(&post.body);
+                    item.allow_delete = !item.is_deleted;
+                    item.target_user_id = Some(post.user_id);
+                }
+            }
+
+            ReportTarget::Comment(comment_id) => {
+                if let Ok(comment) = self.comment_repository.get_comment_unfiltered(comment_id) {
+                    item.is_hidden = comment.hidden_at.is_some();
+                    item.is_deleted = comment.deleted_at.is_some();
+                    item.target_url = (!item.is_hidden && !item.is_deleted)
+                        .then(|| format!("/posts/{}#{}", comment.post_id, comment.id));
+                    item.target_title = self
+                        .post_repository
+                        .get_post_unfiltered(comment.post_id)
+                        .map(|post| format!("Comment on {}", post.title))
+                        .unwrap_or("Comment".to_string());
+                    item.target_excerpt = excerpt(&comment.content);
+                    item.allow_delete = !item.is_deleted;
+                    item.target_user_id = Some(comment.user_id);
+                }
+            }
+
+            ReportTarget::User(user_id) => {
+                if let Ok(user) = self.user_repository.get_user_by_id(user_id) {
+                    item.target_url = Some(format!("/profile/{}", user.id));
+                    item.target_title = format!("Profile of {}", user.name);
+                    item.target_user_id = Some(user.id);
+                }
+            }
+        }
+
+        if let Some(target_user_id) = item.target_user_id {
+            item.target_user_name = self.user_name(user_names, target_user_id);
+        }
+
+        // the content filter counts as one reporter
+        let mut reporters: Vec<Option<i32>> = reports.iter().map(|r| r.reporter_user_id).collect();
+        reporters.sort_unstable();
+        reporters.dedup();
+        item.reporter_count = reporters.len();
+
+        item.reports = reports
+            .into_iter()
+            .map(|report| ReportPublic {
+                id: report.id,
+                reporter_user_id: report.reporter_user_id,
+                reporter_name: match report.reporter_user_id {
+                    Some(reporter_user_id) => self.user_name(user_names, reporter_user_id),
+                    None => REPORT_CONTENT_FILTER_REPORTER_NAME.to_string(),
+                },
+                category_label: ReportCategory::parse(&report.category)
+                    .map(|category| category.label().to_string())
+                    .unwrap_or(report.category.clone()),
+                category: report.category,
+                details: report.details,
+                time_human: time_to_human_readable(report.created_at),
+            })
+            .collect();
+
+        item
+    }
+}
+
+impl ReportService for BasedReportService {
+    fn create_report(
+        &self,
+        reporter_user_id: i32,
+        target: ReportTarget,
+        category: ReportCategory,
+        details: &str,
+    ) -> Result<Report, ReportServiceError> {
+        // only content that is still visible can be reported
+        let target_user_id = match target {
+            ReportTarget::Post(post_id) => {
+                self.post_repository.get_post(post_id).map(|p| p.user_id)
+            }
+            ReportTarget::Comment(comment_id) => self
+                .comment_repository
+                .get_comment(comment_id)
+                .map(|c| c.user_id),
+            ReportTarget::User(user_id) => {
+                self.user_repository.get_user_by_id(user_id).map(|u| u.id)
+            }
+        }
+        .map_err(|_| ReportServiceError::ErrorTargetNotFound)?;
+
+        if target_user_id == reporter_user_id {
+            return Err(ReportServiceError::ErrorOwnContent);
+        }
+
+        let already_reported = self
+            .report_repository
+            .get_open_reports_by_target(target)
+            .map_err(|_| ReportServiceError::ErrorGetReport)?
+            .iter()
+            .any(|report| report.reporter_user_id == Some(reporter_user_id));
+
+        if already_reported {
+            return Err(ReportServiceError::ErrorAlreadyReported);
+        }
+
+        let report = self
+            .report_repository
+            .create_report(&NewReport {
+                reporter_user_id: Some(reporter_user_id),
+                post_id: matches!(target, ReportTarget::Post(_)).then(|| target.id()),
+                comment_id: matches!(target, ReportTarget::Comment(_)).then(|| target.id()),
+                reported_user_id: matches!(target, ReportTarget::User(_)).then(|| target.id()),
+                category: category.as_str(),
+                details: details.trim(),
+            })
+            .map_err(|_| ReportServiceError::ErrorCreateReport)?;
+
+        if self.auto_hide_threshold > 0 {
+            let reporters = self
+                .report_repository
+                .count_open_reporters(target)
+                .map_err(|_| ReportServiceError::ErrorGetReport)?;
+
+            if reporters >= self.auto_hide_threshold {
+                if let Err(e) = self.set_target_hidden(target, true) {
+                    println!("failed to hide reported {:?}: {}", target, e);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn create_filter_report(
+        &self,
+        target: ReportTarget,
+        details: &str,
+    ) -> Result<Report, ReportServiceError> {
+        self.report_repository
+            .create_report(&NewReport {
+                reporter_user_id: None,
+                post_id: matches!(target, ReportTarget::Post(_)).then(|| target.id()),
+                comment_id: matches!(target, ReportTarget::Comment(_)).then(|| target.id()),
+                reported_user_id: matches!(target, ReportTarget::User(_)).then(|| target.id()),
+                category: ReportCategory::Spam.as_str(),
+                details,
+            })
+            .map_err(|_| ReportServiceError::ErrorCreateReport)
+    }
+
+    fn get_report_queue(
+        &self,
+        pagination: &QueryPagination,
+    ) -> Result<ListReportQueueResult, ReportServiceError> {
+        let open_reports = self
+            .report_repository
+            .get_open_reports()
+            .map_err(|_| ReportServiceError::ErrorGetReport)?;
+
+        // group by target, keeping the order in which targets were first reported
+        let mut targets: Vec<ReportTarget> = vec![];
+        let mut reports_by_target: HashMap<ReportTarget, Vec<Report>> = HashMap::new();
+
+        for report in open_reports {
+            let Some(target) = ReportTarget::of_report(&report) else {
+                continue;
+            };
+
+            reports_by_target
+                .entry(target)
+                .or_insert_with(|| {
+                    targets.push(target);
+                    vec![]
+                })
+                .push(report);
+        }
+
+        let total = targets.len() as i64;
+        let mut user_names = HashMap::new();
+
+        let items = targets
+            .into_iter()
+            .skip(pagination.get_offset().max(0) as usize)
+            .take(pagination.limit.max(0) as usize)
+            .map(|target| {
+                let reports = reports_by_target.remove(&target).unwrap_or_default();
+                self.build_queue_item(target, reports, &mut user_names)
+            })
+            .collect();
+
+        Ok(ListReportQueueResult { items, total })
+    }
+
+    fn dismiss_reports(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError> {
+        let row_affected = self.resolve(
+            target,
+            REPORT_STATUS_DISMISSED,
+            REPORT_RESOLUTION_DISMISSED,
+            moderator_user_id,
+        )?;
+
+        self.set_target_hidden(target, false)?;
+
+        Ok(row_affected)
+    }
+
+    fn delete_reported_content(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError> {
+        // the hold keeps the author from restoring it out of their trash
+        let delete_result = match target {
+            ReportTarget::Post(post_id) => self
+                .post_repository
+                .set_post_hidden(post_id, true)
+                .and_then(|_| self.post_repository.delete_post(post_id)),
+            ReportTarget::Comment(comment_id) => self
+                .comment_repository
+                .set_comment_hidden(comment_id, true)
+                .and_then(|_| self.comment_repository.delete_comment(comment_id)),
+            ReportTarget::User(_) => return Err(ReportServiceError::ErrorUnsupportedAction),
+        };
+
+        delete_result.map_err(|_| ReportServiceError::ErrorResolveReport)?;
+
+        self.resolve(
+            target,
+            REPORT_STATUS_RESOLVED,
+            REPORT_RESOLUTION_CONTENT_DELETED,
+            moderator_user_id,
+        )
+    }
+
+    fn resolve_reports_with_ban(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+    ) -> Result<usize, ReportServiceError> {
+        self.resolve(
+            target,
+            REPORT_STATUS_RESOLVED,
+            REPORT_RESOLUTION_USER_BANNED,
+            moderator_user_id,
+        )
+    }
+
+    fn warn_user(
+        &self,
+        target: ReportTarget,
+        moderator_user_id: i32,
+        message: &str,
+    ) -> Result<usize, ReportServiceError> {
+        let target_user_id = self.get_target_user_id(target)?;
+
+        let user = self
+            .user_repository
+            .get_user_by_id(target_user_id)
+            .map_err(|_| ReportServiceError::ErrorTargetNotFound)?;
+
+        let what = match target {
+            ReportTarget::Post(_) => "one of your posts",
+            ReportTarget::Comment(_) => "one of your comments",
+            ReportTarget::User(_) => "your profile",
+        };
+
+        let mut body = format!(
+            "Hello {},\n\nOther members reported {} and a moderator reviewed the reports. \
+             Please keep to the forum rules, repeated reports can lead to a ban.\n",
+            user.name, what
+        );
+        if !message.trim().is_empty() {
+            body.push_str(&format!(
+                "\nMessage from the moderator:\n{}\n",
+                message.trim()
+            ));
+        }
+
+        self.email_service
+            .send_email(&user.email, "A warning from the moderators", &body)
+            .map_err(|e| ReportServiceError::ErrorWarnUser(e.to_string()))?;
+
+        self.resolve(
+            target,
+            REPORT_STATUS_RESOLVED,
+            REPORT_RESOLUTION_USER_WARNED,
+            moderator_user_id,
+        )
+    }
+
+    fn get_target_user_id(&self, target: ReportTarget) -> Result<i32, ReportServiceError> {
+        match target {
+            ReportTarget::Post(post_id) => self
+                .post_repository
+                .get_post_unfiltered(post_id)
+                .map(|post| post.user_id),
+            ReportTarget::Comment(comment_id) => self
+                .comment_repository
+                .get_comment_unfiltered(comment_id)
+                .map(|comment| comment.user_id),
+            ReportTarget::User(user_id) => Ok(user_id),
+        }
+        .map_err(|_| ReportServiceError::ErrorTargetNotFound)
+    }
+}
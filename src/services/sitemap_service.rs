@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::{
+    entities::{
+        post::post_path,
+        sitemap::{SitemapFile, SitemapSection, SitemapUrl},
+    },
+    repositories::sitemap_repository::SitemapRepository,
+};
+
+#[derive(Debug)]
+pub enum SitemapServiceError {
+    ErrorGetIndex,
+    ErrorGetChunk,
+    ErrorChunkNotFound,
+}
+
+impl Display for SitemapServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SitemapServiceError::ErrorGetIndex => write!(f, "Failed to get sitemap index"),
+            SitemapServiceError::ErrorGetChunk => write!(f, "Failed to get sitemap"),
+            SitemapServiceError::ErrorChunkNotFound => write!(f, "Sitemap not found"),
+        }
+    }
+}
+
+pub trait SitemapService: Send + Sync {
+    /// Lists the sitemap files of every section, a section has at least one file
+    fn get_index(&self) -> Result<Vec<SitemapFile>, SitemapServiceError>;
+
+    /// Lists the pages of one sitemap file, chunks are numbered from 1
+    fn get_chunk(
+        &self,
+        section: SitemapSection,
+        chunk: i64,
+    ) -> Result<Vec<SitemapUrl>, SitemapServiceError>;
+}
+
+pub struct BasedSitemapService {
+    sitemap_repository: Arc<dyn SitemapRepository>,
+    chunk_size: i64,
+}
+
+impl BasedSitemapService {
+    pub fn new(sitemap_repository: Arc<dyn SitemapRepository>, chunk_size: i64) -> Self {
+        Self {
+            sitemap_repository,
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    fn count_section(&self, section: SitemapSection) -> Result<i64, SitemapServiceError> {
+        match section {
+            SitemapSection::Posts => self.sitemap_repository.count_sitemap_posts(),
+            SitemapSection::Profiles => self.sitemap_repository.count_sitemap_users(),
+        }
+        .map_err(|_| SitemapServiceError::ErrorGetIndex)
+    }
+}
+
+impl SitemapService for BasedSitemapService {
+    fn get_index(&self) -> Result<Vec<SitemapFile>, SitemapServiceError> {
+        let mut files = vec![];
+
+        for section in SitemapSection::ALL {
+            let total = self.count_section(section)?;
+            // an empty section still gets its first file, crawlers are fine with an empty urlset
+            let chunks = ((total + self.chunk_size - 1) / self.chunk_size).max(1);
+
+            files.extend((1..=chunks).map(|chunk| SitemapFile {
+                section: section.as_str(),
+                chunk,
+                loc: section.chunk_path(chunk),
+            }));
+        }
+
+        Ok(files)
+    }
+
+    fn get_chunk(
+        &self,
+        section: SitemapSection,
+        chunk: i64,
+    ) -> Result<Vec<SitemapUrl>, SitemapServiceError> {
+        let offset = (chunk - 1)
+            .checked_mul(self.chunk_size)
+            .filter(|offset| *offset >= 0)
+            .ok_or(SitemapServiceError::ErrorChunkNotFound)?;
+
+        let urls: Vec<SitemapUrl> = match section {
+            SitemapSection::Posts => self
+                .sitemap_repository
+                .get_sitemap_posts(offset, self.chunk_size)
+                .map_err(|_| SitemapServiceError::ErrorGetChunk)?
+                .into_iter()
+                .map(|(post_id, title, updated_at)| {
+                    SitemapUrl::new(post_path(post_id, &title), updated_at)
+                })
+                .collect(),
+            SitemapSection::Profiles => self
+                .sitemap_repository
+                .get_sitemap_users(offset, self.chunk_size)
+                .map_err(|_| SitemapServiceError::ErrorGetChunk)?
+                .into_iter()
+                .map(|(user_id, updated_at)| {
+                    SitemapUrl::new(format!("/profile/{}", user_id), updated_at)
+                })
+                .collect(),
+        };
+
+        // the first file exists even when the section is empty
+        match urls.is_empty() && chunk > 1 {
+            true => Err(SitemapServiceError::ErrorChunkNotFound),
+            false => Ok(urls),
+        }
+    }
+}
@@ -3,21 +3,98 @@ use std::{
     sync::Arc,
 };
 
+use chrono::NaiveDateTime;
+
 use crate::{
-    entities::user::{validate_user_password, UserPublic},
-    models::{PasswordReset, UpdateUserNameAndProfilePicture, User},
-    repositories::{token_repository::TokenRepository, user_repository::UserRepositoryWithError},
+    entities::{
+        ban::ActiveBan,
+        user::{
+            email_is_reserved, normalize_username, user_is_deleted_placeholder, user_is_moderator,
+            user_to_user_profile_public, validate_user_password, UserProfileFormData,
+            UserProfilePublic, UserPublic, UserStats, UsernameLookup, RESERVED_USERNAMES,
+            USER_ROLES,
+        },
+    },
+    models::{
+        LoginAttempt, NewUserBan, NewUserIdentity, PasswordReset, UpdateUserNameAndProfilePicture,
+        UpdateUserProfile, User, UserBan, UserIdentity,
+    },
+    repositories::{
+        login_attempt_repository::LoginAttemptRepository, token_repository::TokenRepository,
+        user_ban_repository::UserBanRepository, user_identity_repository::UserIdentityRepository,
+        user_repository::UserRepositoryWithError, user_stats_repository::UserStatsRepository,
+    },
+    services::{email_service::EmailService, oauth_service::OAuthUserInfo},
 };
 
+/// Failures older than this are forgotten
+const LOGIN_FAILURE_WINDOW_MINUTES: i64 = 60;
+/// Failures allowed before exponential backoff kicks in
+const LOGIN_BACKOFF_AFTER_FAILURES: i32 = 3;
+/// Upper bound of a single backoff delay
+const LOGIN_BACKOFF_MAX_SECONDS: i64 = 300;
+/// Failures against one account before it is temporarily locked
+const ACCOUNT_LOCKOUT_AFTER_FAILURES: i32 = 10;
+/// Failures from one client address before it is temporarily locked
+const IP_LOCKOUT_AFTER_FAILURES: i32 = 50;
+/// Duration of a temporary lockout
+const LOGIN_LOCKOUT_MINUTES: i64 = 15;
+/// Failures after which the login form requires a Turnstile challenge
+pub const LOGIN_CHALLENGE_AFTER_FAILURES: i32 = 3;
+/// Time a user waits between two handle changes
+pub const USERNAME_CHANGE_COOLDOWN_DAYS: i64 = 30;
+
+fn login_attempt_account_key(user_email: &str) -> String {
+    format!("account:{}", user_email.trim().to_lowercase())
+}
+
+fn login_attempt_ip_key(client_ip: &str) -> String {
+    format!("ip:{}", client_ip)
+}
+
+/// Builds a name accepted by the register form out of the provider profile
+fn identity_user_name(user_info: &OAuthUserInfo, user_email: &str) -> String {
+    let email_name = user_email.split('@').next().unwrap_or_default();
+
+    let sanitized_name = user_info
+        .name
+        .as_deref()
+        .unwrap_or(email_name)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == ' ')
+        .take(32)
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    if sanitized_name.len() < 2 {
+        return "forum user".to_string();
+    }
+
+    sanitized_name
+}
+
 pub trait UserService: Send + Sync {
+    /// Registers a user, the handle is compared case-insensitively against every current and
+    /// previous handle
     fn register_user(
         &self,
         user_name: &str,
+        user_username: &str,
         user_email: &str,
         user_password: &str,
     ) -> Result<User, UserServiceError>;
 
-    fn login_user(&self, user_email: &str, user_password: &str) -> Result<User, UserServiceError>;
+    /// Logs a user in, throttling repeated failures per account and per client address
+    fn login_user(
+        &self,
+        user_email: &str,
+        user_password: &str,
+        client_ip: &str,
+    ) -> Result<User, UserServiceError>;
+
+    /// Returns true when the login form should require a Turnstile challenge
+    fn login_requires_challenge(&self, user_email: Option<&str>, client_ip: &str) -> bool;
 
     fn get_user_by_id(&self, user_id: i32) -> Result<User, UserServiceError>;
 
@@ -25,12 +102,32 @@ pub trait UserService: Send + Sync {
 
     fn get_user_by_email(&self, email: &str) -> Result<User, UserServiceError>;
 
+    /// Finds who a handle points at, a handle given up points at the handle that replaced it
+    fn resolve_username(&self, username: &str) -> Result<UsernameLookup, UserServiceError>;
+
+    /// Gives a user a new handle, at most once per cooldown. The old handle keeps redirecting
+    /// to the user and can not be taken by anyone else
+    fn change_username(&self, user_id: i32, new_username: &str) -> Result<User, UserServiceError>;
+
     fn update_user_data(
         &self,
         user_id: i32,
         new_data: &UpdateUserNameAndProfilePicture,
     ) -> Result<User, UserServiceError>;
 
+    /// The bio, links and signature shown on the profile of a user
+    fn get_user_profile(&self, user_id: i32) -> Result<UserProfilePublic, UserServiceError>;
+
+    /// Replaces the profile fields of a user, surrounding whitespace is dropped
+    fn update_user_profile(
+        &self,
+        user_id: i32,
+        new_profile: &UserProfileFormData,
+    ) -> Result<User, UserServiceError>;
+
+    /// Counts the posts and comments of a user, their reputation and when they were last active
+    fn get_user_stats(&self, user_id: i32) -> Result<UserStats, UserServiceError>;
+
     fn update_user_password(
         &self,
         user_id: i32,
@@ -43,77 +140,466 @@ pub trait UserService: Send + Sync {
         password_reset: &PasswordReset,
         new_password: &str,
     ) -> Result<(), UserServiceError>;
+
+    /// Logs in the user linked to a provider identity
+    ///
+    /// On first sign-in a passwordless user is registered from the provider's verified email.
+    /// An existing account with the same email is never taken over, it has to link the provider
+    /// from settings.
+    fn login_with_identity(
+        &self,
+        provider: &str,
+        user_info: &OAuthUserInfo,
+    ) -> Result<User, UserServiceError>;
+
+    /// Links a provider identity to a signed-in user
+    fn link_user_identity(
+        &self,
+        user_id: i32,
+        provider: &str,
+        user_info: &OAuthUserInfo,
+    ) -> Result<UserIdentity, UserServiceError>;
+
+    /// Unlinks a provider, refusing to remove the user's last way to sign in
+    fn unlink_user_identity(&self, user_id: i32, provider: &str) -> Result<(), UserServiceError>;
+
+    fn get_user_identities(&self, user_id: i32) -> Result<Vec<UserIdentity>, UserServiceError>;
+
+    /// Changes the role of a user, admins can not change their own role
+    fn update_user_role(
+        &self,
+        user_id: i32,
+        admin_user_id: i32,
+        new_role: &str,
+    ) -> Result<User, UserServiceError>;
+
+    /// Bans a user until the given time and records it in the ban history
+    ///
+    /// Moderators can not ban themselves or other moderators.
+    fn ban_user(
+        &self,
+        user_id: i32,
+        moderator_user_id: i32,
+        banned_until: NaiveDateTime,
+        reason: &str,
+    ) -> Result<User, UserServiceError>;
+
+    /// Lifts the ban of a user before it runs out and records it in the ban history
+    fn unban_user(
+        &self,
+        user_id: i32,
+        moderator_user_id: i32,
+        reason: &str,
+    ) -> Result<User, UserServiceError>;
+
+    /// Retrieves the ban history of a user, newest first
+    fn get_user_bans(&self, user_id: i32) -> Result<Vec<UserBan>, UserServiceError>;
 }
 
 pub struct BasedUserService {
     user_repository: Arc<UserRepositoryWithError>,
     token_repository: Arc<dyn TokenRepository>,
+    login_attempt_repository: Arc<dyn LoginAttemptRepository>,
+    user_identity_repository: Arc<dyn UserIdentityRepository>,
+    user_ban_repository: Arc<dyn UserBanRepository>,
+    user_stats_repository: Arc<dyn UserStatsRepository>,
+    email_service: Arc<dyn EmailService>,
 }
 
 impl BasedUserService {
     pub fn new(
         user_repository: Arc<UserRepositoryWithError>,
         token_repository: Arc<dyn TokenRepository>,
+        login_attempt_repository: Arc<dyn LoginAttemptRepository>,
+        user_identity_repository: Arc<dyn UserIdentityRepository>,
+        user_ban_repository: Arc<dyn UserBanRepository>,
+        user_stats_repository: Arc<dyn UserStatsRepository>,
+        email_service: Arc<dyn EmailService>,
     ) -> Self {
         Self {
             user_repository,
             token_repository,
+            login_attempt_repository,
+            user_identity_repository,
+            user_ban_repository,
+            user_stats_repository,
+            email_service,
+        }
+    }
+
+    /// Rejects reserved handles and handles another user has or had
+    fn check_username_available(
+        &self,
+        username: &str,
+        user_id: Option<i32>,
+    ) -> Result<(), UserServiceError> {
+        if RESERVED_USERNAMES.contains(&username) {
+            return Err(UserServiceError::ErrorUsername(UsernameError::Reserved));
+        }
+
+        if self.user_repository.get_user_by_username(username).is_ok() {
+            return Err(UserServiceError::ErrorUsername(UsernameError::Taken));
+        }
+
+        let previous_owner_user_id = self
+            .user_repository
+            .get_previous_username_owner(username)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get previous usernames"))?;
+
+        match previous_owner_user_id {
+            Some(owner_user_id) if Some(owner_user_id) != user_id => {
+                Err(UserServiceError::ErrorUsername(UsernameError::Taken))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the attempt record for a key, ignoring records outside the failure window
+    fn get_active_login_attempt(&self, attempt_key: &str) -> Option<LoginAttempt> {
+        let login_attempt = self
+            .login_attempt_repository
+            .get_login_attempt(attempt_key)
+            .ok()
+            .flatten()?;
+
+        let now = chrono::Utc::now().naive_utc();
+        let is_locked = login_attempt.locked_until.is_some_and(|until| until > now);
+        let window_start = now - chrono::Duration::minutes(LOGIN_FAILURE_WINDOW_MINUTES);
+
+        if !is_locked && login_attempt.last_failed_at < window_start {
+            return None;
+        }
+
+        Some(login_attempt)
+    }
+
+    /// Rejects the attempt when the key is locked or still inside its backoff delay
+    fn check_login_attempt(&self, attempt_key: &str) -> Result<(), UserServiceError> {
+        let login_attempt = match self.get_active_login_attempt(attempt_key) {
+            Some(login_attempt) => login_attempt,
+            None => return Ok(()),
+        };
+
+        let now = chrono::Utc::now().naive_utc();
+
+        if let Some(locked_until) = login_attempt.locked_until {
+            if locked_until > now {
+                return Err(UserServiceError::ErrorLogin(LoginError::Locked(
+                    locked_until,
+                )));
+            }
+        }
+
+        if login_attempt.failed_count >= LOGIN_BACKOFF_AFTER_FAILURES {
+            let exponent = (login_attempt.failed_count - LOGIN_BACKOFF_AFTER_FAILURES).min(16);
+            let backoff_seconds = 2_i64.pow(exponent as u32).min(LOGIN_BACKOFF_MAX_SECONDS);
+            let next_allowed_at =
+                login_attempt.last_failed_at + chrono::Duration::seconds(backoff_seconds);
+
+            if next_allowed_at > now {
+                let retry_after_seconds = (next_allowed_at - now).num_seconds().max(1);
+                return Err(UserServiceError::ErrorLogin(LoginError::TooManyAttempts(
+                    retry_after_seconds,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a failure for a key and locks it once it reaches `lockout_after`
+    ///
+    /// Returns the lock expiry when this failure triggered a new lockout
+    fn record_failed_login(&self, attempt_key: &str, lockout_after: i32) -> Option<NaiveDateTime> {
+        let now = chrono::Utc::now().naive_utc();
+
+        // start counting again when the previous failures fell out of the window
+        if self.get_active_login_attempt(attempt_key).is_none() {
+            let _ = self
+                .login_attempt_repository
+                .clear_login_attempt(attempt_key);
+        }
+
+        let login_attempt = self
+            .login_attempt_repository
+            .record_failed_login(attempt_key, now)
+            .map_err(|e| println!("failed to record failed login: {}", e))
+            .ok()?;
+
+        if login_attempt.failed_count < lockout_after
+            || login_attempt.failed_count % lockout_after != 0
+        {
+            return None;
+        }
+
+        let locked_until = now + chrono::Duration::minutes(LOGIN_LOCKOUT_MINUTES);
+
+        self.login_attempt_repository
+            .lock_login_attempt(attempt_key, locked_until)
+            .map_err(|e| println!("failed to lock login attempt: {}", e))
+            .ok()?;
+
+        Some(locked_until)
+    }
+
+    /// Refuses users under a ban, with the expiry and reason in the error
+    fn check_user_not_banned(&self, user: &User) -> Result<(), UserServiceError> {
+        match ActiveBan::of_user(user) {
+            Some(ban) => Err(UserServiceError::ErrorLogin(LoginError::Banned(ban))),
+            None => Ok(()),
+        }
+    }
+
+    fn record_user_ban(
+        &self,
+        user: &User,
+        moderator_user_id: i32,
+        banned_until: Option<NaiveDateTime>,
+        reason: &str,
+    ) -> Result<User, UserServiceError> {
+        let reason = reason.trim();
+
+        self.user_repository
+            .update_user_ban(user, banned_until, banned_until.map(|_| reason))
+            .map_err(|_| UserServiceError::ErrorUpdateUserData)?;
+
+        let new_user_ban = NewUserBan {
+            user_id: user.id,
+            moderator_user_id: Some(moderator_user_id),
+            banned_until,
+            reason,
+        };
+
+        if let Err(e) = self.user_ban_repository.create_user_ban(&new_user_ban) {
+            println!("failed to record user ban: {}", e);
+        }
+
+        self.get_user_by_id(user.id)
+    }
+
+    fn send_account_locked_email(&self, user: &User, locked_until: NaiveDateTime) {
+        let email_body = format!(
+            "We noticed {} failed login attempts on your Rust Forum account.\n\
+             For your protection, login is disabled until {} UTC.\n\
+             If this wasn't you, consider resetting your password.",
+            ACCOUNT_LOCKOUT_AFTER_FAILURES,
+            locked_until.format("%d/%m/%Y %H:%M:%S")
+        );
+
+        if let Err(e) = self.email_service.send_email(
+            &user.email,
+            "Account temporarily locked - Rust Forum",
+            &email_body,
+        ) {
+            println!("failed to send account locked email: {}", e);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoginError {
+    InvalidCredentials,
+    TooManyAttempts(i64),
+    Locked(NaiveDateTime),
+    Banned(ActiveBan),
+}
+
+impl Display for LoginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoginError::InvalidCredentials => write!(f, "Invalid email or password"),
+            LoginError::TooManyAttempts(retry_after_seconds) => write!(
+                f,
+                "Too many failed login attempts. Try again in {} seconds",
+                retry_after_seconds
+            ),
+            LoginError::Locked(until) => write!(
+                f,
+                "Login temporarily locked until {} UTC",
+                until.format("%d/%m/%Y %H:%M:%S")
+            ),
+            LoginError::Banned(ban) => write!(f, "{}", ban),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum IdentityError {
+    MissingVerifiedEmail,
+    EmailInUse(String),
+    AlreadyLinked,
+    NotLinked,
+    LastSignInMethod,
+}
+
+impl Display for IdentityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentityError::MissingVerifiedEmail => {
+                write!(f, "The provider did not share a verified email address")
+            }
+            IdentityError::EmailInUse(provider) => write!(
+                f,
+                "An account with this email already exists. Log in and link {} from settings",
+                provider
+            ),
+            IdentityError::AlreadyLinked => {
+                write!(f, "This sign in is already linked to an account")
+            }
+            IdentityError::NotLinked => write!(f, "This sign in is not linked to your account"),
+            IdentityError::LastSignInMethod => write!(
+                f,
+                "Set a password or link another provider before removing your only sign in"
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UsernameError {
+    Taken,
+    Reserved,
+    Unchanged,
+    Cooldown(NaiveDateTime),
+}
+
+impl Display for UsernameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsernameError::Taken => write!(f, "This username is already taken"),
+            UsernameError::Reserved => write!(f, "This username is reserved"),
+            UsernameError::Unchanged => write!(f, "This is already your username"),
+            UsernameError::Cooldown(until) => write!(
+                f,
+                "You can change your username again after {} UTC",
+                until.format("%d/%m/%Y %H:%M:%S")
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BanError {
+    OwnAccount,
+    Moderator,
+    NotBanned,
+}
+
+impl Display for BanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BanError::OwnAccount => write!(f, "You can not ban yourself"),
+            BanError::Moderator => write!(f, "Moderators can not be banned"),
+            BanError::NotBanned => write!(f, "This user is not banned"),
         }
     }
 }
 
 #[derive(Debug)]
 pub enum UserServiceError {
-    ErrorLogin,
+    ErrorLogin(LoginError),
+    ErrorIdentity(IdentityError),
+    ErrorBan(BanError),
+    ErrorUsername(UsernameError),
     ErrorRegister,
     ErrorGetData(&'static str),
     ErrorChangePassword,
     ErrorUpdateUserData,
+    ErrorChangeRole(&'static str),
     ErrorInternal,
 }
 
 impl Display for UserServiceError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            UserServiceError::ErrorLogin => write!(f, "Login failed"),
+            UserServiceError::ErrorLogin(reason) => write!(f, "Login failed: {}", reason),
+            UserServiceError::ErrorIdentity(reason) => write!(f, "{}", reason),
+            UserServiceError::ErrorBan(reason) => write!(f, "{}", reason),
+            UserServiceError::ErrorUsername(reason) => write!(f, "{}", reason),
             UserServiceError::ErrorRegister => write!(f, "Registration failed"),
             UserServiceError::ErrorGetData(msg) => write!(f, "Data retrieval error: {}", msg),
             UserServiceError::ErrorChangePassword => write!(f, "Password change failed"),
             UserServiceError::ErrorUpdateUserData => write!(f, "User data update failed"),
+            UserServiceError::ErrorChangeRole(msg) => write!(f, "Role change failed: {}", msg),
             UserServiceError::ErrorInternal => write!(f, "Internal server error"),
         }
     }
 }
 
 impl UserService for BasedUserService {
-    fn login_user(&self, user_email: &str, user_password: &str) -> Result<User, UserServiceError> {
-        // Query the user by email
-        let user: User = self
-            .user_repository
-            .get_user_by_email(user_email)
-            .map_err(|_| UserServiceError::ErrorLogin)?;
+    fn login_user(
+        &self,
+        user_email: &str,
+        user_password: &str,
+        client_ip: &str,
+    ) -> Result<User, UserServiceError> {
+        let account_key = login_attempt_account_key(user_email);
+        let ip_key = login_attempt_ip_key(client_ip);
 
-        let valid = validate_user_password(&user, user_password);
+        // refuse early while either the account or the client is throttled
+        self.check_login_attempt(&ip_key)?;
+        self.check_login_attempt(&account_key)?;
+
+        // Query the user by email
+        let user = self.user_repository.get_user_by_email(user_email).ok();
 
         // Verify the password
-        if valid {
+        if let Some(user) = user.filter(|u| validate_user_password(u, user_password)) {
+            let _ = self
+                .login_attempt_repository
+                .clear_login_attempt(&account_key);
+
+            // the password was right, a banned user is told why they can not get in
+            self.check_user_not_banned(&user)?;
+
             // Return the user if passwords match
-            Ok(user)
-        } else {
-            // Otherwise, return an error
-            Err(UserServiceError::ErrorLogin)
+            return Ok(user);
         }
+
+        // unknown emails are counted too so the throttle does not reveal which accounts exist
+        self.record_failed_login(&ip_key, IP_LOCKOUT_AFTER_FAILURES);
+
+        if let Some(locked_until) =
+            self.record_failed_login(&account_key, ACCOUNT_LOCKOUT_AFTER_FAILURES)
+        {
+            if let Ok(user) = self.user_repository.get_user_by_email(user_email) {
+                self.send_account_locked_email(&user, locked_until);
+            }
+        }
+
+        // Otherwise, return an error
+        Err(UserServiceError::ErrorLogin(LoginError::InvalidCredentials))
+    }
+
+    fn login_requires_challenge(&self, user_email: Option<&str>, client_ip: &str) -> bool {
+        let mut attempt_keys = vec![login_attempt_ip_key(client_ip)];
+        if let Some(user_email) = user_email {
+            attempt_keys.push(login_attempt_account_key(user_email));
+        }
+
+        attempt_keys.iter().any(|attempt_key| {
+            self.get_active_login_attempt(attempt_key)
+                .is_some_and(|a| a.failed_count >= LOGIN_CHALLENGE_AFTER_FAILURES)
+        })
     }
 
     fn register_user(
         &self,
         user_name: &str,
+        user_username: &str,
         user_email: &str,
         user_password: &str,
     ) -> Result<User, UserServiceError> {
+        if email_is_reserved(user_email) {
+            return Err(UserServiceError::ErrorRegister);
+        }
+
+        let user_username = normalize_username(user_username);
+        self.check_username_available(&user_username, None)?;
+
         let create_user_result = self
             .user_repository
-            .create_user(user_name, user_email, user_password)
+            .create_user(user_name, &user_username, user_email, user_password)
             .map_err(|_| UserServiceError::ErrorRegister)?;
 
         Ok(create_user_result)
@@ -156,6 +642,41 @@ impl UserService for BasedUserService {
         Ok(updated_user)
     }
 
+    fn get_user_profile(&self, user_id: i32) -> Result<UserProfilePublic, UserServiceError> {
+        let user = self.get_user_by_id(user_id)?;
+
+        Ok(user_to_user_profile_public(&user))
+    }
+
+    fn update_user_profile(
+        &self,
+        user_id: i32,
+        new_profile: &UserProfileFormData,
+    ) -> Result<User, UserServiceError> {
+        let user = self.get_user_by_id(user_id)?;
+
+        self.user_repository
+            .update_user_profile(
+                &user,
+                &UpdateUserProfile {
+                    bio: new_profile.bio.trim(),
+                    website: new_profile.website.trim(),
+                    location: new_profile.location.trim(),
+                    pronouns: new_profile.pronouns.trim(),
+                    signature: new_profile.signature.trim(),
+                },
+            )
+            .map_err(|_| UserServiceError::ErrorUpdateUserData)?;
+
+        self.get_user_by_id(user_id)
+    }
+
+    fn get_user_stats(&self, user_id: i32) -> Result<UserStats, UserServiceError> {
+        self.user_stats_repository
+            .get_user_stats(user_id)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get user stats"))
+    }
+
     fn update_user_password(
         &self,
         user_id: i32,
@@ -180,6 +701,51 @@ impl UserService for BasedUserService {
         Ok(user)
     }
 
+    fn resolve_username(&self, username: &str) -> Result<UsernameLookup, UserServiceError> {
+        let username = normalize_username(username);
+
+        if let Ok(user) = self.user_repository.get_user_by_username(&username) {
+            return Ok(UsernameLookup::Current(user.id));
+        }
+
+        let previous_owner_user_id = self
+            .user_repository
+            .get_previous_username_owner(&username)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get previous usernames"))?
+            .ok_or(UserServiceError::ErrorGetData("user not found"))?;
+
+        let user = self.get_user_by_id(previous_owner_user_id)?;
+
+        Ok(UsernameLookup::Moved(user.username))
+    }
+
+    fn change_username(&self, user_id: i32, new_username: &str) -> Result<User, UserServiceError> {
+        let user = self.get_user_by_id(user_id)?;
+        let new_username = normalize_username(new_username);
+
+        if new_username == user.username {
+            return Err(UserServiceError::ErrorUsername(UsernameError::Unchanged));
+        }
+
+        // the handle picked at registration can be changed right away
+        if let Some(changed_at) = user.username_changed_at {
+            let next_change_at = changed_at + chrono::Duration::days(USERNAME_CHANGE_COOLDOWN_DAYS);
+            if next_change_at > chrono::Utc::now().naive_utc() {
+                return Err(UserServiceError::ErrorUsername(UsernameError::Cooldown(
+                    next_change_at,
+                )));
+            }
+        }
+
+        self.check_username_available(&new_username, Some(user.id))?;
+
+        self.user_repository
+            .update_user_username(&user, &new_username)
+            .map_err(|_| UserServiceError::ErrorUpdateUserData)?;
+
+        self.get_user_by_id(user.id)
+    }
+
     fn update_user_password_from_reset(
         &self,
         password_reset: &PasswordReset,
@@ -202,4 +768,193 @@ impl UserService for BasedUserService {
 
         Ok(())
     }
+
+    fn login_with_identity(
+        &self,
+        provider: &str,
+        user_info: &OAuthUserInfo,
+    ) -> Result<User, UserServiceError> {
+        let linked_identity = self
+            .user_identity_repository
+            .get_user_identity(provider, &user_info.subject)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get user identity"))?;
+
+        if let Some(identity) = linked_identity {
+            let user = self.get_user_by_id(identity.user_id)?;
+            self.check_user_not_banned(&user)?;
+
+            return Ok(user);
+        }
+
+        let user_email = match (&user_info.email, user_info.email_verified) {
+            (Some(user_email), true) => user_email.trim().to_lowercase(),
+            _ => {
+                return Err(UserServiceError::ErrorIdentity(
+                    IdentityError::MissingVerifiedEmail,
+                ))
+            }
+        };
+
+        if email_is_reserved(&user_email) {
+            return Err(UserServiceError::ErrorRegister);
+        }
+
+        if self.user_repository.get_user_by_email(&user_email).is_ok() {
+            return Err(UserServiceError::ErrorIdentity(IdentityError::EmailInUse(
+                provider.to_string(),
+            )));
+        }
+
+        let user = self
+            .user_repository
+            .create_user_without_password(&identity_user_name(user_info, &user_email), &user_email)
+            .map_err(|_| UserServiceError::ErrorRegister)?;
+
+        // a concurrent callback may have linked the identity meanwhile, an account nobody
+        // can sign in to would otherwise hold on to the email
+        if let Err(e) = self.link_user_identity(user.id, provider, user_info) {
+            if let Err(delete_error) = self.user_repository.delete_user(&user) {
+                println!(
+                    "failed to delete unlinked user {}: {}",
+                    user.id, delete_error
+                );
+            }
+            return Err(e);
+        }
+
+        Ok(user)
+    }
+
+    fn link_user_identity(
+        &self,
+        user_id: i32,
+        provider: &str,
+        user_info: &OAuthUserInfo,
+    ) -> Result<UserIdentity, UserServiceError> {
+        let linked_identity = self
+            .user_identity_repository
+            .get_user_identity(provider, &user_info.subject)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get user identity"))?;
+
+        match linked_identity {
+            Some(identity) if identity.user_id == user_id => return Ok(identity),
+            Some(_) => {
+                return Err(UserServiceError::ErrorIdentity(
+                    IdentityError::AlreadyLinked,
+                ))
+            }
+            None => {}
+        }
+
+        let new_identity = NewUserIdentity {
+            user_id,
+            provider,
+            provider_subject: &user_info.subject,
+            email: user_info.email.as_deref(),
+        };
+
+        // also fails when the user already linked another account of this provider
+        self.user_identity_repository
+            .create_user_identity(&new_identity)
+            .map_err(|_| UserServiceError::ErrorIdentity(IdentityError::AlreadyLinked))
+    }
+
+    fn unlink_user_identity(&self, user_id: i32, provider: &str) -> Result<(), UserServiceError> {
+        let user = self.get_user_by_id(user_id)?;
+        let identities = self.get_user_identities(user_id)?;
+
+        if !identities.iter().any(|i| i.provider == provider) {
+            return Err(UserServiceError::ErrorIdentity(IdentityError::NotLinked));
+        }
+
+        if user.password.is_none() && identities.len() == 1 {
+            return Err(UserServiceError::ErrorIdentity(
+                IdentityError::LastSignInMethod,
+            ));
+        }
+
+        self.user_identity_repository
+            .delete_user_identity(user_id, provider)
+            .map_err(|_| UserServiceError::ErrorInternal)?;
+
+        Ok(())
+    }
+
+    fn get_user_identities(&self, user_id: i32) -> Result<Vec<UserIdentity>, UserServiceError> {
+        self.user_identity_repository
+            .get_user_identities_by_user(user_id)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get user identities"))
+    }
+
+    fn update_user_role(
+        &self,
+        user_id: i32,
+        admin_user_id: i32,
+        new_role: &str,
+    ) -> Result<User, UserServiceError> {
+        if user_id == admin_user_id {
+            return Err(UserServiceError::ErrorChangeRole(
+                "you can not change your own role",
+            ));
+        }
+
+        if !USER_ROLES.contains(&new_role) {
+            return Err(UserServiceError::ErrorChangeRole("unknown role"));
+        }
+
+        let user = self.get_user_by_id(user_id)?;
+
+        if user_is_deleted_placeholder(&user) {
+            return Err(UserServiceError::ErrorChangeRole(
+                "the deleted user placeholder keeps its role",
+            ));
+        }
+
+        self.user_repository
+            .update_user_role(&user, new_role)
+            .map_err(|_| UserServiceError::ErrorUpdateUserData)?;
+
+        self.get_user_by_id(user_id)
+    }
+
+    fn ban_user(
+        &self,
+        user_id: i32,
+        moderator_user_id: i32,
+        banned_until: NaiveDateTime,
+        reason: &str,
+    ) -> Result<User, UserServiceError> {
+        if user_id == moderator_user_id {
+            return Err(UserServiceError::ErrorBan(BanError::OwnAccount));
+        }
+
+        let user = self.get_user_by_id(user_id)?;
+
+        if user_is_moderator(&user) {
+            return Err(UserServiceError::ErrorBan(BanError::Moderator));
+        }
+
+        self.record_user_ban(&user, moderator_user_id, Some(banned_until), reason)
+    }
+
+    fn unban_user(
+        &self,
+        user_id: i32,
+        moderator_user_id: i32,
+        reason: &str,
+    ) -> Result<User, UserServiceError> {
+        let user = self.get_user_by_id(user_id)?;
+
+        if ActiveBan::of_user(&user).is_none() {
+            return Err(UserServiceError::ErrorBan(BanError::NotBanned));
+        }
+
+        self.record_user_ban(&user, moderator_user_id, None, reason)
+    }
+
+    fn get_user_bans(&self, user_id: i32) -> Result<Vec<UserBan>, UserServiceError> {
+        self.user_ban_repository
+            .get_user_bans_by_user(user_id)
+            .map_err(|_| UserServiceError::ErrorGetData("failed to get user bans"))
+    }
 }
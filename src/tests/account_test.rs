@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        db::initialize_db_pool,
+        entities::{
+            account::{UserDeleteAccountFormData, EXPORT_DATA_FILE_NAME},
+            attachment::{AttachmentTarget, AttachmentUpload},
+            user::UserLoginFormData,
+        },
+        models::{UpdateUserNameAndProfilePicture, User},
+        repositories::{
+            account_repository::PostgresAccountRepository,
+            comment_repository::PostgresCommentRepository, post_repository::PostgresPostRepository,
+            user_repository::UserRepository, user_repository_postgres::PostgresUserRepository,
+        },
+        servers::server_actix::create_actix_app,
+        services::account_service::{AccountDeletionMode, AccountService, BasedAccountService},
+        tests::{create_pg_test_user, read_csrf_form_session},
+        utils::{
+            csrf::CSRF_HEADER,
+            token::generate_random_token,
+            zip::{ZipArchiveBuilder, ZipLimitError},
+        },
+        AppKit,
+    };
+
+    /// Registers a user whose profile picture is a file in the static directory
+    fn register_user_with_upload(app_kit: &AppKit) -> (User, String) {
+        let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+
+        let user = app_kit
+            .user_service
+            .register_user("gdpr example", "gdpr_example", &email, "gdprpassword")
+            .unwrap();
+
+        let key = format!("{}.png", generate_random_token(16));
+        app_kit
+            .file_storage
+            .put_file(&key, b"profile picture bytes", "image/png")
+            .unwrap();
+
+        let user = app_kit
+            .user_service
+            .update_user_data(
+                user.id,
+                &UpdateUserNameAndProfilePicture {
+                    name: None,
+                    user_profile_picture_url: Some(&app_kit.file_storage.public_url(&key)),
+                },
+            )
+            .unwrap();
+
+        (user, key)
+    }
+
+    #[actix_web::test]
+    async fn test_should_export_user_data_as_zip() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let (user, key) = register_user_with_upload(&app_kit);
+
+        let export = app_kit.account_service.export_user_data(user.id).unwrap();
+
+        assert_eq!(export.profile.email, user.email);
+        assert!(export.profile.has_password);
+        assert_eq!(export.uploads.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_slice(&export.to_json().unwrap()).unwrap();
+        assert_eq!(json["profile"]["id"], user.id);
+        assert!(json["profile"].get("password").is_none());
+
+        let archive = export.to_zip(app_kit.file_storage.as_ref()).unwrap();
+        let contains = |needle: &[u8]| archive.windows(needle.len()).any(|w| w == needle);
+
+        assert!(archive.starts_with(b"PK\x03\x04"));
+        assert!(contains(EXPORT_DATA_FILE_NAME.as_bytes()));
+        assert!(contains(export.uploads[0].as_bytes()));
+        assert!(contains(b"profile picture bytes"));
+
+        app_kit.file_storage.delete_file(&key).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_should_delete_account_after_confirmation() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let (user, key) = register_user_with_upload(&app_kit);
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let login_page_req = actix_web::test::TestRequest::get()
+            .uri("/users/login")
+            .to_request();
+        let csrf_form_session =
+            read_csrf_form_session(actix_web::test::call_service(&app, login_page_req).await).await;
+
+        let login_req = actix_web::test::TestRequest::post()
+            .uri("/users/login")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserLoginFormData {
+                email: user.email.clone(),
+                password: "gdprpassword".to_string(),
+                cf_turnstile_response: None,
+            })
+            .to_request();
+        let login_resp = actix_web::test::call_service(&app, login_req).await;
+
+        assert_eq!(login_resp.status(), StatusCode::FOUND);
+
+        let session_cookie = login_resp.response().cookies().next().unwrap().into_owned();
+
+        // a wrong password keeps the account
+        let delete_req = actix_web::test::TestRequest::post()
+            .uri("/users/delete")
+            .cookie(session_cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserDeleteAccountFormData {
+                confirm_email: user.email.clone(),
+                password: Some("wrongpassword".to_string()),
+            })
+            .to_request();
+        let delete_resp = actix_web::test::call_service(&app, delete_req).await;
+
+        assert_eq!(
+            delete_resp.headers().get(header::LOCATION).unwrap(),
+            "/users/settings"
+        );
+        assert!(app_kit.user_service.get_user_by_id(user.id).is_ok());
+        assert!(app_kit.file_storage.file_exists(&key).unwrap());
+
+        let delete_req = actix_web::test::TestRequest::post()
+            .uri("/users/delete")
+            .cookie(session_cookie)
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserDeleteAccountFormData {
+                confirm_email: user.email.clone(),
+                password: Some("gdprpassword".to_string()),
+            })
+            .to_request();
+        let delete_resp = actix_web::test::call_service(&app, delete_req).await;
+
+        assert_eq!(delete_resp.headers().get(header::LOCATION).unwrap(), "/");
+        assert!(app_kit.user_service.get_user_by_id(user.id).is_err());
+        assert!(!app_kit.file_storage.file_exists(&key).unwrap());
+
+        // nobody can sign up as the placeholder that receives anonymized content
+        assert!(app_kit
+            .user_service
+            .register_user(
+                "squatter",
+                "squatter",
+                "Deleted-User@rust-forum.invalid",
+                "squatterpassword"
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_should_hand_content_to_the_deleted_user_placeholder() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        // the test app kit keeps users in memory, anonymizing needs the postgres rows
+        let db_pool = Arc::new(initialize_db_pool());
+        let user_repo = Arc::new(PostgresUserRepository::new(db_pool.clone()));
+        let account_service = BasedAccountService::new(
+            user_repo.clone(),
+            Arc::new(PostgresPostRepository::new(db_pool.clone())),
+            Arc::new(PostgresCommentRepository::new(db_pool.clone())),
+            Arc::new(PostgresAccountRepository::new(db_pool.clone())),
+            app_kit.attachment_service.clone(),
+            app_kit.file_storage.clone(),
+            AccountDeletionMode::Anonymize,
+        );
+
+        let author = create_pg_test_user("leaving author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "stays", "body")
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "stays too")
+            .unwrap();
+
+        let prepared = app_kit
+            .attachment_service
+            .prepare_attachments(vec![AttachmentUpload {
+                file_name: "notes.txt".to_string(),
+                bytes: b"notes".to_vec(),
+            }])
+            .unwrap();
+        let attachment = app_kit
+            .attachment_service
+            .store_attachments(author.id, AttachmentTarget::Post(post.id), prepared)
+            .unwrap()
+            .remove(0);
+
+        account_service.delete_account(author.id).unwrap();
+
+        let deleted_user = user_repo.get_deleted_user().unwrap();
+        assert_eq!(
+            app_kit.post_service.get_post(post.id).unwrap().user_id,
+            deleted_user.id
+        );
+        assert_eq!(
+            app_kit
+                .comment_service
+                .get_comment(comment.id)
+                .unwrap()
+                .user_id,
+            deleted_user.id
+        );
+        assert!(user_repo.get_user_by_id(author.id).is_err());
+        assert!(!app_kit
+            .file_storage
+            .file_exists(&attachment.storage_key)
+            .unwrap());
+
+        // the placeholder itself stays
+        assert!(account_service.delete_account(deleted_user.id).is_err());
+    }
+
+    #[test]
+    fn test_should_refuse_zip_entries_past_the_format_limit() {
+        let mut archive = ZipArchiveBuilder::new(chrono::Utc::now().naive_utc());
+
+        for i in 0..u16::MAX {
+            archive.add_file(&i.to_string(), b"").unwrap();
+        }
+
+        assert_eq!(
+            archive.add_file("one too many", b"").err(),
+            Some(ZipLimitError::TooManyEntries)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use dotenv::dotenv;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+    use crate::{
+        entities::attachment::{
+            AttachmentKind, AttachmentUpload, ATTACHMENT_FILE_MAX_BYTES,
+            ATTACHMENT_IMAGE_MAX_BYTES, ATTACHMENT_MAX_PER_TARGET,
+        },
+        services::attachment_service::AttachmentServiceError,
+        AppKit,
+    };
+
+    fn encode_test_image(format: ImageFormat) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(32, 16, Rgba([0, 128, 255, 255]));
+
+        let mut bytes = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, format)
+            .unwrap();
+
+        bytes.into_inner()
+    }
+
+    fn upload(file_name: &str, bytes: &[u8]) -> AttachmentUpload {
+        AttachmentUpload {
+            file_name: file_name.to_string(),
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_should_prepare_image_and_file_attachments() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        // a JPEG carrying an EXIF segment right after the SOI marker
+        let jpeg = encode_test_image(ImageFormat::Jpeg);
+        let mut exif_jpeg = jpeg[..2].to_vec();
+        exif_jpeg.extend_from_slice(b"\xff\xe1\x00\x10Exif\0\0MM\0\x2a\0\0\0\x08");
+        exif_jpeg.extend_from_slice(&jpeg[2..]);
+
+        let prepared = app_kit
+            .attachment_service
+            .prepare_attachments(vec![
+                upload("photo.jpeg", &exif_jpeg),
+                upload("diagram.png", &encode_test_image(ImageFormat::Png)),
+                upload("report.bin", b"%PDF-1.7\n%%EOF\n"),
+                upload("../../etc/notes.md", "# notes\nünïcödé\n".as_bytes()),
+            ])
+            .unwrap();
+
+        let summary: Vec<(AttachmentKind, &str, &str, &str)> = prepared
+            .iter()
+            .map(|p| (p.kind, p.file_name.as_str(), p.content_type, p.extension))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (AttachmentKind::Image, "photo.jpeg", "image/jpeg", "jpg"),
+                (AttachmentKind::Image, "diagram.png", "image/webp", "webp"),
+                (AttachmentKind::File, "report.bin", "application/pdf", "pdf"),
+                (
+                    AttachmentKind::File,
+                    "notes.md",
+                    "text/plain; charset=utf-8",
+                    "md"
+                ),
+            ]
+        );
+
+        // images are re-encoded, their metadata does not survive
+        assert!(!prepared[0].bytes.windows(4).any(|w| w == b"Exif"));
+        assert_eq!(
+            image::load_from_memory(&prepared[1].bytes).unwrap().width(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_should_reject_invalid_attachments() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let prepare = |uploads| app_kit.attachment_service.prepare_attachments(uploads);
+
+        let too_many = (0..=ATTACHMENT_MAX_PER_TARGET)
+            .map(|i| upload(&format!("{}.txt", i), b"text"))
+            .collect();
+        assert!(matches!(
+            prepare(too_many),
+            Err(AttachmentServiceError::ErrorTooManyAttachments)
+        ));
+
+        // the name does not make an executable a text file
+        assert!(matches!(
+            prepare(vec![upload("readme.txt", b"MZ\x90\x00\x03\x00\x00\x00")]),
+            Err(AttachmentServiceError::ErrorUnsupportedType(_))
+        ));
+        assert!(matches!(
+            prepare(vec![upload("script.html", b"<script>alert(1)</script>")]),
+            Err(AttachmentServiceError::ErrorUnsupportedType(_))
+        ));
+
+        let mut large_image = encode_test_image(ImageFormat::Png);
+        large_image.resize(ATTACHMENT_IMAGE_MAX_BYTES + 1, 0);
+        assert!(matches!(
+            prepare(vec![upload("large.png", &large_image)]),
+            Err(AttachmentServiceError::ErrorTooLarge(_))
+        ));
+
+        let mut large_pdf = b"%PDF-1.7\n".to_vec();
+        large_pdf.resize(ATTACHMENT_FILE_MAX_BYTES + 1, b' ');
+        assert!(matches!(
+            prepare(vec![upload("large.pdf", &large_pdf)]),
+            Err(AttachmentServiceError::ErrorTooLarge(_))
+        ));
+
+        let mut broken_image = encode_test_image(ImageFormat::Png);
+        broken_image.truncate(64);
+        assert!(matches!(
+            prepare(vec![upload("broken.png", &broken_image)]),
+            Err(AttachmentServiceError::ErrorInvalidImage(_))
+        ));
+    }
+}
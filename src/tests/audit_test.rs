@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use diesel::RunQueryDsl;
+    use dotenv::dotenv;
+
+    use crate::{
+        db::initialize_db_pool,
+        entities::audit::{AuditAction, AuditLogFilter, AuditLogQuery, AuditRecord, AuditTarget},
+        services::user_service::UserServiceError,
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_record_and_filter_audit_log_entries() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let audit_service = &app_kit.audit_service;
+
+        // ids nobody else records against, the log can not be cleaned up after the test
+        let actor_user_id = rand::random::<i32>().abs();
+        let target_user_id = rand::random::<i32>().abs();
+
+        let entry = audit_service
+            .record(&AuditRecord {
+                actor_user_id,
+                action: AuditAction::UserBan,
+                target: AuditTarget::User(target_user_id),
+                reason: "  spamming links  ",
+                ip_address: "127.0.0.1",
+            })
+            .unwrap();
+        assert_eq!(entry.reason, "spamming links");
+
+        audit_service
+            .record(&AuditRecord {
+                actor_user_id,
+                action: AuditAction::UserUnban,
+                target: AuditTarget::User(target_user_id),
+                reason: "",
+                ip_address: "127.0.0.1",
+            })
+            .unwrap();
+
+        let filter = AuditLogFilter {
+            action: Some(AuditAction::UserBan),
+            actor_user_id: None,
+            target_type: Some("user"),
+            target_id: Some(target_user_id),
+        };
+        let result = audit_service
+            .get_entries(&filter, &QueryPagination::default())
+            .unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.entries[0].id, entry.id);
+        assert_eq!(result.entries[0].actor_user_id, actor_user_id);
+
+        let by_actor = AuditLogQuery {
+            actor: actor_user_id.to_string(),
+            ..Default::default()
+        }
+        .to_filter()
+        .unwrap();
+        let exported = audit_service.export_entries(&by_actor).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].action, AuditAction::UserUnban.as_str());
+
+        // the table itself refuses to change history
+        let mut conn = initialize_db_pool().get().unwrap();
+        assert!(
+            diesel::sql_query(format!("DELETE FROM audit_log WHERE id = {}", entry.id))
+                .execute(&mut conn)
+                .is_err()
+        );
+        assert!(diesel::sql_query(format!(
+            "UPDATE audit_log SET reason = 'nothing' WHERE id = {}",
+            entry.id
+        ))
+        .execute(&mut conn)
+        .is_err());
+    }
+
+    #[test]
+    fn test_should_reject_invalid_audit_log_query() {
+        let query = AuditLogQuery {
+            actor: "someone".to_string(),
+            ..Default::default()
+        };
+        assert!(query.to_filter().is_err());
+
+        let query = AuditLogQuery {
+            action: "user.nothing".to_string(),
+            ..Default::default()
+        };
+        assert!(query.to_filter().is_err());
+
+        let query = AuditLogQuery {
+            action: AuditAction::RoleChange.as_str().to_string(),
+            target_type: "user".to_string(),
+            target_id: " 12 ".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.to_filter().unwrap(),
+            AuditLogFilter {
+                action: Some(AuditAction::RoleChange),
+                actor_user_id: None,
+                target_type: Some("user"),
+                target_id: Some(12),
+            }
+        );
+    }
+
+    #[test]
+    fn test_should_change_role_of_other_users_only() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let user_service = &app_kit.user_service;
+
+        let email = |name: &str| format!("{}-{}@example.com", name, generate_random_token(12));
+        let admin = user_service
+            .register_user("admin", "audit_admin", &email("admin"), "adminpassword")
+            .unwrap();
+        let user = user_service
+            .register_user("member", "audit_member", &email("member"), "memberpassword")
+            .unwrap();
+
+        assert!(matches!(
+            user_service.update_user_role(admin.id, admin.id, "user"),
+            Err(UserServiceError::ErrorChangeRole(_))
+        ));
+        assert!(matches!(
+            user_service.update_user_role(user.id, admin.id, "owner"),
+            Err(UserServiceError::ErrorChangeRole(_))
+        ));
+
+        let user = user_service
+            .update_user_role(user.id, admin.id, "moderator")
+            .unwrap();
+        assert_eq!(user.role, "moderator");
+    }
+}
@@ -0,0 +1,245 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+    use crate::{
+        entities::user::UserLoginFormData,
+        servers::server_actix::create_actix_app,
+        tests::read_csrf_form_session,
+        utils::{
+            avatar::{avatar_file_keys, process_avatar, AvatarError, AVATAR_SIZES},
+            csrf::CSRF_HEADER,
+            token::generate_random_token,
+        },
+        AppKit,
+    };
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// 200x100 image, left half red and right half blue
+    fn encode_test_image(format: ImageFormat) -> Vec<u8> {
+        let image = RgbaImage::from_fn(200, 100, |x, _| if x < 100 { RED } else { BLUE });
+
+        let mut bytes = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, format)
+            .unwrap();
+
+        bytes.into_inner()
+    }
+
+    /// Inserts an EXIF segment with the given orientation right after the JPEG SOI marker
+    fn with_exif_orientation(jpeg: &[u8], orientation: u8) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08".to_vec();
+        exif.extend_from_slice(&[0, 1]); // one IFD entry
+        exif.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]); // no next IFD
+
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xff, 0xe1]);
+        bytes.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        bytes.extend_from_slice(&exif);
+        bytes.extend_from_slice(&jpeg[2..]);
+
+        bytes
+    }
+
+    fn is_close(pixel: Rgba<u8>, expected: Rgba<u8>) -> bool {
+        pixel
+            .0
+            .iter()
+            .zip(expected.0.iter())
+            .all(|(a, b)| a.abs_diff(*b) < 40)
+    }
+
+    #[test]
+    fn test_should_generate_square_avatar_variants() {
+        let variants = process_avatar("test", &encode_test_image(ImageFormat::Png)).unwrap();
+
+        assert_eq!(variants.len(), AVATAR_SIZES.len() * 2);
+
+        for (variant, size) in variants
+            .iter()
+            .zip(AVATAR_SIZES.iter().flat_map(|s| [s, s]))
+        {
+            let decoded = image::load_from_memory(&variant.bytes).unwrap();
+
+            assert_eq!((decoded.width(), decoded.height()), (*size, *size));
+        }
+
+        let file_names: Vec<String> = variants.into_iter().map(|v| v.file_name).collect();
+        assert_eq!(avatar_file_keys("avatar-test-256.jpg"), file_names);
+    }
+
+    #[test]
+    fn test_should_apply_and_strip_exif_orientation() {
+        let jpeg = encode_test_image(ImageFormat::Jpeg);
+
+        // orientation 6, the camera was turned 90 degrees clockwise
+        let rotated_jpeg = with_exif_orientation(&jpeg, 6);
+        assert!(rotated_jpeg.windows(4).any(|w| w == b"Exif"));
+
+        let variants = process_avatar("test", &rotated_jpeg).unwrap();
+        let avatar = &variants
+            .iter()
+            .find(|v| v.file_name.ends_with(".jpg"))
+            .unwrap();
+
+        assert!(!avatar.bytes.windows(4).any(|w| w == b"Exif"));
+
+        let decoded = image::load_from_memory(&avatar.bytes).unwrap().to_rgba8();
+        assert!(is_close(*decoded.get_pixel(32, 4), RED));
+        assert!(is_close(*decoded.get_pixel(32, 60), BLUE));
+    }
+
+    #[test]
+    fn test_should_reject_non_image_upload() {
+        assert!(matches!(
+            process_avatar("test", b"<?php echo 'not an image'; ?>"),
+            Err(AvatarError::UnsupportedFormat)
+        ));
+
+        // a valid signature with a broken body fails to decode
+        let mut truncated = encode_test_image(ImageFormat::Png);
+        truncated.truncate(64);
+        assert!(process_avatar("test", &truncated).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_should_replace_and_clean_up_avatar_files() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+        let user = app_kit
+            .user_service
+            .register_user("avatar example", "avatar_example", &email, "avatarpassword")
+            .unwrap();
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let login_page_req = actix_web::test::TestRequest::get()
+            .uri("/users/login")
+            .to_request();
+        let csrf_form_session =
+            read_csrf_form_session(actix_web::test::call_service(&app, login_page_req).await).await;
+
+        let login_req = actix_web::test::TestRequest::post()
+            .uri("/users/login")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserLoginFormData {
+                email: email.clone(),
+                password: "avatarpassword".to_string(),
+                cf_turnstile_response: None,
+            })
+            .to_request();
+        let login_resp = actix_web::test::call_service(&app, login_req).await;
+        let session_cookie = login_resp.response().cookies().next().unwrap().into_owned();
+
+        let boundary = "avatar-test-boundary";
+        let upload_body = |image: &[u8], csrf_token: Option<&str>| {
+            // forms render the token field ahead of the upload
+            let mut body = match csrf_token {
+                Some(csrf_token) => format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"csrf_token\"\r\n\r\n\
+                     {csrf_token}\r\n"
+                ),
+                None => String::new(),
+            }
+            .into_bytes();
+            // the client claims a GIF, the server trusts the bytes instead
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"new_profile_picture\"; \
+                     filename=\"avatar.gif\"\r\nContent-Type: image/gif\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(image);
+            body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+            body
+        };
+        let upload_req = |uri: &str| {
+            actix_web::test::TestRequest::post()
+                .uri(uri)
+                .cookie(session_cookie.clone())
+                .insert_header((
+                    header::CONTENT_TYPE,
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+        };
+
+        let mut uploaded_urls = vec![];
+
+        // the token comes in the header first, then in the form body
+        for (format, token_in_body) in [(ImageFormat::Png, false), (ImageFormat::Jpeg, true)] {
+            let upload_req = match token_in_body {
+                true => upload_req("/users/profilepicture").set_payload(upload_body(
+                    &encode_test_image(format),
+                    Some(&csrf_form_session.token),
+                )),
+                false => upload_req("/users/profilepicture")
+                    .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+                    .set_payload(upload_body(&encode_test_image(format), None)),
+            }
+            .to_request();
+            let upload_resp = actix_web::test::call_service(&app, upload_req).await;
+
+            assert_eq!(upload_resp.status(), StatusCode::FOUND);
+
+            let url = app_kit
+                .user_service
+                .get_user_by_id(user.id)
+                .unwrap()
+                .user_profile_picture_url
+                .unwrap();
+
+            uploaded_urls.push(url);
+        }
+
+        assert_ne!(uploaded_urls[0], uploaded_urls[1]);
+
+        // a token in the url is not accepted, it would leak through logs and referers
+        let query_req = upload_req(&format!(
+            "/users/profilepicture?csrf_token={}",
+            csrf_form_session.token
+        ))
+        .set_payload(upload_body(&encode_test_image(ImageFormat::Png), None))
+        .to_request();
+        assert_eq!(
+            actix_web::test::call_service(&app, query_req)
+                .await
+                .status(),
+            StatusCode::FOUND
+        );
+        assert_eq!(
+            app_kit
+                .user_service
+                .get_user_by_id(user.id)
+                .unwrap()
+                .user_profile_picture_url
+                .as_ref(),
+            Some(&uploaded_urls[1])
+        );
+
+        let avatar_keys = |url: &String| {
+            avatar_file_keys(&app_kit.file_storage.key_from_public_url(url).unwrap())
+        };
+        let file_exists = |key: &String| app_kit.file_storage.file_exists(key).unwrap();
+
+        // the replaced avatar is gone, every variant of the new one is stored
+        assert!(!avatar_keys(&uploaded_urls[0]).iter().any(file_exists));
+        assert!(avatar_keys(&uploaded_urls[1]).iter().all(file_exists));
+
+        app_kit.account_service.delete_account(user.id).unwrap();
+
+        assert!(!avatar_keys(&uploaded_urls[1]).iter().any(file_exists));
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod tests {
+
+    use chrono::{Duration, Utc};
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::ban::{ban_until_from_days, permanent_ban_until},
+        repositories::user_repository::UserRepository,
+        services::{
+            comment_service::CommentServiceError,
+            post_service::PostServiceError,
+            user_service::{BanError, LoginError, UserServiceError},
+        },
+        tests::{create_pg_test_user, delete_pg_test_user, pg_test_user_repository},
+        utils::token::generate_random_token,
+        AppKit,
+    };
+
+    fn random_email() -> String {
+        format!("{}@example.com", generate_random_token(12).to_lowercase())
+    }
+
+    #[test]
+    fn test_should_refuse_login_of_banned_user() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let user_service = &app_kit.user_service;
+        let client_ip = format!("ban-test-{}", generate_random_token(8));
+
+        let email = random_email();
+        let user = user_service
+            .register_user("banned user", "banned_user", &email, "bannedpassword")
+            .unwrap();
+        let moderator = user_service
+            .register_user(
+                "moderator",
+                "ban_moderator",
+                &random_email(),
+                "moderatorpassword",
+            )
+            .unwrap();
+
+        assert!(matches!(
+            user_service.ban_user(moderator.id, moderator.id, permanent_ban_until(), "self"),
+            Err(UserServiceError::ErrorBan(BanError::OwnAccount))
+        ));
+
+        let banned_until = ban_until_from_days(7).unwrap();
+        user_service
+            .ban_user(user.id, moderator.id, banned_until, "spamming links")
+            .unwrap();
+
+        match user_service.login_user(&email, "bannedpassword", &client_ip) {
+            Err(UserServiceError::ErrorLogin(LoginError::Banned(ban))) => {
+                assert_eq!(ban.banned_until, banned_until);
+                assert!(ban.to_string().contains("spamming links"));
+            }
+            other => panic!("expected a banned login, got {:?}", other),
+        }
+
+        // a wrong password still reads as a wrong password
+        assert!(matches!(
+            user_service.login_user(&email, "wrongpassword", &client_ip),
+            Err(UserServiceError::ErrorLogin(LoginError::InvalidCredentials))
+        ));
+
+        user_service
+            .unban_user(user.id, moderator.id, "appeal accepted")
+            .unwrap();
+        assert!(user_service
+            .login_user(&email, "bannedpassword", &client_ip)
+            .is_ok());
+        assert!(matches!(
+            user_service.unban_user(user.id, moderator.id, ""),
+            Err(UserServiceError::ErrorBan(BanError::NotBanned))
+        ));
+
+        let bans = user_service.get_user_bans(user.id).unwrap();
+        assert_eq!(bans.len(), 2);
+        assert_eq!(bans[0].banned_until, None);
+        assert_eq!(bans[1].banned_until, Some(banned_until));
+        assert_eq!(bans[1].moderator_user_id, Some(moderator.id));
+    }
+
+    #[test]
+    fn test_should_refuse_content_of_banned_user() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("ban author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "before the ban", "body")
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "comment")
+            .unwrap();
+
+        pg_test_user_repository()
+            .update_user_ban(
+                &author,
+                Some(Utc::now().naive_utc() + Duration::days(1)),
+                Some("flooding"),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            app_kit.post_service.create_post(author.id, "title", "body"),
+            Err(PostServiceError::ErrorUserBanned(_))
+        ));
+        assert!(matches!(
+            app_kit
+                .post_service
+                .update_post(post.id, author.id, "title", "body"),
+            Err(PostServiceError::ErrorUserBanned(_))
+        ));
+        assert!(matches!(
+            app_kit
+                .comment_service
+                .create_comment(author.id, post.id, "comment"),
+            Err(CommentServiceError::ErrorUserBanned(_))
+        ));
+        assert!(matches!(
+            app_kit
+                .comment_service
+                .update_comment(comment.id, author.id, "comment"),
+            Err(CommentServiceError::ErrorUserBanned(_))
+        ));
+
+        // a ban that ran out no longer applies
+        pg_test_user_repository()
+            .update_user_ban(
+                &author,
+                Some(Utc::now().naive_utc() - Duration::minutes(1)),
+                Some("flooding"),
+            )
+            .unwrap();
+        assert!(app_kit
+            .post_service
+            .update_post(post.id, author.id, "after the ban", "body")
+            .is_ok());
+
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod tests {
+
+    use actix_web::http::StatusCode;
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::bookmark::BookmarkTarget,
+        servers::server_actix::create_actix_app,
+        services::bookmark_service::BookmarkServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[actix_web::test]
+    async fn test_should_save_posts_and_comments_in_folders() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let bookmark_service = &app_kit.bookmark_service;
+
+        let author = create_pg_test_user("bookmark author");
+        let reader = create_pg_test_user("bookmark reader");
+
+        let pagination = QueryPagination::default();
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "worth saving", &generate_random_token(16))
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "a comment worth saving")
+            .unwrap();
+
+        assert!(matches!(
+            bookmark_service.save_bookmark(reader.id, BookmarkTarget::Post(-1), "", ""),
+            Err(BookmarkServiceError::ErrorTargetNotFound)
+        ));
+
+        let post_bookmark = bookmark_service
+            .save_bookmark(reader.id, BookmarkTarget::Post(post.id), " rust ", "")
+            .unwrap();
+        assert_eq!(post_bookmark.folder, "rust");
+        // saving again keeps one bookmark and replaces the note
+        let post_bookmark_again = bookmark_service
+            .save_bookmark(
+                reader.id,
+                BookmarkTarget::Post(post.id),
+                "rust",
+                "read later",
+            )
+            .unwrap();
+        assert_eq!(post_bookmark_again.id, post_bookmark.id);
+        assert_eq!(post_bookmark_again.note, "read later");
+
+        bookmark_service
+            .save_bookmark(reader.id, BookmarkTarget::Comment(comment.id), "", "")
+            .unwrap();
+        assert!(bookmark_service
+            .is_bookmarked(reader.id, BookmarkTarget::Comment(comment.id))
+            .unwrap());
+        assert!(!bookmark_service
+            .is_bookmarked(author.id, BookmarkTarget::Comment(comment.id))
+            .unwrap());
+        assert_eq!(
+            bookmark_service
+                .get_bookmarked_comment_ids(reader.id, &[comment.id, -1])
+                .unwrap(),
+            vec![comment.id]
+        );
+
+        let saved = bookmark_service
+            .get_bookmarks(reader.id, None, &pagination)
+            .unwrap();
+        assert_eq!(saved.total, 2);
+        assert_eq!(saved.bookmarks[0].target_kind, "comment");
+        assert_eq!(
+            saved.bookmarks[0].target_url,
+            Some(format!("/posts/{}#{}", post.id, comment.id))
+        );
+        assert_eq!(saved.bookmarks[1].target_title, "worth saving");
+
+        let in_folder = bookmark_service
+            .get_bookmarks(reader.id, Some("rust"), &pagination)
+            .unwrap();
+        assert_eq!(in_folder.total, 1);
+        assert_eq!(in_folder.bookmarks[0].bookmark.id, post_bookmark.id);
+        assert_eq!(
+            bookmark_service.get_folders(reader.id).unwrap(),
+            vec!["rust"]
+        );
+
+        // only the owner can change a bookmark
+        assert!(matches!(
+            bookmark_service.update_bookmark(author.id, post_bookmark.id, "mine", ""),
+            Err(BookmarkServiceError::ErrorBookmarkNotFound)
+        ));
+        assert!(bookmark_service
+            .remove_bookmark(author.id, post_bookmark.id)
+            .is_err());
+
+        // deleted content keeps its bookmark without a link, title or excerpt
+        app_kit.comment_service.delete_comment(comment.id).unwrap();
+        let saved = bookmark_service
+            .get_bookmarks(reader.id, None, &pagination)
+            .unwrap();
+        assert_eq!(saved.bookmarks[0].target_url, None);
+        assert_eq!(saved.bookmarks[0].target_title, "(removed)");
+        assert_eq!(saved.bookmarks[0].target_excerpt, "");
+
+        // so does content hidden by moderation
+        app_kit.post_service.set_post_hidden(post.id, true).unwrap();
+        let saved = bookmark_service
+            .get_bookmarks(reader.id, None, &pagination)
+            .unwrap();
+        assert_eq!(saved.bookmarks[1].target_url, None);
+        assert_eq!(saved.bookmarks[1].target_title, "(removed)");
+        assert_eq!(saved.bookmarks[1].target_excerpt, "");
+        app_kit
+            .post_service
+            .set_post_hidden(post.id, false)
+            .unwrap();
+
+        bookmark_service
+            .remove_target_bookmark(reader.id, BookmarkTarget::Comment(comment.id))
+            .unwrap();
+        bookmark_service
+            .remove_bookmark(reader.id, post_bookmark.id)
+            .unwrap();
+        assert_eq!(
+            bookmark_service
+                .get_bookmarks(reader.id, None, &pagination)
+                .unwrap()
+                .total,
+            0
+        );
+
+        // saving needs a session, errors turn into a flash message and a redirect
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/bookmarks/post/{}", post.id))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert!(!bookmark_service
+            .is_bookmarked(reader.id, BookmarkTarget::Post(post.id))
+            .unwrap());
+
+        delete_pg_test_user(&reader);
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::{content_filter::FilterAction, report::ReportTarget},
+        services::{
+            content_filter_service::ContentFilterServiceError, post_service::PostServiceError,
+        },
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_reject_duplicate_and_hold_links_from_new_accounts() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("filtered author");
+
+        let body = format!("first post {}", generate_random_token(12));
+        app_kit
+            .post_service
+            .create_post(author.id, "first", &body)
+            .unwrap();
+        assert!(matches!(
+            app_kit.post_service.create_post(author.id, "again", &body),
+            Err(PostServiceError::ErrorContentRejected(_))
+        ));
+
+        // a fresh account posting three links is held for review
+        let held = app_kit
+            .post_service
+            .create_post(
+                author.id,
+                "links",
+                "https://a.example https://b.example www.c.example",
+            )
+            .unwrap();
+        assert!(held.hidden_at.is_some());
+        assert!(app_kit.post_service.get_post(held.id).is_err());
+
+        let pagination = QueryPagination {
+            page: 1,
+            limit: i64::from(i32::MAX),
+            ..Default::default()
+        };
+        let item = app_kit
+            .report_service
+            .get_report_queue(&pagination)
+            .unwrap()
+            .items
+            .into_iter()
+            .find(|item| item.target_kind == "post" && item.target_id == held.id)
+            .unwrap();
+        assert_eq!(item.reports.len(), 1);
+        assert!(item.reports[0].reporter_user_id.is_none());
+        assert!(item.reports[0].details.starts_with("Held:"));
+
+        // dismissing the filter report publishes the post
+        app_kit
+            .report_service
+            .dismiss_reports(ReportTarget::Post(held.id), author.id)
+            .unwrap();
+        assert!(app_kit.post_service.get_post(held.id).is_ok());
+
+        delete_pg_test_user(&author);
+    }
+
+    #[test]
+    fn test_should_apply_admin_pattern_rules() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let content_filter_service = &app_kit.content_filter_service;
+
+        assert!(matches!(
+            content_filter_service.create_rule("(unclosed", FilterAction::Reject, "", 1),
+            Err(ContentFilterServiceError::ErrorInvalidPattern(_))
+        ));
+
+        let author = create_pg_test_user("pattern author");
+
+        // a word no other test posts, the rules apply to everyone while they exist
+        let word = format!("flagword{}", generate_random_token(8).to_lowercase());
+        let rule = content_filter_service
+            .create_rule(&word, FilterAction::Flag, "test word", author.id)
+            .unwrap();
+
+        let flagged = app_kit
+            .post_service
+            .create_post(
+                author.id,
+                "flagged",
+                &format!("says {}", word.to_uppercase()),
+            )
+            .unwrap();
+        assert!(flagged.hidden_at.is_none());
+
+        let pagination = QueryPagination {
+            page: 1,
+            limit: i64::from(i32::MAX),
+            ..Default::default()
+        };
+        let item = app_kit
+            .report_service
+            .get_report_queue(&pagination)
+            .unwrap()
+            .items
+            .into_iter()
+            .find(|item| item.target_kind == "post" && item.target_id == flagged.id)
+            .unwrap();
+        assert_eq!(item.reports[0].details, "Flagged: test word");
+
+        assert_eq!(content_filter_service.delete_rule(rule.id).unwrap(), 1);
+        assert!(content_filter_service.delete_rule(rule.id).is_err());
+
+        app_kit
+            .report_service
+            .dismiss_reports(ReportTarget::Post(flagged.id), author.id)
+            .unwrap();
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        servers::server_actix::create_actix_app,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::token::generate_random_token,
+        AppKit,
+    };
+
+    #[actix_web::test]
+    async fn test_should_serve_feeds_with_conditional_get() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("feed author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "<b>Fish & chips</b>", &generate_random_token(16))
+            .unwrap();
+        app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "first comment")
+            .unwrap();
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit)).await;
+
+        // profiles are read from the in memory users of the test app kit, the comment feed of
+        // the post only needs postgres
+        let comments_feed_url = format!("/posts/{}/feed.atom", post.id);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&comments_feed_url)
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("application/atom+xml"));
+
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let last_modified = resp.headers().get(header::LAST_MODIFIED).unwrap().clone();
+
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("first comment"));
+        assert!(body.contains("Re: &lt;b&gt;Fish &amp; chips&lt;/b&gt;"));
+
+        for (name, value) in [
+            (header::IF_NONE_MATCH, etag.clone()),
+            (header::IF_MODIFIED_SINCE, last_modified),
+        ] {
+            let req = actix_web::test::TestRequest::get()
+                .uri(&comments_feed_url)
+                .insert_header((name, value))
+                .to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&comments_feed_url)
+            .insert_header((header::IF_NONE_MATCH, "\"stale\""))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/posts/{}/feed.rss", post.id))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("<rss version=\"2.0\""));
+        assert!(body.contains(&format!("/posts/{}#", post.id)));
+
+        // errors turn into a flash message and a redirect
+        for uri in ["/feed.json", "/profile/-1/feed.atom"] {
+            let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FOUND);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/feed.atom")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests {
+
+    use actix_web::http::StatusCode;
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::{
+            follow::FollowCounts,
+            live::{LiveChannel, LiveEvent},
+        },
+        servers::server_actix::create_actix_app,
+        services::follow_service::FollowServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[actix_web::test]
+    async fn test_should_follow_users_and_notify_post_subscribers() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let follow_service = &app_kit.follow_service;
+
+        let author = create_pg_test_user("followed author");
+        let follower = create_pg_test_user("follower");
+        let commenter = create_pg_test_user("commenter");
+
+        let pagination = QueryPagination::default();
+
+        assert!(matches!(
+            follow_service.follow_user(follower.id, follower.id),
+            Err(FollowServiceError::ErrorFollowYourself)
+        ));
+        assert!(matches!(
+            follow_service.follow_user(follower.id, -1),
+            Err(FollowServiceError::ErrorUserNotFound)
+        ));
+
+        follow_service.follow_user(follower.id, author.id).unwrap();
+        // following twice changes nothing
+        follow_service.follow_user(follower.id, author.id).unwrap();
+        assert!(follow_service.is_following(follower.id, author.id).unwrap());
+        assert!(!follow_service.is_following(author.id, follower.id).unwrap());
+        assert_eq!(
+            follow_service.get_follow_counts(author.id).unwrap(),
+            FollowCounts {
+                followers: 1,
+                following: 0
+            }
+        );
+
+        let followers = follow_service
+            .get_followers(author.id, &pagination)
+            .unwrap();
+        assert_eq!(followers.total, 1);
+        assert_eq!(followers.users[0].id, follower.id);
+        let following = follow_service
+            .get_following(follower.id, &pagination)
+            .unwrap();
+        assert_eq!(following.users[0].id, author.id);
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "for my followers", &generate_random_token(16))
+            .unwrap();
+        app_kit
+            .post_service
+            .create_post(commenter.id, "not followed", &generate_random_token(16))
+            .unwrap();
+
+        let followed_posts = follow_service
+            .get_followed_posts(follower.id, &pagination)
+            .unwrap();
+        assert_eq!(followed_posts.total, 1);
+        assert_eq!(followed_posts.posts[0].post.id, post.id);
+
+        assert!(matches!(
+            follow_service.subscribe_post(follower.id, -1),
+            Err(FollowServiceError::ErrorPostNotFound)
+        ));
+        follow_service.subscribe_post(follower.id, post.id).unwrap();
+        assert!(follow_service.is_subscribed(follower.id, post.id).unwrap());
+
+        let mut follower_events = app_kit
+            .live_service
+            .subscribe(LiveChannel::User(follower.id));
+        let mut commenter_events = app_kit
+            .live_service
+            .subscribe(LiveChannel::User(commenter.id));
+
+        app_kit
+            .comment_service
+            .create_comment(commenter.id, post.id, "nice post")
+            .unwrap();
+        assert!(matches!(
+            follower_events.try_recv(),
+            Ok(LiveEvent::UnreadNotifications { count: 1 })
+        ));
+        assert!(commenter_events.try_recv().is_err());
+
+        follow_service
+            .unsubscribe_post(follower.id, post.id)
+            .unwrap();
+        app_kit
+            .comment_service
+            .create_comment(commenter.id, post.id, "anyone?")
+            .unwrap();
+        assert!(follower_events.try_recv().is_err());
+        assert!(follow_service
+            .unsubscribe_post(follower.id, post.id)
+            .is_err());
+
+        follow_service
+            .unfollow_user(follower.id, author.id)
+            .unwrap();
+        assert_eq!(
+            follow_service
+                .get_followed_posts(follower.id, &pagination)
+                .unwrap()
+                .total,
+            0
+        );
+        assert!(follow_service
+            .unfollow_user(follower.id, author.id)
+            .is_err());
+
+        // the Following tab needs a session, errors turn into a flash message and a redirect
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+        let req = actix_web::test::TestRequest::get()
+            .uri("/posts/following")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+
+        for user in [&commenter, &follower, &author] {
+            delete_pg_test_user(user);
+        }
+    }
+}
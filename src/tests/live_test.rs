@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::live::{LiveChannel, LiveEvent},
+        servers::server_actix::create_actix_app,
+        services::live_service::{InMemoryLiveService, LiveService},
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::token::generate_random_token,
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_fan_out_events_to_channel_listeners() {
+        let live_service = InMemoryLiveService::new(2);
+
+        // nobody listens, the event is dropped
+        live_service.publish(LiveChannel::Post(1), LiveEvent::PostDeleted);
+
+        let mut first = live_service.subscribe(LiveChannel::Post(1));
+        let mut second = live_service.subscribe(LiveChannel::Post(1));
+        let mut other = live_service.subscribe(LiveChannel::User(1));
+
+        live_service.publish(LiveChannel::Post(1), LiveEvent::PostLocked { locked: true });
+        assert!(matches!(
+            first.try_recv(),
+            Ok(LiveEvent::PostLocked { locked: true })
+        ));
+        assert!(matches!(
+            second.try_recv(),
+            Ok(LiveEvent::PostLocked { locked: true })
+        ));
+        assert!(other.try_recv().is_err());
+
+        assert_eq!(
+            LiveEvent::UnreadMessages { count: 3 }.to_event_stream(),
+            "data: {\"type\":\"unread_messages\",\"count\":3}\n\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_should_push_comment_changes_to_post_readers_and_post_author() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("live author");
+        let reader = create_pg_test_user("live reader");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "live post", &generate_random_token(16))
+            .unwrap();
+
+        let mut post_events = app_kit.live_service.subscribe(LiveChannel::Post(post.id));
+        let mut author_events = app_kit.live_service.subscribe(LiveChannel::User(author.id));
+
+        let comment = app_kit
+            .comment_service
+            .create_comment(reader.id, post.id, "first!")
+            .unwrap();
+        match post_events.try_recv() {
+            Ok(LiveEvent::CommentCreated { comment: live }) => {
+                assert_eq!(live.id, comment.id);
+                assert_eq!(live.user_name, "live reader");
+                assert_eq!(live.content, "first!");
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(matches!(
+            author_events.try_recv(),
+            Ok(LiveEvent::UnreadNotifications { count: 1 })
+        ));
+
+        // the author replying under their own post is not a notification
+        app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "thanks")
+            .unwrap();
+        assert!(post_events.try_recv().is_ok());
+        assert!(author_events.try_recv().is_err());
+
+        app_kit
+            .comment_service
+            .update_comment(comment.id, reader.id, "first, edited")
+            .unwrap();
+        assert!(matches!(
+            post_events.try_recv(),
+            Ok(LiveEvent::CommentUpdated { ref content, .. }) if content == "first, edited"
+        ));
+
+        app_kit.comment_service.delete_comment(comment.id).unwrap();
+        assert!(matches!(
+            post_events.try_recv(),
+            Ok(LiveEvent::CommentDeleted { comment_id }) if comment_id == comment.id
+        ));
+
+        app_kit.post_service.set_post_locked(post.id, true).unwrap();
+        assert!(matches!(
+            post_events.try_recv(),
+            Ok(LiveEvent::PostLocked { locked: true })
+        ));
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/posts/{}/events", post.id))
+            .to_request();
+        let events_resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(events_resp.status(), StatusCode::OK);
+        assert_eq!(
+            events_resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+
+        // errors turn into a flash message and a redirect
+        for uri in ["/posts/-1/events", "/events"] {
+            let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FOUND);
+        }
+
+        app_kit.post_service.delete_post(post.id).unwrap();
+        assert!(matches!(post_events.try_recv(), Ok(LiveEvent::PostDeleted)));
+
+        // the event stream of the post ends once it is deleted
+        let events_body = actix_web::rt::time::timeout(
+            Duration::from_secs(5),
+            actix_web::test::read_body(events_resp),
+        )
+        .await
+        .unwrap();
+        assert_eq!(events_body, LiveEvent::PostDeleted.to_event_stream());
+
+        delete_pg_test_user(&reader);
+        delete_pg_test_user(&author);
+    }
+}
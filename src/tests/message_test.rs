@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::message::NewConversationFormData,
+        services::message_service::MessageServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::pagination::QueryPagination,
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_parse_message_recipients() {
+        let form = |recipients: &str| NewConversationFormData {
+            recipients: recipients.to_string(),
+            title: String::new(),
+            body: "hello".to_string(),
+        };
+
+        assert_eq!(form(" 3, 5,,3 ").recipient_ids(), Some(vec![3, 5]));
+        assert_eq!(form("3, someone").recipient_ids(), None);
+    }
+
+    #[test]
+    fn test_should_send_direct_and_group_messages_unless_blocked() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let message_service = &app_kit.message_service;
+
+        let alice = create_pg_test_user("alice");
+        let bob = create_pg_test_user("bob");
+        let carol = create_pg_test_user("carol");
+        let first_page = QueryPagination::default();
+
+        assert!(matches!(
+            message_service.start_conversation(alice.id, &[alice.id], "", "hi me"),
+            Err(MessageServiceError::ErrorMessageYourself)
+        ));
+
+        // a second first message lands in the same one-to-one conversation
+        let direct = message_service
+            .start_conversation(alice.id, &[bob.id], "", "hi bob")
+            .unwrap();
+        assert!(!direct.is_group);
+        let again = message_service
+            .start_conversation(alice.id, &[bob.id], "", "are you there?")
+            .unwrap();
+        assert_eq!(again.id, direct.id);
+
+        let inbox = message_service.get_inbox(bob.id, &first_page).unwrap();
+        assert_eq!(inbox.total, 1);
+        assert_eq!(inbox.conversations[0].display_title, "alice");
+        assert_eq!(inbox.conversations[0].unread_count, 2);
+        assert_eq!(
+            inbox.conversations[0].last_message_preview.as_deref(),
+            Some("are you there?")
+        );
+        assert_eq!(message_service.count_unread_messages(alice.id).unwrap(), 0);
+
+        message_service
+            .mark_conversation_read(direct.id, bob.id)
+            .unwrap();
+        assert_eq!(message_service.count_unread_messages(bob.id).unwrap(), 0);
+
+        // the newest message comes last on the first page
+        let messages = message_service
+            .get_messages(direct.id, bob.id, &first_page)
+            .unwrap();
+        assert_eq!(messages.total, 2);
+        assert_eq!(messages.messages[1].message.body, "are you there?");
+        assert!(!messages.messages[1].allow_delete);
+
+        // only the author deletes a message, the text is gone for everyone
+        let message_id = messages.messages[1].message.id;
+        assert!(matches!(
+            message_service.delete_message(direct.id, message_id, bob.id),
+            Err(MessageServiceError::ErrorDeleteMessage)
+        ));
+        message_service
+            .delete_message(direct.id, message_id, alice.id)
+            .unwrap();
+        let messages = message_service
+            .get_messages(direct.id, bob.id, &first_page)
+            .unwrap();
+        assert!(messages.messages[1].message.deleted_at.is_some());
+        assert!(messages.messages[1].message.body.is_empty());
+
+        // a block stops new conversations and messages in existing ones
+        message_service.block_user(bob.id, alice.id).unwrap();
+        assert!(message_service.is_blocked(bob.id, alice.id).unwrap());
+        assert!(matches!(
+            message_service.send_message(direct.id, alice.id, "hello?"),
+            Err(MessageServiceError::ErrorBlocked)
+        ));
+        assert!(matches!(
+            message_service.start_conversation(alice.id, &[bob.id, carol.id], "", "hi all"),
+            Err(MessageServiceError::ErrorBlocked)
+        ));
+        // the blocker can still write
+        message_service
+            .send_message(direct.id, bob.id, "please stop")
+            .unwrap();
+        message_service.unblock_user(bob.id, alice.id).unwrap();
+        message_service
+            .send_message(direct.id, alice.id, "sorry")
+            .unwrap();
+
+        let group = message_service
+            .start_conversation(alice.id, &[bob.id, carol.id], " Team ", "hi team")
+            .unwrap();
+        assert!(group.is_group);
+        assert_eq!(group.title, "Team");
+        assert_eq!(
+            message_service
+                .get_conversation(group.id, carol.id)
+                .unwrap()
+                .members
+                .len(),
+            3
+        );
+
+        message_service
+            .leave_conversation(group.id, carol.id)
+            .unwrap();
+        assert!(matches!(
+            message_service.send_message(group.id, carol.id, "back"),
+            Err(MessageServiceError::ErrorNotMember)
+        ));
+        assert!(matches!(
+            message_service.get_messages(group.id, carol.id, &first_page),
+            Err(MessageServiceError::ErrorNotMember)
+        ));
+        assert!(matches!(
+            message_service.leave_conversation(direct.id, alice.id),
+            Err(MessageServiceError::ErrorLeaveConversation)
+        ));
+
+        // outsiders do not see a conversation
+        assert!(matches!(
+            message_service.get_conversation(direct.id, carol.id),
+            Err(MessageServiceError::ErrorNotMember)
+        ));
+
+        for user in [&alice, &bob, &carol] {
+            delete_pg_test_user(user);
+        }
+    }
+}
@@ -1,9 +1,43 @@
-use actix_web::dev::ServiceResponse;
+use std::sync::{Arc, LazyLock};
 
+use actix_web::{cookie::Cookie, dev::ServiceResponse};
+
+use crate::{
+    db::initialize_db_pool,
+    models::User,
+    repositories::{
+        user_repository::UserRepository, user_repository_postgres::PostgresUserRepository,
+    },
+    utils::token::generate_random_token,
+};
+
+mod account_test;
+mod attachment_test;
+mod audit_test;
+mod avatar_test;
+mod ban_test;
+mod bookmark_test;
+mod content_filter_test;
+mod feed_test;
+mod file_storage_test;
+mod follow_test;
+mod live_test;
+mod message_test;
+mod notification_test;
+mod oauth_test;
+mod pagination_test;
+mod pin_lock_test;
+mod profile_test;
+mod rate_limit_test;
+mod report_test;
+mod revision_test;
+mod seo_test;
+mod trash_test;
+mod username_test;
 mod users_test;
 
 pub async fn debug_response_data(resp: ServiceResponse<crate::servers::server_actix::NestedBody>) {
-    dbg!((&resp).response().status());
+    dbg!(resp.response().status());
 
     dbg!(&resp.headers());
 
@@ -12,3 +46,54 @@ pub async fn debug_response_data(resp: ServiceResponse<crate::servers::server_ac
     let response_body = String::from_utf8(resp_read_body_bytes.to_vec()).unwrap();
     dbg!(&response_body);
 }
+
+/// Session cookie and CSRF token needed to submit a form
+pub struct CsrfFormSession {
+    pub cookie: Cookie<'static>,
+    pub token: String,
+}
+
+/// Reads the session cookie and CSRF token issued by a form page response
+pub async fn read_csrf_form_session(
+    resp: ServiceResponse<crate::servers::server_actix::NestedBody>,
+) -> CsrfFormSession {
+    let cookie = resp
+        .response()
+        .cookies()
+        .next()
+        .expect("form page should set a session cookie")
+        .into_owned();
+
+    let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+
+    let token = body
+        .split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("form page should render a csrf token")
+        .to_string();
+
+    CsrfFormSession { cookie, token }
+}
+
+/// Users in postgres, posts and comments reference them while the test app kit keeps users in memory
+static PG_TEST_USER_REPOSITORY: LazyLock<PostgresUserRepository> =
+    LazyLock::new(|| PostgresUserRepository::new(Arc::new(initialize_db_pool())));
+
+pub fn pg_test_user_repository() -> &'static PostgresUserRepository {
+    &PG_TEST_USER_REPOSITORY
+}
+
+/// Creates a postgres user with a random email, to author content in tests
+pub fn create_pg_test_user(name: &str) -> User {
+    let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+
+    pg_test_user_repository()
+        .create_user_without_password(name, &email)
+        .unwrap()
+}
+
+/// Deletes a user made by `create_pg_test_user`, their content goes with them
+pub fn delete_pg_test_user(user: &User) {
+    pg_test_user_repository().delete_user(user).unwrap();
+}
@@ -0,0 +1,165 @@
+#[cfg(test)]
+mod tests {
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::{
+            live::{LiveChannel, LiveEvent},
+            notification::{NOTIFICATION_KIND_POST_REPLY, NOTIFICATION_KIND_SUBSCRIPTION_REPLY},
+        },
+        servers::server_actix::create_actix_app,
+        services::notification_service::NotificationServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[actix_web::test]
+    async fn test_should_keep_reply_notifications_until_read() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let notification_service = &app_kit.notification_service;
+
+        let author = create_pg_test_user("notified author");
+        let subscriber = create_pg_test_user("notified subscriber");
+        let commenter = create_pg_test_user("notifying commenter");
+
+        let pagination = QueryPagination::default();
+
+        let post = app_kit
+            .post_service
+            .create_post(
+                author.id,
+                "tell me about replies",
+                &generate_random_token(16),
+            )
+            .unwrap();
+        app_kit
+            .follow_service
+            .subscribe_post(subscriber.id, post.id)
+            .unwrap();
+        // the author subscribed to their own post is told once, as the author
+        app_kit
+            .follow_service
+            .subscribe_post(author.id, post.id)
+            .unwrap();
+
+        let mut author_events = app_kit.live_service.subscribe(LiveChannel::User(author.id));
+
+        let comment = app_kit
+            .comment_service
+            .create_comment(commenter.id, post.id, "a reply")
+            .unwrap();
+        assert!(matches!(
+            author_events.try_recv(),
+            Ok(LiveEvent::UnreadNotifications { count: 1 })
+        ));
+        assert!(author_events.try_recv().is_err());
+
+        let author_notifications = notification_service
+            .get_notifications(author.id, &pagination)
+            .unwrap();
+        assert_eq!(author_notifications.total, 1);
+        let notification = &author_notifications.notifications[0];
+        assert_eq!(notification.notification.kind, NOTIFICATION_KIND_POST_REPLY);
+        assert_eq!(notification.notification.comment_id, comment.id);
+        assert_eq!(notification.post_title, "tell me about replies");
+        assert_eq!(notification.actor_name, "notifying commenter");
+        assert!(notification.unread);
+
+        let subscriber_notifications = notification_service
+            .get_notifications(subscriber.id, &pagination)
+            .unwrap();
+        assert_eq!(
+            subscriber_notifications.notifications[0].notification.kind,
+            NOTIFICATION_KIND_SUBSCRIPTION_REPLY
+        );
+        assert_eq!(
+            notification_service
+                .count_unread_notifications(commenter.id)
+                .unwrap(),
+            0
+        );
+
+        // only the receiving user can read a notification
+        let notification_id = notification.notification.id;
+        assert!(matches!(
+            notification_service.read_notification(subscriber.id, notification_id),
+            Err(NotificationServiceError::ErrorNotificationNotFound)
+        ));
+        notification_service
+            .read_notification(author.id, notification_id)
+            .unwrap();
+        assert!(matches!(
+            author_events.try_recv(),
+            Ok(LiveEvent::UnreadNotifications { count: 0 })
+        ));
+        let author_notifications = notification_service
+            .get_notifications(author.id, &pagination)
+            .unwrap();
+        assert_eq!(author_notifications.total, 1);
+        assert!(!author_notifications.notifications[0].unread);
+
+        app_kit
+            .comment_service
+            .create_comment(commenter.id, post.id, "another reply")
+            .unwrap();
+        assert_eq!(
+            notification_service
+                .count_unread_notifications(subscriber.id)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            notification_service.mark_all_read(subscriber.id).unwrap(),
+            2
+        );
+        assert_eq!(
+            notification_service
+                .count_unread_notifications(subscriber.id)
+                .unwrap(),
+            0
+        );
+
+        // replies deleted since are no longer listed or counted
+        app_kit.comment_service.delete_comment(comment.id).unwrap();
+        assert_eq!(
+            notification_service
+                .get_notifications(author.id, &pagination)
+                .unwrap()
+                .total,
+            1
+        );
+        assert_eq!(
+            notification_service
+                .count_unread_notifications(author.id)
+                .unwrap(),
+            1
+        );
+
+        // the list and the read routes need a session
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+        for req in [
+            actix_web::test::TestRequest::get()
+                .uri("/notifications")
+                .to_request(),
+            actix_web::test::TestRequest::get()
+                .uri(&format!("/notifications/{}", notification_id))
+                .to_request(),
+        ] {
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FOUND);
+            assert_ne!(
+                resp.headers().get(header::LOCATION).unwrap(),
+                format!("/posts/{}#{}", post.id, comment.id).as_str()
+            );
+        }
+
+        delete_pg_test_user(&commenter);
+        delete_pg_test_user(&subscriber);
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,269 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::header, http::StatusCode, web, HttpRequest, HttpResponse, HttpServer};
+    use dotenv::dotenv;
+    use serde_json::json;
+
+    use crate::{
+        servers::server_actix::create_actix_app,
+        services::{
+            oauth_service::{BasedOAuthService, OAuthProvider, OAuthUserInfo},
+            user_service::{IdentityError, UserServiceError},
+        },
+        AppKit,
+    };
+
+    const MOCK_PROVIDER: &str = "mock";
+
+    fn mock_issuer_url(req: &HttpRequest) -> String {
+        format!("http://{}", req.connection_info().host())
+    }
+
+    async fn mock_discovery(req: HttpRequest) -> HttpResponse {
+        let issuer_url = mock_issuer_url(&req);
+
+        HttpResponse::Ok().json(json!({
+            "issuer": issuer_url,
+            "authorization_endpoint": format!("{}/authorize", issuer_url),
+            "token_endpoint": format!("{}/token", issuer_url),
+            "userinfo_endpoint": format!("{}/userinfo", issuer_url),
+        }))
+    }
+
+    /// the authorization code is the subject of the user signing in
+    async fn mock_token(
+        form: web::Form<std::collections::HashMap<String, String>>,
+    ) -> HttpResponse {
+        match form
+            .get("code")
+            .filter(|_| form.get("client_secret").is_some())
+        {
+            Some(code) => HttpResponse::Ok().json(json!({
+                "access_token": format!("access-{}", code),
+                "token_type": "Bearer",
+            })),
+            None => HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" })),
+        }
+    }
+
+    async fn mock_userinfo(req: HttpRequest) -> HttpResponse {
+        let subject = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer access-"));
+
+        match subject {
+            Some(subject) => HttpResponse::Ok().json(json!({
+                "sub": subject,
+                "email": format!("{}@example.com", subject),
+                "email_verified": true,
+                "name": format!("Mock {}", subject),
+            })),
+            None => HttpResponse::Unauthorized().finish(),
+        }
+    }
+
+    /// Starts a local OpenID Connect issuer and returns its URL
+    fn start_mock_oidc_issuer() -> String {
+        let server = HttpServer::new(|| {
+            actix_web::App::new()
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(mock_discovery),
+                )
+                .route("/token", web::post().to(mock_token))
+                .route("/userinfo", web::get().to(mock_userinfo))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let issuer_url = format!("http://{}", server.addrs()[0]);
+
+        actix_web::rt::spawn(server.run());
+
+        issuer_url
+    }
+
+    fn app_kit_with_mock_provider(issuer_url: &str) -> AppKit {
+        let mut app_kit = AppKit::new_for_testing();
+
+        app_kit.oauth_service = Arc::new(BasedOAuthService::new(
+            vec![OAuthProvider::openid_connect(
+                MOCK_PROVIDER,
+                "Mock Issuer",
+                issuer_url,
+                "forum-client",
+                "forum-secret",
+            )],
+            "http://localhost:3000",
+        ));
+
+        app_kit
+    }
+
+    #[actix_web::test]
+    async fn test_should_register_and_login_with_oidc_identity() {
+        dotenv().ok();
+
+        let issuer_url = start_mock_oidc_issuer();
+        let app_kit = app_kit_with_mock_provider(&issuer_url);
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let login_req = actix_web::test::TestRequest::get()
+            .uri("/auth/mock/login")
+            .to_request();
+        let login_resp = actix_web::test::call_service(&app, login_req).await;
+
+        assert_eq!(login_resp.status(), StatusCode::FOUND);
+
+        let authorization_url = reqwest::Url::parse(
+            login_resp
+                .headers()
+                .get(header::LOCATION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(authorization_url
+            .as_str()
+            .starts_with(&format!("{}/authorize?", issuer_url)));
+
+        let state = authorization_url
+            .query_pairs()
+            .find(|(key, _)| key == "state")
+            .map(|(_, value)| value.to_string())
+            .unwrap();
+
+        let session_cookie = login_resp.response().cookies().next().unwrap().into_owned();
+
+        let callback_req = actix_web::test::TestRequest::get()
+            .uri(&format!("/auth/mock/callback?code=alice&state={}", state))
+            .cookie(session_cookie)
+            .to_request();
+        let callback_resp = actix_web::test::call_service(&app, callback_req).await;
+
+        assert_eq!(callback_resp.status(), StatusCode::FOUND);
+        assert_eq!(callback_resp.headers().get(header::LOCATION).unwrap(), "/");
+
+        let user = app_kit
+            .user_service
+            .get_user_by_email("alice@example.com")
+            .unwrap();
+
+        assert_eq!(user.name, "Mock alice");
+        assert!(user.password.is_none());
+
+        let identities = app_kit.user_service.get_user_identities(user.id).unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].provider, MOCK_PROVIDER);
+        assert_eq!(identities[0].provider_subject, "alice");
+
+        // the only way to sign in can not be removed until a password is set
+        assert!(matches!(
+            app_kit
+                .user_service
+                .unlink_user_identity(user.id, MOCK_PROVIDER),
+            Err(UserServiceError::ErrorIdentity(
+                IdentityError::LastSignInMethod
+            ))
+        ));
+
+        app_kit
+            .user_service
+            .update_user_password(user.id, "alicepassword")
+            .unwrap();
+
+        assert!(app_kit
+            .user_service
+            .unlink_user_identity(user.id, MOCK_PROVIDER)
+            .is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_should_reject_oauth_callback_with_invalid_state() {
+        dotenv().ok();
+
+        let issuer_url = start_mock_oidc_issuer();
+        let app_kit = app_kit_with_mock_provider(&issuer_url);
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let login_req = actix_web::test::TestRequest::get()
+            .uri("/auth/mock/login")
+            .to_request();
+        let login_resp = actix_web::test::call_service(&app, login_req).await;
+
+        let session_cookie = login_resp.response().cookies().next().unwrap().into_owned();
+
+        let callback_req = actix_web::test::TestRequest::get()
+            .uri("/auth/mock/callback?code=mallory&state=forged")
+            .cookie(session_cookie)
+            .to_request();
+        let callback_resp = actix_web::test::call_service(&app, callback_req).await;
+
+        assert_eq!(callback_resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            callback_resp.headers().get(header::LOCATION).unwrap(),
+            "/users/login"
+        );
+
+        assert!(app_kit
+            .user_service
+            .get_user_by_email("mallory@example.com")
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_should_not_take_over_existing_account_by_email() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let user = app_kit
+            .user_service
+            .register_user(
+                "bob example",
+                "bob_example",
+                "bob@example.com",
+                "bobpassword",
+            )
+            .unwrap();
+
+        let user_info = OAuthUserInfo {
+            subject: "bob-subject".to_string(),
+            email: Some("bob@example.com".to_string()),
+            email_verified: true,
+            name: Some("Bob".to_string()),
+        };
+
+        assert!(matches!(
+            app_kit
+                .user_service
+                .login_with_identity(MOCK_PROVIDER, &user_info),
+            Err(UserServiceError::ErrorIdentity(IdentityError::EmailInUse(
+                _
+            )))
+        ));
+
+        // once linked from settings the identity logs into the existing account
+        app_kit
+            .user_service
+            .link_user_identity(user.id, MOCK_PROVIDER, &user_info)
+            .unwrap();
+
+        let logged_in_user = app_kit
+            .user_service
+            .login_with_identity(MOCK_PROVIDER, &user_info)
+            .unwrap();
+
+        assert_eq!(logged_in_user.id, user.id);
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        handlebars_helper::pagination::HandlebarsPaginationResult,
+        servers::server_actix::create_actix_app, AppKit,
+    };
+
+    fn pagination_result(page: i64, total_pages: i64) -> HandlebarsPaginationResult {
+        HandlebarsPaginationResult {
+            page,
+            limit: 10,
+            total_pages,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_should_render_window_of_pages() {
+        let labels = |result: &HandlebarsPaginationResult| {
+            result
+                .links()
+                .into_iter()
+                .map(|link| match (link.url.is_some(), link.active) {
+                    (_, true) => format!("[{}]", link.label),
+                    (true, false) => link.label,
+                    (false, false) => format!("({})", link.label),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        assert_eq!(
+            labels(&pagination_result(50, 1000)),
+            "First Prev (…) 48 49 [50] 51 52 (…) Next Last"
+        );
+        assert_eq!(
+            labels(&pagination_result(1, 3)),
+            "(First) (Prev) [1] 2 3 Next Last"
+        );
+        assert_eq!(
+            labels(&pagination_result(1000, 1000)),
+            "First Prev (…) 998 999 [1000] (Next) (Last)"
+        );
+        assert!(pagination_result(1, 0).links().is_empty());
+
+        let link = &pagination_result(50, 1000).links()[0];
+        assert_eq!(link.url.as_deref(), Some("?page=1&per_page=10"));
+    }
+
+    #[test]
+    fn test_should_link_neighbour_pages_and_catch_pages_past_the_end() {
+        assert_eq!(
+            pagination_result(2, 3).link_header("/posts/1").unwrap(),
+            r#"</posts/1?page=1&per_page=10>; rel="prev", </posts/1?page=3&per_page=10>; rel="next""#
+        );
+        assert_eq!(pagination_result(1, 1).link_header("/"), None);
+
+        assert_eq!(pagination_result(3, 3).out_of_range_page(), None);
+        assert_eq!(pagination_result(9, 3).out_of_range_page(), Some(3));
+        assert_eq!(pagination_result(1, 0).out_of_range_page(), None);
+        assert_eq!(pagination_result(2, 0).out_of_range_page(), Some(1));
+
+        // the other query parameters of a listing stay, encoded again
+        let filtered = HandlebarsPaginationResult {
+            filters: vec![("folder".to_string(), "to read & later".to_string())],
+            ..pagination_result(2, 3)
+        };
+        assert_eq!(
+            filtered.page_url(3),
+            "?page=3&per_page=10&folder=to+read+%26+later"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_should_redirect_page_past_the_end_to_last_page() {
+        dotenv().ok();
+
+        let app = actix_web::test::init_service(create_actix_app(AppKit::new_for_testing())).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/?page={}&per_page=100&folder=a%26b", i32::MAX))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        let location = resp.headers().get(header::LOCATION).unwrap();
+        assert!(location
+            .to_str()
+            .unwrap()
+            .ends_with("&per_page=100&folder=a%26b"));
+
+        // a page whose offset would overflow is sent to the last page too
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/?page={}&per_page=100", i64::MAX))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::FOUND);
+    }
+}
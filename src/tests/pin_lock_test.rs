@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+
+    use dotenv::dotenv;
+
+    use crate::{
+        services::comment_service::CommentServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{pagination::QueryPagination, token::generate_random_token},
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_list_pinned_posts_apart_and_refuse_comments_on_locked_posts() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let post_service = &app_kit.post_service;
+
+        let author = create_pg_test_user("pinned author");
+
+        let post = post_service
+            .create_post(author.id, "announcement", &generate_random_token(16))
+            .unwrap();
+
+        let pagination = QueryPagination {
+            page: 1,
+            limit: i64::from(i32::MAX),
+            ..Default::default()
+        };
+        let listed = |post_id: i32| {
+            post_service
+                .get_posts_with_user(&pagination)
+                .unwrap()
+                .posts
+                .iter()
+                .any(|p| p.post.id == post_id)
+        };
+        let pinned = |post_id: i32| {
+            post_service
+                .get_pinned_posts_with_user()
+                .unwrap()
+                .iter()
+                .any(|p| p.post.id == post_id)
+        };
+        assert!(listed(post.id));
+        assert!(!pinned(post.id));
+
+        post_service.set_post_pinned(post.id, true).unwrap();
+        assert!(!listed(post.id));
+        assert!(pinned(post.id));
+
+        // moderation does not count as an edit
+        let pinned_post = post_service.get_post(post.id).unwrap();
+        assert!(pinned_post.pinned);
+        assert_eq!(pinned_post.updated_at, post.updated_at);
+
+        post_service.set_post_locked(post.id, true).unwrap();
+        assert!(matches!(
+            app_kit
+                .comment_service
+                .create_comment(author.id, post.id, "too late"),
+            Err(CommentServiceError::ErrorPostLocked)
+        ));
+
+        post_service.set_post_locked(post.id, false).unwrap();
+        app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "welcome back")
+            .unwrap();
+
+        post_service.set_post_pinned(post.id, false).unwrap();
+        assert!(listed(post.id));
+        assert!(post_service.set_post_pinned(-1, true).is_err());
+
+        delete_pg_test_user(&author);
+    }
+}
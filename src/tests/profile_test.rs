@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dotenv::dotenv;
+    use validator::Validate;
+
+    use crate::{
+        db::initialize_db_pool,
+        entities::{bookmark::BookmarkTarget, user::UserProfileFormData},
+        repositories::user_stats_repository::{PostgresUserStatsRepository, UserStatsRepository},
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::{markdown::render_markdown, token::generate_random_token},
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_render_sanitized_markdown() {
+        assert_eq!(
+            render_markdown("Hello **world**"),
+            "<p>Hello <strong>world</strong></p>\n"
+        );
+
+        let html = render_markdown(
+            "<script>alert(1)</script>\n\n[site](https://example.com) [bad](javascript:alert(1)) ![img](https://example.com/a.png)",
+        );
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains(
+            r#"<a href="https://example.com" rel="nofollow ugc noopener noreferrer">site</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_should_validate_profile_fields() {
+        let form = |website: &str| UserProfileFormData {
+            website: website.to_string(),
+            ..Default::default()
+        };
+
+        assert!(form("").validate().is_ok());
+        assert!(form("https://example.com/me").validate().is_ok());
+        assert!(form("javascript:alert(1)").validate().is_err());
+        assert!(form("example.com").validate().is_err());
+
+        let long_bio = UserProfileFormData {
+            bio: "a".repeat(2001),
+            ..Default::default()
+        };
+        assert!(long_bio.validate().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_should_update_profile_and_count_user_stats() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let user = app_kit
+            .user_service
+            .register_user(
+                "profile owner",
+                "profile_owner",
+                "profile-owner@example.com",
+                "profilepassword",
+            )
+            .unwrap();
+        app_kit
+            .user_service
+            .update_user_profile(
+                user.id,
+                &UserProfileFormData {
+                    bio: "I write *rust*".to_string(),
+                    website: " https://example.com ".to_string(),
+                    location: "Berlin".to_string(),
+                    pronouns: "they/them".to_string(),
+                    signature: "cheers".to_string(),
+                },
+            )
+            .unwrap();
+
+        let profile = app_kit.user_service.get_user_profile(user.id).unwrap();
+        assert_eq!(profile.website, "https://example.com");
+        assert_eq!(profile.bio_html, "<p>I write <em>rust</em></p>\n");
+        assert_eq!(profile.pronouns, "they/them");
+
+        // stats are counted from content in postgres, the test app kit keeps users in memory
+        let stats_repo = PostgresUserStatsRepository::new(Arc::new(initialize_db_pool()));
+        let author = create_pg_test_user("stats author");
+        let reader = create_pg_test_user("stats reader");
+
+        let stats = stats_repo.get_user_stats(author.id).unwrap();
+        assert_eq!(stats.post_count, 0);
+        assert_eq!(stats.last_seen_at, None);
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "counted post", &generate_random_token(16))
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "counted comment")
+            .unwrap();
+
+        app_kit
+            .follow_service
+            .follow_user(reader.id, author.id)
+            .unwrap();
+        app_kit
+            .bookmark_service
+            .save_bookmark(reader.id, BookmarkTarget::Comment(comment.id), "", "")
+            .unwrap();
+        // saving your own post earns no reputation
+        app_kit
+            .bookmark_service
+            .save_bookmark(author.id, BookmarkTarget::Post(post.id), "", "")
+            .unwrap();
+
+        let stats = stats_repo.get_user_stats(author.id).unwrap();
+        assert_eq!(stats.post_count, 1);
+        assert_eq!(stats.comment_count, 1);
+        assert_eq!(stats.reputation, 2);
+        assert_eq!(stats.last_seen_at, Some(comment.created_at));
+        assert!(stats.last_seen_human.is_some());
+
+        // a comment under a deleted post is no longer counted
+        app_kit.post_service.delete_post(post.id).unwrap();
+        let stats = stats_repo.get_user_stats(author.id).unwrap();
+        assert_eq!(stats.post_count, 0);
+        assert_eq!(stats.comment_count, 0);
+
+        delete_pg_test_user(&reader);
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::{
+        services::rate_limit_service::{
+            InMemoryRateLimitService, RateLimitService, RateLimitServiceError,
+            CREATE_POST_RATE_LIMIT_POLICY, RESET_PASSWORD_RATE_LIMIT_POLICY,
+        },
+        utils::http::client_ip_behind_proxies,
+    };
+
+    #[actix_web::test]
+    async fn test_should_limit_requests_per_policy_and_key() {
+        let rate_limit_service = InMemoryRateLimitService::new();
+
+        for _ in 0..CREATE_POST_RATE_LIMIT_POLICY.limit {
+            let status = rate_limit_service
+                .hit(&CREATE_POST_RATE_LIMIT_POLICY, "user:1")
+                .await;
+
+            assert!(status.is_ok());
+        }
+
+        let exceeded = rate_limit_service
+            .hit(&CREATE_POST_RATE_LIMIT_POLICY, "user:1")
+            .await;
+
+        assert!(matches!(
+            exceeded,
+            Err(RateLimitServiceError::LimitExceeded { policy_name, .. })
+                if policy_name == CREATE_POST_RATE_LIMIT_POLICY.name
+        ));
+
+        // other users and other policies keep their own counters
+        let other_user = rate_limit_service
+            .hit(&CREATE_POST_RATE_LIMIT_POLICY, "user:2")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            other_user.remaining,
+            CREATE_POST_RATE_LIMIT_POLICY.limit - 1
+        );
+
+        let other_policy = rate_limit_service
+            .hit(&RESET_PASSWORD_RATE_LIMIT_POLICY, "user:1")
+            .await;
+
+        assert!(other_policy.is_ok());
+    }
+
+    #[test]
+    fn test_should_only_trust_forwarded_for_from_trusted_proxies() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        // a client talking to the forum directly can not pick its address
+        assert_eq!(
+            client_ip_behind_proxies(ip("203.0.113.7"), Some("198.51.100.1"), &proxies),
+            ip("203.0.113.7")
+        );
+
+        // behind the proxies, the entries the client wrote itself are skipped
+        assert_eq!(
+            client_ip_behind_proxies(
+                ip("10.0.0.1"),
+                Some("198.51.100.1, 203.0.113.7, 10.0.0.2"),
+                &proxies
+            ),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip_behind_proxies(ip("10.0.0.1"), None, &proxies),
+            ip("10.0.0.1")
+        );
+    }
+}
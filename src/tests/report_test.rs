@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::report::{ReportCategory, ReportTarget},
+        services::report_service::ReportServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::pagination::QueryPagination,
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_hide_reported_post_and_resolve_from_queue() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("report author");
+        let first_reporter = create_pg_test_user("first reporter");
+        let second_reporter = create_pg_test_user("second reporter");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "reported", "body")
+            .unwrap();
+        let target = ReportTarget::Post(post.id);
+
+        assert!(matches!(
+            app_kit
+                .report_service
+                .create_report(author.id, target, ReportCategory::Spam, ""),
+            Err(ReportServiceError::ErrorOwnContent)
+        ));
+
+        app_kit
+            .report_service
+            .create_report(first_reporter.id, target, ReportCategory::Spam, "ads")
+            .unwrap();
+        assert!(matches!(
+            app_kit.report_service.create_report(
+                first_reporter.id,
+                target,
+                ReportCategory::Other,
+                ""
+            ),
+            Err(ReportServiceError::ErrorAlreadyReported)
+        ));
+        assert!(app_kit.post_service.get_post(post.id).is_ok());
+
+        // the test app kit hides content at two distinct reporters
+        app_kit
+            .report_service
+            .create_report(second_reporter.id, target, ReportCategory::Harassment, "")
+            .unwrap();
+        assert!(app_kit.post_service.get_post(post.id).is_err());
+
+        let queue_item = |app_kit: &AppKit| {
+            let pagination = QueryPagination {
+                page: 1,
+                limit: i64::from(i32::MAX),
+                ..Default::default()
+            };
+            app_kit
+                .report_service
+                .get_report_queue(&pagination)
+                .unwrap()
+                .items
+                .into_iter()
+                .find(|item| item.target_kind == "post" && item.target_id == post.id)
+        };
+        let item = queue_item(&app_kit).unwrap();
+        assert!(item.is_hidden);
+        assert!(item.allow_delete);
+        assert_eq!(item.reporter_count, 2);
+        assert_eq!(item.reports.len(), 2);
+
+        // dismissing lifts the hide and empties the queue for the post
+        assert_eq!(
+            app_kit
+                .report_service
+                .dismiss_reports(target, second_reporter.id)
+                .unwrap(),
+            2
+        );
+        assert!(app_kit.post_service.get_post(post.id).is_ok());
+        assert!(queue_item(&app_kit).is_none());
+
+        app_kit
+            .report_service
+            .create_report(first_reporter.id, target, ReportCategory::Spam, "")
+            .unwrap();
+        app_kit
+            .report_service
+            .delete_reported_content(target, second_reporter.id)
+            .unwrap();
+        assert!(queue_item(&app_kit).is_none());
+
+        // deleted content lands in the trash on hold
+        let deleted = app_kit.post_service.get_deleted_post(post.id).unwrap();
+        assert!(deleted.hidden_at.is_some());
+
+        for user in [&author, &first_reporter, &second_reporter] {
+            delete_pg_test_user(user);
+        }
+    }
+}
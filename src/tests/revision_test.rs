@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::revision::{versions_to_revisions_public, RevisionVersion},
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::diff::diff_lines,
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_diff_lines() {
+        let diff: Vec<(&str, String)> =
+            diff_lines("first\r\nsecond\r\nthird", "first\nchanged\nthird")
+                .into_iter()
+                .map(|line| (line.kind, line.text))
+                .collect();
+
+        assert_eq!(
+            diff,
+            vec![
+                ("unchanged", "first".to_string()),
+                ("removed", "second".to_string()),
+                ("added", "changed".to_string()),
+                ("unchanged", "third".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_record_post_revisions_and_roll_back() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("revision author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "first title", "line one\nline two")
+            .unwrap();
+
+        let unedited = app_kit.post_service.get_post_with_user(post.id).unwrap();
+        assert_eq!(unedited.edited_time_human, None);
+        assert!(app_kit
+            .post_service
+            .get_post_revisions(post.id)
+            .unwrap()
+            .is_empty());
+
+        app_kit
+            .post_service
+            .update_post(post.id, author.id, "second title", "line one\nline 2")
+            .unwrap();
+        app_kit
+            .post_service
+            .update_post(post.id, author.id, "second title", "vandalized")
+            .unwrap();
+
+        let revisions = app_kit.post_service.get_post_revisions(post.id).unwrap();
+        let bodies: Vec<&str> = revisions.iter().map(|r| r.body.as_str()).collect();
+        assert_eq!(
+            bodies,
+            vec!["line one\nline two", "line one\nline 2", "vandalized"]
+        );
+
+        let edited = app_kit.post_service.get_post_with_user(post.id).unwrap();
+        assert!(edited.edited_time_human.is_some());
+
+        let versions: Vec<RevisionVersion> = revisions
+            .iter()
+            .cloned()
+            .map(RevisionVersion::from)
+            .collect();
+        let revisions_public = versions_to_revisions_public(&versions, &HashMap::new());
+
+        assert_eq!(revisions_public[0].number, 3);
+        assert!(revisions_public[0].is_current);
+        assert!(revisions_public[0].title_diff.is_empty());
+        assert!(!revisions_public[1].title_diff.is_empty());
+
+        // a rollback is a new revision with the old content
+        let restored = app_kit
+            .post_service
+            .rollback_post(post.id, revisions[1].id, author.id)
+            .unwrap();
+        assert_eq!(restored.body, "line one\nline 2");
+        assert_eq!(
+            app_kit
+                .post_service
+                .get_post_revisions(post.id)
+                .unwrap()
+                .len(),
+            4
+        );
+
+        // revisions of another post are refused
+        let other_post = app_kit
+            .post_service
+            .create_post(author.id, "other", "other")
+            .unwrap();
+        assert!(app_kit
+            .post_service
+            .rollback_post(other_post.id, revisions[0].id, author.id)
+            .is_err());
+
+        delete_pg_test_user(&author);
+    }
+}
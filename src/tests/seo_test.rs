@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        db::initialize_db_pool,
+        entities::{
+            post::{parse_post_path_segment, post_path},
+            sitemap::SitemapSection,
+        },
+        repositories::{
+            sitemap_repository::{PostgresSitemapRepository, SitemapRepository},
+            user_repository::UserRepository,
+        },
+        servers::server_actix::create_actix_app,
+        services::sitemap_service::{BasedSitemapService, SitemapService, SitemapServiceError},
+        tests::{create_pg_test_user, delete_pg_test_user, pg_test_user_repository},
+        utils::{seo::meta_description, slug::slugify, token::generate_random_token},
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_build_post_slugs() {
+        assert_eq!(slugify("Hello, World!  Rust 2024"), "hello-world-rust-2024");
+        assert_eq!(slugify("Ünïcode only: ☃"), "n-code-only");
+        assert_eq!(slugify("☃"), "");
+        assert!(slugify(&"word ".repeat(40)).len() <= 60);
+        assert!(!slugify(&"word ".repeat(40)).ends_with('-'));
+
+        assert_eq!(post_path(7, "Hello World"), "/posts/7-hello-world");
+        assert_eq!(post_path(7, "☃"), "/posts/7");
+
+        assert_eq!(parse_post_path_segment("7-hello-world"), Some(7));
+        assert_eq!(parse_post_path_segment("7"), Some(7));
+        assert_eq!(parse_post_path_segment("-7"), None);
+        assert_eq!(parse_post_path_segment("create"), None);
+
+        assert_eq!(meta_description(" two\n\nlines "), "two lines");
+        let description = meta_description(&"é".repeat(500));
+        assert_eq!(description.chars().count(), 160);
+        assert!(description.ends_with('…'));
+    }
+
+    #[actix_web::test]
+    async fn test_should_redirect_to_canonical_post_url_and_list_it_in_sitemap() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("seo author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "Hello, SEO world!", &generate_random_token(16))
+            .unwrap();
+        let canonical_path = format!("/posts/{}-hello-seo-world", post.id);
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        for (uri, location) in [
+            (format!("/posts/{}", post.id), canonical_path.clone()),
+            (
+                format!("/posts/{}-old-title?page=1", post.id),
+                format!("{}?page=1", canonical_path),
+            ),
+        ] {
+            let req = actix_web::test::TestRequest::get().uri(&uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(
+                resp.headers().get(header::LOCATION).unwrap(),
+                location.as_str()
+            );
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&canonical_path)
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        let canonical_link = body
+            .split(r#"<link rel="canonical" href=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+        assert!(canonical_link.starts_with("http"));
+        assert!(canonical_link.ends_with(&canonical_path));
+        assert!(body.contains(r#"<meta property="og:type" content="article" />"#));
+        assert!(body.contains(r#"<meta name="twitter:card" content="summary" />"#));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/sitemap.xml")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("<sitemapindex"));
+        assert!(body.contains("/sitemaps/posts-1.xml</loc>"));
+        assert!(body.contains("/sitemaps/profiles-1.xml</loc>"));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/sitemaps/posts-1.xml")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(&format!("{}</loc>", canonical_path)));
+
+        // errors turn into a flash message and a redirect
+        for uri in ["/sitemaps/posts-0.xml", "/sitemaps/topics-1.xml"] {
+            let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FOUND);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/robots.txt")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("/sitemap.xml\n"));
+
+        // one url per file, the chunk past the last one does not exist
+        let sitemap_service = BasedSitemapService::new(
+            Arc::new(PostgresSitemapRepository::new(Arc::new(
+                initialize_db_pool(),
+            ))),
+            1,
+        );
+        assert_eq!(
+            sitemap_service
+                .get_chunk(SitemapSection::Posts, 1)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            sitemap_service.get_chunk(SitemapSection::Posts, i64::MAX / 2),
+            Err(SitemapServiceError::ErrorChunkNotFound)
+        ));
+
+        // deleted posts leave the sitemap
+        app_kit.post_service.delete_post(post.id).unwrap();
+        let urls = app_kit
+            .sitemap_service
+            .get_chunk(SitemapSection::Posts, 1)
+            .unwrap();
+        assert!(urls.iter().all(|url| url.loc != canonical_path));
+
+        // the placeholder holding the content of deleted users has no profile to index
+        let deleted_user = pg_test_user_repository().get_deleted_user().unwrap();
+        let sitemap_repository = PostgresSitemapRepository::new(Arc::new(initialize_db_pool()));
+        let total_users = sitemap_repository.count_sitemap_users().unwrap();
+        assert!(sitemap_repository
+            .get_sitemap_users(0, total_users + 100)
+            .unwrap()
+            .iter()
+            .all(|(user_id, _)| *user_id != deleted_user.id));
+
+        delete_pg_test_user(&author);
+    }
+}
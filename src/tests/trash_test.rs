@@ -0,0 +1,222 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, Utc};
+    use diesel::RunQueryDsl;
+    use dotenv::dotenv;
+
+    use crate::{
+        db::initialize_db_pool,
+        entities::{
+            attachment::{AttachmentTarget, AttachmentUpload},
+            trash::TrashPolicy,
+        },
+        services::comment_service::CommentServiceError,
+        tests::{create_pg_test_user, delete_pg_test_user},
+        utils::pagination::QueryPagination,
+        AppKit,
+    };
+
+    #[test]
+    fn test_should_limit_author_restore_to_grace_period() {
+        let policy = TrashPolicy {
+            restore_grace_days: 7,
+            purge_after_days: 30,
+        };
+        let now = Utc::now().naive_utc();
+
+        assert!(policy.author_can_restore(now - Duration::days(6)));
+        assert!(!policy.author_can_restore(now - Duration::days(8)));
+
+        let cutoff = policy.purge_cutoff();
+        assert!(now - Duration::days(31) < cutoff);
+        assert!(now - Duration::days(29) > cutoff);
+    }
+
+    #[test]
+    fn test_should_restore_and_purge_deleted_posts() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("trash author");
+
+        let post = app_kit
+            .post_service
+            .create_post(author.id, "trashed", "body")
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(author.id, post.id, "comment")
+            .unwrap();
+
+        let prepared = app_kit
+            .attachment_service
+            .prepare_attachments(vec![AttachmentUpload {
+                file_name: "notes.txt".to_string(),
+                bytes: b"notes".to_vec(),
+            }])
+            .unwrap();
+        let attachment = app_kit
+            .attachment_service
+            .store_attachments(author.id, AttachmentTarget::Post(post.id), prepared)
+            .unwrap()
+            .remove(0);
+
+        app_kit.comment_service.delete_comment(comment.id).unwrap();
+        app_kit.post_service.delete_post(post.id).unwrap();
+
+        assert!(app_kit.post_service.get_post(post.id).is_err());
+        let trash = app_kit
+            .post_service
+            .get_deleted_posts(Some(author.id), &QueryPagination::default())
+            .unwrap();
+        assert_eq!(trash.total, 1);
+        assert_eq!(trash.posts[0].post.id, post.id);
+
+        // soft deleted content keeps its attachments so it can come back whole
+        assert!(app_kit
+            .attachment_service
+            .get_attachment(attachment.id)
+            .is_ok());
+
+        app_kit.post_service.restore_post(post.id).unwrap();
+        assert!(app_kit.post_service.get_post(post.id).is_ok());
+        assert!(app_kit.post_service.restore_post(post.id).is_err());
+
+        app_kit.comment_service.restore_comment(comment.id).unwrap();
+        assert!(app_kit.comment_service.get_comment(comment.id).is_ok());
+
+        // the post is sent back in time so the cutoffs below can not reach the posts other
+        // tests delete meanwhile in the shared database
+        app_kit.post_service.delete_post(post.id).unwrap();
+        let mut conn = initialize_db_pool().get().unwrap();
+        diesel::sql_query(format!(
+            "UPDATE posts SET deleted_at = '2000-01-01 00:00:00' WHERE id = {}",
+            post.id
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+        // content deleted after the cutoff is left alone
+        let before_deletion = NaiveDate::from_ymd_opt(1999, 12, 31)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap();
+        app_kit
+            .post_service
+            .purge_deleted_posts(before_deletion)
+            .unwrap();
+        assert!(app_kit.post_service.get_deleted_post(post.id).is_ok());
+
+        let after_deletion = before_deletion + Duration::days(2);
+        app_kit
+            .post_service
+            .purge_deleted_posts(after_deletion)
+            .unwrap();
+        assert!(app_kit.post_service.get_deleted_post(post.id).is_err());
+        assert!(app_kit
+            .attachment_service
+            .get_attachment(attachment.id)
+            .is_err());
+
+        delete_pg_test_user(&author);
+    }
+
+    #[test]
+    fn test_should_hide_comments_of_deleted_posts() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let commenter = create_pg_test_user("trash commenter");
+
+        let post = app_kit
+            .post_service
+            .create_post(commenter.id, "post", "body")
+            .unwrap();
+        let comment = app_kit
+            .comment_service
+            .create_comment(commenter.id, post.id, "comment")
+            .unwrap();
+
+        let profile_comments = |app_kit: &AppKit| {
+            app_kit
+                .comment_service
+                .get_comments_by_user(commenter.id, &QueryPagination::default())
+                .unwrap()
+        };
+        assert_eq!(profile_comments(&app_kit).total, 1);
+
+        app_kit.post_service.delete_post(post.id).unwrap();
+
+        let hidden = profile_comments(&app_kit);
+        assert_eq!(hidden.total, 0);
+        assert!(hidden.comments.is_empty());
+        assert!(app_kit.comment_service.get_comment(comment.id).is_err());
+
+        // the comment itself was never deleted, it is not in the trash either
+        assert!(app_kit
+            .comment_service
+            .get_deleted_comment(comment.id)
+            .is_err());
+
+        app_kit.post_service.restore_post(post.id).unwrap();
+
+        let restored = profile_comments(&app_kit);
+        assert_eq!(restored.total, 1);
+        assert_eq!(restored.comments[0].comment.id, comment.id);
+        assert!(app_kit.comment_service.get_comment(comment.id).is_ok());
+
+        delete_pg_test_user(&commenter);
+    }
+
+    #[test]
+    fn test_should_refuse_comments_on_deleted_and_hidden_posts() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let author = create_pg_test_user("trash reply author");
+        let commenter = create_pg_test_user("trash reply commenter");
+
+        let trashed = app_kit
+            .post_service
+            .create_post(author.id, "trashed", "in the trash")
+            .unwrap();
+        let hidden = app_kit
+            .post_service
+            .create_post(author.id, "hidden", "hidden by moderation")
+            .unwrap();
+
+        app_kit.post_service.delete_post(trashed.id).unwrap();
+        app_kit
+            .post_service
+            .set_post_hidden(hidden.id, true)
+            .unwrap();
+
+        for post_id in [trashed.id, hidden.id] {
+            assert!(matches!(
+                app_kit
+                    .comment_service
+                    .create_comment(commenter.id, post_id, "anyone here?"),
+                Err(CommentServiceError::ErrorPostNotFound)
+            ));
+        }
+        assert_eq!(
+            app_kit
+                .notification_service
+                .count_unread_notifications(author.id)
+                .unwrap(),
+            0
+        );
+
+        // back from the trash, the post takes comments again
+        app_kit.post_service.restore_post(trashed.id).unwrap();
+        app_kit
+            .comment_service
+            .create_comment(commenter.id, trashed.id, "welcome back")
+            .unwrap();
+
+        delete_pg_test_user(&commenter);
+        delete_pg_test_user(&author);
+    }
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod tests {
+
+    use actix_web::http::{header, StatusCode};
+    use dotenv::dotenv;
+
+    use crate::{
+        entities::user::{UserLoginFormData, UserUsernameFormData, UsernameLookup},
+        repositories::user_repository::UserRepository,
+        servers::server_actix::create_actix_app,
+        services::user_service::{UserServiceError, UsernameError},
+        tests::read_csrf_form_session,
+        tests::{create_pg_test_user, pg_test_user_repository},
+        utils::{csrf::CSRF_HEADER, token::generate_random_token},
+        AppKit,
+    };
+
+    #[actix_web::test]
+    async fn test_should_resolve_usernames_and_redirect_old_ones() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+        let user_service = &app_kit.user_service;
+
+        let alice = user_service
+            .register_user(
+                "alice",
+                "Alice_Handle",
+                "alice@example.com",
+                "alicepassword",
+            )
+            .unwrap();
+        assert_eq!(alice.username, "alice_handle");
+
+        // handles are unique whatever their casing
+        assert!(matches!(
+            user_service.register_user("other", "ALICE_HANDLE", "other@example.com", "otherpass"),
+            Err(UserServiceError::ErrorUsername(UsernameError::Taken))
+        ));
+        assert!(matches!(
+            user_service.register_user("other", "Admin", "other@example.com", "otherpass"),
+            Err(UserServiceError::ErrorUsername(UsernameError::Reserved))
+        ));
+
+        assert!(matches!(
+            user_service.change_username(alice.id, "ALICE_handle"),
+            Err(UserServiceError::ErrorUsername(UsernameError::Unchanged))
+        ));
+
+        let app = actix_web::test::init_service(create_actix_app(app_kit.clone())).await;
+
+        let login_page_req = actix_web::test::TestRequest::get()
+            .uri("/users/login")
+            .to_request();
+        let csrf_form_session =
+            read_csrf_form_session(actix_web::test::call_service(&app, login_page_req).await).await;
+
+        let login_req = actix_web::test::TestRequest::post()
+            .uri("/users/login")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserLoginFormData {
+                email: alice.email.clone(),
+                password: "alicepassword".to_string(),
+                cf_turnstile_response: None,
+            })
+            .to_request();
+        let login_resp = actix_web::test::call_service(&app, login_req).await;
+        let session_cookie = login_resp.response().cookies().next().unwrap().into_owned();
+
+        // the handle picked at registration can be changed right away, once
+        let change_req = actix_web::test::TestRequest::post()
+            .uri("/users/username")
+            .cookie(session_cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
+            .set_form(&UserUsernameFormData {
+                new_username: "alice_new".to_string(),
+            })
+            .to_request();
+        let change_resp = actix_web::test::call_service(&app, change_req).await;
+        assert_eq!(change_resp.status(), StatusCode::FOUND);
+        let session_cookie = change_resp
+            .response()
+            .cookies()
+            .next()
+            .unwrap()
+            .into_owned();
+
+        let alice = user_service.get_user_by_id(alice.id).unwrap();
+        assert_eq!(alice.username, "alice_new");
+        assert!(matches!(
+            user_service.change_username(alice.id, "alice_newer"),
+            Err(UserServiceError::ErrorUsername(UsernameError::Cooldown(_)))
+        ));
+
+        assert_eq!(
+            user_service.resolve_username("Alice_New").unwrap(),
+            UsernameLookup::Current(alice.id)
+        );
+        assert_eq!(
+            user_service.resolve_username("alice_handle").unwrap(),
+            UsernameLookup::Moved("alice_new".to_string())
+        );
+        assert!(user_service.resolve_username("nobody_here").is_err());
+
+        // nobody else can take a handle that still redirects
+        assert!(matches!(
+            user_service.register_user("other", "alice_handle", "other@example.com", "otherpass"),
+            Err(UserServiceError::ErrorUsername(UsernameError::Taken))
+        ));
+
+        // profiles need a session
+        let req = actix_web::test::TestRequest::get()
+            .uri("/u/alice_new")
+            .cookie(session_cookie.clone())
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for (uri, location) in [
+            ("/u/Alice_New", "/u/alice_new"),
+            (
+                "/u/alice_handle/comments?page=1",
+                "/u/alice_new/comments?page=1",
+            ),
+        ] {
+            let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(resp.headers().get(header::LOCATION).unwrap(), location);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/u/nobody_here")
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_ne!(resp.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_should_release_old_username_taken_back_by_its_owner() {
+        dotenv().ok();
+
+        let user_repo = pg_test_user_repository();
+        let user = create_pg_test_user("Handle Owner!");
+        // generated from the name, with a random part
+        assert!(user.username.starts_with("handle_owner_"));
+
+        // the database refuses handles that skipped normalization
+        assert!(user_repo
+            .update_user_username(&user, "Mixed_Case_Handle")
+            .is_err());
+        let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+        assert!(user_repo
+            .create_user("mixed case", "Mixed_Case_Handle", &email, "mixedpassword")
+            .is_err());
+
+        let first = user.username.clone();
+        let second = format!("second_{}", generate_random_token(8).to_lowercase());
+
+        user_repo.update_user_username(&user, &second).unwrap();
+        let user = user_repo.get_user_by_username(&second).unwrap();
+        assert!(user.username_changed_at.is_some());
+        assert_eq!(
+            user_repo.get_previous_username_owner(&first).unwrap(),
+            Some(user.id)
+        );
+
+        user_repo.update_user_username(&user, &first).unwrap();
+        assert_eq!(user_repo.get_previous_username_owner(&first).unwrap(), None);
+        assert_eq!(
+            user_repo.get_previous_username_owner(&second).unwrap(),
+            Some(user.id)
+        );
+
+        // previous handles go away with the account
+        user_repo.delete_user(&user).unwrap();
+        assert_eq!(
+            user_repo.get_previous_username_owner(&second).unwrap(),
+            None
+        );
+    }
+}
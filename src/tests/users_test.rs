@@ -1,10 +1,14 @@
 #[cfg(test)]
 mod tests {
-    use crate::tests::debug_response_data;
+    use crate::tests::{debug_response_data, read_csrf_form_session};
+    use crate::utils::csrf::CSRF_HEADER;
     use crate::{
         entities::user::{UserLoginFormData, UserRegisterFormData},
         servers::server_actix::create_actix_app,
-        tests, AppKit,
+        services::user_service::{LoginError, UserServiceError, LOGIN_CHALLENGE_AFTER_FAILURES},
+        tests,
+        utils::token::generate_random_token,
+        AppKit,
     };
     use actix_web::http::StatusCode;
     use dotenv::dotenv;
@@ -40,15 +44,25 @@ mod tests {
 
         let app = actix_web::test::init_service(actix_app).await;
 
+        let register_page_req = actix_web::test::TestRequest::get()
+            .uri("/users/register")
+            .to_request();
+        let csrf_form_session =
+            read_csrf_form_session(actix_web::test::call_service(&app, register_page_req).await)
+                .await;
+
         let user_register_form_data = UserRegisterFormData {
             email: "adam@example.com".to_string(),
             name: "adam example".to_string(),
+            username: "Adam_Example".to_string(),
             password: "adampassword".to_string(),
             cf_turnstile_response: None,
         };
 
         let req = actix_web::test::TestRequest::post()
             .uri("/users/register")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
             .set_form(&user_register_form_data)
             .to_request();
 
@@ -61,6 +75,8 @@ mod tests {
         let user = get_user_by_email.unwrap();
         assert_eq!(user.email, "adam@example.com");
         assert_eq!(user.name, "adam example");
+        // handles are stored in lowercase
+        assert_eq!(user.username, "adam_example");
         assert_eq!(user.role, "user");
 
         assert_eq!(resp.status(), StatusCode::FOUND);
@@ -80,15 +96,25 @@ mod tests {
 
         let app = actix_web::test::init_service(actix_app).await;
 
+        let register_page_req = actix_web::test::TestRequest::get()
+            .uri("/users/register")
+            .to_request();
+        let csrf_form_session =
+            read_csrf_form_session(actix_web::test::call_service(&app, register_page_req).await)
+                .await;
+
         let user_register_form_data = UserRegisterFormData {
             email: "adam@example.com".to_string(),
             name: "adam rustforum".to_string(),
+            username: "adam_rustforum".to_string(),
             password: "adampassword".to_string(),
             cf_turnstile_response: None,
         };
 
         let register_req = actix_web::test::TestRequest::post()
             .uri("/users/register")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
             .set_form(&user_register_form_data)
             .to_request();
 
@@ -104,6 +130,8 @@ mod tests {
 
         let login_req = actix_web::test::TestRequest::post()
             .uri("/users/login")
+            .cookie(csrf_form_session.cookie.clone())
+            .insert_header((CSRF_HEADER, csrf_form_session.token.as_str()))
             .set_form(&user_login_form)
             .to_request();
 
@@ -113,4 +141,85 @@ mod tests {
 
         debug_response_data(login_resp).await;
     }
+
+    #[actix_web::test]
+    async fn test_should_reject_form_post_without_csrf_token() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let actix_app = create_actix_app(app_kit.clone());
+
+        let app = actix_web::test::init_service(actix_app).await;
+
+        let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+
+        let user_register_form_data = UserRegisterFormData {
+            email: email.clone(),
+            name: "csrf example".to_string(),
+            username: "csrf_example".to_string(),
+            password: "csrfpassword".to_string(),
+            cf_turnstile_response: None,
+        };
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/users/register")
+            .set_form(&user_register_form_data)
+            .to_request();
+
+        let resp = actix_web::test::call_service(&app, req).await;
+
+        // the error handler turns the rejection into a flash message and a redirect
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert!(app_kit.user_service.get_user_by_email(&email).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_should_throttle_repeated_failed_logins() {
+        dotenv().ok();
+
+        let app_kit = AppKit::new_for_testing();
+
+        let email = format!("{}@example.com", generate_random_token(12).to_lowercase());
+        let client_ip = format!("test-{}", generate_random_token(12));
+
+        app_kit
+            .user_service
+            .register_user(
+                "throttle example",
+                "throttle_example",
+                &email,
+                "throttlepassword",
+            )
+            .unwrap();
+
+        assert!(!app_kit
+            .user_service
+            .login_requires_challenge(Some(&email), &client_ip));
+
+        for _ in 0..LOGIN_CHALLENGE_AFTER_FAILURES {
+            let login_result = app_kit
+                .user_service
+                .login_user(&email, "wrongpassword", &client_ip);
+
+            assert!(matches!(
+                login_result,
+                Err(UserServiceError::ErrorLogin(LoginError::InvalidCredentials))
+            ));
+        }
+
+        assert!(app_kit
+            .user_service
+            .login_requires_challenge(Some(&email), &client_ip));
+
+        // even the correct password is refused while the backoff delay is running
+        let login_result = app_kit
+            .user_service
+            .login_user(&email, "throttlepassword", &client_ip);
+
+        assert!(matches!(
+            login_result,
+            Err(UserServiceError::ErrorLogin(LoginError::TooManyAttempts(_)))
+        ));
+    }
 }
@@ -0,0 +1,9 @@
+use crate::{entities::audit::AuditRecord, services::audit_service::AuditService};
+
+/// Records a privileged action that already happened, a failure to record is logged and
+/// never undoes the action
+pub fn record_audit(audit_service: &dyn AuditService, record: AuditRecord) {
+    if let Err(e) = audit_service.record(&record) {
+        println!("{} for {:?}", e, record);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::Cursor,
+};
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, Rgb, RgbImage,
+};
+
+/// Square sizes in pixels generated for every avatar
+pub const AVATAR_SIZES: [u32; 3] = [64, 128, 256];
+
+/// Size linked from `user_profile_picture_url`
+pub const AVATAR_DEFAULT_SIZE: u32 = 256;
+
+/// Prefix of generated avatar files, any other profile picture is a legacy upload
+pub const AVATAR_FILE_PREFIX: &str = "avatar-";
+
+const AVATAR_EXTENSIONS: [&str; 2] = ["jpg", "webp"];
+const AVATAR_JPEG_QUALITY: u8 = 85;
+
+/// Larger sources are rejected before decoding, no avatar or attachment needs more
+const AVATAR_MAX_SOURCE_DIMENSION: u32 = 8192;
+const AVATAR_MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum AvatarError {
+    UnsupportedFormat,
+    InvalidImage(String),
+    EncodeFailed(String),
+}
+
+impl Display for AvatarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AvatarError::UnsupportedFormat => {
+                write!(f, "Unsupported image format, use JPEG, PNG or WebP")
+            }
+            AvatarError::InvalidImage(msg) => write!(f, "Invalid image: {}", msg),
+            AvatarError::EncodeFailed(msg) => write!(f, "Failed to encode avatar: {}", msg),
+        }
+    }
+}
+
+/// One resized and re-encoded avatar file
+pub struct AvatarVariant {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
+
+pub fn avatar_file_name(avatar_id: &str, size: u32, extension: &str) -> String {
+    format!("{AVATAR_FILE_PREFIX}{avatar_id}-{size}.{extension}")
+}
+
+/// Decodes an uploaded JPEG, PNG or WebP image with its EXIF orientation applied
+///
+/// The format is sniffed from the bytes, the client sent content type is not trusted.
+/// Dimensions and memory are limited before the pixels are decoded.
+pub fn decode_upload_image(data: &[u8]) -> Result<(DynamicImage, ImageFormat), AvatarError> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| AvatarError::InvalidImage(e.to_string()))?;
+
+    let format = match reader.format() {
+        Some(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)) => format,
+        _ => return Err(AvatarError::UnsupportedFormat),
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(AVATAR_MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(AVATAR_MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(AVATAR_MAX_DECODE_BYTES);
+    reader.limits(limits);
+
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| AvatarError::InvalidImage(e.to_string()))?;
+    let orientation = decoder
+        .orientation()
+        .map_err(|e| AvatarError::InvalidImage(e.to_string()))?;
+
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| AvatarError::InvalidImage(e.to_string()))?;
+    image.apply_orientation(orientation);
+
+    Ok((image, format))
+}
+
+/// Encodes an image as JPEG, flattening transparency on white
+pub fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, AvatarError> {
+    let mut jpeg_bytes = vec![];
+    flatten_on_white(image)
+        .write_with_encoder(JpegEncoder::new_with_quality(
+            &mut jpeg_bytes,
+            AVATAR_JPEG_QUALITY,
+        ))
+        .map_err(|e| AvatarError::EncodeFailed(e.to_string()))?;
+
+    Ok(jpeg_bytes)
+}
+
+/// Encodes an image as lossless WebP
+pub fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>, AvatarError> {
+    let mut webp_bytes = vec![];
+    DynamicImage::ImageRgba8(image.to_rgba8())
+        .write_with_encoder(WebPEncoder::new_lossless(&mut webp_bytes))
+        .map_err(|e| AvatarError::EncodeFailed(e.to_string()))?;
+
+    Ok(webp_bytes)
+}
+
+/// Decodes an uploaded image and renders every avatar variant
+///
+/// Only pixels are re-encoded, so EXIF and other metadata never reach the file storage.
+/// The EXIF orientation is applied first so rotated phone photos stay upright.
+pub fn process_avatar(avatar_id: &str, data: &[u8]) -> Result<Vec<AvatarVariant>, AvatarError> {
+    let (image, _) = decode_upload_image(data)?;
+
+    let mut variants = vec![];
+
+    for size in AVATAR_SIZES {
+        let resized = image.resize_to_fill(size, size, FilterType::Lanczos3);
+
+        let jpeg_bytes = encode_jpeg(&resized)?;
+        let webp_bytes = encode_webp(&resized)?;
+
+        variants.push(AvatarVariant {
+            file_name: avatar_file_name(avatar_id, size, "jpg"),
+            bytes: jpeg_bytes,
+        });
+        variants.push(AvatarVariant {
+            file_name: avatar_file_name(avatar_id, size, "webp"),
+            bytes: webp_bytes,
+        });
+    }
+
+    Ok(variants)
+}
+
+/// JPEG has no alpha channel, transparent areas become white instead of black
+fn flatten_on_white(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Every storage key belonging to a profile picture key
+///
+/// Generated avatars expand to all their variants, legacy uploads to the single file.
+pub fn avatar_file_keys(key: &str) -> Vec<String> {
+    let avatar_id = key
+        .strip_prefix(AVATAR_FILE_PREFIX)
+        .and_then(|rest| rest.strip_suffix(&format!("-{AVATAR_DEFAULT_SIZE}.jpg")));
+
+    match avatar_id {
+        Some(avatar_id) => AVATAR_SIZES
+            .iter()
+            .flat_map(|size| {
+                AVATAR_EXTENSIONS
+                    .iter()
+                    .map(move |extension| avatar_file_name(avatar_id, *size, extension))
+            })
+            .collect(),
+        None => vec![key.to_string()],
+    }
+}
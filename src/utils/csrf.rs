@@ -0,0 +1,47 @@
+use actix_session::{Session, SessionInsertError};
+use serde_json::{json, Value};
+
+use crate::utils::{handlebars_helper::update_handlebars_data, token::generate_random_token};
+
+pub const SESSION_KEY_CSRF_TOKEN: &str = "csrf_token";
+pub const CSRF_FORM_FIELD: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Returns the session CSRF token, creating one on first use
+pub fn get_or_create_csrf_token(session: &Session) -> Result<String, SessionInsertError> {
+    if let Ok(Some(token)) = session.get::<String>(SESSION_KEY_CSRF_TOKEN) {
+        return Ok(token);
+    }
+
+    let token = generate_random_token(32);
+    session.insert(SESSION_KEY_CSRF_TOKEN, &token)?;
+
+    Ok(token)
+}
+
+/// Adds the session CSRF token to the template data for the `csrf_field` helper
+pub fn handlebars_add_csrf_token(
+    session: &Session,
+    data: &mut Value,
+) -> Result<(), actix_web::Error> {
+    let token = get_or_create_csrf_token(session)?;
+
+    update_handlebars_data(data, CSRF_FORM_FIELD, json!(token));
+
+    Ok(())
+}
+
+/// Compares two tokens in constant time
+pub fn csrf_token_equal(expected: &str, provided: &str) -> bool {
+    let (expected, provided) = (expected.as_bytes(), provided.as_bytes());
+
+    if expected.len() != provided.len() {
+        return false;
+    }
+
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
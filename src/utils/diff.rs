@@ -0,0 +1,36 @@
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+/// One line of a rendered diff, `kind` is `added`, `removed` or `unchanged`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: &'static str,
+    /// `+`, `-` or a space, like a unified diff
+    pub marker: &'static str,
+    pub text: String,
+}
+
+/// Line diff between two versions of a text
+///
+/// Form submissions use CRLF line endings, they are normalized so only content changes show.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old = old.replace("\r\n", "\n");
+    let new = new.replace("\r\n", "\n");
+
+    TextDiff::from_lines(&old, &new)
+        .iter_all_changes()
+        .map(|change| {
+            let (kind, marker) = match change.tag() {
+                ChangeTag::Insert => ("added", "+"),
+                ChangeTag::Delete => ("removed", "-"),
+                ChangeTag::Equal => ("unchanged", " "),
+            };
+
+            DiffLine {
+                kind,
+                marker,
+                text: change.value().trim_end_matches('\n').to_string(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,11 @@
+/// Characters of content shown where it is listed apart from its page, like the moderation
+/// queue and the Saved tab
+const EXCERPT_MAX_CHARS: usize = 200;
+
+/// The start of a text, cut after `EXCERPT_MAX_CHARS` characters and marked with an ellipsis
+pub fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(EXCERPT_MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
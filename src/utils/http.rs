@@ -1,4 +1,15 @@
-use actix_web::{http::header, HttpRequest, HttpResponse};
+use std::{
+    net::IpAddr,
+    sync::LazyLock,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use actix_web::{
+    http::header::{self, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch},
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use chrono::NaiveDateTime;
+use sha2::{Digest, Sha256};
 
 pub fn create_redirect(to_url: &str) -> HttpResponse {
     HttpResponse::Found()
@@ -15,3 +26,100 @@ pub fn redirect_back(req: &HttpRequest) -> HttpResponse {
 
     create_redirect(previous_url)
 }
+
+/// Proxies allowed to tell the client address through `X-Forwarded-For`, from the comma
+/// separated `TRUSTED_PROXIES`
+static TRUSTED_PROXIES: LazyLock<Vec<IpAddr>> = LazyLock::new(|| {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|proxy| proxy.trim().parse().ok())
+        .collect()
+});
+
+/// Address of the client, throttles and lockouts are keyed on it so clients must not pick it
+pub fn get_client_ip(req: &HttpRequest) -> String {
+    let forwarded_for = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|h| h.to_str().ok());
+
+    match req.peer_addr() {
+        Some(peer_addr) => {
+            client_ip_behind_proxies(peer_addr.ip(), forwarded_for, &TRUSTED_PROXIES).to_string()
+        }
+        None => "unknown".to_string(),
+    }
+}
+
+/// The peer address, unless the peer is a trusted proxy. Then the right-most forwarded address
+/// that is not a trusted proxy, the entries left of it were written by the client
+pub fn client_ip_behind_proxies(
+    peer_ip: IpAddr,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> IpAddr {
+    if !trusted_proxies.contains(&peer_ip) {
+        return peer_ip;
+    }
+
+    let mut client_ip = peer_ip;
+    for forwarded_ip in forwarded_for.unwrap_or_default().rsplit(',') {
+        match forwarded_ip.trim().parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => client_ip = ip,
+            Ok(ip) => return ip,
+            Err(_) => break,
+        }
+    }
+
+    client_ip
+}
+
+/// Absolute url of the forum without a trailing slash, from `APP_DOMAIN_URL` or the request
+pub fn get_app_url(req: &HttpRequest) -> String {
+    match std::env::var("APP_DOMAIN_URL") {
+        Ok(app_domain_url) if !app_domain_url.is_empty() => {
+            app_domain_url.trim_end_matches('/').to_string()
+        }
+        _ => {
+            let conn_info = req.connection_info();
+            format!("{}://{}", conn_info.scheme(), conn_info.host())
+        }
+    }
+}
+
+/// Strong ETag derived from the content of a response body
+pub fn body_etag(body: &[u8]) -> EntityTag {
+    let digest = Sha256::digest(body);
+    let tag: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+
+    EntityTag::new_strong(tag)
+}
+
+/// A timestamp as an HTTP date, which only keeps whole seconds
+pub fn to_http_date(time: NaiveDateTime) -> HttpDate {
+    let seconds = time.and_utc().timestamp().max(0) as u64;
+
+    HttpDate::from(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Whether the copy the client already has is current, `If-None-Match` wins over
+/// `If-Modified-Since` as in RFC 9110
+pub fn is_not_modified(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    last_modified: Option<HttpDate>,
+) -> bool {
+    if req.headers().contains_key(header::IF_NONE_MATCH) {
+        return match req.get_header::<IfNoneMatch>() {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+            None => false,
+        };
+    }
+
+    match (req.get_header::<IfModifiedSince>(), last_modified) {
+        (Some(IfModifiedSince(since)), Some(last_modified)) => last_modified <= since,
+        _ => false,
+    }
+}
@@ -0,0 +1,25 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// Rendered links leave no referrer and pass no ranking to the linked site
+const MARKDOWN_LINK_REL: &str = "nofollow ugc noopener noreferrer";
+
+/// Renders user written markdown to html safe to embed in a page
+///
+/// The sanitizer strips raw html tags it does not allow from the markdown, keeping their text
+/// but dropping scripts and styles whole. Links only keep the http, https and mailto schemes and
+/// images are dropped so profiles can not load remote content.
+pub fn render_markdown(text: &str) -> String {
+    let parser = Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser);
+
+    ammonia::Builder::default()
+        .rm_tags(["img"])
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some(MARKDOWN_LINK_REL))
+        .clean(&unsafe_html)
+        .to_string()
+}
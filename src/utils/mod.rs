@@ -1,11 +1,21 @@
+pub mod audit;
+pub mod avatar;
+pub mod csrf;
+pub mod diff;
 pub mod email;
+pub mod excerpt;
 pub mod flash;
 pub mod formdata;
 pub mod handlebars_helper;
 pub mod http;
+pub mod markdown;
 pub mod pagination;
 pub mod session;
+pub mod seo;
+pub mod slug;
 pub mod time;
 pub mod token;
+pub mod trash;
 pub mod turnstile;
 pub mod users;
+pub mod zip;
@@ -8,11 +8,18 @@ use serde::Deserialize;
 pub struct QueryPagination {
     pub page: i64,
     pub limit: i64,
+    /// The other query parameters of the listing, e.g. a folder, kept in the pager links
+    #[serde(skip)]
+    pub filters: Vec<(String, String)>,
 }
 
 impl Default for QueryPagination {
     fn default() -> Self {
-        QueryPagination { page: 1, limit: 10 }
+        QueryPagination {
+            page: 1,
+            limit: 10,
+            filters: vec![],
+        }
     }
 }
 
@@ -30,42 +37,42 @@ impl FromRequest for QueryPagination {
         // Default values
         let mut pagination = QueryPagination::default();
 
-        let query = req.query_string();
+        let query = serde_urlencoded::from_str::<Vec<(String, String)>>(req.query_string())
+            .unwrap_or_default();
 
         // Extract query parameters
-        for pair in query.split('&') {
-            let mut parts = pair.split('=');
-
-            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
-                match key {
-                    "page" => {
-                        if let Ok(parsed_page) = value.parse::<i64>() {
-                            if parsed_page >= 1 {
-                                pagination.page = parsed_page;
-                            }
+        for (key, value) in query {
+            match key.as_str() {
+                "page" => {
+                    if let Ok(parsed_page) = value.parse::<i64>() {
+                        if parsed_page >= 1 {
+                            pagination.page = parsed_page;
                         }
                     }
+                }
 
-                    "per_page" => {
-                        if let Ok(limit) = value.parse::<i64>() {
-                            if (1..=100).contains(&limit) {
-                                pagination.limit = limit;
-                            }
+                "per_page" => {
+                    if let Ok(limit) = value.parse::<i64>() {
+                        if (1..=100).contains(&limit) {
+                            pagination.limit = limit;
                         }
                     }
-
-                    _ => {}
                 }
+
+                _ => pagination.filters.push((key, value)),
             }
         }
 
+        // keeps the offset of huge pages in range, the listings redirect them to their last page
+        pagination.page = pagination.page.min(i64::MAX / pagination.limit);
+
         ready(Ok(pagination))
     }
 }
 
 impl QueryPagination {
     pub fn get_offset(&self) -> i64 {
-        (self.page - 1) * self.limit
+        (self.page - 1).saturating_mul(self.limit)
     }
 
     pub fn get_limit(&self) -> i64 {
@@ -0,0 +1,79 @@
+use actix_web::HttpRequest;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{handlebars_helper::update_handlebars_data, http::get_app_url};
+
+/// Longest description kept in the meta tags, search engines cut around this length
+const META_DESCRIPTION_MAX_CHARS: usize = 160;
+
+/// What the OpenGraph tags describe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenGraphType {
+    Website,
+    Article,
+    Profile,
+}
+
+/// The canonical url and the OpenGraph and Twitter card data of a page, the title is the page title
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageMeta {
+    pub canonical_url: String,
+    pub description: String,
+    pub og_type: OpenGraphType,
+    pub image_url: Option<String>,
+}
+
+impl PageMeta {
+    /// Meta of the page at `path`, pages past the first keep their page number in the canonical url
+    pub fn new(
+        req: &HttpRequest,
+        path: &str,
+        page: i64,
+        og_type: OpenGraphType,
+        description: &str,
+    ) -> Self {
+        let canonical_url = match page > 1 {
+            true => format!("{}{}?page={}", get_app_url(req), path, page),
+            false => format!("{}{}", get_app_url(req), path),
+        };
+
+        Self {
+            canonical_url,
+            description: meta_description(description),
+            og_type,
+            image_url: None,
+        }
+    }
+
+    /// Adds a preview image, paths on this forum are made absolute
+    pub fn with_image(mut self, req: &HttpRequest, image_url: Option<&str>) -> Self {
+        self.image_url = image_url.map(|url| match url.starts_with('/') {
+            true => format!("{}{}", get_app_url(req), url),
+            false => url.to_string(),
+        });
+        self
+    }
+}
+
+/// Text on a single line cut to the length of a meta description
+pub fn meta_description(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match text.chars().count() > META_DESCRIPTION_MAX_CHARS {
+        true => {
+            // one char is left for the ellipsis
+            let (end, _) = text
+                .char_indices()
+                .nth(META_DESCRIPTION_MAX_CHARS - 1)
+                .unwrap();
+            format!("{}…", text[..end].trim_end())
+        }
+        false => text,
+    }
+}
+
+pub fn handlebars_add_page_meta(data: &mut Value, meta: &PageMeta) {
+    update_handlebars_data(data, "meta", json!(meta));
+}
@@ -0,0 +1,23 @@
+/// Longest slug kept in a url, longer titles are cut on a word boundary
+const SLUG_MAX_CHARS: usize = 60;
+
+/// Lowercase ascii words joined by dashes, empty when the text has no ascii letters or digits
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+
+    for word in text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if !slug.is_empty() && slug.len() + 1 + word.len() > SLUG_MAX_CHARS {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+
+    slug.truncate(SLUG_MAX_CHARS);
+    slug
+}
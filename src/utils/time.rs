@@ -10,3 +10,11 @@ pub fn time_to_human_readable(naive_datetime: NaiveDateTime) -> String {
     // Format the timestamp into a more readable format
     datetime.format("%d/%m/%Y %H:%M:%S").to_string()
 }
+
+/// The edit time of content that changed after it was created
+pub fn edited_time_to_human_readable(
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+) -> Option<String> {
+    (updated_at > created_at).then(|| time_to_human_readable(updated_at))
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::{entities::trash::TrashPurgeResult, AppKit};
+
+/// How often the background job looks for expired trash
+pub const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Hard deletes every post and comment that sat in the trash longer than the policy allows
+pub fn purge_expired_trash(app_kit: &AppKit) -> Result<TrashPurgeResult, String> {
+    let cutoff = app_kit.trash_policy.purge_cutoff();
+
+    // comments first, purging a post takes its comments along
+    let comments = app_kit
+        .comment_service
+        .purge_deleted_comments(cutoff)
+        .map_err(|e| e.to_string())?;
+
+    let posts = app_kit
+        .post_service
+        .purge_deleted_posts(cutoff)
+        .map_err(|e| e.to_string())?;
+
+    Ok(TrashPurgeResult { posts, comments })
+}
+
+/// Runs `purge_expired_trash` on a background thread every `TRASH_PURGE_INTERVAL`
+pub fn spawn_trash_purge_job(app_kit: AppKit) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || loop {
+        match purge_expired_trash(&app_kit) {
+            Ok(result) if result != TrashPurgeResult::default() => println!(
+                "trash purge removed {} posts and {} comments",
+                result.posts, result.comments
+            ),
+            Ok(_) => {}
+            Err(e) => println!("trash purge failed: {}", e),
+        }
+
+        std::thread::sleep(TRASH_PURGE_INTERVAL);
+    })
+}
@@ -45,7 +45,7 @@ pub struct TurnstileResponse {
 /// # Returns
 /// A `Result` containing:
 /// * `Ok(TurnstileResponse)` - If the API call was successful. You must still check
-///                            `response.success` to see if validation passed.
+///   `response.success` to see if validation passed.
 /// * `Err(reqwest::Error)` - If the request to the Cloudflare API failed.
 async fn validate_turnstile(
     secret_key: &str,
@@ -96,6 +96,12 @@ pub async fn validate_turnstile_wrapper(response_token: &str) -> Result<bool, Tu
                     }
                 })?;
 
+            if !result.success {
+                return Err(TurnstileError {
+                    message: "Turnstile verification failed.".to_string(),
+                });
+            }
+
             Ok(result.success)
         } else {
             Err(TurnstileError {
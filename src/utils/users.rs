@@ -1,12 +1,16 @@
+use std::collections::HashMap;
+
 use actix_session::Session;
 
-use crate::entities::user::{UserPublic, SESSION_KEY_USER};
+use crate::entities::user::{user_is_admin, user_is_moderator, UserPublic, SESSION_KEY_USER};
+use crate::services::user_service::UserService;
 
 pub fn get_session_user(session: &Session) -> Result<UserPublic, actix_web::Error> {
     if let Ok(Some(user)) = session.get::<UserPublic>(SESSION_KEY_USER) {
         Ok(UserPublic {
             id: user.id,
             name: user.name,
+            username: user.username,
             created_at: user.created_at,
             user_profile_picture_url: user.user_profile_picture_url,
         })
@@ -15,6 +19,42 @@ pub fn get_session_user(session: &Session) -> Result<UserPublic, actix_web::Erro
     }
 }
 
+/// Whether the user behind a session is a moderator, the role is read fresh from the user store
+pub fn session_user_is_moderator(user_service: &dyn UserService, session_user_id: i32) -> bool {
+    user_service
+        .get_user_by_id(session_user_id)
+        .map(|user| user_is_moderator(&user))
+        .unwrap_or(false)
+}
+
+/// Whether the user behind a session is an admin, the role is read fresh from the user store
+pub fn session_user_is_admin(user_service: &dyn UserService, session_user_id: i32) -> bool {
+    user_service
+        .get_user_by_id(session_user_id)
+        .map(|user| user_is_admin(&user))
+        .unwrap_or(false)
+}
+
+/// Names of the given users, users that no longer exist are left out
+pub fn get_user_names(
+    user_service: &dyn UserService,
+    user_ids: impl IntoIterator<Item = i32>,
+) -> HashMap<i32, String> {
+    let mut user_names = HashMap::new();
+
+    for user_id in user_ids {
+        if user_names.contains_key(&user_id) {
+            continue;
+        }
+
+        if let Ok(user) = user_service.get_user_by_id(user_id) {
+            user_names.insert(user_id, user.name);
+        }
+    }
+
+    user_names
+}
+
 #[macro_export]
 macro_rules! validate_password_and_confirm_password {
     ($form:expr) => {{
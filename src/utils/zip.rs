@@ -0,0 +1,155 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// 2.0, the lowest version that knows about folders
+const ZIP_VERSION: u16 = 20;
+/// General purpose flag bit 11, file names are UTF-8
+const ZIP_FLAG_UTF8: u16 = 1 << 11;
+
+/// An entry that does not fit the 16 and 32 bit fields of a ZIP archive without ZIP64
+#[derive(Debug, PartialEq, Eq)]
+pub enum ZipLimitError {
+    TooManyEntries,
+    FileNameTooLong,
+    TooLarge,
+}
+
+impl Display for ZipLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZipLimitError::TooManyEntries => write!(f, "ZIP archive has too many entries"),
+            ZipLimitError::FileNameTooLong => write!(f, "ZIP entry file name is too long"),
+            ZipLimitError::TooLarge => write!(f, "ZIP archive is larger than 4 GiB"),
+        }
+    }
+}
+
+/// Builds an uncompressed ZIP archive in memory
+///
+/// Entries are stored as is, exports are mostly JSON and already compressed images.
+/// Archives over 4 GiB or 65535 entries (ZIP64) are refused.
+pub struct ZipArchiveBuilder {
+    buffer: Vec<u8>,
+    central_directory: Vec<u8>,
+    entry_count: u16,
+    modified_at: NaiveDateTime,
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+impl ZipArchiveBuilder {
+    pub fn new(modified_at: NaiveDateTime) -> Self {
+        Self {
+            buffer: vec![],
+            central_directory: vec![],
+            entry_count: 0,
+            modified_at,
+        }
+    }
+
+    /// MS-DOS time and date, as stored in the file headers
+    fn dos_date_time(&self) -> (u16, u16) {
+        let time = (self.modified_at.hour() << 11)
+            | (self.modified_at.minute() << 5)
+            | (self.modified_at.second() / 2);
+        let date = ((self.modified_at.year().clamp(1980, 2107) - 1980) as u32) << 9
+            | (self.modified_at.month() << 5)
+            | self.modified_at.day();
+
+        (time as u16, date as u16)
+    }
+
+    pub fn add_file(
+        &mut self,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<&mut Self, ZipLimitError> {
+        let entry_count = self
+            .entry_count
+            .checked_add(1)
+            .ok_or(ZipLimitError::TooManyEntries)?;
+        let file_name_length =
+            u16::try_from(file_name.len()).map_err(|_| ZipLimitError::FileNameTooLong)?;
+        let size = u32::try_from(content.len()).map_err(|_| ZipLimitError::TooLarge)?;
+        let local_header_offset =
+            u32::try_from(self.buffer.len()).map_err(|_| ZipLimitError::TooLarge)?;
+
+        // both headers of the entry included, the offsets written by finish have to fit as well
+        let archive_size = self.buffer.len()
+            + self.central_directory.len()
+            + 2 * (46 + file_name.len())
+            + content.len();
+        if u32::try_from(archive_size).is_err() {
+            return Err(ZipLimitError::TooLarge);
+        }
+
+        let (dos_time, dos_date) = self.dos_date_time();
+        let crc = crc32fast::hash(content);
+
+        put_u32(&mut self.buffer, LOCAL_FILE_HEADER_SIGNATURE);
+        put_u16(&mut self.buffer, ZIP_VERSION);
+        put_u16(&mut self.buffer, ZIP_FLAG_UTF8);
+        put_u16(&mut self.buffer, 0); // stored
+        put_u16(&mut self.buffer, dos_time);
+        put_u16(&mut self.buffer, dos_date);
+        put_u32(&mut self.buffer, crc);
+        put_u32(&mut self.buffer, size);
+        put_u32(&mut self.buffer, size);
+        put_u16(&mut self.buffer, file_name_length);
+        put_u16(&mut self.buffer, 0); // extra field length
+        self.buffer.extend_from_slice(file_name.as_bytes());
+        self.buffer.extend_from_slice(content);
+
+        let directory = &mut self.central_directory;
+        put_u32(directory, CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(directory, ZIP_VERSION); // made by
+        put_u16(directory, ZIP_VERSION); // needed to extract
+        put_u16(directory, ZIP_FLAG_UTF8);
+        put_u16(directory, 0); // stored
+        put_u16(directory, dos_time);
+        put_u16(directory, dos_date);
+        put_u32(directory, crc);
+        put_u32(directory, size);
+        put_u32(directory, size);
+        put_u16(directory, file_name_length);
+        put_u16(directory, 0); // extra field length
+        put_u16(directory, 0); // comment length
+        put_u16(directory, 0); // disk number
+        put_u16(directory, 0); // internal attributes
+        put_u32(directory, 0); // external attributes
+        put_u32(directory, local_header_offset);
+        directory.extend_from_slice(file_name.as_bytes());
+
+        self.entry_count = entry_count;
+
+        Ok(self)
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let central_directory_offset = self.buffer.len() as u32;
+        let central_directory_size = self.central_directory.len() as u32;
+
+        self.buffer.append(&mut self.central_directory);
+
+        put_u32(&mut self.buffer, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut self.buffer, 0); // this disk
+        put_u16(&mut self.buffer, 0); // disk with the central directory
+        put_u16(&mut self.buffer, self.entry_count);
+        put_u16(&mut self.buffer, self.entry_count);
+        put_u32(&mut self.buffer, central_directory_size);
+        put_u32(&mut self.buffer, central_directory_offset);
+        put_u16(&mut self.buffer, 0); // comment length
+
+        self.buffer
+    }
+}